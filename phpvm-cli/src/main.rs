@@ -0,0 +1,477 @@
+//! `phpvm` command-line interface: the same operations as the GUI, for scripts,
+//! CI pipelines and SSH sessions. Results go to stdout (as JSON with `--json`),
+//! progress and errors to stderr.
+
+mod serve;
+
+use clap::{Parser, Subcommand};
+use phpvm_core::config::Config;
+use phpvm_core::manager::PhpResolutionStatus;
+use phpvm_core::{PhpManager, PhpVmError};
+use serde_json::json;
+use std::io::Write;
+use std::process::ExitCode;
+
+#[cfg(target_os = "windows")]
+const DEFAULT_SHELL: &str = "powershell";
+#[cfg(not(target_os = "windows"))]
+const DEFAULT_SHELL: &str = "bash";
+
+#[derive(Parser)]
+#[command(name = "phpvm", version, about = "PHP Version Manager")]
+struct Cli {
+    /// Print results as JSON
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Work in this profile instead of the active one
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// Only log messages this severe or more: error, warn, info or debug. They're also
+    /// printed to stderr
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Install a version, e.g. 8.3.0 (the configured default variant, or thread safe,
+    /// unless --ts/--nts). Without one, set up what .phpvmrc in the current directory asks for
+    Install {
+        version: Option<String>,
+        /// Install the thread-safe build
+        #[arg(long, conflicts_with = "nts")]
+        ts: bool,
+        /// Install the non-thread-safe build
+        #[arg(long)]
+        nts: bool,
+        /// Replace an existing install of this variant
+        #[arg(long)]
+        force: bool,
+        /// Download the build made with this compiler instead of the branch's usual one:
+        /// vs17, vs16, vc15, VC15, VC14 or VC11
+        #[arg(long, requires = "version")]
+        compiler_tag: Option<String>,
+        /// Keep only these bundled extensions, e.g. curl,mbstring, and delete the others.
+        /// Only reinstalling brings them back
+        #[arg(long, value_delimiter = ',', requires = "version")]
+        keep_ext: Option<Vec<String>>,
+    },
+    /// Make a version active: "8.3.0-nts", "8.3.0" or a branch like "8.2"
+    Use { version: String },
+    /// Upgrade an install to the newest patch of its branch, keeping php.ini and extensions
+    Upgrade {
+        version: String,
+        /// Remove the old patch afterwards
+        #[arg(long)]
+        remove_old: bool,
+    },
+    /// Remove an installed version, e.g. 8.3.0-nts
+    Remove {
+        version: String,
+        /// Remove it even if it's active, switching to another version first
+        #[arg(long)]
+        force: bool,
+    },
+    /// List installed versions
+    List,
+    /// List versions available for download that this OS can install
+    Available,
+    /// Print the active version
+    Active,
+    /// Release and EOL dates, download URL and checksum of a version, e.g. 8.3.0
+    Info { version: String },
+    /// The URL an install downloads first and the fallbacks it tries, e.g. 8.3.0
+    Url {
+        version: String,
+        /// For the non-thread-safe build
+        #[arg(long)]
+        nts: bool,
+        /// Probe the URLs now and report each one's HTTP status and size
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print date.timezone from the active version's php.ini, or set it to an IANA name
+    /// such as Europe/Lisbon
+    Timezone {
+        timezone: Option<String>,
+        /// Use this install instead of the active one, e.g. 8.3.0-nts
+        #[arg(long)]
+        version: Option<String>,
+    },
+    /// Installed, active and available versions and PATH state
+    Status,
+    /// Print a snippet that puts a version first on PATH for this shell only, e.g.
+    /// `eval "$(phpvm env 8.1)"`. Without a version, use the nearest .phpvmrc or .php-version
+    Env {
+        version: Option<String>,
+        /// cmd, powershell or bash
+        #[arg(long, default_value = DEFAULT_SHELL)]
+        shell: String,
+    },
+    /// Serve a JSON API on 127.0.0.1 for editor plugins and scripts: GET /versions,
+    /// GET /active, POST /install (progress as Server-Sent Events), POST /switch
+    Serve {
+        #[arg(long, default_value_t = 7788)]
+        port: u16,
+        /// Require `Authorization: Bearer <token>` (or `?token=`) on every request.
+        /// Without it a random token is made up and printed
+        #[arg(long, conflicts_with = "no_token")]
+        token: Option<String>,
+        /// Accept requests without a token. Any local process can then use the API
+        #[arg(long)]
+        no_token: bool,
+    },
+    /// Show whether the phpvm directory is on PATH
+    Path {
+        /// Add it to PATH
+        #[arg(long)]
+        set: bool,
+    },
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("error: failed to start async runtime: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match runtime.block_on(run(&cli)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            let error = PhpVmError::from(e);
+            if cli.json {
+                println!("{}", json!({ "error": { "code": error.code(), "message": error.message() } }));
+            } else {
+                eprintln!("error: {}", error.message());
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: &Cli) -> anyhow::Result<()> {
+    if let Some(profile) = &cli.profile {
+        phpvm_core::config::validate_profile_name(profile)?;
+        if !phpvm_core::config::list_profiles().contains(profile) {
+            return Err(PhpVmError::InvalidArgument(format!("Profile {} doesn't exist", profile)).into());
+        }
+        std::env::set_var(phpvm_core::config::PHPVM_PROFILE_ENV, profile);
+    }
+    if let Some(level) = &cli.log_level {
+        phpvm_core::logging::set_log_level(level)?;
+        // Without --log-level stderr is left to progress and errors
+        phpvm_core::logging::init_tracing();
+    }
+    let manager = PhpManager::new()?;
+
+    match &cli.command {
+        Command::Install { version: None, .. } => {
+            let report = manager
+                .apply_project_config(&std::env::current_dir()?, Some(progress_to_stderr()))
+                .await?;
+            print_result(cli.json, serde_json::to_value(&report)?, || report.steps.join("\n"));
+        }
+        Command::Install { version: Some(version), ts, nts, force, compiler_tag, keep_ext } => {
+            let options = phpvm_core::install::InstallOptions {
+                compiler_tag: compiler_tag.clone(),
+                keep_extensions: keep_ext.clone(),
+                checksum_progress: None,
+            };
+            let thread_safe = match (ts, nts) {
+                (true, _) => Some(true),
+                (_, true) => Some(false),
+                _ => None,
+            };
+            manager.install_with_options(version, thread_safe, *force, Some(progress_to_stderr()), options).await?;
+            let thread_safe = thread_safe.or(manager.get_default_variant()).unwrap_or(true);
+            let installed = format!("{}-{}", version, if thread_safe { "ts" } else { "nts" });
+            print_result(cli.json, json!({ "installed": installed }), || format!("Installed PHP {}", installed));
+        }
+        Command::Use { version } => {
+            let outcome = manager.switch_checked(version).await?;
+            let active = manager.get_active()?;
+            if let (false, Some(warning)) = (cli.json, &outcome.path_warning) {
+                eprintln!("warning: {}", warning);
+            }
+            print_result(cli.json, json!({ "active": active, "path_warning": outcome.path_warning }), || {
+                format!("Now using PHP {}", active.as_deref().unwrap_or(version))
+            });
+        }
+        Command::Upgrade { version, remove_old } => {
+            let report = manager.upgrade(version, *remove_old, Some(progress_to_stderr())).await?;
+            print_result(cli.json, serde_json::to_value(&report)?, || report.steps.join("\n"));
+        }
+        Command::Remove { version, force } => {
+            manager.remove(version, *force).await?;
+            print_result(cli.json, json!({ "removed": version }), || format!("Removed PHP {}", version));
+        }
+        Command::List => {
+            let active = manager.get_active()?;
+            let installed = manager.list_installed()?;
+            print_result(cli.json, json!({ "installed": installed, "active": active }), || {
+                installed
+                    .iter()
+                    .map(|v| format!("{} {}", if active.as_ref() == Some(v) { "*" } else { " " }, v))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        Command::Available => {
+            let installable = manager.list_installable().await?;
+            let available = &installable.versions;
+            print_result(cli.json, json!({ "available": available, "reason": installable.reason }), || {
+                installable.reason.clone().unwrap_or_else(|| available.join("\n"))
+            });
+        }
+        Command::Active => {
+            let active = manager.get_active_detailed()?;
+            let value = match &active {
+                Some(active) => json!({
+                    "active": active.version,
+                    "base_version": active.base_version,
+                    "variant": active.variant,
+                    "install_path": active.install_path,
+                }),
+                None => json!({ "active": null }),
+            };
+            print_result(cli.json, value, || {
+                active.as_ref().map(|active| active.version.clone()).unwrap_or_else(|| "none".to_string())
+            });
+        }
+        Command::Info { version } => {
+            phpvm_core::version::validate_version_str(version)?;
+            let info = manager
+                .get_version_info(version)
+                .await?
+                .ok_or_else(|| PhpVmError::InvalidVersion(format!("Invalid version: {}", version)))?;
+            print_result(cli.json, serde_json::to_value(&info)?, || {
+                let field = |value: &Option<String>| value.clone().unwrap_or_else(|| "unknown".to_string());
+                [
+                    format!("PHP {}", info.version),
+                    format!("Released: {}", field(&info.release_date)),
+                    format!("Active support until: {}", field(&info.active_support_until)),
+                    format!("EOL: {}", field(&info.eol_date)),
+                    format!("Download: {}", field(&info.download_url)),
+                    format!("SHA256: {}", field(&info.checksum)),
+                ]
+                .join("\n")
+            });
+        }
+        Command::Url { version, nts, check: true } => {
+            let check = manager.check_version_downloadable(version, Some(!nts)).await?;
+            print_result(cli.json, serde_json::to_value(&check)?, || {
+                check
+                    .probes
+                    .iter()
+                    .map(|probe| {
+                        let status = match (probe.status_code, &probe.error) {
+                            (Some(code), _) => code.to_string(),
+                            (None, Some(error)) => format!("failed: {}", error),
+                            (None, None) => "failed".to_string(),
+                        };
+                        let size = probe
+                            .content_length
+                            .map(|bytes| format!(" ({})", phpvm_core::platform::format_bytes(bytes)))
+                            .unwrap_or_default();
+                        format!("{} {}{}", status, probe.url, size)
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            });
+        }
+        Command::Url { version, nts, check: false } => {
+            let urls = manager.get_download_url(version, Some(!nts))?;
+            print_result(cli.json, serde_json::to_value(&urls)?, || {
+                std::iter::once(urls.primary.clone()).chain(urls.fallbacks.clone()).collect::<Vec<_>>().join("\n")
+            });
+        }
+        Command::Timezone { timezone, version } => {
+            let version = match version {
+                Some(version) => version.clone(),
+                None => manager
+                    .get_active()?
+                    .ok_or_else(|| PhpVmError::InvalidArgument("No active version; pass --version".to_string()))?,
+            };
+            let timezone = match timezone {
+                Some(timezone) => Some(manager.set_timezone(&version, timezone)?),
+                None => manager.get_timezone(&version)?,
+            };
+            print_result(cli.json, json!({ "version": version, "timezone": timezone }), || {
+                timezone.clone().unwrap_or_else(|| "not set".to_string())
+            });
+        }
+        Command::Status => {
+            let report = manager.status_report().await?;
+            print_result(cli.json, serde_json::to_value(&report)?, || {
+                let mut lines = vec![format!("Active: {}", report.active.as_deref().unwrap_or("none"))];
+                lines.push(format!("Installed ({}):", report.install_dir.display()));
+                lines.extend(
+                    report
+                        .installed
+                        .iter()
+                        .map(|i| format!("  {} {}", if i.active { "*" } else { " " }, i.version)),
+                );
+                match &report.available_error {
+                    Some(error) => lines.push(format!("Available: unknown ({})", error)),
+                    None => lines.push(format!("Available: {}", report.available.join(", "))),
+                }
+                lines.push(format!("On PATH: {}", if report.path_configured { "yes" } else { "no" }));
+                lines.join("\n")
+            });
+        }
+        Command::Env { version, shell } => {
+            let shell = phpvm_core::platform::ShellKind::parse(shell)?;
+            let version = match version {
+                Some(version) => version.clone(),
+                None => {
+                    let dir = std::env::current_dir()?;
+                    phpvm_core::project::detect_project_version(&dir)?.ok_or_else(|| {
+                        PhpVmError::InvalidArgument(format!(
+                            "No .phpvmrc or .php-version in {} or its parents",
+                            dir.display()
+                        ))
+                    })?
+                }
+            };
+            let info = manager.shell_activate(&version, Some(shell))?;
+            print_result(cli.json, serde_json::to_value(&info)?, || {
+                info.script.clone().unwrap_or_default().trim_end().to_string()
+            });
+        }
+        Command::Serve { port, token, no_token } => {
+            let listener = tokio::net::TcpListener::bind(("127.0.0.1", *port)).await?;
+            let token = match (token, no_token) {
+                (_, true) => None,
+                (Some(token), false) => Some(token.clone()),
+                (None, false) => {
+                    let token = serve::generate_token();
+                    eprintln!("Token: {}", token);
+                    Some(token)
+                }
+            };
+            eprintln!(
+                "Serving the phpvm API on http://127.0.0.1:{}{}",
+                port,
+                if token.is_some() { " (token required)" } else { "" }
+            );
+            serve::serve(listener, manager, token).await?;
+        }
+        Command::Path { set } => {
+            if *set {
+                if !Config::load()?.manage_path {
+                    return Err(PhpVmError::InvalidArgument(
+                        "PATH management is turned off (manage_path in config.json)".to_string(),
+                    )
+                    .into());
+                }
+                manager.ensure_path_set()?;
+            }
+            let configured = manager.is_path_configured()?;
+            let resolution = manager.resolve_php()?;
+            let dir = resolution.shim_dir.to_string_lossy().to_string();
+            let value = json!({ "path_configured": configured, "dir": dir, "resolution": resolution });
+            print_result(cli.json, value, || {
+                let mut text = if configured {
+                    format!("{} is on PATH", dir)
+                } else {
+                    format!("{} is not on PATH (run `phpvm path --set`)", dir)
+                };
+                match (resolution.status, &resolution.resolved) {
+                    (PhpResolutionStatus::Shadowed, Some(php)) => {
+                        text.push_str(&format!("\nbut `php` in this shell runs {}", php.display()))
+                    }
+                    (PhpResolutionStatus::NotFound, _) if configured => {
+                        text.push_str("\nbut this shell doesn't see it yet; open a new terminal")
+                    }
+                    _ => {}
+                }
+                text
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Print `value` as JSON, or the human-readable form built by `text`
+fn print_result(json: bool, value: serde_json::Value, text: impl FnOnce() -> String) {
+    if json {
+        println!("{}", value);
+    } else {
+        let text = text();
+        if !text.is_empty() {
+            println!("{}", text);
+        }
+    }
+}
+
+/// Download progress as a single updating line on stderr
+fn progress_to_stderr() -> phpvm_core::download::ProgressCallback {
+    Box::new(|downloaded: u64, total: u64, speed_mbps: f64| {
+        let mut stderr = std::io::stderr();
+        if total > 0 {
+            let _ = write!(
+                stderr,
+                "\rDownloading {} / {} ({}%) {:.2} MB/s   ",
+                phpvm_core::platform::format_bytes(downloaded),
+                phpvm_core::platform::format_bytes(total),
+                downloaded * 100 / total,
+                speed_mbps
+            );
+        } else {
+            let _ = write!(stderr, "\rDownloading {}   ", phpvm_core::platform::format_bytes(downloaded));
+        }
+        if total > 0 && downloaded >= total {
+            let _ = writeln!(stderr);
+        }
+        let _ = stderr.flush();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition_and_parsing() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["phpvm", "install", "8.3.0", "--nts", "--json"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(cli.command, Command::Install { ref version, ts: false, nts: true, force: false, .. } if version.as_deref() == Some("8.3.0")));
+        let cli = Cli::try_parse_from(["phpvm", "install", "8.3.0"]).unwrap();
+        assert!(matches!(cli.command, Command::Install { ts: false, nts: false, .. }));
+        let cli = Cli::try_parse_from(["phpvm", "install", "8.3.0", "--ts"]).unwrap();
+        assert!(matches!(cli.command, Command::Install { ts: true, nts: false, .. }));
+        assert!(Cli::try_parse_from(["phpvm", "install", "8.3.0", "--ts", "--nts"]).is_err());
+        let cli = Cli::try_parse_from(["phpvm", "install", "7.4.33", "--compiler-tag", "vs16"]).unwrap();
+        assert!(matches!(cli.command, Command::Install { ref compiler_tag, .. } if compiler_tag.as_deref() == Some("vs16")));
+        assert!(Cli::try_parse_from(["phpvm", "install", "--compiler-tag", "vs16"]).is_err());
+        let cli = Cli::try_parse_from(["phpvm", "install", "8.3.0", "--keep-ext", "curl,mbstring"]).unwrap();
+        assert!(matches!(cli.command, Command::Install { keep_ext: Some(ref keep), .. } if keep == &["curl", "mbstring"]));
+        let cli = Cli::try_parse_from(["phpvm", "install"]).unwrap();
+        assert!(matches!(cli.command, Command::Install { version: None, .. }));
+
+        let cli = Cli::try_parse_from(["phpvm", "list", "--profile", "client-a"]).unwrap();
+        assert_eq!(cli.profile.as_deref(), Some("client-a"));
+
+        let cli = Cli::try_parse_from(["phpvm", "env", "--shell", "cmd"]).unwrap();
+        assert!(matches!(cli.command, Command::Env { version: None, ref shell } if shell == "cmd"));
+
+        let cli = Cli::try_parse_from(["phpvm", "path", "--set"]).unwrap();
+        assert!(matches!(cli.command, Command::Path { set: true }));
+
+        let cli = Cli::try_parse_from(["phpvm", "timezone", "Europe/Lisbon", "--version", "8.3.0-nts"]).unwrap();
+        assert!(matches!(cli.command, Command::Timezone { timezone: Some(ref tz), version: Some(_) } if tz == "Europe/Lisbon"));
+        assert!(Cli::try_parse_from(["phpvm", "use"]).is_err());
+    }
+}
@@ -0,0 +1,464 @@
+//! `phpvm serve`: a small JSON API on 127.0.0.1 for editor plugins and scripts.
+//!
+//! - `GET /versions` installed versions and the active one (`?available=true` for
+//!   versions offered for download)
+//! - `GET /active` the active version
+//! - `POST /install` `{"version": "8.3.0", "nts": false, "force": false}`; without
+//!   `nts` the configured default variant is installed. With `Accept: text/event-stream`
+//!   progress is streamed as Server-Sent Events
+//! - `POST /switch` `{"version": "8.2"}`; `path_warning` in the answer says why PATH
+//!   couldn't be updated, if it couldn't
+//!
+//! With a token (`phpvm serve` makes one up unless told not to), every request must send
+//! `Authorization: Bearer <token>` or, for EventSource clients that can't set headers,
+//! `?token=<token>`. So that web pages can't drive the API, requests must name the server
+//! in `Host` (`127.0.0.1:<port>` or `localhost:<port>`, which DNS rebinding can't fake),
+//! an `Origin`, if sent, must be a loopback one, and POST bodies need
+//! `Content-Type: application/json`, which a cross-site form can't send without a
+//! preflight. One request per connection; failures answer
+//! `{"error": {"code": ..., "message": ...}}`.
+
+use phpvm_core::{PhpManager, PhpVmError};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, Mutex};
+
+/// Requests larger than this are refused; the API only takes small JSON bodies
+const MAX_BODY_BYTES: usize = 64 * 1024;
+const MAX_HEADER_LINES: usize = 100;
+/// Minimum time between two progress events, as in the GUI
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    /// Header names lower-cased
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+/// Accept connections on `listener` until the process is stopped
+pub async fn serve(listener: TcpListener, manager: PhpManager, token: Option<String>) -> anyhow::Result<()> {
+    let port = listener.local_addr()?.port();
+    let manager = Arc::new(Mutex::new(manager));
+    let token = Arc::new(token);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let (manager, token) = (manager.clone(), token.clone());
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, port, manager, token).await {
+                eprintln!("serve: {}: {:#}", peer, e);
+            }
+        });
+    }
+}
+
+/// A token for `serve` when the user didn't pick one: 32 hex digits from SipHash keyed
+/// with std's OS-seeded random keys, so it can't be guessed from outside the process
+pub fn generate_token() -> String {
+    use std::hash::{BuildHasher, Hasher};
+    (0..2u64)
+        .map(|i| {
+            let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+            hasher.write_u64(i);
+            format!("{:016x}", hasher.finish())
+        })
+        .collect()
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    port: u16,
+    manager: Arc<Mutex<PhpManager>>,
+    token: Arc<Option<String>>,
+) -> anyhow::Result<()> {
+    let request = match read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(e) => {
+            let error = PhpVmError::InvalidArgument(format!("Bad request: {}", e));
+            return respond_error(&mut stream, &error).await;
+        }
+    };
+    if let Some(reason) = forbidden(&request, port) {
+        let body = json!({ "error": { "code": "FORBIDDEN", "message": reason } });
+        return respond(&mut stream, 403, &body).await;
+    }
+    if !authorized(&request, token.as_deref()) {
+        let body = json!({ "error": { "code": "UNAUTHORIZED", "message": "Missing or wrong token" } });
+        return respond(&mut stream, 401, &body).await;
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/versions") => {
+            let manager = manager.lock().await;
+            let result = if request.query.get("available").is_some_and(|v| v == "true" || v == "1") {
+                manager.list_available().await.map(|available| json!({ "available": available }))
+            } else {
+                manager
+                    .list_installed()
+                    .and_then(|installed| Ok(json!({ "installed": installed, "active": manager.get_active()? })))
+            };
+            respond_result(&mut stream, result).await
+        }
+        ("GET", "/active") => {
+            let result = manager.lock().await.get_active().map(|active| json!({ "active": active }));
+            respond_result(&mut stream, result).await
+        }
+        ("POST", "/switch") => {
+            let version = match json_body(&request).and_then(|body| string_field(&body, "version")) {
+                Ok(version) => version,
+                Err(e) => return respond_error(&mut stream, &e).await,
+            };
+            let manager = manager.lock().await;
+            let result = match manager.switch_checked(&version).await {
+                Ok(outcome) => manager
+                    .get_active()
+                    .map(|active| json!({ "active": active, "path_warning": outcome.path_warning })),
+                Err(e) => Err(e),
+            };
+            respond_result(&mut stream, result).await
+        }
+        ("POST", "/install") => handle_install(stream, request, manager).await,
+        _ => {
+            let body = json!({ "error": { "code": "NOT_FOUND", "message": format!("No route for {} {}", request.method, request.path) } });
+            respond(&mut stream, 404, &body).await
+        }
+    }
+}
+
+enum InstallEvent {
+    Progress(u64, u64, f64),
+    Done(anyhow::Result<String>),
+}
+
+/// Run an install, streaming progress as SSE `progress` events followed by one `done`
+/// or `error` event when the client accepts `text/event-stream`, else answering once
+/// it has finished
+async fn handle_install(mut stream: TcpStream, request: Request, manager: Arc<Mutex<PhpManager>>) -> anyhow::Result<()> {
+    let (body, version) = match json_body(&request).and_then(|body| Ok((string_field(&body, "version")?, body))) {
+        Ok((version, body)) => (body, version),
+        Err(e) => return respond_error(&mut stream, &e).await,
+    };
+    // Absent means "the default variant", like `phpvm install` without --ts/--nts
+    let thread_safe = body.get("nts").and_then(Value::as_bool).map(|nts| !nts);
+    let force = body.get("force").and_then(Value::as_bool).unwrap_or(false);
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let progress_tx = tx.clone();
+    let mut last_sent: Option<Instant> = None;
+    let progress: phpvm_core::download::ProgressCallback = Box::new(move |downloaded: u64, total: u64, speed_mbps: f64| {
+        let now = Instant::now();
+        let due = last_sent.is_none_or(|last| now.duration_since(last) >= PROGRESS_INTERVAL);
+        if due || (total > 0 && downloaded == total) {
+            let _ = progress_tx.send(InstallEvent::Progress(downloaded, total, speed_mbps));
+            last_sent = Some(now);
+        }
+    });
+    tokio::spawn(async move {
+        let manager = manager.lock().await;
+        let installed_ts = thread_safe.or(manager.get_default_variant()).unwrap_or(true);
+        let result = manager
+            .install(&version, thread_safe, force, Some(progress))
+            .await
+            .map(|()| format!("{}-{}", version, if installed_ts { "ts" } else { "nts" }));
+        let _ = tx.send(InstallEvent::Done(result));
+    });
+
+    let streaming = request.headers.get("accept").is_some_and(|accept| accept.contains("text/event-stream"));
+    if streaming {
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n")
+            .await?;
+    }
+    while let Some(event) = rx.recv().await {
+        match event {
+            InstallEvent::Progress(downloaded, total, speed_mbps) if streaming => {
+                let percent = (downloaded * 100).checked_div(total).unwrap_or(0);
+                let data = json!({ "downloaded": downloaded, "total": total, "speed_mbps": speed_mbps, "percent": percent });
+                stream.write_all(format!("event: progress\ndata: {}\n\n", data).as_bytes()).await?;
+            }
+            InstallEvent::Progress(..) => {}
+            InstallEvent::Done(result) => {
+                let result = result.map(|installed| json!({ "installed": installed }));
+                return match (streaming, result) {
+                    (true, Ok(body)) => stream.write_all(format!("event: done\ndata: {}\n\n", body).as_bytes()).await.map_err(Into::into),
+                    (true, Err(e)) => {
+                        let error = PhpVmError::from(e);
+                        let data = json!({ "code": error.code(), "message": error.message() });
+                        stream.write_all(format!("event: error\ndata: {}\n\n", data).as_bytes()).await.map_err(Into::into)
+                    }
+                    (false, result) => respond_result(&mut stream, result).await,
+                };
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse one HTTP/1.1 request: request line, headers, and a `Content-Length` body
+async fn read_request(stream: &mut TcpStream) -> anyhow::Result<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).await?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        anyhow::bail!("malformed request line {:?}", line.trim_end());
+    };
+    let (path, query) = parse_target(target);
+    let method = method.to_string();
+
+    let mut headers = HashMap::new();
+    for _ in 0..=MAX_HEADER_LINES {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            anyhow::bail!("connection closed inside the headers");
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            let length: usize = headers.get("content-length").map(|v: &String| v.parse()).transpose()?.unwrap_or(0);
+            if length > MAX_BODY_BYTES {
+                anyhow::bail!("body over {} bytes", MAX_BODY_BYTES);
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await?;
+            return Ok(Request { method, path, query, headers, body });
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    anyhow::bail!("more than {} header lines", MAX_HEADER_LINES)
+}
+
+/// "/versions?available=true" -> ("/versions", {"available": "true"}). Values aren't
+/// percent-decoded, so tokens passed in the query should stick to URL-safe characters.
+fn parse_target(target: &str) -> (String, HashMap<String, String>) {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (key.to_string(), value.to_string())
+        })
+        .collect();
+    (path.to_string(), query)
+}
+
+/// Why a request that may come from a web page is refused, or None if it's fine: `Host`
+/// must be this server under a loopback name, and a browser's `Origin` a loopback one
+fn forbidden(request: &Request, port: u16) -> Option<String> {
+    let host = request.headers.get("host").map(|host| host.to_ascii_lowercase());
+    let expected = [format!("127.0.0.1:{}", port), format!("localhost:{}", port)];
+    if !host.as_ref().is_some_and(|host| expected.contains(host)) {
+        return Some(format!("Host must be 127.0.0.1:{} or localhost:{}", port, port));
+    }
+    match request.headers.get("origin") {
+        Some(origin) if !is_loopback_origin(origin) => Some(format!("Origin {} is not allowed", origin)),
+        _ => None,
+    }
+}
+
+/// "http://localhost:5173", "https://127.0.0.1" or "http://[::1]:8080"
+fn is_loopback_origin(origin: &str) -> bool {
+    let Some(authority) = origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://")) else {
+        return false;
+    };
+    let host = match authority.strip_prefix('[') {
+        Some(rest) => rest.split_once(']').map(|(host, _)| host).unwrap_or(rest),
+        None => authority.split(':').next().unwrap_or(authority),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+fn authorized(request: &Request, token: Option<&str>) -> bool {
+    let Some(token) = token else { return true };
+    let bearer = request
+        .headers
+        .get("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    [bearer, request.query.get("token").map(String::as_str)]
+        .into_iter()
+        .flatten()
+        .any(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+}
+
+/// Compare without stopping at the first difference, so response times don't give the
+/// token away a byte at a time
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// The request's JSON body. `Content-Type: application/json` is required: HTML forms
+/// can't send it, so a page can't post here without a CORS preflight, which is never
+/// answered.
+fn json_body(request: &Request) -> Result<Value, PhpVmError> {
+    let json = request
+        .headers
+        .get("content-type")
+        .and_then(|value| value.split(';').next())
+        .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"));
+    if !json {
+        return Err(PhpVmError::InvalidArgument("Content-Type must be application/json".to_string()));
+    }
+    serde_json::from_slice(&request.body).map_err(|e| PhpVmError::InvalidArgument(format!("Body must be JSON: {}", e)))
+}
+
+/// A required string field of a JSON body
+fn string_field(body: &Value, field: &str) -> Result<String, PhpVmError> {
+    body.get(field)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| PhpVmError::InvalidArgument(format!("Body needs a \"{}\" string", field)))
+}
+
+/// HTTP status for an error: 400 for bad input, 404/409 for install state, 500 otherwise
+fn status_for(error: &PhpVmError) -> u16 {
+    match error {
+        PhpVmError::InvalidArgument(_) | PhpVmError::InvalidVersion(_) => 400,
+        PhpVmError::NotInstalled(_) => 404,
+        PhpVmError::AlreadyInstalled(_) | PhpVmError::InstallInProgress(_) | PhpVmError::ActiveVersionInUse(_) => 409,
+        PhpVmError::Network(_) => 502,
+        _ => 500,
+    }
+}
+
+async fn respond_result(stream: &mut TcpStream, result: anyhow::Result<Value>) -> anyhow::Result<()> {
+    match result {
+        Ok(body) => respond(stream, 200, &body).await,
+        Err(e) => respond_error(stream, &PhpVmError::from(e)).await,
+    }
+}
+
+async fn respond_error(stream: &mut TcpStream, error: &PhpVmError) -> anyhow::Result<()> {
+    let body = json!({ "error": { "code": error.code(), "message": error.message() } });
+    respond(stream, status_for(error), &body).await
+}
+
+async fn respond(stream: &mut TcpStream, status: u16, body: &Value) -> anyhow::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        409 => "Conflict",
+        502 => "Bad Gateway",
+        _ => "Internal Server Error",
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Send a raw request and return the status code and body
+    async fn send(port: u16, request: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let status = response.split_whitespace().nth(1).unwrap().parse().unwrap();
+        let body = response.split_once("\r\n\r\n").map(|(_, body)| body.to_string()).unwrap_or_default();
+        (status, body)
+    }
+
+    #[test]
+    fn test_parse_target() {
+        let (path, query) = parse_target("/versions?available=true&token=abc");
+        assert_eq!(path, "/versions");
+        assert_eq!(query.get("available").map(String::as_str), Some("true"));
+        assert_eq!(query.get("token").map(String::as_str), Some("abc"));
+        assert!(parse_target("/active").1.is_empty());
+    }
+
+    #[test]
+    fn test_origin_and_token_checks() {
+        for origin in ["http://localhost:5173", "https://127.0.0.1", "http://[::1]:8080", "http://127.0.0.2"] {
+            assert!(is_loopback_origin(origin), "{}", origin);
+        }
+        for origin in ["null", "https://example.com", "http://localhost.example.com", "http://127.0.0.1.nip.io", "file://"] {
+            assert!(!is_loopback_origin(origin), "{}", origin);
+        }
+
+        assert!(constant_time_eq(b"s3cret", b"s3cret"));
+        assert!(!constant_time_eq(b"s3cret", b"s3creT"));
+        assert!(!constant_time_eq(b"s3cret", b"s3cre"));
+
+        let token = generate_token();
+        assert_eq!(token.len(), 32);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, generate_token());
+    }
+
+    #[tokio::test]
+    async fn test_serve_requires_token_and_answers_json() {
+        let home = tempfile::tempdir().unwrap();
+        std::env::set_var(phpvm_core::config::PHPVM_HOME_ENV, home.path());
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve(listener, PhpManager::new().unwrap(), Some("s3cret".to_string())));
+
+        let host = format!("Host: localhost:{}\r\n", port);
+        let (status, body) = send(port, &format!("GET /active HTTP/1.1\r\n{}\r\n", host)).await;
+        assert_eq!(status, 401);
+        assert!(body.contains("UNAUTHORIZED"));
+
+        let (status, body) = send(port, &format!("GET /active HTTP/1.1\r\n{}Authorization: Bearer s3cret\r\n\r\n", host)).await;
+        assert_eq!(status, 200);
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), json!({ "active": null }));
+
+        let (status, body) = send(port, &format!("GET /versions?token=s3cret HTTP/1.1\r\nHost: 127.0.0.1:{}\r\n\r\n", port)).await;
+        assert_eq!(status, 200);
+        assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), json!({ "installed": [], "active": null }));
+
+        // DNS rebinding (another host name) and cross-site pages are refused even with the token
+        let (status, body) = send(port, &format!("GET /active?token=s3cret HTTP/1.1\r\nHost: evil.example:{}\r\n\r\n", port)).await;
+        assert_eq!(status, 403);
+        assert!(body.contains("FORBIDDEN"));
+        let (status, _) = send(port, "GET /active?token=s3cret HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status, 403);
+        let (status, _) = send(port, &format!("GET /active?token=s3cret HTTP/1.1\r\n{}Origin: https://example.com\r\n\r\n", host)).await;
+        assert_eq!(status, 403);
+        let (status, _) = send(port, &format!("GET /active?token=s3cret HTTP/1.1\r\n{}Origin: http://localhost:3000\r\n\r\n", host)).await;
+        assert_eq!(status, 200);
+
+        let switch = format!(
+            "POST /switch HTTP/1.1\r\n{}Authorization: Bearer s3cret\r\nContent-Type: application/json\r\nContent-Length: 21\r\n\r\n{{\"version\": \"8.2.10\"}}",
+            host
+        );
+        let (status, body) = send(port, &switch).await;
+        assert_eq!(status, 404, "{}", body);
+        assert!(body.contains("NOT_INSTALLED"));
+        // A form post can't set Content-Type: application/json
+        let (status, body) = send(port, &switch.replace("application/json", "text/plain")).await;
+        assert_eq!(status, 400);
+        assert!(body.contains("Content-Type"));
+
+        let install = format!("POST /install?token=s3cret HTTP/1.1\r\n{}Content-Type: application/json\r\nContent-Length: 2\r\n\r\n{{}}", host);
+        let (status, _) = send(port, &install).await;
+        assert_eq!(status, 400);
+        let (status, _) = send(port, &format!("DELETE /active?token=s3cret HTTP/1.1\r\n{}\r\n", host)).await;
+        assert_eq!(status, 404);
+
+        std::env::remove_var(phpvm_core::config::PHPVM_HOME_ENV);
+    }
+}
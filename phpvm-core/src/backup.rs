@@ -0,0 +1,237 @@
+use crate::config;
+use crate::error::PhpVmError;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Written into every backup so a restore knows where the paths inside it pointed
+pub const BACKUP_MANIFEST_FILE: &str = "phpvm-backup.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// The base directory the backup was taken from
+    pub base_dir: PathBuf,
+    pub created_at: String,
+    pub include_versions: bool,
+    pub include_cache: bool,
+}
+
+/// What `backup_all` wrote
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupReport {
+    pub path: PathBuf,
+    pub files: usize,
+    /// Uncompressed size of the files
+    pub bytes: u64,
+}
+
+/// What `restore_all` put back
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub files: usize,
+    /// Paths in config.json and state.json moved from the old base directory to this one
+    pub rewritten_paths: usize,
+}
+
+/// Zip the phpvm base directory into `dest_zip`: config, every profile's state, logs and
+/// stats, plus installed versions and the download cache when asked. The `current`
+/// shims and earlier backups saved in the base directory are left out; switching
+/// rebuilds the shims. Installs outside the base directory (a custom `install_dir`)
+/// aren't included. Unix file modes are kept, so restored executables still run.
+pub fn backup_all(dest_zip: &Path, include_versions: bool, include_cache: bool) -> anyhow::Result<BackupReport> {
+    let base_dir = config::get_base_directory();
+    crate::logging::log_message("INFO", &format!("Backing up {:?} to {:?}", base_dir, dest_zip));
+
+    if let Some(parent) = dest_zip.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let file = fs::File::create(dest_zip).with_context(|| format!("Failed to create {:?}", dest_zip))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    let manifest = BackupManifest {
+        base_dir: base_dir.clone(),
+        created_at: crate::date::today_iso(),
+        include_versions,
+        include_cache,
+    };
+    zip.start_file(BACKUP_MANIFEST_FILE, options)?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+
+    let mut report = BackupReport { path: dest_zip.to_path_buf(), files: 0, bytes: 0 };
+    let mut pending = vec![base_dir.clone()];
+    while let Some(dir) = pending.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", dir)),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            // The backup may be written inside the directory it backs up
+            if path == dest_zip {
+                continue;
+            }
+            let relative = path.strip_prefix(&base_dir)?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                let name = entry.file_name();
+                let skipped = name == "current"
+                    || (name == "versions" && !include_versions)
+                    || (name == "cache" && !include_cache && dir == base_dir);
+                if !skipped {
+                    pending.push(path);
+                }
+            } else if file_type.is_file() {
+                // Otherwise every backup would carry all the ones before it
+                if is_backup_archive(&path) {
+                    continue;
+                }
+                let name = relative.to_string_lossy().replace('\\', "/");
+                #[cfg(unix)]
+                let options = {
+                    use std::os::unix::fs::PermissionsExt;
+                    options.unix_permissions(entry.metadata()?.permissions().mode())
+                };
+                zip.start_file(name, options)?;
+                let mut source = fs::File::open(&path).with_context(|| format!("Failed to read {:?}", path))?;
+                report.bytes += std::io::copy(&mut source, &mut zip)?;
+                report.files += 1;
+            }
+        }
+    }
+    zip.finish()?;
+
+    crate::logging::log_message("INFO", &format!("Backed up {} files ({} bytes)", report.files, report.bytes));
+    Ok(report)
+}
+
+/// Whether `path` is a zip written by `backup_all`
+fn is_backup_archive(path: &Path) -> bool {
+    if path.extension().is_none_or(|ext| !ext.eq_ignore_ascii_case("zip")) {
+        return false;
+    }
+    fs::File::open(path)
+        .ok()
+        .and_then(|file| zip::ZipArchive::new(file).ok())
+        .is_some_and(|mut archive| archive.by_name(BACKUP_MANIFEST_FILE).is_ok())
+}
+
+/// Unpack a `backup_all` archive over the base directory. The archive must contain
+/// config.json and state.json, and nothing is written until it's been checked. Files
+/// the backup doesn't have are left alone, so restoring a backup made without versions
+/// keeps the installed ones. Paths in config.json and every state.json that pointed
+/// into the backed-up base directory are moved to this one.
+pub fn restore_all(src_zip: &Path) -> anyhow::Result<RestoreReport> {
+    let base_dir = config::get_base_directory();
+    crate::logging::log_message("INFO", &format!("Restoring {:?} from {:?}", base_dir, src_zip));
+
+    let file = fs::File::open(src_zip).with_context(|| format!("Failed to open {:?}", src_zip))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .map_err(|e| PhpVmError::InvalidArgument(format!("{:?} is not a phpvm backup: {}", src_zip, e)))?;
+
+    let mut names = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let entry = archive.by_index(i)?;
+        let name = entry
+            .enclosed_name()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| PhpVmError::InvalidArgument(format!("Unsafe path in backup: {:?}", entry.name())))?;
+        names.push(name);
+    }
+    for required in ["config.json", "state.json"] {
+        if !names.iter().any(|name| name == Path::new(required)) {
+            return Err(PhpVmError::InvalidArgument(format!("{:?} has no {}; not a phpvm backup", src_zip, required)).into());
+        }
+    }
+
+    let manifest: Option<BackupManifest> = match archive.by_name(BACKUP_MANIFEST_FILE) {
+        Ok(mut entry) => {
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            serde_json::from_str(&content).ok()
+        }
+        Err(_) => None,
+    };
+
+    let mut report = RestoreReport { files: 0, rewritten_paths: 0 };
+    for (i, name) in names.iter().enumerate() {
+        if name == Path::new(BACKUP_MANIFEST_FILE) {
+            continue;
+        }
+        let mut entry = archive.by_index(i)?;
+        let target = base_dir.join(name);
+        if entry.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut out = fs::File::create(&target).with_context(|| format!("Failed to write {:?}", target))?;
+        std::io::copy(&mut entry, &mut out)?;
+        #[cfg(unix)]
+        if let Some(mode) = entry.unix_mode() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&target, fs::Permissions::from_mode(mode & 0o7777))?;
+        }
+        report.files += 1;
+    }
+
+    // Hand-made archives have no manifest; the default install_dir sits in the base directory
+    let old_base = manifest.map(|m| m.base_dir).or_else(|| {
+        let content = fs::read_to_string(base_dir.join("config.json")).ok()?;
+        let config: serde_json::Value = serde_json::from_str(&content).ok()?;
+        let install_dir = PathBuf::from(config.get("install_dir")?.as_str()?);
+        (install_dir.file_name()? == "versions").then(|| install_dir.parent().map(Path::to_path_buf))?
+    });
+    if let Some(old_base) = old_base.filter(|old| *old != base_dir) {
+        report.rewritten_paths = rewrite_restored_paths(&base_dir, &old_base)?;
+    }
+
+    crate::logging::log_message(
+        "INFO",
+        &format!("Restored {} files, moved {} paths to {:?}", report.files, report.rewritten_paths, base_dir),
+    );
+    Ok(report)
+}
+
+/// Point paths under `old_base` in config.json and each profile's state.json at `base_dir`.
+/// Edits the JSON in place so fields this version doesn't know survive.
+fn rewrite_restored_paths(base_dir: &Path, old_base: &Path) -> anyhow::Result<usize> {
+    let rebase = |value: &mut serde_json::Value| -> bool {
+        let Some(rest) = value.as_str().and_then(|path| Path::new(path).strip_prefix(old_base).ok()) else {
+            return false;
+        };
+        *value = serde_json::Value::String(base_dir.join(rest).to_string_lossy().to_string());
+        true
+    };
+    let mut rewritten = 0;
+
+    let config_path = base_dir.join("config.json");
+    let mut config: serde_json::Value = serde_json::from_str(&fs::read_to_string(&config_path)?)?;
+    for key in ["install_dir", "download_cache"] {
+        if config.get_mut(key).is_some_and(rebase) {
+            rewritten += 1;
+        }
+    }
+    fs::write(&config_path, serde_json::to_string_pretty(&config)?)?;
+
+    let profiles = fs::read_dir(base_dir.join("profiles")).into_iter().flatten().flatten().map(|entry| entry.path());
+    for state_path in std::iter::once(base_dir.to_path_buf()).chain(profiles).map(|dir| dir.join("state.json")) {
+        let Ok(content) = fs::read_to_string(&state_path) else { continue };
+        let mut state: serde_json::Value = serde_json::from_str(&content)
+            .with_context(|| format!("Invalid {:?} in backup", state_path))?;
+        let Some(metadata) = state.get_mut("install_metadata").and_then(|m| m.as_object_mut()) else { continue };
+        for entry in metadata.values_mut() {
+            if entry.get_mut("install_path").is_some_and(rebase) {
+                rewritten += 1;
+            }
+        }
+        fs::write(&state_path, serde_json::to_string_pretty(&state)?)?;
+    }
+    Ok(rewritten)
+}
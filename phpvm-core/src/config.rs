@@ -3,31 +3,245 @@ use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// Layout of this file; older ones are upgraded by `load`. See `schema::migrate_config`.
+    #[serde(default = "crate::schema::legacy_schema_version")]
+    pub schema_version: u32,
     pub install_dir: PathBuf,
+    /// Base version of the active install ("8.3.0"), mirrored here for tools reading
+    /// config.json. `PhpState::active_version` ("8.3.0-ts") is authoritative.
     pub active_version: Option<String>,
     pub download_cache: PathBuf,
     pub providers: Vec<ProviderConfig>,
+    /// Extra base URLs serving the GitHub releases API (`{base}/{owner}/{repo}/releases/latest`),
+    /// tried when api.github.com is rate-limited or down
+    #[serde(default)]
+    pub update_mirrors: Vec<String>,
+    /// Hosts mirroring PHP release archives under the same paths, e.g.
+    /// `https://php-mirror.example.com` for `https://windows.php.net/downloads/releases/...`.
+    /// Tried in order when the original host fails or stalls.
+    #[serde(default)]
+    pub download_mirrors: Vec<String>,
+    /// How `switch` exposes the active version in the `current` directory (Windows)
+    #[serde(default)]
+    pub switch_strategy: SwitchStrategy,
+    /// GitHub token sent with update checks and downloads (raises the API rate limit
+    /// from 60 to 5000 requests/hour). Never logged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_token: Option<String>,
+    /// User-Agent for update checks and downloads, for proxies that filter on it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Whether `switch` adds the `current` directory to PATH. Turn off when PATH is
+    /// managed elsewhere (dotfiles, IT policy); the `current` shim is still updated.
+    #[serde(default = "default_manage_path")]
+    pub manage_path: bool,
+    /// Whether downloaded archives stay in the cache after a successful install (fast
+    /// reinstalls) or are deleted straight away to reclaim disk space
+    #[serde(default = "default_keep_archives_after_install")]
+    pub keep_archives_after_install: bool,
+    /// Threads used to checksum an install after extracting it; 0 means one per CPU
+    #[serde(default)]
+    pub checksum_threads: usize,
+    /// Command run after each successful install. Off unless set; see `PostInstallHook`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_install_hook: Option<PostInstallHook>,
+    /// Variant installed when none is given (true = TS, false = NTS) and preferred by
+    /// branch switches like `switch("8.2")`. None until the user picks one: installs then
+    /// fall back to TS and the GUI asks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_thread_safe: Option<bool>,
+    /// Seconds to wait for a download server to accept the connection
+    #[serde(default = "default_download_connect_timeout_secs")]
+    pub download_connect_timeout_secs: u64,
+    /// Seconds a download may go without receiving data before it fails (so a stalled
+    /// transfer errors out and can be retried instead of hanging)
+    #[serde(default = "default_download_idle_timeout_secs")]
+    pub download_idle_timeout_secs: u64,
+    /// Profile phpvm works in; None is the default profile. `$PHPVM_PROFILE` overrides it
+    /// for one process. See `get_profile_directory`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_profile: Option<String>,
+    /// How `list_available` filters and orders the available versions
+    #[serde(default)]
+    pub available_filter: AvailableFilter,
+    /// When set, new installs keep only these bundled extensions (e.g. ["curl", "mbstring"])
+    /// and delete the other libraries in `ext`. Getting one back means reinstalling.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_extensions: Option<Vec<String>>,
+}
+
+/// Filtering and ordering of the available version list. Missing keys take their
+/// defaults, which show every fetched version, newest first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AvailableFilter {
+    /// Keep versions whose branch is past its end of life
+    pub include_eol: bool,
+    /// Only versions in a major.minor branch that has an installed version
+    pub only_installed_branches: bool,
+    /// At most this many versions, counted after filtering and sorting
+    pub limit: Option<usize>,
+    /// Oldest first instead of newest first
+    pub ascending: bool,
+}
+
+impl Default for AvailableFilter {
+    fn default() -> Self {
+        AvailableFilter {
+            include_eol: true,
+            only_installed_branches: false,
+            limit: None,
+            ascending: false,
+        }
+    }
+}
+
+/// A shell command run after a version is installed, e.g. to copy a shared php.ini or
+/// install Composer.
+///
+/// WARNING: this executes an arbitrary command from config.json with the user's rights.
+///
+/// `{install_dir}` and `{version}` (e.g. "8.3.0-nts") in the command are replaced before it
+/// runs; quote them yourself if paths may contain spaces. Output goes to the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PostInstallHook {
+    /// Run with `cmd /C` on Windows
+    #[serde(default)]
+    pub windows: Option<String>,
+    /// Run with `sh -c` on Linux and macOS
+    #[serde(default)]
+    pub unix: Option<String>,
+    /// Whether a non-zero exit fails (and rolls back) the install, instead of only being logged
+    #[serde(default = "default_fail_on_error")]
+    pub fail_on_error: bool,
+}
+
+fn default_fail_on_error() -> bool {
+    true
+}
+
+impl PostInstallHook {
+    /// The command template for the current OS, if one is set
+    pub fn command_template(&self) -> Option<&str> {
+        let command = if cfg!(target_os = "windows") { &self.windows } else { &self.unix };
+        command.as_deref().map(str::trim).filter(|c| !c.is_empty())
+    }
+
+    /// Fill in the `{install_dir}` and `{version}` placeholders
+    pub fn render(template: &str, install_dir: &std::path::Path, version: &str) -> String {
+        template
+            .replace("{install_dir}", &install_dir.to_string_lossy())
+            .replace("{version}", version)
+    }
+}
+
+fn default_manage_path() -> bool {
+    true
+}
+
+fn default_keep_archives_after_install() -> bool {
+    true
+}
+
+fn default_download_connect_timeout_secs() -> u64 {
+    30
+}
+
+fn default_download_idle_timeout_secs() -> u64 {
+    60
+}
+
+/// What `switch` puts in the `current` directory on Windows. `php.bat` is written and
+/// `current` is added to PATH with every strategy; Unix always symlinks `current/php`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchStrategy {
+    /// Copy php.exe and its DLLs into `current\`. Suits IDEs that want a real
+    /// `current\php.exe` and never re-read the setting: VS Code (`php.validate.executablePath`),
+    /// PhpStorm, NetBeans. Slowest, and antivirus may rescan the copies on every switch.
+    #[default]
+    Copy,
+    /// Point a `current\php` directory junction at the version directory (no admin rights
+    /// needed). Suits IDEs configured once with `current\php\php.exe` (VS Code, PhpStorm,
+    /// Sublime/LSP servers). Switching is instant; tools that resolve reparse points see the
+    /// real version path.
+    Junction,
+    /// Only write `php.bat`. Suits terminal-only use, or IDEs pointed directly at a version
+    /// directory (e.g. PhpStorm with one interpreter per version).
+    BatOnly,
+    /// `BatOnly`, plus a `php.bat` launcher in `%LOCALAPPDATA%\Microsoft\WindowsApps`, which
+    /// is already on every user's PATH, so terminals opened right after the first switch
+    /// find `php` without waiting for the PATH change to reach them. Real app execution
+    /// aliases need a packaged app; this is the batch-file equivalent. When that directory
+    /// is missing or not on PATH, `current` on PATH is all there is, as with `BatOnly`.
+    WindowsAppsAlias,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProviderConfig {
     pub name: String,
     pub url: String,
+    /// Check archives downloaded from this provider against its `sha256sum.txt`, failing
+    /// the install when there's no checksum. Turn off for mirrors that publish none.
     pub verify_checksum: bool,
 }
 
+/// How a configured provider's `url` is scraped for versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    /// Built-in chain: versionlog.com for metadata, then the windows.php.net listing at `url`
+    Official,
+    /// versionlog.com release table at `url`
+    Versionlog,
+    /// Apache-style directory listing of release archives (windows.php.net or a mirror of it)
+    DirectoryListing,
+}
+
+impl ProviderConfig {
+    /// Resolve the scraper to use from the provider name.
+    /// `official`, `versionlog` and `php.net` are built-in names; anything else is
+    /// treated as a custom mirror exposing a windows.php.net style directory listing.
+    pub fn kind(&self) -> ProviderKind {
+        match self.name.to_lowercase().as_str() {
+            "official" => ProviderKind::Official,
+            "versionlog" => ProviderKind::Versionlog,
+            _ => ProviderKind::DirectoryListing,
+        }
+    }
+}
+
+pub fn default_providers() -> Vec<ProviderConfig> {
+    vec![ProviderConfig {
+        name: "official".to_string(),
+        url: "https://windows.php.net/downloads/releases/".to_string(),
+        verify_checksum: true,
+    }]
+}
+
 impl Default for Config {
     fn default() -> Self {
         let base_dir = get_base_directory();
         Config {
+            schema_version: crate::schema::CONFIG_SCHEMA_VERSION,
             install_dir: base_dir.join("versions"),
             active_version: None,
             download_cache: base_dir.join("cache"),
-            providers: vec![ProviderConfig {
-                name: "official".to_string(),
-                url: "https://windows.php.net/downloads/releases/".to_string(),
-                verify_checksum: true,
-            }],
+            providers: default_providers(),
+            update_mirrors: Vec::new(),
+            download_mirrors: Vec::new(),
+            switch_strategy: SwitchStrategy::default(),
+            github_token: None,
+            user_agent: None,
+            manage_path: true,
+            keep_archives_after_install: true,
+            checksum_threads: 0,
+            post_install_hook: None,
+            default_thread_safe: None,
+            download_connect_timeout_secs: default_download_connect_timeout_secs(),
+            download_idle_timeout_secs: default_download_idle_timeout_secs(),
+            active_profile: None,
+            available_filter: AvailableFilter::default(),
+            keep_extensions: None,
         }
     }
 }
@@ -35,14 +249,25 @@ impl Default for Config {
 impl Config {
     pub fn load() -> anyhow::Result<Self> {
         let config_path = get_config_path();
-        if config_path.exists() {
+        let mut config = if config_path.exists() {
             let content = std::fs::read_to_string(&config_path)?;
-            Ok(serde_json::from_str(&content)?)
+            let mut value: serde_json::Value = serde_json::from_str(&content)?;
+            let migrated = crate::schema::migrate_config(&mut value, &serde_json::to_value(Config::default())?)?;
+            let config: Config = serde_json::from_value(value)?;
+            if migrated {
+                config.save()?;
+            }
+            config
         } else {
             let config = Config::default();
             config.save()?;
-            Ok(config)
+            config
+        };
+        // Other profiles keep their versions inside the profile directory
+        if get_active_profile() != DEFAULT_PROFILE {
+            config.install_dir = get_profile_directory().join("versions");
         }
+        Ok(config)
     }
 
     pub fn save(&self) -> anyhow::Result<()> {
@@ -50,16 +275,71 @@ impl Config {
         if let Some(parent) = config_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        let content = serde_json::to_string_pretty(self)?;
+        let mut to_save = self.clone();
+        if get_active_profile() != DEFAULT_PROFILE {
+            // `install_dir` was swapped for the profile's by `load`; keep the configured one
+            to_save.install_dir = profile_install_dir(DEFAULT_PROFILE);
+        }
+        let content = serde_json::to_string_pretty(&to_save)?;
         std::fs::write(&config_path, content)?;
         Ok(())
     }
 }
 
+/// Environment variable that relocates all phpvm data (portable mode, hermetic tests)
+pub const PHPVM_HOME_ENV: &str = "PHPVM_HOME";
+
+/// Where the base directory came from, in order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BaseDirSource {
+    /// `$PHPVM_HOME`
+    PhpvmHome,
+    /// `<local data dir>/phpvm`, e.g. %LOCALAPPDATA%\phpvm or ~/.local/share/phpvm
+    DataLocalDir,
+    /// `~/.phpvm`, when the platform has no local data dir
+    HomeDir,
+    /// `<temp dir>/phpvm`, when there's no home directory either (data may not persist)
+    TempDir,
+}
+
+/// Pick the base directory from the candidates, first available wins
+pub fn pick_base_directory(
+    phpvm_home: Option<std::ffi::OsString>,
+    data_local_dir: Option<PathBuf>,
+    home_dir: Option<PathBuf>,
+) -> (PathBuf, BaseDirSource) {
+    if let Some(home) = phpvm_home.filter(|home| !home.is_empty()) {
+        return (PathBuf::from(home), BaseDirSource::PhpvmHome);
+    }
+    if let Some(dir) = data_local_dir {
+        return (dir.join("phpvm"), BaseDirSource::DataLocalDir);
+    }
+    if let Some(home) = home_dir {
+        return (home.join(".phpvm"), BaseDirSource::HomeDir);
+    }
+    (std::env::temp_dir().join("phpvm"), BaseDirSource::TempDir)
+}
+
+/// The base directory and where it came from, see `pick_base_directory`
+pub fn resolve_base_directory() -> (PathBuf, BaseDirSource) {
+    let resolved = pick_base_directory(std::env::var_os(PHPVM_HOME_ENV), dirs::data_local_dir(), dirs::home_dir());
+
+    // Only worth a note when it's not the usual place. Logged via tracing, since the log
+    // file itself lives under the base directory.
+    static REPORTED: std::sync::Once = std::sync::Once::new();
+    if matches!(resolved.1, BaseDirSource::HomeDir | BaseDirSource::TempDir) {
+        REPORTED.call_once(|| {
+            tracing::warn!("No local data directory available, using {:?} ({:?})", resolved.0, resolved.1)
+        });
+    }
+    resolved
+}
+
+/// `$PHPVM_HOME` when set and non-empty, otherwise `<local data dir>/phpvm`, falling
+/// back to `~/.phpvm` and then `<temp dir>/phpvm`
 pub fn get_base_directory() -> PathBuf {
-    dirs::data_local_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("phpvm")
+    resolve_base_directory().0
 }
 
 pub fn get_config_path() -> PathBuf {
@@ -67,7 +347,104 @@ pub fn get_config_path() -> PathBuf {
 }
 
 pub fn get_state_path() -> PathBuf {
-    get_base_directory().join("state.json")
+    get_profile_directory().join("state.json")
+}
+
+/// Environment variable selecting the profile for one process (`phpvm --profile`)
+pub const PHPVM_PROFILE_ENV: &str = "PHPVM_PROFILE";
+
+/// The profile that uses the base directory itself, as before profiles existed
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Profile names become directory names: 1-64 letters, digits, `-` or `_`
+pub fn validate_profile_name(name: &str) -> anyhow::Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(crate::error::PhpVmError::InvalidArgument(format!(
+            "Invalid profile name {:?}: use 1-64 letters, digits, '-' or '_'",
+            name
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// `$PHPVM_PROFILE` when set, else `active_profile` from config.json, else the default
+/// profile. Invalid names fall back to the default profile.
+pub fn get_active_profile() -> String {
+    let from_env = std::env::var(PHPVM_PROFILE_ENV).ok().filter(|name| !name.is_empty());
+    // Read the field directly: Config::load itself depends on the active profile
+    let name = from_env.or_else(|| {
+        let content = std::fs::read_to_string(get_config_path()).ok()?;
+        let config: serde_json::Value = serde_json::from_str(&content).ok()?;
+        config.get("active_profile")?.as_str().map(str::to_string)
+    });
+    match name {
+        Some(name) if validate_profile_name(&name).is_ok() => name,
+        _ => DEFAULT_PROFILE.to_string(),
+    }
+}
+
+/// Directory of a profile's state.json, `current` shim and (for non-default profiles)
+/// installed versions: the base directory for the default profile, otherwise
+/// `<base>/profiles/<name>`
+pub fn profile_directory(name: &str) -> PathBuf {
+    if name == DEFAULT_PROFILE {
+        get_base_directory()
+    } else {
+        get_base_directory().join("profiles").join(name)
+    }
+}
+
+/// Where a profile's versions are installed: the `install_dir` in config.json for the
+/// default profile, `<profile dir>/versions` for the others (whichever profile is active)
+pub fn profile_install_dir(name: &str) -> PathBuf {
+    if name != DEFAULT_PROFILE {
+        return profile_directory(name).join("versions");
+    }
+    std::fs::read_to_string(get_config_path())
+        .ok()
+        .and_then(|content| serde_json::from_str::<Config>(&content).ok())
+        .map(|stored| stored.install_dir)
+        .unwrap_or_else(|| get_base_directory().join("versions"))
+}
+
+/// `profile_directory` of the active profile
+pub fn get_profile_directory() -> PathBuf {
+    profile_directory(&get_active_profile())
+}
+
+/// The default profile followed by every profile created under `<base>/profiles`, sorted
+pub fn list_profiles() -> Vec<String> {
+    let mut profiles: Vec<String> = std::fs::read_dir(get_base_directory().join("profiles"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name != DEFAULT_PROFILE && validate_profile_name(name).is_ok())
+        .collect();
+    profiles.sort();
+    profiles.insert(0, DEFAULT_PROFILE.to_string());
+    profiles
+}
+
+/// Create an empty profile. Fails if it already exists.
+pub fn create_profile(name: &str) -> anyhow::Result<PathBuf> {
+    validate_profile_name(name)?;
+    if list_profiles().iter().any(|profile| profile == name) {
+        anyhow::bail!("Profile {} already exists", name);
+    }
+    let dir = profile_directory(name);
+    std::fs::create_dir_all(dir.join("versions"))?;
+    Ok(dir)
+}
+
+/// Local usage counters, see `stats::UsageStats`
+pub fn get_stats_path() -> PathBuf {
+    get_base_directory().join("stats.json")
 }
 
 pub fn get_log_path() -> PathBuf {
@@ -80,6 +457,29 @@ mod tests {
     use std::fs;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_pick_base_directory_order() {
+        let data = Some(PathBuf::from("/data"));
+        let home = Some(PathBuf::from("/home/user"));
+
+        assert_eq!(
+            pick_base_directory(Some("/portable".into()), data.clone(), home.clone()),
+            (PathBuf::from("/portable"), BaseDirSource::PhpvmHome)
+        );
+        // An empty PHPVM_HOME counts as unset
+        assert_eq!(
+            pick_base_directory(Some("".into()), data.clone(), home.clone()),
+            (PathBuf::from("/data/phpvm"), BaseDirSource::DataLocalDir)
+        );
+        assert_eq!(
+            pick_base_directory(None, None, home),
+            (PathBuf::from("/home/user/.phpvm"), BaseDirSource::HomeDir)
+        );
+        let (dir, source) = pick_base_directory(None, None, None);
+        assert_eq!(source, BaseDirSource::TempDir);
+        assert_eq!(dir, std::env::temp_dir().join("phpvm"));
+    }
+
     #[test]
     fn test_config_default() {
         let config = Config::default();
@@ -116,6 +516,80 @@ mod tests {
         assert_eq!(loaded.providers.len(), 1);
     }
 
+    #[test]
+    fn test_config_without_update_mirrors_loads() {
+        // Configs written before `update_mirrors` existed must still load
+        let content = r#"{
+            "install_dir": "/tmp/phpvm/versions",
+            "active_version": null,
+            "download_cache": "/tmp/phpvm/cache",
+            "providers": []
+        }"#;
+        let loaded: Config = serde_json::from_str(content).unwrap();
+        assert!(loaded.update_mirrors.is_empty());
+        assert!(loaded.download_mirrors.is_empty());
+        assert_eq!(loaded.available_filter, AvailableFilter::default());
+        assert_eq!(loaded.switch_strategy, SwitchStrategy::Copy);
+        assert!(loaded.github_token.is_none());
+        assert!(loaded.user_agent.is_none());
+        assert!(loaded.manage_path);
+        assert!(loaded.keep_archives_after_install);
+        assert!(loaded.post_install_hook.is_none());
+
+        // Unset optional settings aren't written out, so the token key only appears when set
+        let saved = serde_json::to_string(&loaded).unwrap();
+        assert!(!saved.contains("github_token"));
+    }
+
+    #[test]
+    fn test_default_thread_safe_round_trip() {
+        let mut config: Config = serde_json::from_str(
+            r#"{ "install_dir": "/v", "active_version": null, "download_cache": "/c", "providers": [] }"#,
+        )
+        .unwrap();
+        assert_eq!(config.default_thread_safe, None);
+        assert!(!serde_json::to_string(&config).unwrap().contains("default_thread_safe"));
+
+        config.default_thread_safe = Some(false);
+        let loaded: Config = serde_json::from_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(loaded.default_thread_safe, Some(false));
+    }
+
+    #[test]
+    fn test_post_install_hook() {
+        let hook: PostInstallHook = serde_json::from_str(
+            r#"{ "windows": "copy C:\\shared\\php.ini \"{install_dir}\"", "unix": "cp ~/php.ini '{install_dir}/'" }"#,
+        )
+        .unwrap();
+        assert!(hook.fail_on_error);
+        let template = hook.command_template().unwrap();
+        assert!(template.contains("{install_dir}"));
+        assert_eq!(
+            PostInstallHook::render("setup {version} {install_dir}", std::path::Path::new("/phpvm/versions/php-8.3.0-nts"), "8.3.0-nts"),
+            "setup 8.3.0-nts /phpvm/versions/php-8.3.0-nts"
+        );
+
+        let empty = PostInstallHook { windows: Some(" ".to_string()), unix: None, fail_on_error: false };
+        assert_eq!(empty.command_template(), None);
+    }
+
+    #[test]
+    fn test_validate_profile_name() {
+        for name in ["client-a", "acme_2024", "default", "X"] {
+            assert!(validate_profile_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", "../escape", "a/b", "with space", "dot.name", &"x".repeat(65)] {
+            assert!(validate_profile_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_switch_strategy_serialization() {
+        assert_eq!(serde_json::to_string(&SwitchStrategy::BatOnly).unwrap(), "\"bat_only\"");
+        let strategy: SwitchStrategy = serde_json::from_str("\"junction\"").unwrap();
+        assert_eq!(strategy, SwitchStrategy::Junction);
+    }
+
     #[test]
     fn test_get_base_directory() {
         let base = get_base_directory();
@@ -156,4 +630,19 @@ mod tests {
         assert_eq!(provider.url, "https://example.com");
         assert!(provider.verify_checksum);
     }
+
+    #[test]
+    fn test_provider_kind() {
+        let provider = |name: &str| ProviderConfig {
+            name: name.to_string(),
+            url: "https://example.com/".to_string(),
+            verify_checksum: false,
+        };
+
+        assert_eq!(provider("official").kind(), ProviderKind::Official);
+        assert_eq!(provider("versionlog").kind(), ProviderKind::Versionlog);
+        assert_eq!(provider("VersionLog").kind(), ProviderKind::Versionlog);
+        assert_eq!(provider("php.net").kind(), ProviderKind::DirectoryListing);
+        assert_eq!(provider("internal-mirror").kind(), ProviderKind::DirectoryListing);
+    }
 }
@@ -0,0 +1,97 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Today's UTC date as "YYYY-MM-DD"
+pub fn today_iso() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Whether an ISO date ("YYYY-MM-DD") is strictly before `today` (also ISO)
+pub fn is_before(date: &str, today: &str) -> bool {
+    // ISO dates compare correctly as strings
+    date < today
+}
+
+/// Days from `from` to `to` (both "YYYY-MM-DD"), negative if `to` is earlier.
+/// None if either date doesn't parse.
+pub fn days_between(from: &str, to: &str) -> Option<i64> {
+    Some(days_from_iso(to)? - days_from_iso(from)?)
+}
+
+fn days_from_iso(date: &str) -> Option<i64> {
+    let mut parts = date.splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u32 = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+/// Inverse of `civil_from_days`: days since 1970-01-01 for a civil date
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Convert days since 1970-01-01 to a (year, month, day) civil date
+/// (Howard Hinnant's algorithm, valid for the proleptic Gregorian calendar)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(20_088), (2024, 12, 31));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn test_days_between() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2024, 12, 31), 20_088);
+        assert_eq!(days_between("2025-12-31", "2026-01-01"), Some(1));
+        assert_eq!(days_between("2026-01-01", "2025-12-31"), Some(-1));
+        assert_eq!(days_between("2024-02-28", "2024-03-01"), Some(2));
+        assert_eq!(days_between("2024-01-01", "not a date"), None);
+    }
+
+    #[test]
+    fn test_today_iso_format() {
+        let today = today_iso();
+        assert_eq!(today.len(), 10);
+        assert_eq!(today.matches('-').count(), 2);
+    }
+
+    #[test]
+    fn test_is_before() {
+        assert!(is_before("2025-12-31", "2026-01-01"));
+        assert!(!is_before("2026-01-01", "2026-01-01"));
+        assert!(!is_before("2027-12-31", "2026-01-01"));
+    }
+}
@@ -1,88 +1,156 @@
 use crate::config;
+use crate::error::PhpVmError;
 use crate::logging;
 use anyhow::Context;
 use sha2::{Digest, Sha256};
 use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use futures::StreamExt;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
-use std::time::{Instant, Duration};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Instant, Duration, SystemTime};
+
+/// Where `Downloader::download_file` put the file and whether it was already cached
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadOutcome {
+    pub path: PathBuf,
+    pub from_cache: bool,
+}
+
+/// What a cache cleanup deleted
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct CacheCleanReport {
+    /// Cache file names
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+/// Called with (bytes downloaded, total bytes, speed in MB/s) during a download
+pub type ProgressCallback = Box<dyn FnMut(u64, u64, f64) + Send + Sync>;
 
 pub struct Downloader {
     client: reqwest::Client,
     cache_dir: PathBuf,
+    /// Longest a download may go without receiving any data before it's abandoned
+    idle_timeout: Duration,
 }
 
 impl Downloader {
+    /// Downloader using the timeouts from config.json
     pub fn new() -> anyhow::Result<Self> {
+        let config = config::Config::load()?;
+        Self::with_timeouts(
+            Duration::from_secs(config.download_connect_timeout_secs),
+            Duration::from_secs(config.download_idle_timeout_secs),
+        )
+    }
+
+    pub fn with_timeouts(connect_timeout: Duration, idle_timeout: Duration) -> anyhow::Result<Self> {
         let cache_dir = config::get_base_directory().join("cache");
         fs::create_dir_all(&cache_dir)?;
 
         Ok(Downloader {
             client: reqwest::Client::builder()
                 .user_agent("phpvm/0.1.0")
+                .connect_timeout(connect_timeout)
                 .build()?,
             cache_dir,
+            idle_timeout,
         })
     }
 
+    /// Stable cache file name for a URL: the first 128 bits of its SHA256, hex-encoded.
+    /// Anything that needs to map cache files back to URLs must use this.
+    pub fn cache_key(url: &str) -> String {
+        let hash = Sha256::digest(url.as_bytes());
+        hex::encode(&hash[..16])
+    }
+
+    /// Cache key used by older releases. `DefaultHasher` isn't stable across
+    /// Rust versions, so it's only kept to migrate existing cache files.
+    fn legacy_cache_key(url: &str) -> String {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        format!("{:x}", hasher.finish())
+    }
+
+    /// Rename a file cached under the legacy key for `url` to its stable key.
+    /// Returns true if a file was migrated.
+    pub fn migrate_legacy_entry(cache_dir: &Path, url: &str) -> std::io::Result<bool> {
+        let legacy_path = cache_dir.join(Self::legacy_cache_key(url));
+        let new_path = cache_dir.join(Self::cache_key(url));
+        if !legacy_path.is_file() || new_path.exists() {
+            return Ok(false);
+        }
+        fs::rename(&legacy_path, &new_path)?;
+        logging::log_message("INFO", &format!("Migrated cached file {:?} to {:?}", legacy_path, new_path));
+        Ok(true)
+    }
+
+    /// Cache path for `url`, after migrating any legacy entry. The file may not exist.
+    pub fn cached_path(&self, url: &str) -> PathBuf {
+        if let Err(e) = Self::migrate_legacy_entry(&self.cache_dir, url) {
+            logging::log_message("WARN", &format!("Failed to migrate legacy cache entry for {}: {}", url, e));
+        }
+        self.cache_dir.join(Self::cache_key(url))
+    }
+
+    /// Whether `download_file(url, None, _)` would be served from the cache
+    pub fn is_cached(&self, url: &str) -> bool {
+        self.cached_path(url).is_file()
+    }
+
+    /// Download `url` into the cache, or reuse the cached copy. The progress callback
+    /// only fires for live downloads; check `DownloadOutcome::from_cache` for cache hits.
     pub async fn download_file(
         &self,
         url: &str,
         expected_checksum: Option<&str>,
         mut progress_callback: Option<Box<dyn FnMut(u64, u64, f64) + Send + Sync>>,
-    ) -> anyhow::Result<PathBuf> {
-        let mut hasher = DefaultHasher::new();
-        url.hash(&mut hasher);
-        let url_hash = format!("{:x}", hasher.finish());
-        let cache_path = self.cache_dir.join(&url_hash);
+    ) -> anyhow::Result<DownloadOutcome> {
+        let cache_path = self.cached_path(url);
 
         if cache_path.exists() {
-            if let Some(checksum) = expected_checksum {
-                let actual = self.calculate_checksum(&cache_path).await?;
-                if actual == checksum {
-                    // File is cached - emit progress event with full download info
-                    if let Some(callback) = &mut progress_callback {
-                        let file_size = fs::metadata(&cache_path)
-                            .map(|m| m.len())
-                            .unwrap_or(0);
-                        // Emit cached file info: downloaded = total (100% complete)
-                        callback(file_size, file_size, 0.0); // Speed is 0 for cached files
-                    }
-                    return Ok(cache_path);
-                }
-            } else {
-                // File is cached - emit progress event with full download info
-                if let Some(callback) = &mut progress_callback {
-                    let file_size = fs::metadata(&cache_path)
-                        .map(|m| m.len())
-                        .unwrap_or(0);
-                    // Emit cached file info: downloaded = total (100% complete)
-                    callback(file_size, file_size, 0.0); // Speed is 0 for cached files
-                }
-                return Ok(cache_path);
+            let usable = match expected_checksum {
+                Some(checksum) => self.calculate_checksum(&cache_path).await? == checksum,
+                None => true,
+            };
+            if usable {
+                logging::log_message("INFO", &format!("Using cached file {:?} for {}", cache_path, url));
+                return Ok(DownloadOutcome { path: cache_path, from_cache: true });
             }
         }
 
         tracing::info!("Downloading from: {}", url);
         logging::log_message("INFO", &format!("Downloading from: {}", url));
         
-        let response = self
-            .client
-            .get(url)
-            .send()
+        // No overall timeout: a slow link may legitimately take long for a 30 MB archive,
+        // so only waiting for data (headers or the next chunk) is bounded
+        let stalled = || {
+            PhpVmError::Network(format!(
+                "Download stalled: no data from {} for {} seconds",
+                url,
+                self.idle_timeout.as_secs()
+            ))
+        };
+        let response = tokio::time::timeout(self.idle_timeout, self.client.get(url).send())
             .await
+            .map_err(|_| stalled())?
             .with_context(|| format!("Failed to send request to: {}", url))?;
 
         let status = response.status();
         if !status.is_success() {
-            anyhow::bail!("HTTP error {} when downloading from: {}", status, url);
+            return Err(PhpVmError::Network(format!("HTTP error {} when downloading from: {}", status, url)).into());
         }
 
         let total_size = response.content_length();
         logging::log_message("INFO", &format!("Download size: {} bytes", total_size.unwrap_or(0)));
+        if let Some(total) = total_size {
+            crate::platform::ensure_space(&self.cache_dir, total)?;
+        }
         
         let mut file = fs::File::create(&cache_path)
             .with_context(|| format!("Failed to create cache file: {:?}", cache_path))?;
@@ -95,8 +163,22 @@ impl Downloader {
         let mut last_downloaded = 0u64;
         const UPDATE_INTERVAL: Duration = Duration::from_millis(100); // Update every 100ms
 
-        while let Some(item) = stream.next().await {
-            let chunk = item.with_context(|| "Error while downloading chunk")?;
+        loop {
+            // A partial file must not be mistaken for a cached archive later
+            let chunk = match tokio::time::timeout(self.idle_timeout, stream.next()).await {
+                Ok(Some(Ok(chunk))) => chunk,
+                Ok(None) => break,
+                Ok(Some(Err(e))) => {
+                    drop(file);
+                    let _ = fs::remove_file(&cache_path);
+                    return Err(anyhow::Error::new(e).context("Error while downloading chunk"));
+                }
+                Err(_) => {
+                    drop(file);
+                    let _ = fs::remove_file(&cache_path);
+                    return Err(stalled().into());
+                }
+            };
             file.write_all(&chunk)
                 .with_context(|| "Failed to write chunk to file")?;
             downloaded += chunk.len() as u64;
@@ -129,20 +211,22 @@ impl Downloader {
         }
         
         logging::log_message("INFO", &format!("Download completed: {} bytes saved to {:?}", downloaded, cache_path));
+        crate::stats::record(crate::stats::UsageEvent::Downloaded(downloaded));
 
         if let Some(checksum) = expected_checksum {
             let actual = self.calculate_checksum(&cache_path).await?;
             if actual != checksum {
                 fs::remove_file(&cache_path)?;
-                anyhow::bail!(
+                return Err(PhpVmError::ChecksumMismatch(format!(
                     "Checksum mismatch: expected {}, got {}",
                     checksum,
                     actual
-                );
+                ))
+                .into());
             }
         }
 
-        Ok(cache_path)
+        Ok(DownloadOutcome { path: cache_path, from_cache: false })
     }
 
     async fn calculate_checksum(&self, path: &PathBuf) -> anyhow::Result<String> {
@@ -153,3 +237,272 @@ impl Downloader {
         Ok(hex::encode(hash))
     }
 }
+
+fn cache_leases() -> &'static Mutex<HashMap<PathBuf, usize>> {
+    static LEASES: OnceLock<Mutex<HashMap<PathBuf, usize>>> = OnceLock::new();
+    LEASES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Marks a cache file as in use by an install, so another install finishing at the
+/// same time doesn't delete it underneath this one. Released on drop.
+pub struct CacheLease {
+    path: PathBuf,
+}
+
+impl CacheLease {
+    pub fn acquire(path: PathBuf) -> Self {
+        *cache_leases().lock().unwrap().entry(path.clone()).or_insert(0) += 1;
+        CacheLease { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Release the lease and delete the file, unless another lease still holds it.
+    /// Returns whether the file was deleted.
+    pub fn release_and_delete(self) -> std::io::Result<bool> {
+        // Hold the lock while deleting so no one can take a lease in between
+        let leases = cache_leases().lock().unwrap();
+        if leases.get(&self.path).copied().unwrap_or(0) > 1 {
+            return Ok(false);
+        }
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for CacheLease {
+    fn drop(&mut self) {
+        let mut leases = cache_leases().lock().unwrap();
+        if let Some(count) = leases.get_mut(&self.path) {
+            *count -= 1;
+            if *count == 0 {
+                leases.remove(&self.path);
+            }
+        }
+    }
+}
+
+/// Delete the archives in `cache_dir` whose name isn't in `keep`. Subdirectories (cached
+/// version lists, changelogs) and files an install holds a lease on are left alone.
+pub fn remove_cache_files_except(cache_dir: &Path, keep: &HashSet<String>) -> anyhow::Result<CacheCleanReport> {
+    let mut report = CacheCleanReport::default();
+    let entries = match fs::read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read cache directory {:?}", cache_dir)),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() || keep.contains(&name) {
+            continue;
+        }
+        let path = entry.path();
+        // Same lock as `CacheLease::release_and_delete`, so no install can take it meanwhile
+        let leases = cache_leases().lock().unwrap();
+        if leases.contains_key(&path) {
+            continue;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => {
+                report.freed_bytes += metadata.len();
+                report.removed.push(name);
+            }
+            Err(e) => logging::log_message("WARN", &format!("Failed to remove cached file {:?}: {}", path, e)),
+        }
+    }
+    report.removed.sort();
+    Ok(report)
+}
+
+/// Where the SHA256 a cached archive was verified against is recorded, so the archive
+/// can be reused without fetching the published checksum again (e.g. offline). Kept in
+/// a `checksums` subdirectory, which cache cleaning leaves alone.
+fn verified_checksum_path(archive: &Path) -> Option<PathBuf> {
+    Some(archive.parent()?.join("checksums").join(archive.file_name()?))
+}
+
+/// Record that `archive` matched its published SHA256 `checksum`
+pub fn record_verified_checksum(archive: &Path, checksum: &str) -> anyhow::Result<()> {
+    let path = verified_checksum_path(archive).with_context(|| format!("No cache directory for {:?}", archive))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    }
+    fs::write(&path, checksum).with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// The SHA256 `archive` was verified against when it was downloaded, if recorded
+pub fn verified_checksum(archive: &Path) -> Option<String> {
+    let recorded = fs::read_to_string(verified_checksum_path(archive)?).ok()?;
+    Some(recorded.trim().to_string()).filter(|hash| !hash.is_empty())
+}
+
+/// Size and modification time, used to tell whether a file changed since it was hashed
+type FileStamp = (u64, SystemTime);
+
+fn sha256_memo() -> &'static Mutex<HashMap<PathBuf, (FileStamp, String)>> {
+    static MEMO: OnceLock<Mutex<HashMap<PathBuf, (FileStamp, String)>>> = OnceLock::new();
+    MEMO.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// Hex-encoded SHA256 of a file's contents
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).with_context(|| format!("Failed to read {:?}", path))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// SHA256 computed earlier by `file_sha256`, if the file's size and mtime are unchanged
+pub fn known_sha256(path: &Path) -> Option<String> {
+    let stamp = file_stamp(path)?;
+    let memo = sha256_memo().lock().unwrap();
+    memo.get(path).filter(|(known, _)| *known == stamp).map(|(_, hash)| hash.clone())
+}
+
+/// SHA256 of a file's contents, hashed on a blocking thread (archives are tens of MB)
+/// and remembered until the file's size or mtime changes
+pub async fn file_sha256(path: PathBuf) -> anyhow::Result<String> {
+    if let Some(hash) = known_sha256(&path) {
+        return Ok(hash);
+    }
+    let stamp = file_stamp(&path);
+    let hash = {
+        let path = path.clone();
+        tokio::task::spawn_blocking(move || sha256_file(&path))
+            .await
+            .context("Hashing task failed")??
+    };
+    if let Some(stamp) = stamp {
+        sha256_memo().lock().unwrap().insert(path, (stamp, hash.clone()));
+    }
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_remove_cache_files_except() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path();
+        for (name, content) in [("kept", "a"), ("orphan", "bcd"), ("leased", "ef")] {
+            fs::write(cache_dir.join(name), content).unwrap();
+        }
+        fs::create_dir_all(cache_dir.join("versions")).unwrap();
+        let _lease = CacheLease::acquire(cache_dir.join("leased"));
+
+        let keep: HashSet<String> = ["kept".to_string()].into();
+        let report = remove_cache_files_except(cache_dir, &keep).unwrap();
+        assert_eq!(report, CacheCleanReport { removed: vec!["orphan".to_string()], freed_bytes: 3 });
+        assert!(cache_dir.join("kept").exists());
+        assert!(cache_dir.join("leased").exists());
+        assert!(cache_dir.join("versions").is_dir());
+
+        let missing = remove_cache_files_except(&cache_dir.join("nope"), &keep).unwrap();
+        assert_eq!(missing, CacheCleanReport::default());
+    }
+
+    #[test]
+    fn test_cache_key_is_stable() {
+        let url = "https://windows.php.net/downloads/releases/php-8.3.0-Win32-vs16-x64.zip";
+        let key = Downloader::cache_key(url);
+        assert_eq!(key.len(), 32);
+        assert_eq!(key, hex::encode(&Sha256::digest(url.as_bytes())[..16]));
+        assert_ne!(key, Downloader::cache_key("https://example.com/other.zip"));
+    }
+
+    #[tokio::test]
+    async fn test_file_sha256_is_memoized_until_file_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("archive");
+        fs::write(&path, b"archive").unwrap();
+
+        assert_eq!(known_sha256(&path), None);
+        let hash = file_sha256(path.clone()).await.unwrap();
+        assert_eq!(hash, hex::encode(Sha256::digest(b"archive")));
+        assert_eq!(known_sha256(&path), Some(hash));
+
+        // A different size invalidates the remembered hash
+        fs::write(&path, b"other archive").unwrap();
+        assert_eq!(known_sha256(&path), None);
+        assert_eq!(file_sha256(path).await.unwrap(), hex::encode(Sha256::digest(b"other archive")));
+    }
+
+    #[test]
+    fn test_cache_lease_keeps_shared_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("archive");
+        fs::write(&path, b"archive").unwrap();
+
+        let first = CacheLease::acquire(path.clone());
+        let second = CacheLease::acquire(path.clone());
+        assert!(!first.release_and_delete().unwrap());
+        assert!(path.exists());
+
+        assert!(second.release_and_delete().unwrap());
+        assert!(!path.exists());
+        assert!(!cache_leases().lock().unwrap().contains_key(&path));
+    }
+
+    #[test]
+    fn test_migrate_legacy_entry() {
+        let temp_dir = TempDir::new().unwrap();
+        let url = "https://windows.php.net/downloads/releases/php-8.3.0-Win32-vs16-x64.zip";
+        let legacy_path = temp_dir.path().join(Downloader::legacy_cache_key(url));
+        fs::write(&legacy_path, b"archive").unwrap();
+
+        assert!(Downloader::migrate_legacy_entry(temp_dir.path(), url).unwrap());
+        assert!(!legacy_path.exists());
+        let new_path = temp_dir.path().join(Downloader::cache_key(url));
+        assert_eq!(fs::read(new_path).unwrap(), b"archive");
+
+        // Nothing left to migrate
+        assert!(!Downloader::migrate_legacy_entry(temp_dir.path(), url).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cached_download_skips_progress() {
+        let temp_dir = TempDir::new().unwrap();
+        let downloader = Downloader {
+            client: reqwest::Client::new(),
+            cache_dir: temp_dir.path().to_path_buf(),
+            idle_timeout: Duration::from_secs(5),
+        };
+        // Nothing listens here, so only a cache hit can succeed
+        let url = "http://127.0.0.1:9/php-8.3.0-Win32-vs16-x64.zip";
+        assert!(!downloader.is_cached(url));
+        fs::write(temp_dir.path().join(Downloader::cache_key(url)), b"archive").unwrap();
+        assert!(downloader.is_cached(url));
+
+        let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let outcome = downloader
+            .download_file(
+                url,
+                None,
+                Some(Box::new(move |_, _, _| {
+                    counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                })),
+            )
+            .await
+            .unwrap();
+
+        assert!(outcome.from_cache);
+        assert_eq!(outcome.path, temp_dir.path().join(Downloader::cache_key(url)));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,169 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+/// Error categories the frontend can branch on. Each variant carries the
+/// human-readable message shown to the user.
+///
+/// Core code keeps returning `anyhow::Result`; raise one of these (directly or as
+/// a `.context(...)`) where the category matters, and convert at the command
+/// boundary with `PhpVmError::from(anyhow_error)`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PhpVmError {
+    #[error("{0}")]
+    NotInstalled(String),
+    #[error("{0}")]
+    AlreadyInstalled(String),
+    #[error("{0}")]
+    InstallInProgress(String),
+    #[error("{0}")]
+    ActiveVersionInUse(String),
+    #[error("{0}")]
+    InvalidVersion(String),
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("{0}")]
+    Network(String),
+    #[error("{0}")]
+    ChecksumMismatch(String),
+    #[error("{0}")]
+    ExtractionFailed(String),
+    #[error("{0}")]
+    Io(String),
+    #[error("{0}")]
+    UpdateFailed(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+impl PhpVmError {
+    /// Stable identifier for the error category, serialized as `code`
+    pub fn code(&self) -> &'static str {
+        match self {
+            PhpVmError::NotInstalled(_) => "NOT_INSTALLED",
+            PhpVmError::AlreadyInstalled(_) => "ALREADY_INSTALLED",
+            PhpVmError::InstallInProgress(_) => "INSTALL_IN_PROGRESS",
+            PhpVmError::ActiveVersionInUse(_) => "ACTIVE_VERSION_IN_USE",
+            PhpVmError::InvalidVersion(_) => "INVALID_VERSION",
+            PhpVmError::InvalidArgument(_) => "INVALID_ARGUMENT",
+            PhpVmError::Network(_) => "NETWORK",
+            PhpVmError::ChecksumMismatch(_) => "CHECKSUM_MISMATCH",
+            PhpVmError::ExtractionFailed(_) => "EXTRACTION_FAILED",
+            PhpVmError::Io(_) => "IO",
+            PhpVmError::UpdateFailed(_) => "UPDATE_FAILED",
+            PhpVmError::Other(_) => "OTHER",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            PhpVmError::NotInstalled(m)
+            | PhpVmError::AlreadyInstalled(m)
+            | PhpVmError::InstallInProgress(m)
+            | PhpVmError::ActiveVersionInUse(m)
+            | PhpVmError::InvalidVersion(m)
+            | PhpVmError::InvalidArgument(m)
+            | PhpVmError::Network(m)
+            | PhpVmError::ChecksumMismatch(m)
+            | PhpVmError::ExtractionFailed(m)
+            | PhpVmError::Io(m)
+            | PhpVmError::UpdateFailed(m)
+            | PhpVmError::Other(m) => m,
+        }
+    }
+
+    /// Same category, different message
+    fn with_message(&self, message: String) -> Self {
+        match self {
+            PhpVmError::NotInstalled(_) => PhpVmError::NotInstalled(message),
+            PhpVmError::AlreadyInstalled(_) => PhpVmError::AlreadyInstalled(message),
+            PhpVmError::InstallInProgress(_) => PhpVmError::InstallInProgress(message),
+            PhpVmError::ActiveVersionInUse(_) => PhpVmError::ActiveVersionInUse(message),
+            PhpVmError::InvalidVersion(_) => PhpVmError::InvalidVersion(message),
+            PhpVmError::InvalidArgument(_) => PhpVmError::InvalidArgument(message),
+            PhpVmError::Network(_) => PhpVmError::Network(message),
+            PhpVmError::ChecksumMismatch(_) => PhpVmError::ChecksumMismatch(message),
+            PhpVmError::ExtractionFailed(_) => PhpVmError::ExtractionFailed(message),
+            PhpVmError::Io(_) => PhpVmError::Io(message),
+            PhpVmError::UpdateFailed(_) => PhpVmError::UpdateFailed(message),
+            PhpVmError::Other(_) => PhpVmError::Other(message),
+        }
+    }
+}
+
+/// Serialized as `{ "code": "NOT_INSTALLED", "message": "..." }`
+impl Serialize for PhpVmError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("PhpVmError", 2)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", self.message())?;
+        state.end()
+    }
+}
+
+/// Keeps the outermost message (what users saw before) and takes the category from
+/// the first `PhpVmError` found in the context chain, falling back to the kind of
+/// the underlying error.
+impl From<anyhow::Error> for PhpVmError {
+    fn from(e: anyhow::Error) -> Self {
+        let message = e.to_string();
+
+        if let Some(typed) = e.downcast_ref::<PhpVmError>() {
+            return typed.with_message(message);
+        }
+        for cause in e.chain() {
+            if let Some(typed) = cause.downcast_ref::<PhpVmError>() {
+                return typed.with_message(message);
+            }
+            if cause.downcast_ref::<reqwest::Error>().is_some() {
+                return PhpVmError::Network(message);
+            }
+            if cause.downcast_ref::<std::io::Error>().is_some() {
+                return PhpVmError::Io(message);
+            }
+        }
+        PhpVmError::Other(message)
+    }
+}
+
+impl From<String> for PhpVmError {
+    fn from(message: String) -> Self {
+        PhpVmError::Other(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_serializes_code_and_message() {
+        let error = PhpVmError::NotInstalled("Version 8.3.0 is not installed".to_string());
+        assert_eq!(
+            serde_json::to_value(&error).unwrap(),
+            serde_json::json!({ "code": "NOT_INSTALLED", "message": "Version 8.3.0 is not installed" })
+        );
+    }
+
+    #[test]
+    fn test_from_anyhow_keeps_category_and_outer_message() {
+        let inner: anyhow::Result<()> = Err(PhpVmError::ChecksumMismatch("Checksum mismatch".to_string()).into());
+        let error = PhpVmError::from(inner.context("Failed to download PHP archive").unwrap_err());
+        assert_eq!(error, PhpVmError::ChecksumMismatch("Failed to download PHP archive".to_string()));
+
+        let typed_context: anyhow::Result<()> = Err(anyhow::anyhow!("invalid zip header"))
+            .context(PhpVmError::ExtractionFailed("Failed to extract archive".to_string()));
+        let error = PhpVmError::from(typed_context.unwrap_err());
+        assert_eq!(error.code(), "EXTRACTION_FAILED");
+        assert_eq!(error.message(), "Failed to extract archive");
+    }
+
+    #[test]
+    fn test_from_anyhow_classifies_untyped_errors() {
+        let io_error = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "denied");
+        let error = PhpVmError::from(anyhow::Error::new(io_error).context("Failed to create directory"));
+        assert_eq!(error, PhpVmError::Io("Failed to create directory".to_string()));
+
+        let error = PhpVmError::from(anyhow::anyhow!("something else"));
+        assert_eq!(error.code(), "OTHER");
+    }
+}
@@ -0,0 +1,425 @@
+use crate::error::PhpVmError;
+use crate::provider::Provider;
+use crate::state::InstallMetadata;
+use crate::version::PhpVersion;
+use anyhow::Context;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// PE machine type for x64 images
+const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+const IMAGE_FILE_MACHINE_I386: u16 = 0x014c;
+
+/// The build an extension DLL has to match: PHP major.minor, thread safety and compiler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionTarget {
+    pub major: u8,
+    pub minor: u8,
+    pub thread_safe: bool,
+    /// Lowercase compiler tag, e.g. "vc15", "vs16", "vs17"
+    pub compiler_tag: String,
+}
+
+impl ExtensionTarget {
+    /// Derive the target from an install's metadata: version and variant from
+    /// "8.3.0-nts", compiler tag from the archive URL it was installed from
+    pub fn from_metadata(metadata: &InstallMetadata) -> anyhow::Result<Self> {
+        let (base, variant) = metadata
+            .version
+            .rsplit_once('-')
+            .filter(|(_, variant)| *variant == "ts" || *variant == "nts")
+            .ok_or_else(|| PhpVmError::InvalidVersion(format!("Install {} has no TS/NTS variant", metadata.version)))?;
+        let version = PhpVersion::from_string(base)?;
+
+        let compiler_tag = metadata
+            .compiler
+            .clone()
+            .or_else(|| compiler_tag_from_url(&metadata.source))
+            .unwrap_or_else(|| Provider::get_vs_version(version.major, version.minor).to_lowercase());
+
+        Ok(ExtensionTarget {
+            major: version.major,
+            minor: version.minor,
+            thread_safe: variant == "ts",
+            compiler_tag,
+        })
+    }
+}
+
+/// A downloadable PHP extension. Implementations only resolve where the DLL for a
+/// given PHP build lives; downloading, verification and php.ini wiring are shared.
+pub trait ExtensionProvider: Send + Sync {
+    /// Short name used in php.ini, e.g. "xdebug"
+    fn name(&self) -> &'static str;
+
+    /// Whether the DLL is loaded with `zend_extension=` rather than `extension=`
+    fn is_zend_extension(&self) -> bool;
+
+    /// File name the DLL is stored under in the version's `ext` directory
+    fn dll_file_name(&self) -> String {
+        format!("php_{}.dll", self.name())
+    }
+
+    /// URL of the 64-bit Windows DLL built for `target`
+    fn download_url(&self, target: &ExtensionTarget) -> anyhow::Result<String>;
+}
+
+/// Xdebug builds from xdebug.org
+pub struct XdebugProvider;
+
+impl XdebugProvider {
+    /// Newest Xdebug release supporting a PHP branch
+    fn xdebug_version(major: u8, minor: u8) -> Option<&'static str> {
+        match (major, minor) {
+            (7, 2..=4) => Some("3.1.6"),
+            (8, 0) => Some("3.3.2"),
+            (8, 1..=4) => Some("3.4.2"),
+            _ => None,
+        }
+    }
+}
+
+impl ExtensionProvider for XdebugProvider {
+    fn name(&self) -> &'static str {
+        "xdebug"
+    }
+
+    fn is_zend_extension(&self) -> bool {
+        true
+    }
+
+    fn download_url(&self, target: &ExtensionTarget) -> anyhow::Result<String> {
+        let xdebug_version = Self::xdebug_version(target.major, target.minor).ok_or_else(|| {
+            PhpVmError::InvalidVersion(format!(
+                "No Xdebug build is known for PHP {}.{}",
+                target.major, target.minor
+            ))
+        })?;
+        let nts = if target.thread_safe { "" } else { "-nts" };
+        Ok(format!(
+            "https://xdebug.org/files/php_xdebug-{}-{}.{}-{}{}-x86_64.dll",
+            xdebug_version, target.major, target.minor, target.compiler_tag, nts
+        ))
+    }
+}
+
+/// Pull the compiler tag ("vs16", "vc15", ...) out of a windows.php.net archive URL
+pub fn compiler_tag_from_url(url: &str) -> Option<String> {
+    let re = Regex::new(r"(?i)-(v[cs]\d+)-(?:x64|x86)").ok()?;
+    re.captures(url).map(|c| c[1].to_lowercase())
+}
+
+/// Read the target machine from a PE image's COFF header
+pub fn dll_machine(bytes: &[u8]) -> Option<u16> {
+    if bytes.get(0..2)? != b"MZ" {
+        return None;
+    }
+    let pe_offset = u32::from_le_bytes(bytes.get(0x3c..0x40)?.try_into().ok()?) as usize;
+    if bytes.get(pe_offset..pe_offset + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let machine = bytes.get(pe_offset + 4..pe_offset + 6)?;
+    Some(u16::from_le_bytes([machine[0], machine[1]]))
+}
+
+/// Reject anything that isn't a 64-bit DLL, since phpvm installs x64 builds of PHP
+pub fn ensure_x64_dll(bytes: &[u8]) -> anyhow::Result<()> {
+    match dll_machine(bytes) {
+        Some(IMAGE_FILE_MACHINE_AMD64) => Ok(()),
+        Some(IMAGE_FILE_MACHINE_I386) => anyhow::bail!("Downloaded DLL is 32-bit but PHP is 64-bit"),
+        Some(machine) => anyhow::bail!(
+            "Downloaded DLL targets an unsupported architecture (machine 0x{:04x})",
+            machine
+        ),
+        None => anyhow::bail!("Downloaded file is not a Windows DLL"),
+    }
+}
+
+/// Enable an extension in php.ini content: replaces an existing (possibly commented out)
+/// line for it, otherwise appends one. Other lines are left as they are.
+pub fn enable_in_ini(content: &str, name: &str, dll_path: &str, zend: bool) -> String {
+    let directive = if zend { "zend_extension" } else { "extension" };
+    let enabled_line = format!("{}=\"{}\"", directive, dll_path);
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+
+    let refers_to_extension = |line: &str| {
+        let line = line.trim_start().trim_start_matches(';').trim_start();
+        line.split_once('=')
+            .map(|(key, value)| {
+                key.trim() == directive && value.trim().trim_matches('"').to_lowercase().contains(name)
+            })
+            .unwrap_or(false)
+    };
+
+    let mut replaced = false;
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        if refers_to_extension(line) {
+            if !replaced {
+                lines.push(enabled_line.clone());
+                replaced = true;
+            }
+            // Drop further duplicates so only one copy is loaded
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    if !replaced {
+        lines.push(String::new());
+        lines.push(format!("[{}]", name));
+        lines.push(enabled_line);
+    }
+
+    let mut result = lines.join(newline);
+    result.push_str(newline);
+    result
+}
+
+/// Comment out every `extension=` / `zend_extension=` line in php.ini content that loads
+/// `name`. Other lines, including already commented ones, are left as they are.
+pub fn disable_in_ini(content: &str, name: &str) -> String {
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let loads_extension = |line: &str| {
+        line.split_once('=')
+            .map(|(key, value)| {
+                matches!(key.trim(), "extension" | "zend_extension")
+                    && crate::ini::extension_name(value.trim().trim_matches('"')).eq_ignore_ascii_case(name)
+            })
+            .unwrap_or(false)
+    };
+
+    let mut result = content
+        .lines()
+        .map(|line| if loads_extension(line) { format!(";{}", line) } else { line.to_string() })
+        .collect::<Vec<_>>()
+        .join(newline);
+    result.push_str(newline);
+    result
+}
+
+/// Library named by the last uncommented `extension=` (or `zend_extension=` when `zend`)
+/// line in php.ini content that loads `name`, as written: absolute, or relative to the
+/// extension directory. None if no such line is active.
+pub fn enabled_library(content: &str, name: &str, zend: bool) -> Option<String> {
+    let directive = if zend { "zend_extension" } else { "extension" };
+    content.lines().rev().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        let library = value.split(';').next().unwrap_or(value).trim().trim_matches('"');
+        (key.trim() == directive && crate::ini::extension_name(library).eq_ignore_ascii_case(name))
+            .then(|| library.to_string())
+    })
+}
+
+/// What `strip_extensions` deleted
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StripReport {
+    /// Extension names, e.g. "curl"
+    pub removed: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+/// Delete every extension library in `install_path/ext` whose name isn't in `keep`, and
+/// comment out the lines loading them in php.ini (if there is one). Files in `ext` that
+/// aren't extension libraries (`php_*.dll`, `*.so`) are left alone. A kept name with no
+/// library (a typo would otherwise strip the extension it meant) fails with
+/// InvalidArgument before anything is deleted. There is no undo; getting a removed
+/// extension back means reinstalling the version.
+pub fn strip_extensions(install_path: &Path, keep: &[String]) -> anyhow::Result<StripReport> {
+    if let Some(name) = keep
+        .iter()
+        .find(|name| name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+    {
+        return Err(PhpVmError::InvalidArgument(format!("Invalid extension name {:?}", name)).into());
+    }
+
+    let ext_dir = install_path.join("ext");
+    let mut report = StripReport::default();
+    let mut libraries = Vec::new();
+    match fs::read_dir(&ext_dir) {
+        Ok(entries) => {
+            for entry in entries {
+                let entry = entry?;
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let is_library = (file_name.starts_with("php_") && file_name.ends_with(".dll")) || file_name.ends_with(".so");
+                if is_library && entry.file_type()?.is_file() {
+                    libraries.push((crate::ini::extension_name(&file_name), entry.path()));
+                }
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", ext_dir)),
+    }
+    libraries.sort();
+    if let Some(unknown) = keep
+        .iter()
+        .find(|kept| !libraries.iter().any(|(name, _)| kept.eq_ignore_ascii_case(name)))
+    {
+        return Err(PhpVmError::InvalidArgument(format!("No extension named {:?} in {:?}", unknown, ext_dir)).into());
+    }
+
+    for (name, path) in libraries {
+        if keep.iter().any(|kept| kept.eq_ignore_ascii_case(&name)) {
+            continue;
+        }
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {:?}", path))?;
+        report.freed_bytes += size;
+        report.removed.push(name);
+    }
+
+    let ini_path = install_path.join("php.ini");
+    if !report.removed.is_empty() && ini_path.exists() {
+        let content = fs::read_to_string(&ini_path)?;
+        let stripped = report.removed.iter().fold(content.clone(), |ini, name| disable_in_ini(&ini, name));
+        if stripped != content {
+            fs::write(&ini_path, stripped)?;
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn metadata(version: &str, source: &str) -> InstallMetadata {
+        InstallMetadata {
+            version: version.to_string(),
+            install_path: PathBuf::from("/phpvm/versions"),
+            installed_at: "0".to_string(),
+            checksum: None,
+            source: source.to_string(),
+            compiler: None,
+        }
+    }
+
+    #[test]
+    fn test_target_from_metadata() {
+        let target = ExtensionTarget::from_metadata(&metadata(
+            "8.3.0-nts",
+            "https://windows.php.net/downloads/releases/php-8.3.0-nts-Win32-vs16-x64.zip",
+        ))
+        .unwrap();
+        assert_eq!(
+            target,
+            ExtensionTarget { major: 8, minor: 3, thread_safe: false, compiler_tag: "vs16".to_string() }
+        );
+
+        // Unknown source URL falls back to the compiler PHP ships with
+        let target = ExtensionTarget::from_metadata(&metadata("7.4.33-ts", "http://127.0.0.1/archive")).unwrap();
+        assert_eq!(target.compiler_tag, "vc15");
+        assert!(target.thread_safe);
+
+        // The compiler recorded at install time wins
+        let mut recorded = metadata("8.3.0-ts", "http://127.0.0.1/archive");
+        recorded.compiler = Some("vs17".to_string());
+        assert_eq!(ExtensionTarget::from_metadata(&recorded).unwrap().compiler_tag, "vs17");
+    }
+
+    #[test]
+    fn test_xdebug_download_url() {
+        let provider = XdebugProvider;
+        let target = |major, minor, thread_safe, tag: &str| ExtensionTarget {
+            major,
+            minor,
+            thread_safe,
+            compiler_tag: tag.to_string(),
+        };
+
+        assert_eq!(
+            provider.download_url(&target(8, 3, true, "vs16")).unwrap(),
+            "https://xdebug.org/files/php_xdebug-3.4.2-8.3-vs16-x86_64.dll"
+        );
+        assert_eq!(
+            provider.download_url(&target(8, 4, false, "vs17")).unwrap(),
+            "https://xdebug.org/files/php_xdebug-3.4.2-8.4-vs17-nts-x86_64.dll"
+        );
+        assert_eq!(
+            provider.download_url(&target(7, 4, false, "vc15")).unwrap(),
+            "https://xdebug.org/files/php_xdebug-3.1.6-7.4-vc15-nts-x86_64.dll"
+        );
+        assert!(provider.download_url(&target(5, 6, true, "vc11")).is_err());
+        assert_eq!(provider.dll_file_name(), "php_xdebug.dll");
+    }
+
+    #[test]
+    fn test_dll_machine() {
+        let mut image = vec![0u8; 0x100];
+        image[0..2].copy_from_slice(b"MZ");
+        image[0x3c..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        image[0x80..0x84].copy_from_slice(b"PE\0\0");
+        image[0x84..0x86].copy_from_slice(&IMAGE_FILE_MACHINE_AMD64.to_le_bytes());
+        assert_eq!(dll_machine(&image), Some(IMAGE_FILE_MACHINE_AMD64));
+        assert!(ensure_x64_dll(&image).is_ok());
+
+        image[0x84..0x86].copy_from_slice(&IMAGE_FILE_MACHINE_I386.to_le_bytes());
+        assert!(ensure_x64_dll(&image).unwrap_err().to_string().contains("32-bit"));
+
+        assert_eq!(dll_machine(b"<html>Not Found</html>"), None);
+    }
+
+    #[test]
+    fn test_enable_in_ini() {
+        let ini = "[PHP]\r\nmemory_limit=128M\r\n;zend_extension=xdebug\r\n;extension=curl\r\n";
+        let updated = enable_in_ini(ini, "xdebug", "C:\\php\\ext\\php_xdebug.dll", true);
+        assert_eq!(
+            updated,
+            "[PHP]\r\nmemory_limit=128M\r\nzend_extension=\"C:\\php\\ext\\php_xdebug.dll\"\r\n;extension=curl\r\n"
+        );
+        // Idempotent
+        assert_eq!(enable_in_ini(&updated, "xdebug", "C:\\php\\ext\\php_xdebug.dll", true), updated);
+
+        let appended = enable_in_ini("[PHP]\n", "xdebug", "/ext/php_xdebug.dll", true);
+        assert_eq!(appended, "[PHP]\n\n[xdebug]\nzend_extension=\"/ext/php_xdebug.dll\"\n");
+    }
+
+    #[test]
+    fn test_enabled_library() {
+        let ini = "[PHP]\n;zend_extension=xdebug\nextension=curl\n";
+        assert_eq!(enabled_library(ini, "xdebug", true), None);
+        assert_eq!(enabled_library(ini, "curl", false).as_deref(), Some("curl"));
+
+        let ini = enable_in_ini(ini, "xdebug", "C:\\php\\ext\\php_xdebug.dll", true);
+        assert_eq!(enabled_library(&ini, "xdebug", true).as_deref(), Some("C:\\php\\ext\\php_xdebug.dll"));
+        // Loaded as a zend extension, not a plain one
+        assert_eq!(enabled_library(&ini, "xdebug", false), None);
+    }
+
+    #[test]
+    fn test_strip_extensions_keeps_only_listed() {
+        let install = tempfile::tempdir().unwrap();
+        let ext_dir = install.path().join("ext");
+        fs::create_dir_all(&ext_dir).unwrap();
+        for (name, size) in [("php_curl.dll", 10), ("php_mbstring.dll", 20), ("php_gd.dll", 30), ("php_opcache.dll", 40)] {
+            fs::write(ext_dir.join(name), vec![0u8; size]).unwrap();
+        }
+        fs::write(ext_dir.join("libsodium.dll"), b"dependency").unwrap();
+        fs::write(
+            install.path().join("php.ini"),
+            "extension=curl\nextension=\"C:\\php\\ext\\php_gd.dll\"\n;extension=mbstring\nzend_extension=opcache\n",
+        )
+        .unwrap();
+
+        let report = strip_extensions(install.path(), &["curl".to_string(), "MBSTRING".to_string()]).unwrap();
+        assert_eq!(report, StripReport { removed: vec!["gd".to_string(), "opcache".to_string()], freed_bytes: 70 });
+        assert!(ext_dir.join("php_curl.dll").exists() && ext_dir.join("php_mbstring.dll").exists());
+        assert!(!ext_dir.join("php_gd.dll").exists());
+        // Not an extension, so not ours to judge
+        assert!(ext_dir.join("libsodium.dll").exists());
+        assert_eq!(
+            fs::read_to_string(install.path().join("php.ini")).unwrap(),
+            "extension=curl\n;extension=\"C:\\php\\ext\\php_gd.dll\"\n;extension=mbstring\n;zend_extension=opcache\n"
+        );
+
+        assert_eq!(strip_extensions(install.path(), &["curl".to_string(), "mbstring".to_string()]).unwrap(), StripReport::default());
+        assert!(strip_extensions(install.path(), &["../curl".to_string()]).is_err());
+
+        // A misspelt name fails before deleting anything
+        let error = strip_extensions(install.path(), &["curl".to_string(), "mbstrng".to_string()]).unwrap_err();
+        assert!(matches!(error.downcast_ref::<PhpVmError>(), Some(PhpVmError::InvalidArgument(_))));
+        assert!(ext_dir.join("php_curl.dll").exists() && ext_dir.join("php_mbstring.dll").exists());
+    }
+}
@@ -0,0 +1,222 @@
+use crate::logging;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// VS Code setting pointing the built-in PHP validation at an executable
+pub const VSCODE_PHP_SETTING: &str = "php.validate.executablePath";
+
+/// User settings.json of VS Code: %APPDATA%\Code\User on Windows,
+/// ~/Library/Application Support/Code/User on macOS, ~/.config/Code/User elsewhere
+pub fn vscode_settings_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("Code").join("User").join("settings.json"))
+}
+
+/// Matches `"<key>": <string or null>` in settings.json, which is JSON with comments
+fn setting_regex(key: &str) -> Regex {
+    Regex::new(&format!(r#""{}"\s*:\s*(?:"(?:[^"\\]|\\.)*"|null)"#, regex::escape(key))).unwrap()
+}
+
+/// Set a string setting in settings.json content. Edits the text rather than
+/// re-serializing it, so comments, ordering and other settings are kept.
+pub fn set_string_setting(content: &str, key: &str, value: &str) -> anyhow::Result<String> {
+    let entry = format!("{}: {}", serde_json::to_string(key)?, serde_json::to_string(value)?);
+    let existing = setting_regex(key);
+    if existing.is_match(content) {
+        return Ok(existing.replace(content, regex::NoExpand(&entry)).into_owned());
+    }
+    if content.trim().is_empty() {
+        return Ok(format!("{{\n    {}\n}}\n", entry));
+    }
+
+    let open = content
+        .find('{')
+        .ok_or_else(|| anyhow::anyhow!("settings.json doesn't contain a JSON object"))?;
+    let rest = &content[open + 1..];
+    let separator = if rest.trim_start().starts_with('}') { "\n" } else { "," };
+    Ok(format!("{}\n    {}{}{}", &content[..=open], entry, separator, rest))
+}
+
+/// Value of a string setting in settings.json content; None when it's missing or null
+pub fn string_setting(content: &str, key: &str) -> Option<String> {
+    let found = setting_regex(key).find(content)?.as_str();
+    // The match starts with the quoted key, which the regex matches literally
+    let value = found[key.len() + 2..].trim_start().strip_prefix(':')?;
+    serde_json::from_str::<Option<String>>(value.trim()).ok()?
+}
+
+/// Remove a setting (and its trailing comma) from settings.json content
+pub fn remove_setting(content: &str, key: &str) -> String {
+    let pattern = format!(r"\n?[ \t]*{}[ \t]*,?", setting_regex(key).as_str());
+    Regex::new(&pattern).unwrap().replace(content, "").into_owned()
+}
+
+/// Point VS Code at `php_exe`. The first registration keeps a copy of the file as it
+/// was as `settings.json.phpvm-backup` (never overwritten afterwards) and records the
+/// setting's previous value, which `unregister_from_vscode_at` puts back. Creates
+/// settings.json if VS Code never wrote one.
+pub fn register_with_vscode_at(settings_path: &Path, php_exe: &Path) -> anyhow::Result<()> {
+    let content = match fs::read_to_string(settings_path) {
+        Ok(content) => {
+            let backup = backup_path(settings_path);
+            if !backup.exists() {
+                fs::copy(settings_path, backup)?;
+            }
+            content
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let updated = set_string_setting(&content, VSCODE_PHP_SETTING, &php_exe.to_string_lossy())?;
+    if let Some(parent) = settings_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Registering again must not record phpvm's own value as the user's
+    let record_path = registration_path(settings_path);
+    let previous = match read_registration(&record_path) {
+        Some(record) => record.previous,
+        None => string_setting(&content, VSCODE_PHP_SETTING),
+    };
+    let record = Registration { previous, registered: php_exe.to_string_lossy().into_owned() };
+    fs::write(&record_path, serde_json::to_string_pretty(&record)?)?;
+    fs::write(settings_path, updated)?;
+    logging::log_message("INFO", &format!("Set {} in {:?} to {:?}", VSCODE_PHP_SETTING, settings_path, php_exe));
+    Ok(())
+}
+
+/// Undo `register_with_vscode_at`: put back the value the setting had before, or
+/// remove it if it had none. Nothing changes unless the setting still holds what phpvm
+/// wrote, so a value set since (or a setting phpvm never registered) is left alone, like
+/// the other settings. Returns false if there was nothing to undo.
+pub fn unregister_from_vscode_at(settings_path: &Path) -> anyhow::Result<bool> {
+    let record_path = registration_path(settings_path);
+    let Some(record) = read_registration(&record_path) else { return Ok(false) };
+    let content = match fs::read_to_string(settings_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    fs::remove_file(&record_path)?;
+    if string_setting(&content, VSCODE_PHP_SETTING).as_deref() != Some(record.registered.as_str()) {
+        return Ok(false);
+    }
+    let previous = record.previous;
+    let updated = match &previous {
+        Some(value) => set_string_setting(&content, VSCODE_PHP_SETTING, value)?,
+        None => remove_setting(&content, VSCODE_PHP_SETTING),
+    };
+    fs::write(settings_path, updated)?;
+    match previous {
+        Some(value) => logging::log_message("INFO", &format!("Restored {} in {:?} to {:?}", VSCODE_PHP_SETTING, settings_path, value)),
+        None => logging::log_message("INFO", &format!("Removed {} from {:?}", VSCODE_PHP_SETTING, settings_path)),
+    }
+    Ok(true)
+}
+
+fn backup_path(settings_path: &Path) -> PathBuf {
+    sibling_path(settings_path, ".phpvm-backup")
+}
+
+/// What `register_with_vscode_at` changed, kept next to settings.json until unregistering
+#[derive(Debug, Serialize, Deserialize)]
+struct Registration {
+    /// The setting's value before the first registration
+    previous: Option<String>,
+    /// The executable phpvm set it to
+    registered: String,
+}
+
+fn registration_path(settings_path: &Path) -> PathBuf {
+    sibling_path(settings_path, ".phpvm-registration")
+}
+
+fn read_registration(path: &Path) -> Option<Registration> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn sibling_path(settings_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = settings_path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    settings_path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SETTINGS: &str = "{\n    // Editor\n    \"editor.fontSize\": 14,\n    \"files.trimTrailingWhitespace\": true\n}\n";
+
+    #[test]
+    fn test_set_string_setting_keeps_other_settings_and_comments() {
+        let updated = set_string_setting(SETTINGS, VSCODE_PHP_SETTING, r"C:\phpvm\current\php.exe").unwrap();
+        assert_eq!(
+            updated,
+            "{\n    \"php.validate.executablePath\": \"C:\\\\phpvm\\\\current\\\\php.exe\",\n    // Editor\n    \
+             \"editor.fontSize\": 14,\n    \"files.trimTrailingWhitespace\": true\n}\n"
+        );
+
+        // Replaced in place the second time
+        let again = set_string_setting(&updated, VSCODE_PHP_SETTING, "/usr/bin/php").unwrap();
+        assert!(again.contains("\"php.validate.executablePath\": \"/usr/bin/php\","));
+        assert_eq!(again.matches(VSCODE_PHP_SETTING).count(), 1);
+
+        assert_eq!(
+            set_string_setting("{}", VSCODE_PHP_SETTING, "/php").unwrap(),
+            "{\n    \"php.validate.executablePath\": \"/php\"\n}"
+        );
+        assert_eq!(
+            set_string_setting("", VSCODE_PHP_SETTING, "/php").unwrap(),
+            "{\n    \"php.validate.executablePath\": \"/php\"\n}\n"
+        );
+        assert!(set_string_setting("[]", VSCODE_PHP_SETTING, "/php").is_err());
+    }
+
+    #[test]
+    fn test_remove_setting_restores_original() {
+        let updated = set_string_setting(SETTINGS, VSCODE_PHP_SETTING, "/usr/bin/php").unwrap();
+        assert_eq!(remove_setting(&updated, VSCODE_PHP_SETTING), SETTINGS);
+        assert_eq!(remove_setting(SETTINGS, VSCODE_PHP_SETTING), SETTINGS);
+    }
+
+    #[test]
+    fn test_register_and_unregister_with_vscode() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let settings = temp_dir.path().join("Code").join("User").join("settings.json");
+
+        // No settings.json yet
+        register_with_vscode_at(&settings, Path::new("/phpvm/current/php")).unwrap();
+        let content = fs::read_to_string(&settings).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(parsed[VSCODE_PHP_SETTING], "/phpvm/current/php");
+
+        assert!(unregister_from_vscode_at(&settings).unwrap());
+
+        fs::write(&settings, SETTINGS).unwrap();
+        register_with_vscode_at(&settings, Path::new("/phpvm/current/php")).unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&settings)).unwrap(), SETTINGS);
+
+        assert!(unregister_from_vscode_at(&settings).unwrap());
+        assert_eq!(fs::read_to_string(&settings).unwrap(), SETTINGS);
+        assert!(!unregister_from_vscode_at(&settings).unwrap());
+    }
+
+    #[test]
+    fn test_unregister_restores_the_users_own_setting() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let settings = temp_dir.path().join("settings.json");
+        let own = set_string_setting(SETTINGS, VSCODE_PHP_SETTING, "/opt/php/bin/php").unwrap();
+        fs::write(&settings, &own).unwrap();
+        assert_eq!(string_setting(&own, VSCODE_PHP_SETTING).as_deref(), Some("/opt/php/bin/php"));
+
+        // Registering twice keeps the original backup and previous value
+        register_with_vscode_at(&settings, Path::new("/phpvm/current/php")).unwrap();
+        register_with_vscode_at(&settings, Path::new("/phpvm/current/php")).unwrap();
+        assert_eq!(fs::read_to_string(backup_path(&settings)).unwrap(), own);
+
+        assert!(unregister_from_vscode_at(&settings).unwrap());
+        assert_eq!(fs::read_to_string(&settings).unwrap(), own);
+        assert_eq!(fs::read_to_string(backup_path(&settings)).unwrap(), own);
+        assert!(!unregister_from_vscode_at(&settings).unwrap());
+    }
+}
@@ -0,0 +1,201 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// How bad a php.ini problem is. Errors are settings PHP couldn't parse (everything
+/// after them in the file may be ignored); warnings are things PHP skipped, like an
+/// extension it couldn't load, while otherwise starting normally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IniIssueSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IniIssueKind {
+    /// The ini file itself doesn't parse ("syntax error, unexpected ...")
+    Syntax,
+    /// `extension=`/`zend_extension=` pointing at a DLL PHP can't load
+    ExtensionLoad,
+    /// Any other startup message
+    Other,
+}
+
+/// A problem PHP reported while reading php.ini at startup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IniIssue {
+    pub severity: IniIssueSeverity,
+    pub kind: IniIssueKind,
+    /// PHP's message without the "PHP Warning:" prefix
+    pub message: String,
+    /// Line in the ini file, when PHP names one (startup warnings say "Unknown on line 0")
+    pub line: Option<u32>,
+    /// Name of the extension that failed to load, for `ExtensionLoad`
+    pub extension: Option<String>,
+}
+
+/// Pull ini problems out of what `php -c <ini> --version` printed. PHP writes startup
+/// messages both to stderr ("PHP Warning:  ...") and, with display_startup_errors, to
+/// stdout ("Warning: ..."), so both streams are read and duplicates dropped.
+pub fn parse_startup_messages(output: &str) -> Vec<IniIssue> {
+    let prefix = Regex::new(r"^(?:PHP\s+)?(Parse error|Fatal error|Warning|Notice|Deprecated|PHP)\s*:\s+(.*)$").unwrap();
+    let location = Regex::new(r"\s+in\s+(.+?)\s+on\s+line\s+(\d+)\s*$").unwrap();
+    let library = Regex::new(r#"Unable to load dynamic library '([^']+)'"#).unwrap();
+
+    let mut issues: Vec<IniIssue> = Vec::new();
+    for line in output.lines().map(str::trim) {
+        let Some(captures) = prefix.captures(line) else { continue };
+        let label = &captures[1];
+        let full_message = captures[2].trim();
+
+        let (message, line_number) = match location.captures(full_message) {
+            Some(loc) => {
+                let in_ini = loc[1].to_lowercase().ends_with(".ini");
+                let line_number = loc[2].parse().ok().filter(|n| in_ini && *n > 0);
+                (full_message[..loc.get(0).unwrap().start()].trim(), line_number)
+            }
+            None => (full_message, None),
+        };
+        let message = message.trim_start_matches("PHP Startup:").trim().to_string();
+
+        let extension = library.captures(&message).map(|c| extension_name(&c[1]));
+        let kind = if message.starts_with("syntax error") {
+            IniIssueKind::Syntax
+        } else if extension.is_some() {
+            IniIssueKind::ExtensionLoad
+        } else {
+            IniIssueKind::Other
+        };
+        let severity = match (kind, label) {
+            (IniIssueKind::Syntax, _) | (_, "Fatal error" | "Parse error") => IniIssueSeverity::Error,
+            _ => IniIssueSeverity::Warning,
+        };
+
+        let issue = IniIssue { severity, kind, message, line: line_number, extension };
+        if !issues.contains(&issue) {
+            issues.push(issue);
+        }
+    }
+    issues
+}
+
+/// "php_xdebug.dll", "C:\php\ext\php_curl.dll" or "redis.so" -> "xdebug", "curl", "redis"
+pub(crate) fn extension_name(library: &str) -> String {
+    let file = library.rsplit(['/', '\\']).next().unwrap_or(library);
+    let stem = file.split('.').next().unwrap_or(file);
+    stem.strip_prefix("php_").unwrap_or(stem).to_string()
+}
+
+/// Value of directive `key` in php.ini content, without surrounding quotes. PHP uses
+/// the last uncommented setting, so this does too; None when every one is commented out.
+pub fn get_directive(content: &str, key: &str) -> Option<String> {
+    content
+        .lines()
+        .rev()
+        .find_map(|line| directive_value(line, key))
+        .map(|value| value.trim_matches('"').to_string())
+}
+
+/// Set directive `key` to `value` in php.ini content. The first uncommented setting is
+/// replaced and later ones dropped (PHP would use the last); otherwise the first
+/// commented-out one (";date.timezone =") is replaced, keeping its place in the file;
+/// otherwise the directive is appended under a `[section]` header.
+pub fn set_directive(content: &str, section: &str, key: &str, value: &str) -> String {
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let setting = format!("{} = \"{}\"", key, value);
+    let commented = |line: &str| {
+        line.trim_start()
+            .strip_prefix(';')
+            .is_some_and(|rest| directive_value(rest, key).is_some())
+    };
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let active: Vec<usize> = (0..lines.len()).filter(|&i| directive_value(&lines[i], key).is_some()).collect();
+    if let Some(&first) = active.first() {
+        lines[first] = setting;
+        for &i in active[1..].iter().rev() {
+            lines.remove(i);
+        }
+    } else if let Some(i) = lines.iter().position(|line| commented(line)) {
+        lines[i] = setting;
+    } else {
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.push(format!("[{}]", section));
+        lines.push(setting);
+    }
+
+    let mut result = lines.join(newline);
+    result.push_str(newline);
+    result
+}
+
+/// The raw value of an uncommented `key = value` line, comment stripped
+fn directive_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let (name, value) = line.split_once('=')?;
+    if name.trim() != key {
+        return None;
+    }
+    // Quoted values may contain ';', unquoted ones end at it
+    let value = value.trim();
+    let value = match value.strip_prefix('"').and_then(|rest| rest.find('"').map(|end| &value[..end + 2])) {
+        Some(quoted) => quoted,
+        None => value.split(';').next().unwrap_or(value).trim(),
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_startup_messages() {
+        let stderr = "PHP:  syntax error, unexpected '=' in C:\\php\\php.ini on line 12\r\n\
+            PHP Warning:  PHP Startup: Unable to load dynamic library 'php_xdebug.dll' (tried: C:\\php\\ext\\php_xdebug.dll (The specified module could not be found)) in Unknown on line 0\r\n";
+        let stdout = "\nWarning: PHP Startup: Unable to load dynamic library 'php_xdebug.dll' (tried: C:\\php\\ext\\php_xdebug.dll (The specified module could not be found)) in Unknown on line 0\n\
+            PHP 8.3.0 (cli) (built: Nov 21 2023 10:38:47) (ZTS Visual C++ 2019 x64)\n";
+
+        let issues = parse_startup_messages(&format!("{}\n{}", stderr, stdout));
+        assert_eq!(issues.len(), 2);
+
+        assert_eq!(issues[0].severity, IniIssueSeverity::Error);
+        assert_eq!(issues[0].kind, IniIssueKind::Syntax);
+        assert_eq!(issues[0].message, "syntax error, unexpected '='");
+        assert_eq!(issues[0].line, Some(12));
+
+        assert_eq!(issues[1].severity, IniIssueSeverity::Warning);
+        assert_eq!(issues[1].kind, IniIssueKind::ExtensionLoad);
+        assert_eq!(issues[1].extension.as_deref(), Some("xdebug"));
+        assert_eq!(issues[1].line, None);
+        assert!(issues[1].message.starts_with("Unable to load dynamic library"));
+    }
+
+    #[test]
+    fn test_get_and_set_directive() {
+        let template = "[Date]\r\n; Defines the default timezone\r\n;date.timezone =\r\n\r\n[mail function]\r\nSMTP = localhost\r\n";
+        assert_eq!(get_directive(template, "date.timezone"), None);
+
+        let set = set_directive(template, "Date", "date.timezone", "Europe/Lisbon");
+        assert!(set.contains("[Date]\r\n; Defines the default timezone\r\ndate.timezone = \"Europe/Lisbon\"\r\n"));
+        assert_eq!(get_directive(&set, "date.timezone").as_deref(), Some("Europe/Lisbon"));
+
+        let duplicated = "date.timezone = UTC ; set by hand\n[Other]\ndate.timezone=\"Asia/Tokyo\"\n";
+        assert_eq!(get_directive(duplicated, "date.timezone").as_deref(), Some("Asia/Tokyo"));
+        let set = set_directive(duplicated, "Date", "date.timezone", "Europe/Lisbon");
+        assert_eq!(set, "date.timezone = \"Europe/Lisbon\"\n[Other]\n");
+
+        let set = set_directive("memory_limit = 128M", "Date", "date.timezone", "UTC");
+        assert_eq!(set, "memory_limit = 128M\n\n[Date]\ndate.timezone = \"UTC\"\n");
+        assert_eq!(get_directive(&set, "memory_limit").as_deref(), Some("128M"));
+    }
+
+    #[test]
+    fn test_clean_output_has_no_issues() {
+        let output = "PHP 8.3.0 (cli) (built: Nov 21 2023 10:38:47) (NTS)\nZend Engine v4.3.0, Copyright (c) Zend Technologies\n";
+        assert!(parse_startup_messages(output).is_empty());
+        assert_eq!(extension_name("/usr/lib/php/redis.so"), "redis");
+    }
+}
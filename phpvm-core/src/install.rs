@@ -1,12 +1,87 @@
 use crate::config;
+use crate::error::PhpVmError;
 use crate::state::{InstallMetadata, PhpState};
 use crate::version::PhpVersion;
-use crate::download::Downloader;
+use crate::download::{CacheLease, DownloadOutcome, Downloader, ProgressCallback};
+use crate::provider::Provider;
 use anyhow::Context;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::SystemTime;
 
+/// Install directories being installed into by this process. Shared across Installer
+/// instances, since callers may each create their own PhpManager.
+fn installs_in_progress() -> &'static Mutex<HashSet<PathBuf>> {
+    static IN_PROGRESS: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    IN_PROGRESS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Claims an install directory for one install. Dropping it frees the directory whether
+/// the install finished, failed or was cancelled.
+struct InstallGuard(PathBuf);
+
+impl InstallGuard {
+    /// Fails fast with `InstallInProgress` if another install holds `install_path`
+    fn acquire(install_path: &Path, label: &str) -> Result<Self, PhpVmError> {
+        if !installs_in_progress().lock().unwrap().insert(install_path.to_path_buf()) {
+            return Err(PhpVmError::InstallInProgress(format!("An install of PHP {} is already in progress", label)));
+        }
+        Ok(InstallGuard(install_path.to_path_buf()))
+    }
+}
+
+impl Drop for InstallGuard {
+    fn drop(&mut self) {
+        installs_in_progress().lock().unwrap().remove(&self.0);
+    }
+}
+
+/// What an install would do, resolved without touching the network or disk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallPlan {
+    pub version: String,
+    pub variant: String, // "ts" or "nts"
+    pub thread_safe: bool,
+    pub compiler_tag: String,
+    pub download_url: String,
+    pub install_path: PathBuf,
+}
+
+/// Where an install downloads from: `primary` first, then each of `fallbacks` in turn
+/// when the one before it is missing or its host fails
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadUrls {
+    pub primary: String,
+    pub fallbacks: Vec<String>,
+}
+
+/// Called with (files hashed, total files) while an install directory is checksummed,
+/// possibly from several threads at once
+pub type ChecksumProgress = Box<dyn Fn(usize, usize) + Send + Sync>;
+
+/// Less common knobs for `install_version_with_options`
+#[derive(Default)]
+pub struct InstallOptions {
+    /// Download the build made with this compiler instead of the one the branch normally
+    /// uses, e.g. "vs16" for a 7.4 published that way. Must be in `KNOWN_COMPILER_TAGS`.
+    pub compiler_tag: Option<String>,
+    /// Keep only these bundled extensions and delete the rest from `ext` (see
+    /// `extension::strip_extensions`); None falls back to `keep_extensions` in config.json
+    pub keep_extensions: Option<Vec<String>>,
+    pub checksum_progress: Option<ChecksumProgress>,
+}
+
+/// Marks directory checksums made by `calculate_checksum_with`; older ones have no prefix
+const CHECKSUM_V2_PREFIX: &str = "v2:";
+/// Read buffer for checksumming; PHP installs are mostly multi-megabyte DLLs
+const CHECKSUM_BUFFER_SIZE: usize = 1024 * 1024;
+/// Free space wanted for extracting, as a multiple of the archive size (PHP zips
+/// extract to roughly 2-3 times their size)
+const EXTRACT_SPACE_FACTOR: u64 = 3;
+
 pub struct Installer {
     downloader: Downloader,
     config: config::Config,
@@ -20,14 +95,75 @@ impl Installer {
         })
     }
 
+    /// Resolve the download URL, compiler tag and target directory for a version/variant
+    pub fn plan(&self, version: &PhpVersion, thread_safe: bool) -> InstallPlan {
+        Self::plan_for(&self.config.install_dir, version, thread_safe)
+    }
+
+    /// `plan` with an explicit compiler tag, which is validated; None is the same as `plan`
+    pub fn plan_with_compiler(&self, version: &PhpVersion, thread_safe: bool, compiler_tag: Option<&str>) -> anyhow::Result<InstallPlan> {
+        Self::plan_for_compiler(&self.config.install_dir, version, thread_safe, compiler_tag)
+    }
+
+    /// Pure version of `plan_with_compiler` for a given install directory
+    pub fn plan_for_compiler(
+        install_dir: &Path,
+        version: &PhpVersion,
+        thread_safe: bool,
+        compiler_tag: Option<&str>,
+    ) -> anyhow::Result<InstallPlan> {
+        let mut plan = Self::plan_for(install_dir, version, thread_safe);
+        if let Some(tag) = compiler_tag {
+            let tag = Provider::validate_compiler_tag(tag)?;
+            plan.compiler_tag = tag.to_string();
+            plan.download_url = Provider::download_url_for_compiler(&plan.version, version.major, version.minor, thread_safe, tag);
+        }
+        Ok(plan)
+    }
+
+    /// Pure version of `plan` for a given install directory
+    pub fn plan_for(install_dir: &Path, version: &PhpVersion, thread_safe: bool) -> InstallPlan {
+        let version_str = version.to_string();
+        let variant = if thread_safe { "ts" } else { "nts" };
+
+        let compiler_tag = Provider::get_vs_version(version.major, version.minor);
+        let download_url = Provider::generate_download_url(&version_str, version.major, version.minor, thread_safe);
+
+        InstallPlan {
+            install_path: install_dir.join(format!("php-{}-{}", version_str, variant)),
+            version: version_str,
+            variant: variant.to_string(),
+            thread_safe,
+            compiler_tag: compiler_tag.to_string(),
+            download_url,
+        }
+    }
+
     pub async fn install_version(
         &self,
         version: &PhpVersion,
         source_url: Option<&str>,
         thread_safe: Option<bool>,
+        force_reinstall: bool,
         progress_callback: Option<Box<dyn FnMut(u64, u64, f64) + Send + Sync>>,
+    ) -> anyhow::Result<PathBuf> {
+        self.install_version_with_options(version, source_url, thread_safe, force_reinstall, progress_callback, InstallOptions::default())
+            .await
+    }
+
+    /// `install_version` with `options`: an explicit compiler tag, and progress while the
+    /// extracted files are checksummed, which takes a few seconds for a full PHP build
+    pub async fn install_version_with_options(
+        &self,
+        version: &PhpVersion,
+        source_url: Option<&str>,
+        thread_safe: Option<bool>,
+        force_reinstall: bool,
+        progress_callback: Option<ProgressCallback>,
+        options: InstallOptions,
     ) -> anyhow::Result<PathBuf> {
         let version_str = version.to_string();
+        crate::version::validate_version_str(&version_str)?;
         // Log the thread_safe parameter to debug
         crate::logging::log_message("DEBUG", &format!("Install request: version={}, thread_safe={:?} (is_some={}, is_none={})", 
             version_str, thread_safe, thread_safe.is_some(), thread_safe.is_none()));
@@ -54,70 +190,43 @@ impl Installer {
             }
         };
         
-        let variant_suffix = if thread_safe_flag { "ts" } else { "nts" };
-        let install_path = self.config.install_dir.join(format!("php-{}-{}", version_str, variant_suffix));
+        let plan = Self::plan_for_compiler(&self.config.install_dir, version, thread_safe_flag, options.compiler_tag.as_deref())?;
+        let variant_suffix = plan.variant.as_str();
+        let install_path = plan.install_path.clone();
         
         crate::logging::log_message("DEBUG", &format!("Installing variant: {} (path: {:?})", variant_suffix.to_uppercase(), install_path));
 
-        // Check if this specific variant is already installed (verify it's complete)
-        if install_path.exists() {
-            let php_exe = crate::platform::get_php_executable_path(&install_path);
-            if php_exe.exists() {
-                anyhow::bail!("PHP {} ({}) is already installed", version_str, variant_suffix.to_uppercase());
-            } else {
-                // Directory exists but installation is incomplete, clean it up
-                crate::logging::log_message("WARN", &format!("Found incomplete installation for {} ({}), cleaning up", version_str, variant_suffix));
-                if let Err(e) = fs::remove_dir_all(&install_path) {
-                    crate::logging::log_message("ERROR", &format!("Failed to clean up incomplete installation: {}", e));
-                    anyhow::bail!("Found incomplete installation for {} ({}). Please manually remove {:?} and try again", version_str, variant_suffix, install_path);
-                }
+        // Two installs of the same target would extract over each other
+        let _guard = InstallGuard::acquire(&install_path, &format!("{} ({})", version_str, variant_suffix.to_uppercase()))?;
+
+        // Installs only ever appear at install_path by a rename, so a directory there
+        // without PHP was damaged afterwards; force reinstall replaces it like any other
+        if install_path.exists() && !force_reinstall {
+            if crate::platform::get_php_executable_path(&install_path).exists() {
+                return Err(PhpVmError::AlreadyInstalled(format!("PHP {} ({}) is already installed", version_str, variant_suffix.to_uppercase())).into());
             }
+            anyhow::bail!(
+                "{:?} exists but has no PHP executable; prune broken installs or reinstall with force",
+                install_path
+            );
+        }
+
+        // Left behind only if a previous install of this target crashed; the guard
+        // above means nothing else is writing to it
+        let staging_path = staging_path_for(&install_path);
+        if staging_path.exists() {
+            crate::logging::log_message("INFO", &format!("Removing stale staging directory {:?}", staging_path));
+            fs::remove_dir_all(&staging_path)
+                .with_context(|| format!("Failed to remove stale staging directory {:?}", staging_path))?;
         }
 
         tracing::info!("Installing PHP {}", version_str);
         crate::logging::log_message("INFO", &format!("Installing PHP {}", version_str));
 
         let url = source_url.map(|s| s.to_string()).unwrap_or_else(|| {
-            // Determine Visual Studio/Visual C++ version based on PHP version
-            // PHP 8.4+ uses vs17 (Visual Studio 2017/2019)
-            // PHP 8.0-8.3 uses vs16 (Visual Studio 2016)
-            // PHP 7.4 uses vc15 (Visual C++ 2017) or vs16
-            // PHP 7.2-7.3 uses VC15 (Visual C++ 2017) - archived, capital VC
-            // PHP 7.0-7.1 uses VC14 (Visual C++ 2015) - archived, capital VC
-            // PHP 5.6 uses VC11 (Visual C++ 2012) - archived, capital VC
-            let (vs_version, base_url) = if version.major > 8 || (version.major == 8 && version.minor >= 4) {
-                ("vs17", "https://windows.php.net/downloads/releases/")
-            } else if version.major == 8 {
-                ("vs16", "https://windows.php.net/downloads/releases/")
-            } else if version.major == 7 {
-                if version.minor >= 4 {
-                    ("vc15", "https://windows.php.net/downloads/releases/")
-                } else if version.minor >= 2 {
-                    ("VC15", "https://windows.php.net/downloads/releases/archives/")
-                } else {
-                    ("VC14", "https://windows.php.net/downloads/releases/archives/")
-                }
-            } else {
-                // PHP 5.6 and earlier
-                ("VC11", "https://windows.php.net/downloads/releases/archives/")
-            };
-            
-            // Use thread_safe_flag to determine TS or NTS build
-            // CORRECT URL FORMAT:
-            // TS:  php-{version}-Win32-{vs}-x64.zip
-            // NTS: php-{version}-nts-Win32-{vs}-x64.zip (nts comes AFTER version, BEFORE Win32)
-            let url = if thread_safe_flag {
-                let u = format!("{}php-{}-Win32-{}-x64.zip", base_url, version_str, vs_version);
-                crate::logging::log_message("DEBUG", &format!("Building TS URL (thread_safe_flag=true): {}", u));
-                eprintln!("[Installer] Building TS URL: {}", u);
-                u
-            } else {
-                let u = format!("{}php-{}-nts-Win32-{}-x64.zip", base_url, version_str, vs_version);
-                crate::logging::log_message("DEBUG", &format!("Building NTS URL (thread_safe_flag=false): {}", u));
-                eprintln!("[Installer] Building NTS URL: {}", u);
-                u
-            };
-            url
+            crate::logging::log_message("DEBUG", &format!("Building {} URL (thread_safe_flag={}): {}", variant_suffix.to_uppercase(), thread_safe_flag, plan.download_url));
+            eprintln!("[Installer] Building {} URL: {}", variant_suffix.to_uppercase(), plan.download_url);
+            plan.download_url.clone()
         });
         
         crate::logging::log_message("INFO", &format!("Final download URL: {}", url));
@@ -126,51 +235,57 @@ impl Installer {
         eprintln!("[Installer] URL contains 'x64.zip' (TS): {}", url.ends_with("x64.zip") && !url.contains("-nts"));
         crate::logging::log_message("INFO", &format!("Downloading PHP {} ({})", version_str, variant_suffix.to_uppercase()));
 
-        let archive_path = self
-            .downloader
-            .download_file(&url, None, progress_callback)
-            .await
-            .with_context(|| format!("Failed to download PHP archive from: {}", url))?;
-        
-        crate::logging::log_message("INFO", &format!("Archive downloaded to: {:?}", archive_path));
+        let (url, download, lease) = self.download_archive(&plan, source_url, progress_callback).await?;
+        let archive_path = download.path;
+
+        if download.from_cache {
+            crate::logging::log_message("INFO", &format!("Using cached archive: {:?}", archive_path));
+        } else {
+            crate::logging::log_message("INFO", &format!("Archive downloaded to: {:?}", archive_path));
+        }
+
+        // Fail up front instead of with an IO error halfway through extracting
+        let archive_size = fs::metadata(&archive_path).map(|m| m.len()).unwrap_or(0);
+        crate::platform::ensure_space(&install_path, archive_size * EXTRACT_SPACE_FACTOR)?;
+
+        // Store version with variant suffix for identification
+        let version_with_variant = format!("{}-{}", version_str, variant_suffix);
 
         tracing::info!("Extracting archive...");
-        crate::logging::log_message("INFO", &format!("Extracting archive from: {:?} to: {:?}", archive_path, install_path));
-        
-        // Extract archive, and clean up on failure
-        let extract_result = self.extract_archive(&archive_path, &install_path)
-            .with_context(|| format!("Failed to extract archive from {:?} to {:?}", archive_path, install_path));
-        
-        if let Err(ref e) = extract_result {
-            crate::logging::log_message("ERROR", &format!("Extraction failed: {}", e));
-            // Clean up partial installation
-            if install_path.exists() {
-                crate::logging::log_message("INFO", &format!("Cleaning up partial installation at: {:?}", install_path));
-                let _ = fs::remove_dir_all(&install_path);
-            }
+        crate::logging::log_message("INFO", &format!("Extracting archive from: {:?} to: {:?}", archive_path, staging_path));
+        let staged = self.stage_install(&archive_path, &staging_path, &version_with_variant, options.keep_extensions.as_ref().or(self.config.keep_extensions.as_ref()));
+        if let Err(e) = staged {
+            crate::logging::log_message("ERROR", &format!("{:#}, removing {:?}", e, staging_path));
+            let _ = fs::remove_dir_all(&staging_path);
+            return Err(e);
+        }
+
+        if let Err(e) = commit_staged_install(&staging_path, &install_path) {
+            let _ = fs::remove_dir_all(&staging_path);
+            return Err(e);
         }
-        
-        extract_result?;
-        
-        // Verify installation is complete by checking for PHP executable
         let php_exe = crate::platform::get_php_executable_path(&install_path);
-        if !php_exe.exists() {
-            crate::logging::log_message("ERROR", &format!("PHP executable not found after extraction: {:?}", php_exe));
-            // Clean up incomplete installation
-            if install_path.exists() {
-                crate::logging::log_message("INFO", &format!("Cleaning up incomplete installation at: {:?}", install_path));
-                let _ = fs::remove_dir_all(&install_path);
+        crate::logging::log_message("INFO", &format!("PHP executable found at: {:?}", php_exe));
+
+        // Before the checksum, so files the hook adds (e.g. php.ini) don't fail verify_integrity
+        if let Some(hook) = &self.config.post_install_hook {
+            if let Err(e) = run_post_install_hook(hook, &install_path, &version_with_variant) {
+                if hook.fail_on_error {
+                    crate::logging::log_message("ERROR", &format!("{:#}, removing {:?}", e, install_path));
+                    let _ = fs::remove_dir_all(&install_path);
+                    return Err(e);
+                }
+                crate::logging::log_message("WARN", &format!("{:#} (ignored, fail_on_error is off)", e));
             }
-            anyhow::bail!("Installation incomplete: PHP executable not found at {:?}", php_exe);
         }
-        
-        crate::logging::log_message("INFO", &format!("PHP executable found at: {:?}", php_exe));
 
-        let checksum = self.calculate_checksum(&install_path).await?;
+        crate::logging::log_message("INFO", &format!("Verifying installation at {:?}", install_path));
+        let checksum = Self::calculate_checksum_reporting(&install_path, self.config.checksum_threads, options.checksum_progress.as_ref())?;
+
+        // The archive name says which compiler built it; a custom source URL may not
+        let expected_compiler = crate::extension::compiler_tag_from_url(&url).unwrap_or_else(|| plan.compiler_tag.to_lowercase());
+        let compiler = reconcile_build(&php_exe, &version_str, thread_safe_flag, expected_compiler);
 
-        // Store version with variant suffix for identification
-        let version_with_variant = format!("{}-{}", version_str, variant_suffix);
-        
         let metadata = InstallMetadata {
             version: version_with_variant.clone(),
             install_path: install_path.clone(),
@@ -181,33 +296,230 @@ impl Installer {
                 .to_string(),
             checksum: Some(checksum),
             source: url.to_string(),
+            compiler: Some(compiler),
         };
 
         let mut state = PhpState::load()?;
         state.add_version(version_with_variant.clone(), metadata);
         state.save()?;
 
+        // Reinstalling the active version: the shim, or with the copy strategy the copied
+        // php.exe and DLLs, still refer to the install that was replaced
+        if state.active_version.as_deref() == Some(version_with_variant.as_str()) {
+            match crate::manager::point_current_at(&self.config, &php_exe, &install_path) {
+                Ok(()) => crate::logging::log_message("INFO", &format!("Refreshed the current shim for active PHP {}", version_with_variant)),
+                Err(e) => crate::logging::log_message(
+                    "WARN",
+                    &format!("PHP {} is active but its shim couldn't be refreshed, switch to it again: {:#}", version_with_variant, e),
+                ),
+            }
+        }
+
+        if !self.config.keep_archives_after_install {
+            let archive = lease.path().to_path_buf();
+            match lease.release_and_delete() {
+                Ok(true) => crate::logging::log_message("INFO", &format!("Removed cached archive {:?}", archive)),
+                Ok(false) => crate::logging::log_message("INFO", &format!("Keeping cached archive {:?}, another install is using it", archive)),
+                Err(e) => crate::logging::log_message("WARN", &format!("Failed to remove cached archive {:?}: {}", archive, e)),
+            }
+        }
+
+        crate::stats::record(crate::stats::UsageEvent::Install);
         tracing::info!("Successfully installed PHP {}", version_with_variant);
         Ok(install_path)
     }
 
-    pub async fn remove_version(&self, version: &PhpVersion) -> anyhow::Result<()> {
-        let version_str = version.to_string();
-        
-        // Check if version string includes variant suffix (e.g., "8.5.1-ts")
-        let (base_version_str, variant_suffix) = if version_str.contains("-ts") || version_str.contains("-nts") {
-            let parts: Vec<&str> = version_str.split('-').collect();
-            if parts.len() >= 4 {
-                // Format: "8.5.1-ts" or "8.5.1-nts"
-                let base = parts[0..3].join(".");
-                let variant = parts[3];
-                (base, Some(variant))
-            } else {
-                (version_str.clone(), None)
+    /// Extract into `staging_path`, check the result holds a PHP executable and strip
+    /// extensions not in `keep`. The caller removes `staging_path` on error.
+    fn stage_install(
+        &self,
+        archive_path: &PathBuf,
+        staging_path: &PathBuf,
+        version_with_variant: &str,
+        keep: Option<&Vec<String>>,
+    ) -> anyhow::Result<()> {
+        self.extract_archive(archive_path, staging_path)
+            .with_context(|| PhpVmError::ExtractionFailed(format!("Failed to extract archive from {:?} to {:?}", archive_path, staging_path)))?;
+
+        let php_exe = crate::platform::get_php_executable_path(staging_path);
+        if !php_exe.exists() {
+            anyhow::bail!("Installation incomplete: PHP executable not found at {:?}", php_exe);
+        }
+
+        if let Some(keep) = keep {
+            let report = crate::extension::strip_extensions(staging_path, keep)?;
+            crate::logging::log_message(
+                "WARN",
+                &format!(
+                    "Removed {} extensions from PHP {} ({} freed); reinstall to get them back",
+                    report.removed.len(),
+                    version_with_variant,
+                    crate::platform::format_bytes(report.freed_bytes)
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    /// The URLs `download_archive` tries for a plan, before cached archives are moved to
+    /// the front, each with the URL it mirrors (which checksums are looked up against)
+    pub fn download_attempts(&self, plan: &InstallPlan, source_url: Option<&str>) -> anyhow::Result<Vec<(String, String)>> {
+        // Superseded patches move to the archives directory and some releases carry a
+        // neighbouring compiler tag, so try those if the planned URL is gone (only for
+        // URLs we built ourselves)
+        let candidate_urls = match source_url {
+            Some(url) => vec![url.to_string()],
+            None => {
+                let version = PhpVersion::from_string(&plan.version)?;
+                if plan.compiler_tag == Provider::get_vs_version(version.major, version.minor) {
+                    Provider::install_download_urls(&plan.version, version.major, version.minor, plan.thread_safe)
+                } else {
+                    Provider::install_download_urls_for_compiler(
+                        &plan.version,
+                        version.major,
+                        version.minor,
+                        plan.thread_safe,
+                        &plan.compiler_tag,
+                    )
+                }
             }
-        } else {
-            (version_str.clone(), None)
         };
+
+        // Host by host: a 503 from windows.php.net moves on to the next mirror, a 404 first
+        // tries the other candidates on the same host
+        let mut attempts: Vec<(String, String)> = candidate_urls.iter().map(|url| (url.clone(), url.clone())).collect();
+        for mirror in &self.config.download_mirrors {
+            attempts.extend(
+                candidate_urls
+                    .iter()
+                    .filter_map(|url| Provider::mirrored_url(url, mirror).map(|mirrored| (mirrored, url.clone()))),
+            );
+        }
+        Ok(attempts)
+    }
+
+    /// Download (or reuse from the cache) the archive for a plan. Without a `source_url`,
+    /// the archives directory and the neighbouring compiler tag are tried when the
+    /// planned URL is gone. When the host fails
+    /// or stalls, the same paths are tried on each of `download_mirrors` in turn; an
+    /// archive already cached from any of them is used first. An archive that fails
+    /// verification counts as a failed attempt, so the next candidate is tried; if none
+    /// works, that verification error is reported over the download errors. Returns
+    /// the URL that worked, the download, and a lease keeping the cached file from
+    /// concurrent deletion.
+    pub async fn download_archive(
+        &self,
+        plan: &InstallPlan,
+        source_url: Option<&str>,
+        progress_callback: Option<ProgressCallback>,
+    ) -> anyhow::Result<(String, DownloadOutcome, CacheLease)> {
+        let mut attempts = self.download_attempts(plan, source_url)?;
+        attempts.sort_by_cached_key(|(url, _)| !self.downloader.is_cached(url));
+
+        // Each attempt needs its own boxed callback, so share the caller's between them
+        let shared_callback = progress_callback.map(|callback| Arc::new(Mutex::new(callback)));
+        let mut first_error = None;
+        let mut verify_error = None;
+        for (candidate, original) in attempts {
+            let callback = shared_callback.clone().map(|shared| {
+                Box::new(move |done: u64, total: u64, speed: f64| {
+                    let mut callback = shared.lock().unwrap();
+                    (*callback)(done, total, speed)
+                }) as ProgressCallback
+            });
+            // Taken before downloading so a concurrent install can't delete the file meanwhile
+            let lease = CacheLease::acquire(self.downloader.cached_path(&candidate));
+            let download = match self
+                .downloader
+                .download_file(&candidate, None, callback)
+                .await
+                .with_context(|| format!("Failed to download PHP archive from: {}", candidate))
+            {
+                Ok(download) => download,
+                Err(e) => {
+                    crate::logging::log_message("WARN", &format!("{:#}", e));
+                    first_error.get_or_insert(e);
+                    continue;
+                }
+            };
+            if candidate != original {
+                crate::logging::log_message("INFO", &format!("Downloaded {} from mirror {}", original, candidate));
+            }
+            match self.verify_download(&original, &candidate, &download).await {
+                Ok(()) => return Ok((candidate, download, lease)),
+                Err(e) => {
+                    crate::logging::log_message("WARN", &format!("{:#}", e));
+                    verify_error.get_or_insert(e);
+                }
+            }
+        }
+        Err(verify_error.or(first_error).unwrap_or_else(|| anyhow::anyhow!("No download URL for PHP {}", plan.version)))
+    }
+
+    /// Check a downloaded archive against its published SHA256 the way the provider it
+    /// came from is configured: `verify_checksum: false` skips the check (mirrors that
+    /// publish no checksums), `true` requires one. Downloads no provider serves are
+    /// checked when a checksum is published and logged as unverified otherwise.
+    /// For an archive fetched from a download mirror, `url` is the URL it mirrors: the
+    /// checksum published there wins, and the mirror's own is used if that host is down.
+    /// A verified archive's hash is recorded next to it, so reusing it from the cache
+    /// needs no network; failing to fetch the checksums is only an error for providers
+    /// that require one.
+    async fn verify_download(&self, url: &str, downloaded_from: &str, download: &DownloadOutcome) -> anyhow::Result<()> {
+        let archive = download.path.as_path();
+        let provider = Provider::with_providers(self.config.providers.clone())?;
+        let source = provider.provider_for_download(url);
+        if let Some(unverified) = source.filter(|p| !p.verify_checksum) {
+            crate::logging::log_message(
+                "INFO",
+                &format!("Not verifying {}: checksum verification is off for provider '{}'", url, unverified.name),
+            );
+            return Ok(());
+        }
+        if download.from_cache {
+            if let Some(recorded) = crate::download::verified_checksum(archive) {
+                if crate::download::file_sha256(archive.to_path_buf()).await?.eq_ignore_ascii_case(&recorded) {
+                    crate::logging::log_message("INFO", &format!("Cached archive for {} was verified when downloaded", url));
+                    return Ok(());
+                }
+            }
+        }
+
+        let mut expected = provider.checksum_for_url(url).await;
+        if !matches!(expected, Ok(Some(_))) && downloaded_from != url {
+            if let Ok(Some(mirrored)) = provider.checksum_for_url(downloaded_from).await {
+                expected = Ok(Some(mirrored));
+            }
+        }
+        let expected = match (expected, source) {
+            (Ok(expected), _) => expected,
+            (Err(e), Some(required_by)) => {
+                return Err(e.context(format!(
+                    "Couldn't fetch the published checksum for {}, which provider '{}' requires",
+                    url, required_by.name
+                )));
+            }
+            (Err(e), None) => {
+                crate::logging::log_message("WARN", &format!("Couldn't fetch the published checksum for {}: {:#}", url, e));
+                None
+            }
+        };
+        let verified = verify_archive_checksum(downloaded_from, archive, expected.as_deref(), source.map(|p| p.name.as_str())).await?;
+        if let Some(checksum) = expected.filter(|_| verified) {
+            if let Err(e) = crate::download::record_verified_checksum(archive, &checksum) {
+                crate::logging::log_message("WARN", &format!("Failed to record the checksum of {}: {:#}", url, e));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn remove_version(&self, version: &PhpVersion) -> anyhow::Result<()> {
+        // The variant travels in the version's suffix (e.g. 8.5.1 with suffix "ts")
+        let base_version_str = format!("{}.{}.{}", version.major, version.minor, version.patch);
+        let variant_suffix = version
+            .suffix
+            .as_deref()
+            .filter(|suffix| *suffix == "ts" || *suffix == "nts");
         
         // Determine install path based on variant
         let install_path = if let Some(variant) = variant_suffix {
@@ -241,13 +553,13 @@ impl Installer {
         };
 
         if !install_path.exists() {
-            anyhow::bail!("Version {} is not installed", version_with_variant);
+            return Err(PhpVmError::NotInstalled(format!("Version {} is not installed", version_with_variant)).into());
         }
 
         let mut state = PhpState::load()?;
         // Check if this specific variant is active
         if state.active_version.as_deref() == Some(&version_with_variant) {
-            anyhow::bail!("Cannot remove active version. Switch to another version first.");
+            return Err(PhpVmError::ActiveVersionInUse("Cannot remove active version. Switch to another version first.".to_string()).into());
         }
 
         let variant_display = variant_suffix.unwrap_or("");
@@ -257,6 +569,7 @@ impl Installer {
 
         state.remove_version(&version_with_variant);
         state.save()?;
+        crate::stats::record(crate::stats::UsageEvent::Remove);
 
         tracing::info!("Successfully removed PHP {} ({})", base_version_str, variant_display);
         Ok(())
@@ -337,7 +650,70 @@ impl Installer {
         Ok(())
     }
 
-    async fn calculate_checksum(&self, path: &PathBuf) -> anyhow::Result<String> {
+    /// SHA256 over an install directory tree. Files are visited recursively in sorted
+    /// order and each contributes its relative path and contents, so the result is
+    /// reproducible across runs and machines and changes when files move or are renamed.
+    pub fn calculate_checksum(path: &Path) -> anyhow::Result<String> {
+        Self::calculate_checksum_with(path, 0)
+    }
+
+    /// Checksum of every file under `path`, recorded as "v2:<hex>". Files are hashed
+    /// independently on up to `threads` threads (0 = one per CPU), then the per-file
+    /// digests are combined in sorted path order, so the result never depends on scheduling.
+    pub fn calculate_checksum_with(path: &Path, threads: usize) -> anyhow::Result<String> {
+        Self::calculate_checksum_reporting(path, threads, None)
+    }
+
+    /// `calculate_checksum_with`, calling `progress` with (files hashed, total files)
+    /// once before hashing starts and after each file
+    pub fn calculate_checksum_reporting(path: &Path, threads: usize, progress: Option<&ChecksumProgress>) -> anyhow::Result<String> {
+        use rayon::prelude::*;
+        use sha2::{Digest, Sha256};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let files = Self::sorted_files(path)?;
+        let total = files.len();
+        if let Some(progress) = progress {
+            progress(0, total);
+        }
+        let hashed = AtomicUsize::new(0);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .context("Failed to start checksum threads")?;
+        let digests = pool.install(|| {
+            files
+                .par_iter()
+                .map(|(_, file_path)| {
+                    let digest = Self::hash_file(file_path);
+                    if let Some(progress) = progress {
+                        progress(hashed.fetch_add(1, Ordering::Relaxed) + 1, total);
+                    }
+                    digest
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        })?;
+
+        let mut hasher = Sha256::new();
+        for ((relative, _), (len, digest)) in files.iter().zip(digests) {
+            hasher.update(relative.as_bytes());
+            hasher.update([0u8]);
+            hasher.update(len.to_le_bytes());
+            hasher.update(digest);
+        }
+        Ok(format!("{}{}", CHECKSUM_V2_PREFIX, hex::encode(hasher.finalize())))
+    }
+
+    /// Whether `recorded` is in the "v2:" format `calculate_checksum` writes. Releases
+    /// before it recorded `calculate_legacy_checksum`, which can't be checked reliably.
+    pub fn is_current_checksum(recorded: &str) -> bool {
+        recorded.starts_with(CHECKSUM_V2_PREFIX)
+    }
+
+    /// The checksum releases before "v2:" recorded: one SHA256 over the contents of the
+    /// top-level files only, in `read_dir` order. That order isn't guaranteed to be the
+    /// same between runs, so a mismatch doesn't prove anything changed.
+    pub fn calculate_legacy_checksum(path: &Path) -> anyhow::Result<String> {
         use sha2::{Digest, Sha256};
         use std::io::Read;
 
@@ -345,20 +721,400 @@ impl Installer {
         let mut buffer = vec![0u8; 8192];
 
         for entry in fs::read_dir(path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() {
-                let mut file = fs::File::open(&path)?;
-                loop {
-                    let n = file.read(&mut buffer)?;
-                    if n == 0 {
-                        break;
-                    }
-                    hasher.update(&buffer[..n]);
+            let file_path = entry?.path();
+            if !file_path.is_file() {
+                continue;
+            }
+            let mut file = fs::File::open(&file_path)?;
+            loop {
+                let n = file.read(&mut buffer)?;
+                if n == 0 {
+                    break;
                 }
+                hasher.update(&buffer[..n]);
             }
         }
 
         Ok(hex::encode(hasher.finalize()))
     }
+
+    /// Size and SHA256 of one file
+    fn hash_file(path: &Path) -> anyhow::Result<(u64, [u8; 32])> {
+        use sha2::{Digest, Sha256};
+        use std::io::Read;
+
+        let mut file = fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+        let len = file.metadata()?.len();
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; CHECKSUM_BUFFER_SIZE.min(len as usize).max(1)];
+        loop {
+            let n = file.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buffer[..n]);
+        }
+        Ok((len, hasher.finalize().into()))
+    }
+
+    /// Every file under `path` with its "/"-separated relative path, sorted by that path
+    fn sorted_files(path: &Path) -> anyhow::Result<Vec<(String, PathBuf)>> {
+        let mut files = Vec::new();
+        Self::collect_files(path, &mut files)?;
+
+        let mut relative_paths: Vec<(String, PathBuf)> = files
+            .into_iter()
+            .map(|file| {
+                let relative = file
+                    .strip_prefix(path)
+                    .unwrap_or(&file)
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                (relative, file)
+            })
+            .collect();
+        relative_paths.sort();
+        Ok(relative_paths)
+    }
+
+    fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.is_dir() {
+                Self::collect_files(&path, files)?;
+            } else if path.is_file() {
+                files.push(path);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where an install is extracted before it's renamed to `install_path`. Not "php-"
+/// prefixed, so nothing scanning the install dir for versions picks it up.
+pub fn staging_path_for(install_path: &Path) -> PathBuf {
+    let name = install_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    install_path.with_file_name(format!(".staging-{}", name))
+}
+
+/// Rename a finished staging directory to `install_path`. An install already there
+/// (a force reinstall) is moved aside first and only deleted once the new one is in
+/// place, so it's put back if the rename fails.
+fn commit_staged_install(staging_path: &Path, install_path: &Path) -> anyhow::Result<()> {
+    if !install_path.exists() {
+        return fs::rename(staging_path, install_path)
+            .with_context(|| format!("Failed to move {:?} into place at {:?}", staging_path, install_path));
+    }
+
+    let name = install_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let replaced = install_path.with_file_name(format!(".replaced-{}", name));
+    if replaced.exists() {
+        fs::remove_dir_all(&replaced).with_context(|| format!("Failed to remove {:?}", replaced))?;
+    }
+    crate::logging::log_message("INFO", &format!("Force reinstall: replacing {:?}", install_path));
+    fs::rename(install_path, &replaced).with_context(|| format!("Failed to remove {:?} for reinstalling", install_path))?;
+    if let Err(e) = fs::rename(staging_path, install_path) {
+        let _ = fs::rename(&replaced, install_path);
+        return Err(e).with_context(|| format!("Failed to move {:?} into place at {:?}", staging_path, install_path));
+    }
+    if let Err(e) = fs::remove_dir_all(&replaced) {
+        crate::logging::log_message("WARN", &format!("Failed to remove replaced install {:?}: {}", replaced, e));
+    }
+    Ok(())
+}
+
+/// Run the configured post-install command for this OS, logging its output.
+/// Fails if it can't be started or exits non-zero.
+fn run_post_install_hook(hook: &config::PostInstallHook, install_path: &Path, version: &str) -> anyhow::Result<()> {
+    let Some(template) = hook.command_template() else {
+        return Ok(());
+    };
+    let command_line = config::PostInstallHook::render(template, install_path, version);
+    crate::logging::log_message("INFO", &format!("Running post-install hook: {}", command_line));
+
+    #[cfg(target_os = "windows")]
+    let output = {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        std::process::Command::new("cmd")
+            .arg("/C")
+            .raw_arg(&command_line)
+            .current_dir(install_path)
+            .creation_flags(CREATE_NO_WINDOW)
+            .output()
+    };
+    #[cfg(not(target_os = "windows"))]
+    let output = std::process::Command::new("sh")
+        .arg("-c")
+        .arg(&command_line)
+        .current_dir(install_path)
+        .output();
+
+    let output = output.with_context(|| format!("Failed to start post-install hook: {}", command_line))?;
+    for (stream, bytes) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        for line in String::from_utf8_lossy(bytes).lines().filter(|l| !l.trim().is_empty()) {
+            crate::logging::log_message("INFO", &format!("[post-install {}] {}", stream, line));
+        }
+    }
+    if !output.status.success() {
+        anyhow::bail!("Post-install hook for PHP {} exited with {}", version, output.status);
+    }
+    Ok(())
+}
+
+/// Check the freshly installed PHP against what was asked for, using its `php -i`.
+/// Returns the compiler tag to record: the reported one when it differs from
+/// `expected_compiler`. Mismatched versions or variants are logged, not fatal, and so
+/// is a PHP that can't run here (e.g. a Windows build on another OS).
+fn reconcile_build(php_exe: &Path, version: &str, thread_safe: bool, expected_compiler: String) -> String {
+    let build = match crate::platform::query_php_build(php_exe) {
+        Ok(build) => build,
+        Err(e) => {
+            crate::logging::log_message("DEBUG", &format!("Not verifying PHP {} build: {:#}", version, e));
+            return expected_compiler;
+        }
+    };
+    if build.php_version != version {
+        crate::logging::log_message("WARN", &format!("Installed PHP {} reports version {}", version, build.php_version));
+    }
+    if build.thread_safe != thread_safe {
+        crate::logging::log_message(
+            "WARN",
+            &format!(
+                "Installed PHP {} reports thread safety {}, but the {} build was requested",
+                version,
+                if build.thread_safe { "enabled" } else { "disabled" },
+                if thread_safe { "ts" } else { "nts" }
+            ),
+        );
+    }
+    match build.compiler {
+        Some(reported) if reported != expected_compiler => {
+            crate::logging::log_message(
+                "WARN",
+                &format!("Installed PHP {} was built with {}, not {}; recording {}", version, reported, expected_compiler, reported),
+            );
+            reported
+        }
+        _ => expected_compiler,
+    }
+}
+
+/// Compare an archive with its published SHA256. `required_by` names the provider that
+/// enforces verification: without a checksum the download fails instead of going
+/// through unverified. A mismatching archive is deleted so it isn't reused from the
+/// cache. Returns whether the archive was verified.
+pub async fn verify_archive_checksum(url: &str, archive: &Path, expected: Option<&str>, required_by: Option<&str>) -> anyhow::Result<bool> {
+    let Some(expected) = expected else {
+        if let Some(provider) = required_by {
+            return Err(PhpVmError::ChecksumMismatch(format!(
+                "No published checksum for {}, and provider '{}' requires one (set its verify_checksum to false to skip)",
+                url, provider
+            ))
+            .into());
+        }
+        crate::logging::log_message("WARN", &format!("No published checksum for {}, installing it unverified", url));
+        return Ok(false);
+    };
+
+    let actual = crate::download::file_sha256(archive.to_path_buf()).await?;
+    if !actual.eq_ignore_ascii_case(expected) {
+        let _ = fs::remove_file(archive);
+        return Err(PhpVmError::ChecksumMismatch(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            url, expected, actual
+        ))
+        .into());
+    }
+    crate::logging::log_message("INFO", &format!("Verified SHA256 of {}", url));
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_install_guard_refuses_the_same_target_until_dropped() {
+        let temp_dir = TempDir::new().unwrap();
+        let ts = temp_dir.path().join("php-8.3.0-ts");
+        let nts = temp_dir.path().join("php-8.3.0-nts");
+
+        let guard = InstallGuard::acquire(&ts, "8.3.0 (TS)").unwrap();
+        let error = InstallGuard::acquire(&ts, "8.3.0 (TS)").err().unwrap();
+        assert_eq!(error.code(), "INSTALL_IN_PROGRESS");
+        // Another target installs alongside it
+        let other = InstallGuard::acquire(&nts, "8.3.0 (NTS)").unwrap();
+
+        drop(guard);
+        assert!(InstallGuard::acquire(&ts, "8.3.0 (TS)").is_ok());
+        drop(other);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_post_install_hook() {
+        let temp_dir = TempDir::new().unwrap();
+        let hook = |command: &str| config::PostInstallHook {
+            windows: None,
+            unix: Some(command.to_string()),
+            fail_on_error: true,
+        };
+
+        run_post_install_hook(&hook("echo {version} > '{install_dir}/hook.txt'"), temp_dir.path(), "8.3.0-nts").unwrap();
+        assert_eq!(fs::read_to_string(temp_dir.path().join("hook.txt")).unwrap(), "8.3.0-nts\n");
+
+        let err = run_post_install_hook(&hook("echo failing >&2; exit 3"), temp_dir.path(), "8.3.0-nts").unwrap_err();
+        assert!(err.to_string().contains("exited with"));
+
+        // No command for this OS means nothing to run
+        let windows_only = config::PostInstallHook { windows: Some("exit 1".to_string()), unix: None, fail_on_error: true };
+        assert!(run_post_install_hook(&windows_only, temp_dir.path(), "8.3.0-nts").is_ok());
+    }
+
+    #[test]
+    fn test_plan_for_archived_nts() {
+        let version = PhpVersion::from_string("7.2.34").unwrap();
+        let plan = Installer::plan_for(Path::new("/phpvm/versions"), &version, false);
+
+        assert_eq!(plan.version, "7.2.34");
+        assert_eq!(plan.variant, "nts");
+        assert!(!plan.thread_safe);
+        assert_eq!(plan.compiler_tag, "VC15");
+        assert_eq!(
+            plan.download_url,
+            "https://windows.php.net/downloads/releases/archives/php-7.2.34-nts-Win32-VC15-x64.zip"
+        );
+        assert_eq!(plan.install_path, PathBuf::from("/phpvm/versions/php-7.2.34-nts"));
+    }
+
+    #[test]
+    fn test_plan_with_explicit_compiler_tag() {
+        let version = PhpVersion::from_string("7.4.33").unwrap();
+        let plan = Installer::plan_for_compiler(Path::new("/phpvm/versions"), &version, true, Some("vs16")).unwrap();
+        assert_eq!(plan.compiler_tag, "vs16");
+        assert_eq!(plan.download_url, "https://windows.php.net/downloads/releases/php-7.4.33-Win32-vs16-x64.zip");
+        assert_eq!(plan.install_path, PathBuf::from("/phpvm/versions/php-7.4.33-ts"));
+
+        assert_eq!(
+            Installer::plan_for_compiler(Path::new("/phpvm/versions"), &version, true, None).unwrap(),
+            Installer::plan_for(Path::new("/phpvm/versions"), &version, true)
+        );
+        let error = Installer::plan_for_compiler(Path::new("/phpvm/versions"), &version, true, Some("vs16/../x")).unwrap_err();
+        assert_eq!(PhpVmError::from(error).code(), "INVALID_ARGUMENT");
+    }
+
+    #[test]
+    fn test_plan_matches_provider_urls() {
+        let cases = [
+            ("8.3.29", true, "vs16", "https://windows.php.net/downloads/releases/php-8.3.29-Win32-vs16-x64.zip"),
+            ("8.3.29", false, "vs16", "https://windows.php.net/downloads/releases/php-8.3.29-nts-Win32-vs16-x64.zip"),
+            ("8.5.1", true, "vs17", "https://windows.php.net/downloads/releases/php-8.5.1-Win32-vs17-x64.zip"),
+            ("7.4.33", false, "vc15", "https://windows.php.net/downloads/releases/php-7.4.33-nts-Win32-vc15-x64.zip"),
+            // Archived branches, with the capitalised tags windows.php.net uses there
+            ("7.3.33", true, "VC15", "https://windows.php.net/downloads/releases/archives/php-7.3.33-Win32-VC15-x64.zip"),
+            ("7.2.34", false, "VC15", "https://windows.php.net/downloads/releases/archives/php-7.2.34-nts-Win32-VC15-x64.zip"),
+            ("7.1.33", false, "VC14", "https://windows.php.net/downloads/releases/archives/php-7.1.33-nts-Win32-VC14-x64.zip"),
+            ("7.0.33", true, "VC14", "https://windows.php.net/downloads/releases/archives/php-7.0.33-Win32-VC14-x64.zip"),
+        ];
+        for (version_str, thread_safe, compiler_tag, url) in cases {
+            let version = PhpVersion::from_string(version_str).unwrap();
+            let plan = Installer::plan_for(Path::new("/phpvm/versions"), &version, thread_safe);
+            assert_eq!(plan.download_url, url, "URL mismatch for {} (thread_safe={})", version_str, thread_safe);
+            assert_eq!(plan.compiler_tag, compiler_tag);
+        }
+    }
+
+    #[test]
+    fn test_calculate_checksum_is_reproducible() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["php.exe", "php8ts.dll", "php.ini-development", "license.txt"] {
+            fs::write(temp_dir.path().join(name), name.as_bytes()).unwrap();
+        }
+
+        let first = Installer::calculate_checksum(temp_dir.path()).unwrap();
+        let second = Installer::calculate_checksum(temp_dir.path()).unwrap();
+        assert_eq!(first, second);
+
+        fs::write(temp_dir.path().join("php.exe"), b"corrupted").unwrap();
+        assert_ne!(first, Installer::calculate_checksum(temp_dir.path()).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_checksum_includes_subdirectories() {
+        let first_dir = TempDir::new().unwrap();
+        let second_dir = TempDir::new().unwrap();
+        for dir in [first_dir.path(), second_dir.path()] {
+            fs::create_dir_all(dir.join("ext")).unwrap();
+            fs::write(dir.join("php.exe"), b"php").unwrap();
+            fs::write(dir.join("ext").join("php_curl.dll"), b"curl").unwrap();
+        }
+
+        // Same tree in two locations hashes the same
+        let first = Installer::calculate_checksum(first_dir.path()).unwrap();
+        assert_eq!(first, Installer::calculate_checksum(second_dir.path()).unwrap());
+
+        // Changing a file in a subdirectory changes the hash
+        fs::write(second_dir.path().join("ext").join("php_curl.dll"), b"changed").unwrap();
+        assert_ne!(first, Installer::calculate_checksum(second_dir.path()).unwrap());
+    }
+
+    #[test]
+    fn test_checksum_is_independent_of_thread_count() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("ext")).unwrap();
+        for i in 0..50 {
+            fs::write(temp_dir.path().join("ext").join(format!("php_{}.dll", i)), vec![i as u8; i * 1000]).unwrap();
+        }
+
+        let single = Installer::calculate_checksum_with(temp_dir.path(), 1).unwrap();
+        assert!(single.starts_with(CHECKSUM_V2_PREFIX));
+        assert_eq!(single, Installer::calculate_checksum_with(temp_dir.path(), 4).unwrap());
+        assert_eq!(single, Installer::calculate_checksum(temp_dir.path()).unwrap());
+
+        // Reporting progress doesn't change the result, and every file is counted once
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let recorded = calls.clone();
+        let progress: ChecksumProgress = Box::new(move |done, total| recorded.lock().unwrap().push((done, total)));
+        assert_eq!(single, Installer::calculate_checksum_reporting(temp_dir.path(), 4, Some(&progress)).unwrap());
+        let mut calls = calls.lock().unwrap().clone();
+        calls.sort();
+        assert_eq!(calls, (0..=50).map(|done| (done, 50)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_legacy_checksum_matches_the_pre_v2_algorithm() {
+        use sha2::{Digest, Sha256};
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("php.exe"), b"php").unwrap();
+        fs::create_dir_all(temp_dir.path().join("ext")).unwrap();
+        fs::write(temp_dir.path().join("ext").join("php_curl.dll"), b"curl").unwrap();
+
+        // What releases before v2 recorded: the top-level files' contents and nothing else
+        let baseline = hex::encode(Sha256::digest(b"php"));
+        let legacy = Installer::calculate_legacy_checksum(temp_dir.path()).unwrap();
+        assert_eq!(legacy, baseline);
+        assert!(!Installer::is_current_checksum(&legacy));
+        assert!(Installer::is_current_checksum(&Installer::calculate_checksum(temp_dir.path()).unwrap()));
+    }
+
+    #[test]
+    fn test_calculate_checksum_detects_renames() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("ext")).unwrap();
+        fs::write(temp_dir.path().join("ext").join("php_curl.dll"), b"curl").unwrap();
+        let before = Installer::calculate_checksum(temp_dir.path()).unwrap();
+
+        fs::rename(
+            temp_dir.path().join("ext").join("php_curl.dll"),
+            temp_dir.path().join("php_curl.dll"),
+        )
+        .unwrap();
+        assert_ne!(before, Installer::calculate_checksum(temp_dir.path()).unwrap());
+    }
 }
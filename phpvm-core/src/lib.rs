@@ -1,13 +1,24 @@
+pub mod backup;
 pub mod config;
+pub mod date;
 pub mod download;
+pub mod error;
+pub mod extension;
+pub mod ide;
+pub mod ini;
 pub mod install;
 pub mod logging;
 pub mod manager;
 pub mod platform;
+pub mod project;
 pub mod provider;
+pub mod schema;
 pub mod state;
+pub mod stats;
+pub mod timezone;
 pub mod version;
 
+pub use error::PhpVmError;
 pub use manager::PhpManager;
 pub use provider::VersionInfo;
 pub use state::{PhpState, InstallMetadata};
@@ -1,13 +1,34 @@
 use crate::config;
+use crate::error::PhpVmError;
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::io::Write;
-use std::path::PathBuf;
-use std::sync::Mutex;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{reload, Registry};
 
 static LOG_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
 static MAX_LOG_SIZE: u64 = 10 * 1024 * 1024; // 10 MB
 
+/// Levels from most to least severe; a message is written when its index is at most
+/// the threshold's
+const LEVELS: [&str; 4] = ["ERROR", "WARN", "INFO", "DEBUG"];
+static LOG_LEVEL: AtomicU8 = AtomicU8::new(if cfg!(debug_assertions) { 3 } else { 2 });
+/// Level filter of the subscriber `init_tracing` installed, kept at LOG_LEVEL
+static TRACING_FILTER: OnceLock<reload::Handle<LevelFilter, Registry>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogFileInfo {
+    pub name: String,
+    pub size: u64,
+    pub modified: String, // Unix timestamp in seconds, like cached files
+}
+
 pub fn init() -> anyhow::Result<()> {
     let log_path = config::get_log_path();
     
@@ -30,6 +51,31 @@ pub fn init() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Install the global `tracing` subscriber, printing to stderr. Its level filter follows
+/// `set_log_level`, so direct `tracing::*` calls get the same threshold as the log file.
+/// Does nothing if a subscriber is already installed.
+pub fn init_tracing() {
+    install_tracing(std::io::stderr);
+}
+
+fn install_tracing<W>(writer: W)
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    let (filter, handle) = reload::Layer::new(level_filter(LOG_LEVEL.load(Ordering::Relaxed)));
+    let subscriber = tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(writer));
+    if tracing::subscriber::set_global_default(subscriber).is_ok() {
+        let _ = TRACING_FILTER.set(handle);
+    }
+}
+
+/// The tracing filter for an index into LEVELS
+fn level_filter(index: u8) -> LevelFilter {
+    [LevelFilter::ERROR, LevelFilter::WARN, LevelFilter::INFO, LevelFilter::DEBUG][index as usize]
+}
+
 fn rotate_log(log_path: &PathBuf) -> anyhow::Result<()> {
     // Create backup with timestamp
     let timestamp = SystemTime::now()
@@ -49,7 +95,38 @@ fn rotate_log(log_path: &PathBuf) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Index of `level` in LEVELS, ignoring case; None for anything else
+fn level_index(level: &str) -> Option<u8> {
+    LEVELS.iter().position(|l| l.eq_ignore_ascii_case(level)).map(|i| i as u8)
+}
+
+/// Only write messages at `level` ("ERROR", "WARN", "INFO" or "DEBUG") or more severe,
+/// to the log file and the `init_tracing` subscriber, until the process exits or this is
+/// called again
+pub fn set_log_level(level: &str) -> anyhow::Result<()> {
+    let index = level_index(level.trim()).ok_or_else(|| {
+        PhpVmError::InvalidArgument(format!("Unknown log level {:?}; use one of {}", level, LEVELS.join(", ")))
+    })?;
+    LOG_LEVEL.store(index, Ordering::Relaxed);
+    if let Some(handle) = TRACING_FILTER.get() {
+        handle
+            .reload(level_filter(index))
+            .map_err(|e| anyhow::anyhow!("Failed to update the tracing level: {}", e))?;
+    }
+    log_message("INFO", &format!("Log level set to {}", LEVELS[index as usize]));
+    Ok(())
+}
+
+/// The current threshold: DEBUG in debug builds and INFO in release ones until changed
+pub fn get_log_level() -> &'static str {
+    LEVELS[LOG_LEVEL.load(Ordering::Relaxed) as usize]
+}
+
 pub fn log_message(level: &str, message: &str) {
+    // Unknown levels count as INFO, as they do for tracing below
+    if level_index(level).unwrap_or(2) > LOG_LEVEL.load(Ordering::Relaxed) {
+        return;
+    }
     if let Some(ref log_path) = *LOG_FILE.lock().unwrap() {
         if let Ok(mut file) = fs::OpenOptions::new()
             .create(true)
@@ -81,3 +158,199 @@ fn format_timestamp(secs: u64) -> String {
     // For a proper implementation, use chrono crate
     format!("{}", secs)
 }
+
+/// Return the last `max_lines` lines of the log file (oldest first)
+pub fn read_log_tail(max_lines: usize) -> anyhow::Result<Vec<String>> {
+    let log_path = config::get_log_path();
+    if !log_path.exists() {
+        return Ok(Vec::new());
+    }
+    read_tail(&log_path, max_lines)
+}
+
+/// Truncate the log file, keeping it in place so the logger can keep appending
+pub fn clear_log() -> anyhow::Result<()> {
+    let log_path = config::get_log_path();
+    if log_path.exists() {
+        fs::File::create(&log_path)?;
+    }
+    log_message("INFO", "Log cleared");
+    Ok(())
+}
+
+/// List the active log and its rotated backups, active log first then newest backups
+pub fn list_log_files() -> anyhow::Result<Vec<LogFileInfo>> {
+    let log_path = config::get_log_path();
+    match log_path.parent() {
+        Some(logs_dir) if logs_dir.exists() => list_log_files_in(logs_dir, &log_path),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Delete rotated backups beyond the `keep` most recent. The active log is never removed.
+/// Returns the number of files deleted.
+pub fn purge_old_logs(keep: usize) -> anyhow::Result<usize> {
+    let log_path = config::get_log_path();
+    let removed = match log_path.parent() {
+        Some(logs_dir) if logs_dir.exists() => purge_old_logs_in(logs_dir, &log_path, keep)?,
+        _ => 0,
+    };
+    log_message("INFO", &format!("Purged {} old log file(s), kept {}", removed, keep));
+    Ok(removed)
+}
+
+fn is_log_file_name(name: &str) -> bool {
+    name.starts_with("phpvm") && name.ends_with(".log")
+}
+
+fn list_log_files_in(logs_dir: &Path, active_log: &Path) -> anyhow::Result<Vec<LogFileInfo>> {
+    let active_name = active_log.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(logs_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !is_log_file_name(&name) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        files.push(LogFileInfo {
+            name,
+            size: metadata.len(),
+            modified: modified.to_string(),
+        });
+    }
+
+    // Active log first, then backups newest first (rotated names embed the timestamp)
+    files.sort_by(|a, b| {
+        (b.name == active_name)
+            .cmp(&(a.name == active_name))
+            .then_with(|| b.modified.parse::<u64>().unwrap_or(0).cmp(&a.modified.parse::<u64>().unwrap_or(0)))
+            .then_with(|| b.name.cmp(&a.name))
+    });
+    Ok(files)
+}
+
+fn purge_old_logs_in(logs_dir: &Path, active_log: &Path, keep: usize) -> anyhow::Result<usize> {
+    let active_name = active_log.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    let mut removed = 0;
+
+    let backups = list_log_files_in(logs_dir, active_log)?
+        .into_iter()
+        .filter(|f| f.name != active_name);
+    for file in backups.skip(keep) {
+        fs::remove_file(logs_dir.join(&file.name))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Read the last `max_lines` lines of a file by scanning backwards in chunks,
+/// so large logs don't have to be loaded into memory
+fn read_tail(path: &Path, max_lines: usize) -> anyhow::Result<Vec<String>> {
+    const CHUNK_SIZE: u64 = 8192;
+
+    if max_lines == 0 {
+        return Ok(Vec::new());
+    }
+
+    let mut file = fs::File::open(path)?;
+    let file_len = file.metadata()?.len();
+    let mut pos = file_len;
+    let mut buffer: Vec<u8> = Vec::new();
+
+    // Stop once we have one more newline than requested lines (the trailing one doesn't count)
+    while pos > 0 && buffer.iter().filter(|&&b| b == b'\n').count() <= max_lines {
+        let read_size = CHUNK_SIZE.min(pos);
+        pos -= read_size;
+        file.seek(SeekFrom::Start(pos))?;
+        let mut chunk = vec![0u8; read_size as usize];
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&buffer);
+        buffer = chunk;
+    }
+
+    let content = String::from_utf8_lossy(&buffer);
+    let lines: Vec<&str> = content.lines().collect();
+    // The first line may be partial if we stopped mid-file
+    let start = lines.len().saturating_sub(max_lines);
+    Ok(lines[start..].iter().map(|l| l.to_string()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_read_tail_returns_last_lines() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("phpvm.log");
+        let content: String = (0..5000).map(|i| format!("line {}\n", i)).collect();
+        fs::write(&log_path, content).unwrap();
+
+        let tail = read_tail(&log_path, 3).unwrap();
+        assert_eq!(tail, vec!["line 4997", "line 4998", "line 4999"]);
+    }
+
+    #[test]
+    fn test_list_and_purge_log_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let active = temp_dir.path().join("phpvm.log");
+        fs::write(&active, "current").unwrap();
+        for ts in ["1700000000", "1700000100", "1700000200"] {
+            fs::write(temp_dir.path().join(format!("phpvm.{}.log", ts)), ts).unwrap();
+        }
+        fs::write(temp_dir.path().join("other.log"), "not ours").unwrap();
+        fs::write(temp_dir.path().join("phpvm.txt"), "not a log").unwrap();
+
+        let files = list_log_files_in(temp_dir.path(), &active).unwrap();
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[0].name, "phpvm.log");
+
+        let removed = purge_old_logs_in(temp_dir.path(), &active, 1).unwrap();
+        assert_eq!(removed, 2);
+        assert!(active.exists());
+        assert!(temp_dir.path().join("other.log").exists());
+        assert!(temp_dir.path().join("phpvm.txt").exists());
+        assert_eq!(list_log_files_in(temp_dir.path(), &active).unwrap().len(), 2);
+
+        // keep = 0 still leaves the active log alone
+        assert_eq!(purge_old_logs_in(temp_dir.path(), &active, 0).unwrap(), 1);
+        assert!(active.exists());
+    }
+
+    #[test]
+    fn test_set_log_level() {
+        install_tracing(std::io::sink);
+        let original = get_log_level();
+        set_log_level("warn").unwrap();
+        assert_eq!(get_log_level(), "WARN");
+        // The tracing subscriber follows the same threshold
+        assert_eq!(LevelFilter::current(), LevelFilter::WARN);
+        assert!(set_log_level("verbose").is_err());
+        assert_eq!(get_log_level(), "WARN");
+        set_log_level("error").unwrap();
+        assert_eq!(LevelFilter::current(), LevelFilter::ERROR);
+        set_log_level(original).unwrap();
+    }
+
+    #[test]
+    fn test_read_tail_short_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("phpvm.log");
+        fs::write(&log_path, "first\nsecond").unwrap();
+
+        assert_eq!(read_tail(&log_path, 10).unwrap(), vec!["first", "second"]);
+        assert!(read_tail(&log_path, 0).unwrap().is_empty());
+    }
+}
@@ -1,13 +1,324 @@
 use crate::config;
+use crate::download::Downloader;
+use crate::error::PhpVmError;
+use crate::extension::{self, ExtensionProvider, ExtensionTarget, XdebugProvider};
 use crate::install::Installer;
 use crate::logging;
 use crate::platform;
 use crate::provider::Provider;
 use crate::state::PhpState;
-use crate::version::PhpVersion;
+use crate::version::{validate_version_spec, validate_version_str, PhpVersion};
 use anyhow::Context;
+use serde::{Deserialize, Serialize};
 use std::fs;
 
+/// What `PhpManager::uninstall_all` removed, left in place, or failed to remove
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UninstallReport {
+    pub removed: Vec<String>,
+    pub kept: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// A directory under the install dir that looks like a PHP install but has no PHP executable
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokenInstall {
+    /// Variant string from the directory name, e.g. "8.3.0-nts"
+    pub version: String,
+    pub path: std::path::PathBuf,
+    /// Whether state still lists it as installed
+    pub in_state: bool,
+}
+
+pub use crate::platform::BuildInfo;
+
+/// An available version with an archive already in the download cache, per variant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheReadyVersion {
+    pub version: String,
+    pub ts: bool,
+    pub nts: bool,
+}
+
+/// An installed version as listed in a `StatusReport`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledStatus {
+    /// e.g. "8.3.0-nts"
+    pub version: String,
+    /// "ts" or "nts"
+    pub variant: String,
+    pub active: bool,
+    pub path: std::path::PathBuf,
+}
+
+/// Installed, active, available and PATH state in one struct, for scripts and
+/// integrators. The JSON field names are a stable contract: fields may be added,
+/// existing ones aren't renamed or removed without bumping `schema_version`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusReport {
+    pub schema_version: u32,
+    pub installed: Vec<InstalledStatus>,
+    pub active: Option<String>,
+    /// Versions offered for download; empty when they couldn't be fetched
+    pub available: Vec<String>,
+    /// Why `available` couldn't be fetched, if it couldn't
+    pub available_error: Option<String>,
+    pub path_configured: bool,
+    pub install_dir: std::path::PathBuf,
+}
+
+pub const STATUS_REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// What `switch` did besides making the version active
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SwitchOutcome {
+    /// Why the `current` directory couldn't be added to PATH, if it couldn't (e.g. a
+    /// policy blocks the write). The switch itself still happened.
+    pub path_warning: Option<String>,
+}
+
+/// What `shell_activate` resolved, for one shell session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivationInfo {
+    /// The install, e.g. "8.1.27-nts"
+    pub version: String,
+    /// Directory to put first on PATH
+    pub path_dir: std::path::PathBuf,
+    /// Snippet that does that when sourced or evaluated, if a shell was given
+    pub script: Option<String>,
+}
+
+/// Whether `php` on the live PATH is the one phpvm manages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhpResolutionStatus {
+    /// `php` runs phpvm's shim
+    Active,
+    /// Another php comes first on PATH
+    Shadowed,
+    /// No php on PATH at all
+    NotFound,
+}
+
+/// Where `php` resolves on this process's PATH, compared with phpvm's `current` directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhpResolution {
+    pub status: PhpResolutionStatus,
+    /// The php PATH finds first, if any
+    pub resolved: Option<std::path::PathBuf>,
+    /// phpvm's `current` directory, where the shim lives
+    pub shim_dir: std::path::PathBuf,
+}
+
+/// The available versions this machine can actually install
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallableVersions {
+    /// `std::env::consts::OS` and `ARCH`, e.g. "windows" and "x86_64"
+    pub os: String,
+    pub arch: String,
+    /// Empty when installs aren't supported here; `reason` says why
+    pub versions: Vec<String>,
+    pub reason: Option<String>,
+}
+
+/// Whether an install could download its archive right now
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DownloadCheck {
+    /// The URL the install would use: the first that answered, else the primary one
+    pub url: String,
+    pub status_code: Option<u16>,
+    pub reachable: bool,
+    pub content_length: Option<u64>,
+    /// Every URL probed, in the order the installer tries them
+    pub probes: Vec<crate::provider::UrlProbe>,
+}
+
+/// The active install, split into its parts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActiveVersion {
+    /// As `get_active` reports it, e.g. "8.3.0-ts"
+    pub version: String,
+    /// "8.3.0"
+    pub base_version: String,
+    /// "ts" or "nts"
+    pub variant: String,
+    pub install_path: std::path::PathBuf,
+}
+
+/// The php this process's PATH really runs, next to the active version phpvm has recorded
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectivePhp {
+    /// The php PATH finds first, if any
+    pub path: Option<std::path::PathBuf>,
+    /// What its `php -v` reports; None if it doesn't run
+    pub version: Option<String>,
+    /// Whether it's phpvm's shim or one of phpvm's installs
+    pub managed: bool,
+    /// phpvm's active version
+    pub active: Option<String>,
+}
+
+/// Every version a cached archive can belong to: each patch from .0 up to the newest
+/// listed one in the listed branches (the list only names the newest), plus `recorded`
+/// versions such as installed or pinned ones ("8.1.27-nts"), in whichever branch.
+pub fn cache_owner_versions(listed: &[crate::provider::VersionInfo], recorded: &[String]) -> Vec<PhpVersion> {
+    let mut versions = std::collections::BTreeSet::new();
+    for info in listed {
+        let Ok(newest) = PhpVersion::from_string(&info.version) else { continue };
+        for patch in 0..=newest.patch {
+            versions.insert(PhpVersion { major: newest.major, minor: newest.minor, patch, suffix: None });
+        }
+        versions.insert(newest);
+    }
+    for version in recorded {
+        let base = version.strip_suffix("-ts").or_else(|| version.strip_suffix("-nts")).unwrap_or(version);
+        if let Ok(parsed) = PhpVersion::from_string(base) {
+            versions.insert(parsed);
+        }
+    }
+    versions.into_iter().collect()
+}
+
+/// The probing half of `PhpManager::check_version_downloadable`, for `urls` from
+/// `get_download_url`; needs no manager, so no lock is held while probing
+pub async fn probe_download_urls(provider: &Provider, urls: &crate::install::DownloadUrls) -> DownloadCheck {
+    let mut probes = Vec::new();
+    for url in std::iter::once(&urls.primary).chain(&urls.fallbacks) {
+        let probe = provider.probe_download(url).await;
+        logging::log_message("DEBUG", &format!("Probed {}: {:?}", url, probe.status_code));
+        let reachable = probe.reachable;
+        probes.push(probe);
+        if reachable {
+            break;
+        }
+    }
+    let chosen = probes.iter().find(|probe| probe.reachable).unwrap_or(&probes[0]).clone();
+    DownloadCheck {
+        url: chosen.url,
+        status_code: chosen.status_code,
+        reachable: chosen.reachable,
+        content_length: chosen.content_length,
+        probes,
+    }
+}
+
+/// `PhpManager::check_versions_online` with a provider taken from the manager beforehand
+pub async fn check_versions_online_with(provider: &Provider, version_strs: &[String]) -> std::collections::HashMap<String, bool> {
+    let known = provider.fetch_available_versions().await.unwrap_or_default();
+
+    let mut urls = std::collections::HashMap::new();
+    for version_str in version_strs {
+        let url = known
+            .iter()
+            .find(|v| &v.version == version_str)
+            .and_then(|v| v.download_url.clone())
+            .or_else(|| {
+                PhpVersion::from_string(version_str)
+                    .ok()
+                    .map(|v| Provider::generate_download_url(version_str, v.major, v.minor, true))
+            });
+        if let Some(url) = url {
+            urls.insert(version_str.clone(), url);
+        }
+    }
+
+    let unique_urls: Vec<String> = urls.values().cloned().collect();
+    let availability = provider.check_urls_available(&unique_urls).await;
+
+    version_strs
+        .iter()
+        .map(|v| {
+            let online = urls
+                .get(v)
+                .and_then(|url| availability.get(url))
+                .copied()
+                .unwrap_or(false);
+            (v.clone(), online)
+        })
+        .collect()
+}
+
+/// Which of `versions` have a cached archive, checking every URL an install of that
+/// variant could fetch from. Versions with neither variant cached are left out.
+pub fn cache_ready_versions(versions: &[crate::provider::VersionInfo], is_cached: impl Fn(&str) -> bool) -> Vec<CacheReadyVersion> {
+    let variant_cached = |info: &crate::provider::VersionInfo, version: &PhpVersion, thread_safe: bool| {
+        let listed = info
+            .download_url
+            .iter()
+            .filter(|url| Provider::detect_thread_safe_from_url(url) == Some(thread_safe))
+            .cloned();
+        Provider::install_download_urls(&info.version, version.major, version.minor, thread_safe)
+            .into_iter()
+            .chain(listed)
+            .any(|url| is_cached(&url))
+    };
+
+    versions
+        .iter()
+        .filter_map(|info| {
+            let version = PhpVersion::from_string(&info.version).ok()?;
+            let ts = variant_cached(info, &version, true);
+            let nts = variant_cached(info, &version, false);
+            (ts || nts).then(|| CacheReadyVersion { version: info.version.clone(), ts, nts })
+        })
+        .collect()
+}
+
+/// Keep config.json's copy of the active version (its base, "8.3.0") in step with
+/// state.json's, so older readers of config.json see the same version
+fn mirror_active_to_config(active: Option<&str>) -> anyhow::Result<()> {
+    let base = active.map(|version| version.split('-').next().unwrap_or(version).to_string());
+    let mut config = config::Config::load()?;
+    if config.active_version != base {
+        config.active_version = base;
+        config.save()?;
+    }
+    Ok(())
+}
+
+/// Which of `installed` the `current` shim runs: the symlink target on Unix, the path in
+/// php.bat on Windows. None if there's no shim or it points elsewhere.
+fn active_from_shim(installed: &[String]) -> Option<String> {
+    let current = platform::get_current_path();
+    let target = fs::read_link(&current)
+        .map(|path| path.to_string_lossy().into_owned())
+        .or_else(|_| fs::read_to_string(&current))
+        .ok()?;
+    installed
+        .iter()
+        .find(|version| target.contains(&format!("php-{}", version)))
+        .cloned()
+}
+
+/// Modules loaded by one installed version but not the other, from `PhpManager::diff_modules`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleDiff {
+    pub from: String,
+    pub to: String,
+    /// Loaded by `to` but not `from`
+    pub added: Vec<String>,
+    /// Loaded by `from` but not `to`
+    pub removed: Vec<String>,
+}
+
+impl ModuleDiff {
+    pub fn between(from: &str, from_modules: &[String], to: &str, to_modules: &[String]) -> Self {
+        let missing_from = |modules: &[String], other: &[String]| -> Vec<String> {
+            modules
+                .iter()
+                .filter(|m| !other.iter().any(|o| o.eq_ignore_ascii_case(m)))
+                .cloned()
+                .collect()
+        };
+        ModuleDiff {
+            from: from.to_string(),
+            to: to.to_string(),
+            added: missing_from(to_modules, from_modules),
+            removed: missing_from(from_modules, to_modules),
+        }
+    }
+}
+
 pub struct PhpManager {
     installer: Installer,
     config: config::Config,
@@ -20,16 +331,120 @@ impl PhpManager {
         
         logging::log_message("INFO", "PHP Manager initialized");
         
-        Ok(PhpManager {
+        let manager = PhpManager {
             installer: Installer::new()?,
             config: config::Config::load()?,
-        })
+        };
+        if let Err(e) = manager.migrate_legacy_installs() {
+            logging::log_message("WARN", &format!("Failed to migrate legacy installs: {:#}", e));
+        }
+        Ok(manager)
+    }
+
+    /// Rename installs from before TS/NTS variants were tracked ("php-8.2.0") to the
+    /// suffixed layout ("php-8.2.0-ts") and update state to match. The variant comes from
+    /// the "Thread Safety" line of `php -i`, or TS (what phpvm installed then) if PHP
+    /// can't be run. Returns the new version names; safe to run repeatedly. An install that
+    /// can't be moved doesn't stop the others: state is saved for the ones that were, then
+    /// the failures are returned as one error.
+    pub fn migrate_legacy_installs(&self) -> anyhow::Result<Vec<String>> {
+        let mut migrated = Vec::new();
+        if !self.config.install_dir.exists() {
+            return Ok(migrated);
+        }
+
+        let mut state = PhpState::load()?;
+        let mut failures = Vec::new();
+        for entry in fs::read_dir(&self.config.install_dir)? {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    failures.push(format!("Failed to read {:?}: {}", self.config.install_dir, e));
+                    continue;
+                }
+            };
+            let Some(version) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix("php-"))
+                .filter(|v| PhpVersion::from_string(v).is_ok_and(|parsed| parsed.suffix.is_none()))
+                .map(str::to_string)
+            else {
+                continue;
+            };
+            let php_exe = platform::get_php_executable_path(&path);
+            if !path.is_dir() || !php_exe.exists() {
+                continue;
+            }
+
+            let thread_safe = match platform::query_php_build(&php_exe) {
+                Ok(build) => build.thread_safe,
+                Err(e) => {
+                    logging::log_message("WARN", &format!("{:#}; assuming {:?} is thread safe", e, path));
+                    true
+                }
+            };
+            let new_version = format!("{}-{}", version, if thread_safe { "ts" } else { "nts" });
+            let new_path = self.config.install_dir.join(format!("php-{}", new_version));
+            if new_path.exists() {
+                logging::log_message(
+                    "WARN",
+                    &format!("Not migrating {:?}: {:?} already exists", path, new_path),
+                );
+                continue;
+            }
+
+            if let Err(e) = fs::rename(&path, &new_path) {
+                failures.push(format!("Failed to rename {:?} to {:?}: {}", path, new_path, e));
+                continue;
+            }
+            state.rename_version(&version, &new_version, &new_path);
+            if state.active_version.as_deref() == Some(new_version.as_str()) {
+                // The shim still points into the old directory
+                if let Err(e) = point_current_at(&self.config, &platform::get_php_executable_path(&new_path), &new_path) {
+                    failures.push(format!("Failed to repoint the current version at {:?}: {:#}", new_path, e));
+                }
+            }
+            logging::log_message("INFO", &format!("Migrated legacy install {} to {:?}", version, new_path));
+            migrated.push(new_version);
+        }
+
+        if !migrated.is_empty() {
+            state.save()?;
+        }
+        if !failures.is_empty() {
+            anyhow::bail!("{}", failures.join("; "));
+        }
+        Ok(migrated)
+    }
+
+    /// Install a version. An existing install of the same variant is refused unless
+    /// `force_reinstall` is set, which deletes its directory and installs it again.
+    pub async fn install(&self, version_str: &str, thread_safe: Option<bool>, force_reinstall: bool, progress_callback: Option<Box<dyn FnMut(u64, u64, f64) + Send + Sync>>) -> anyhow::Result<()> {
+        self.install_with_options(version_str, thread_safe, force_reinstall, progress_callback, Default::default()).await
     }
 
-    pub async fn install(&self, version_str: &str, thread_safe: Option<bool>, progress_callback: Option<Box<dyn FnMut(u64, u64, f64) + Send + Sync>>) -> anyhow::Result<()> {
+    /// `install` with `options`: a compiler tag overriding the branch's usual one (for
+    /// builds published under another tag), and (files hashed, total files) reports while
+    /// the extracted install is checksummed, the quiet phase between the download
+    /// finishing and the install being registered
+    pub async fn install_with_options(
+        &self,
+        version_str: &str,
+        thread_safe: Option<bool>,
+        force_reinstall: bool,
+        progress_callback: Option<crate::download::ProgressCallback>,
+        options: crate::install::InstallOptions,
+    ) -> anyhow::Result<()> {
         logging::log_message("INFO", &format!("Starting installation of PHP {}", version_str));
+        validate_version_str(version_str)?;
         let version = PhpVersion::from_string(version_str)?;
-        match self.installer.install_version(&version, None, thread_safe, progress_callback).await {
+        let thread_safe = thread_safe.or(self.config.default_thread_safe);
+        match self
+            .installer
+            .install_version_with_options(&version, None, thread_safe, force_reinstall, progress_callback, options)
+            .await
+        {
             Ok(_) => {
                 logging::log_message("INFO", &format!("Successfully installed PHP {}", version_str));
                 Ok(())
@@ -41,15 +456,56 @@ impl PhpManager {
         }
     }
 
-    pub async fn remove(&self, version_str: &str) -> anyhow::Result<()> {
+    /// Dry run of `install`: report the URL, variant, compiler tag and target directory
+    /// that would be used, without downloading or writing anything
+    pub fn plan_install(&self, version_str: &str, thread_safe: Option<bool>) -> anyhow::Result<crate::install::InstallPlan> {
+        self.plan_install_with_compiler(version_str, thread_safe, None)
+    }
+
+    /// `plan_install` for an install with an explicit compiler tag
+    pub fn plan_install_with_compiler(
+        &self,
+        version_str: &str,
+        thread_safe: Option<bool>,
+        compiler_tag: Option<&str>,
+    ) -> anyhow::Result<crate::install::InstallPlan> {
+        validate_version_str(version_str)?;
+        let version = PhpVersion::from_string(version_str)?;
+        self.installer
+            .plan_with_compiler(&version, thread_safe.or(self.config.default_thread_safe).unwrap_or(true), compiler_tag)
+    }
+
+    /// The URL an install of this version and variant would download first, and the
+    /// fallbacks it tries after it in order, including configured mirrors. Touches
+    /// neither the network nor the disk, so a failing URL can be checked by hand.
+    pub fn get_download_url(&self, version_str: &str, thread_safe: Option<bool>) -> anyhow::Result<crate::install::DownloadUrls> {
+        let plan = self.plan_install(version_str, thread_safe)?;
+        let mut urls = self.installer.download_attempts(&plan, None)?.into_iter().map(|(url, _)| url);
+        let primary = urls.next().unwrap_or(plan.download_url);
+        Ok(crate::install::DownloadUrls { primary, fallbacks: urls.collect() })
+    }
+
+    /// Download a version's archive into the cache without installing it, so a later
+    /// `install` works offline. The archive is checked against the published SHA256
+    /// as its provider's `verify_checksum` says, like an install.
+    pub async fn prefetch(&self, version_str: &str, thread_safe: Option<bool>, progress_callback: Option<crate::download::ProgressCallback>) -> anyhow::Result<std::path::PathBuf> {
+        validate_version_str(version_str)?;
+        let version = PhpVersion::from_string(version_str)?;
+        let plan = self.installer.plan(&version, thread_safe.or(self.config.default_thread_safe).unwrap_or(true));
+        let (_, download, _lease) = self.installer.download_archive(&plan, None, progress_callback).await?;
+
+        logging::log_message("INFO", &format!("Prefetched PHP {} to {:?}", version_str, download.path));
+        Ok(download.path)
+    }
+
+    /// Remove an installed version. The active version is refused unless `force` is set,
+    /// in which case phpvm first switches to another install (see `switch_away_from`).
+    pub async fn remove(&self, version_str: &str, force: bool) -> anyhow::Result<()> {
+        validate_version_str(version_str)?;
         // version_str can be "8.5.1-ts" or "8.5.1-nts"
         // Extract base version for PhpVersion parsing (first 3 parts: major.minor.patch)
-        let base_version_str = if version_str.contains("-ts") || version_str.contains("-nts") {
-            version_str.split('-').take(3).collect::<Vec<_>>().join(".")
-        } else {
-            version_str.to_string()
-        };
-        let version = PhpVersion::from_string(&base_version_str)?;
+        let base_version_str = version_str.split('-').next().unwrap_or(version_str);
+        let version = PhpVersion::from_string(base_version_str)?;
         // Pass the full version string (with variant) to remove_version
         // We'll need to update remove_version signature, but for now, store it in version's suffix
         let mut version_with_variant = version.clone();
@@ -58,25 +514,86 @@ impl PhpManager {
         } else if version_str.contains("-nts") {
             version_with_variant.suffix = Some("nts".to_string());
         }
-        self.installer.remove_version(&version_with_variant).await?;
-        Ok(())
+        match self.installer.remove_version(&version_with_variant).await {
+            Err(e) if force && matches!(e.downcast_ref::<PhpVmError>(), Some(PhpVmError::ActiveVersionInUse(_))) => {
+                let active = PhpState::load()?.active_version.unwrap_or_default();
+                self.switch_away_from(&active).await?;
+                self.installer.remove_version(&version_with_variant).await
+            }
+            result => result,
+        }?;
+        mirror_active_to_config(PhpState::load()?.active_version.as_deref())
     }
 
-    pub async fn switch(&self, version_str: &str) -> anyhow::Result<()> {
-        // version_str can be "8.5.1-ts" or "8.5.1-nts" or just "8.5.1" (use first available)
+    /// Make something other than `version` active: the last known good version, else the
+    /// newest other install. With nothing else usable, PHP is deactivated so the `current`
+    /// shim never points at a directory about to be deleted.
+    async fn switch_away_from(&self, version: &str) -> anyhow::Result<()> {
+        let state = PhpState::load()?;
+        let mut candidates: Vec<String> = self.list_installed()?.into_iter().filter(|v| v != version).collect();
+        candidates.sort_by(|a, b| {
+            let va = PhpVersion::from_string(a.split('-').next().unwrap_or(a)).unwrap_or_default();
+            let vb = PhpVersion::from_string(b.split('-').next().unwrap_or(b)).unwrap_or_default();
+            vb.cmp(&va)
+        });
+        if let Some(good) = state.last_known_good.filter(|good| candidates.contains(good)) {
+            candidates.retain(|v| *v != good);
+            candidates.insert(0, good);
+        }
+
+        for candidate in candidates {
+            match self.switch(&candidate).await {
+                Ok(_) => {
+                    logging::log_message("INFO", &format!("Switched from {} to {} before removing it", version, candidate));
+                    return Ok(());
+                }
+                Err(e) => logging::log_message("WARN", &format!("Could not switch to {}: {:#}", candidate, e)),
+            }
+        }
+
+        logging::log_message("INFO", &format!("No other version to switch to, deactivating {}", version));
+        self.deactivate()
+    }
+
+    /// Remove the `current` shim and clear the active version
+    fn deactivate(&self) -> anyhow::Result<()> {
+        let current_path = platform::get_current_path();
+        #[cfg(target_os = "windows")]
+        if let Some(current_dir) = current_path.parent() {
+            clear_current_dir(current_dir);
+            update_windows_apps_launcher(self.config.switch_strategy, None);
+        }
+        // symlink_metadata so a Unix symlink is removed even when its target is gone
+        if fs::symlink_metadata(&current_path).is_ok() {
+            fs::remove_file(&current_path).with_context(|| format!("Failed to remove {:?}", current_path))?;
+        }
+
+        let mut state = PhpState::load()?;
+        state.active_version = None;
+        state.save()?;
+        mirror_active_to_config(None)
+    }
+
+    pub async fn switch(&self, version_str: &str) -> anyhow::Result<SwitchOutcome> {
+        // version_str can be "8.5.1-ts", "8.5.1", or a branch like "8.2" (newest installed patch)
+        validate_version_spec(version_str)?;
         let state = PhpState::load()?;
-        
-        // Find the installed version (with variant suffix)
-        let installed_version = if state.installed_versions.contains(&version_str.to_string()) {
-            version_str.to_string()
-        } else {
-            // Try to find any variant of this version
-            state.installed_versions
-                .iter()
-                .find(|v| v.starts_with(&format!("{}", version_str.split('-').next().unwrap_or(version_str))))
-                .cloned()
-                .ok_or_else(|| anyhow::anyhow!("Version {} is not installed", version_str))?
-        };
+
+        // When the spec leaves the variant open, use the configured default variant,
+        // else keep the kind of build in use
+        let preferred_variant = self
+            .config
+            .default_thread_safe
+            .map(|thread_safe| if thread_safe { "ts" } else { "nts" })
+            .or_else(|| {
+                state
+                    .active_version
+                    .as_deref()
+                    .and_then(|active| active.rsplit_once('-'))
+                    .map(|(_, variant)| variant)
+                    .filter(|variant| *variant == "ts" || *variant == "nts")
+            });
+        let installed_version = resolve_switch_target(version_str, &state.installed_versions, preferred_variant)?;
 
         let version_dir = self
             .config
@@ -97,109 +614,100 @@ impl PhpManager {
         let current_path = platform::get_current_path();
         let current_dir = current_path.parent()
             .ok_or_else(|| anyhow::anyhow!("Invalid current path"))?;
-        fs::create_dir_all(current_dir)?;
-
-        #[cfg(target_os = "windows")]
-        {
-            let current_dir = current_path.parent()
-                .ok_or_else(|| anyhow::anyhow!("Invalid current path"))?;
-            
-            // Create php.exe in current directory for IDE compatibility
-            // IDEs (like VS Code) expect php.exe directly in the current directory
-            // They check: C:\Users\...\phpvm\current\php.exe
-            let php_exe_in_current = current_dir.join("php.exe");
-            
-            // Remove any existing php.exe and DLLs if they exist
-            if php_exe_in_current.exists() {
-                let _ = fs::remove_file(&php_exe_in_current);
-            }
-            
-            // Clean up old DLL files from previous version
-            if let Ok(entries) = fs::read_dir(current_dir) {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        let path = entry.path();
-                        if path.is_file() {
-                            if let Some(ext) = path.extension() {
-                                if ext == "dll" || ext == "DLL" {
-                                    let _ = fs::remove_file(&path);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            
-            // Copy php.exe to current directory
-            // This is the most reliable method that works without admin privileges
-            // and ensures IDEs can find and validate the PHP executable
-            fs::copy(&php_exe, &php_exe_in_current)
-                .context("Failed to copy php.exe to current directory for IDE compatibility")?;
-            
-            tracing::info!("Copied php.exe to current directory for IDE compatibility: {:?}", php_exe_in_current);
-            
-            // Copy all DLL files from the version directory to current directory
-            // PHP requires DLLs to be in the same directory or in PATH
-            if let Ok(entries) = fs::read_dir(&version_dir) {
-                for entry in entries {
-                    if let Ok(entry) = entry {
-                        let path = entry.path();
-                        if path.is_file() {
-                            if let Some(file_name) = path.file_name() {
-                                if let Some(ext) = path.extension() {
-                                    if ext == "dll" || ext == "DLL" {
-                                        let dll_in_current = current_dir.join(file_name);
-                                        if let Err(e) = fs::copy(&path, &dll_in_current) {
-                                            tracing::warn!("Failed to copy DLL {:?} to current directory: {}", path, e);
-                                        } else {
-                                            tracing::info!("Copied DLL {:?} to current directory", file_name);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-            
-            // Also create php.bat for command-line compatibility (backward compatibility)
-            let php_exe_str = php_exe.to_string_lossy().replace("\\", "\\\\");
-            let batch_content = format!(
-                "@echo off\n\"{}\" %*",
-                php_exe_str
-            );
-            fs::write(&current_path, batch_content)?;
-        }
+        point_current_at(&self.config, &php_exe, &version_dir)?;
 
-        #[cfg(not(target_os = "windows"))]
-        {
-            // On Unix, create a symlink or shell script
-            if current_path.exists() {
-                fs::remove_file(&current_path)?;
+        let mut outcome = SwitchOutcome::default();
+        if self.config.manage_path {
+            // The shim already points at the new version, so a blocked PATH write (e.g. by
+            // group policy) leaves a working switch that only needs the PATH entry added
+            if let Err(e) = platform::add_to_path(&current_dir.to_path_buf()) {
+                let warning = format!("{} could not be added to PATH: {:#}", current_dir.display(), e);
+                logging::log_message("WARN", &format!("Switched to PHP {}, but {}", installed_version, warning));
+                outcome.path_warning = Some(warning);
             }
-            std::os::unix::fs::symlink(&php_exe, &current_path)?;
-            use std::os::unix::fs::PermissionsExt;
-            let mut perms = fs::metadata(&current_path)?.permissions();
-            perms.set_mode(0o755);
-            fs::set_permissions(&current_path, perms)?;
+        } else {
+            logging::log_message("INFO", &format!("PATH management is disabled, not adding {:?} to PATH", current_dir));
         }
 
-        let current_dir_buf = current_dir.to_path_buf();
-        platform::add_to_path(&current_dir_buf)
-            .context("Failed to add PHP to PATH")?;
-
         let mut state = PhpState::load()?;
         state.set_active(installed_version.clone());
         state.save()?;
+        mirror_active_to_config(Some(&installed_version))?;
 
-        // Update config (store base version without variant for compatibility)
-        let mut config = config::Config::load()?;
-        let base_version = installed_version.split('-').next().unwrap_or(&installed_version).to_string();
-        config.active_version = Some(base_version);
-        config.save()?;
-
+        crate::stats::record(crate::stats::UsageEvent::Switch(&installed_version));
         tracing::info!("Successfully switched to PHP {}", installed_version);
-        Ok(())
+        Ok(outcome)
+    }
+
+    /// `switch`, then check the newly active PHP actually runs (`verify_runtime`). If it
+    /// doesn't, e.g. because a Visual C++ runtime is missing, the previous version and
+    /// shim are restored (or PHP is deactivated if none was active) and an error says why.
+    pub async fn switch_checked(&self, version_str: &str) -> anyhow::Result<SwitchOutcome> {
+        let before = PhpState::load()?;
+        let outcome = self.switch(version_str).await?;
+
+        let Some(new_active) = self.get_active()? else {
+            return Ok(outcome);
+        };
+        let runtime_error = match self.verify_runtime(&new_active) {
+            Ok(_) => return Ok(outcome),
+            Err(e) => e,
+        };
+        logging::log_message("ERROR", &format!("PHP {} failed to run after switching: {:#}", new_active, runtime_error));
+
+        let restored = match before.active_version.as_deref() {
+            Some(previous) if previous != new_active => {
+                self.switch(previous).await.with_context(|| {
+                    format!("PHP {} failed to run and switching back to {} failed too", new_active, previous)
+                })?;
+                // Put back what switching away from the broken version overwrote
+                let mut state = PhpState::load()?;
+                state.last_known_good = before.last_known_good.clone();
+                state.save()?;
+                format!("switched back to PHP {}", previous)
+            }
+            _ => {
+                self.deactivate()?;
+                "no PHP version is active now".to_string()
+            }
+        };
+        Err(anyhow::anyhow!(
+            "PHP {} doesn't run ({:#}); {}",
+            new_active,
+            runtime_error,
+            restored
+        ))
+    }
+
+    /// Run `php -v` for an installed version and return its first line, e.g.
+    /// "PHP 8.3.0 (cli) ...". Fails if PHP can't start, times out or exits with an error.
+    pub fn verify_runtime(&self, version_str: &str) -> anyhow::Result<String> {
+        let installed_version = self.resolve_installed_variant(version_str)?;
+        let version_dir = self.config.install_dir.join(format!("php-{}", installed_version));
+        let php_exe = platform::get_php_executable_path(&version_dir);
+        let output = platform::run_php(&php_exe, &["-v"], platform::PHP_QUERY_TIMEOUT)?;
+        Ok(output.lines().next().unwrap_or_default().trim().to_string())
+    }
+
+    /// Switch to the TS or NTS build of a version (e.g. "8.3.0"), which must already be installed
+    pub async fn switch_variant(&self, base_version: &str, thread_safe: bool) -> anyhow::Result<SwitchOutcome> {
+        validate_version_str(base_version)?;
+        let base_version = base_version.split('-').next().unwrap_or(base_version);
+        PhpVersion::from_string(base_version)?;
+
+        let variant_suffix = if thread_safe { "ts" } else { "nts" };
+        let version_with_variant = format!("{}-{}", base_version, variant_suffix);
+
+        if !self.list_installed()?.contains(&version_with_variant) {
+            anyhow::bail!(
+                "PHP {} ({}) is not installed. Install the {} variant first.",
+                base_version,
+                variant_suffix.to_uppercase(),
+                variant_suffix.to_uppercase()
+            );
+        }
+
+        self.switch(&version_with_variant).await
     }
 
     pub fn list_installed(&self) -> anyhow::Result<Vec<String>> {
@@ -227,11 +735,8 @@ impl PhpManager {
                                 installed.push(version_with_variant.to_string());
                                 logging::log_message("DEBUG", &format!("Found installed version on disk: {}", version_with_variant));
                             } else {
-                                logging::log_message("WARN", &format!("Directory {} exists but PHP executable not found, cleaning up", dir_name));
-                                // Clean up incomplete installation
-                                if let Err(e) = fs::remove_dir_all(&path) {
-                                    logging::log_message("ERROR", &format!("Failed to clean up incomplete installation {}: {}", dir_name, e));
-                                }
+                                // Installs are extracted elsewhere and renamed in, so this was damaged after installing; reported by find_broken_installs
+                                logging::log_message("WARN", &format!("Directory {} exists but PHP executable not found", dir_name));
                             }
                         }
                     }
@@ -264,73 +769,1248 @@ impl PhpManager {
         Ok(installed)
     }
 
-    pub fn get_active(&self) -> anyhow::Result<Option<String>> {
-        let state = PhpState::load()?;
-        Ok(state.active_version)
+    /// Recreate state.json from the install directory, for when it was lost or corrupted.
+    /// Every `php-<version>-<ts|nts>` directory with a PHP executable is recorded with a
+    /// fresh checksum and source "unknown". The active version is kept if the `current`
+    /// shim still points at one of them; pins survive if the old state is readable.
+    /// The old file is kept as `state.json.bak`.
+    pub fn rebuild_state_from_disk(&self) -> anyhow::Result<PhpState> {
+        let mut installs = Vec::new();
+        if self.config.install_dir.exists() {
+            for entry in fs::read_dir(&self.config.install_dir)? {
+                let path = entry?.path();
+                let Some(version) = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.strip_prefix("php-"))
+                    .filter(|v| v.ends_with("-ts") || v.ends_with("-nts"))
+                    .filter(|v| validate_version_str(v).is_ok())
+                    .map(str::to_string)
+                else {
+                    continue;
+                };
+                if path.is_dir() && platform::get_php_executable_path(&path).exists() {
+                    installs.push((version, path));
+                }
+            }
+        }
+        installs.sort();
+
+        let old_state = PhpState::load().ok();
+        let mut state = PhpState::default();
+        for (version, path) in &installs {
+            let installed_at = fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs().to_string())
+                .unwrap_or_default();
+            let metadata = crate::state::InstallMetadata {
+                version: version.clone(),
+                install_path: path.clone(),
+                installed_at,
+                checksum: Some(Installer::calculate_checksum_with(path, self.config.checksum_threads)?),
+                source: "unknown".to_string(),
+                compiler: None,
+            };
+            state.add_version(version.clone(), metadata);
+        }
+
+        let installed: Vec<String> = installs.into_iter().map(|(version, _)| version).collect();
+        state.active_version = active_from_shim(&installed);
+        if let Some(old_state) = old_state {
+            state.pinned_versions = old_state.pinned_versions.into_iter().filter(|v| installed.contains(v)).collect();
+            state.last_known_good = old_state.last_known_good.filter(|v| installed.contains(v));
+        }
+
+        let state_path = config::get_state_path();
+        if state_path.exists() {
+            fs::copy(&state_path, state_path.with_extension("json.bak"))
+                .context("Failed to back up state.json")?;
+        }
+        state.save()?;
+        mirror_active_to_config(state.active_version.as_deref())?;
+        logging::log_message(
+            "INFO",
+            &format!("Rebuilt state from disk: {} installs, active {:?}", installed.len(), state.active_version),
+        );
+        Ok(state)
     }
-    
-    pub fn get_version_metadata(&self, version_str: &str) -> anyhow::Result<Option<crate::state::InstallMetadata>> {
+
+    /// Directories under the install dir named like PHP installs ("php-8.3.0-nts") or
+    /// recorded in state, but with no PHP executable. Other `php-*` folders in a shared
+    /// install dir aren't phpvm's. Only reports; see `prune_broken_installs`.
+    pub fn find_broken_installs(&self) -> anyhow::Result<Vec<BrokenInstall>> {
+        let mut broken = Vec::new();
+        if !self.config.install_dir.exists() {
+            return Ok(broken);
+        }
+
         let state = PhpState::load()?;
-        Ok(state.get_metadata(version_str).cloned())
-    }
+        for entry in fs::read_dir(&self.config.install_dir)? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let Some(version) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(|n| n.strip_prefix("php-"))
+                .map(|v| v.to_string())
+            else {
+                continue;
+            };
+            if !is_install_dir_version(&version) && !state.installed_versions.contains(&version) {
+                continue;
+            }
+            if platform::get_php_executable_path(&path).exists() {
+                continue;
+            }
+            broken.push(BrokenInstall {
+                in_state: state.installed_versions.contains(&version),
+                version,
+                path,
+            });
+        }
 
-    pub async fn list_available(&self) -> anyhow::Result<Vec<String>> {
-        logging::log_message("DEBUG", "Fetching available PHP versions");
-        let provider = Provider::new()?;
-        // Increased limit to 20 to show all major.minor branches (currently ~12 from 5.6 to 8.5)
-        // This ensures all versions from versionlog.com are displayed
-        let versions = provider.get_top_versions(20).await?;
-        let version_strings: Vec<String> = versions.iter().map(|v| v.version.clone()).collect();
-        logging::log_message("DEBUG", &format!("Found {} available versions", version_strings.len()));
-        Ok(version_strings)
+        broken.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(broken)
     }
-    
-    pub async fn get_version_info(&self, version_str: &str) -> anyhow::Result<Option<crate::provider::VersionInfo>> {
-        let provider = Provider::new()?;
-        let versions = provider.fetch_available_versions().await?;
-        
-        // Try to find version in fetched list
-        if let Some(mut info) = versions.into_iter().find(|v| v.version == version_str) {
-            // Ensure EOL date is populated even if parsing failed
-            if info.eol_date.is_none() {
-                if let Ok(version) = PhpVersion::from_string(version_str) {
-                    info.eol_date = Provider::get_eol_date(version.major, version.minor);
-                }
+
+    /// Delete the directories reported by `find_broken_installs` and drop their state
+    /// entries. Returns what was removed.
+    pub fn prune_broken_installs(&self) -> anyhow::Result<Vec<BrokenInstall>> {
+        let broken = self.find_broken_installs()?;
+        let mut state = PhpState::load()?;
+        let mut removed = Vec::new();
+
+        for install in broken {
+            if state.is_pinned(&install.version) {
+                logging::log_message("INFO", &format!("Keeping pinned install {:?}", install.path));
+                continue;
             }
-            // Ensure download URL is populated
-            if info.download_url.is_none() {
-                if let Ok(version) = PhpVersion::from_string(version_str) {
-                    info.download_url = Some(Provider::generate_download_url(version_str, version.major, version.minor));
-                }
+            if let Err(e) = fs::remove_dir_all(&install.path) {
+                logging::log_message("ERROR", &format!("Failed to remove broken install {:?}: {}", install.path, e));
+                continue;
             }
-            return Ok(Some(info));
+            logging::log_message("INFO", &format!("Removed broken install {:?}", install.path));
+            if install.in_state {
+                state.remove_version(&install.version);
+            }
+            removed.push(install);
         }
-        
-        // Version not in fetched list - create VersionInfo with EOL date from get_eol_date
-        if let Ok(version) = PhpVersion::from_string(version_str) {
-            let eol_date = Provider::get_eol_date(version.major, version.minor);
-            let download_url = Some(Provider::generate_download_url(version_str, version.major, version.minor));
-            
-            Ok(Some(crate::provider::VersionInfo {
-                version: version_str.to_string(),
-                release_date: None,
-                eol_date,
-                download_url,
-                checksum: None,
-            }))
+
+        state.save()?;
+        mirror_active_to_config(state.active_version.as_deref())?;
+        Ok(removed)
+    }
+
+    /// Keep an installed version ("8.1.34" or "8.1.34-nts") out of pruning and patch update
+    /// checks. Returns the pinned variant.
+    pub fn pin_version(&self, version_str: &str) -> anyhow::Result<String> {
+        let installed_version = self.resolve_installed_variant(version_str)?;
+        let mut state = PhpState::load()?;
+        if state.pin_version(&installed_version) {
+            state.save()?;
+            logging::log_message("INFO", &format!("Pinned PHP {}", installed_version));
+        }
+        Ok(installed_version)
+    }
+
+    /// Undo `pin_version`. A bare "8.1.34" unpins both variants.
+    pub fn unpin_version(&self, version_str: &str) -> anyhow::Result<()> {
+        validate_version_str(version_str)?;
+        let mut state = PhpState::load()?;
+        let base = version_str.split('-').next().unwrap_or(version_str);
+        let candidates = if version_str.ends_with("-ts") || version_str.ends_with("-nts") {
+            vec![version_str.to_string()]
         } else {
-            Ok(None)
+            vec![format!("{}-ts", base), format!("{}-nts", base)]
+        };
+        let mut changed = false;
+        for candidate in &candidates {
+            changed |= state.unpin_version(candidate);
         }
+        if changed {
+            state.save()?;
+            logging::log_message("INFO", &format!("Unpinned PHP {}", version_str));
+        }
+        Ok(())
     }
-    
-    pub fn is_path_configured(&self) -> anyhow::Result<bool> {
-        let current_path = platform::get_current_path();
-        let current_dir = current_path.parent()
+
+    /// Resolve "8.3.0" or "8.3.0-nts" to an installed variant, preferring TS when unspecified
+    fn resolve_installed_variant(&self, version_str: &str) -> anyhow::Result<String> {
+        validate_version_str(version_str)?;
+        let installed = self.list_installed()?;
+        if installed.contains(&version_str.to_string()) {
+            return Ok(version_str.to_string());
+        }
+        let base = version_str.split('-').next().unwrap_or(version_str);
+        [format!("{}-ts", base), format!("{}-nts", base)]
+            .into_iter()
+            .find(|v| installed.contains(v))
+            .ok_or_else(|| anyhow::Error::new(PhpVmError::NotInstalled(format!("Version {} is not installed", version_str))))
+    }
+
+    /// Script that puts an installed version first on PATH for one shell session,
+    /// without touching the global PATH or the active version
+    pub fn generate_activation_script(&self, version_str: &str, shell: platform::ShellKind) -> anyhow::Result<String> {
+        let info = self.shell_activate(version_str, Some(shell))?;
+        Ok(info.script.unwrap_or_default())
+    }
+
+    /// Per-shell counterpart of `switch`: resolve `version_str` ("8.1", "8.3.0-nts", ...)
+    /// to an install and return the directory to put first on PATH, plus a snippet for
+    /// `shell` that does it. Neither the active version nor the global PATH change, so
+    /// other shells keep the global version. With `project::detect_project_version` this
+    /// is what an "auto-use on cd" shell hook needs.
+    pub fn shell_activate(&self, version_str: &str, shell: Option<platform::ShellKind>) -> anyhow::Result<ActivationInfo> {
+        validate_version_spec(version_str)?;
+        let preferred_variant = self.config.default_thread_safe.map(|thread_safe| if thread_safe { "ts" } else { "nts" });
+        let installed_version = resolve_switch_target(version_str, &self.list_installed()?, preferred_variant)?;
+
+        let version_dir = self.config.install_dir.join(format!("php-{}", installed_version));
+        let php_exe = platform::get_php_executable_path(&version_dir);
+        if !php_exe.exists() {
+            anyhow::bail!("PHP executable not found: {:?}", php_exe);
+        }
+        let path_dir = php_exe
+            .parent()
+            .ok_or_else(|| anyhow::anyhow!("Invalid PHP executable path: {:?}", php_exe))?
+            .to_path_buf();
+        let script = shell.map(|shell| platform::activation_script(&path_dir, &installed_version, shell));
+        Ok(ActivationInfo { version: installed_version, path_dir, script })
+    }
+
+    /// The php.ini the active version loads, as reported by its `php --ini`. Uses the
+    /// version directory's php.exe, which is what php.bat and PATH run. Falls back to
+    /// `<version dir>/php.ini` when PHP reports none or can't be run.
+    /// Returns None when no version is active.
+    pub fn active_ini_path(&self) -> anyhow::Result<Option<std::path::PathBuf>> {
+        let active = match PhpState::load()?.active_version {
+            Some(active) => active,
+            None => return Ok(None),
+        };
+        let version_dir = self.config.install_dir.join(format!("php-{}", active));
+        let php_exe = platform::get_php_executable_path(&version_dir);
+
+        let loaded = match platform::query_loaded_ini(&php_exe) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                logging::log_message("WARN", &format!("Could not query php.ini for PHP {}: {}", active, e));
+                None
+            }
+        };
+        Ok(Some(loaded.unwrap_or_else(|| version_dir.join("php.ini"))))
+    }
+
+    /// Check an installed version's php.ini by starting PHP with it (`php -c <ini> --version`)
+    /// and collecting the startup errors and warnings it prints. An empty list means
+    /// PHP read the file without complaint.
+    pub fn lint_ini(&self, version_str: &str) -> anyhow::Result<Vec<crate::ini::IniIssue>> {
+        let installed_version = self.resolve_installed_variant(version_str)?;
+        let version_dir = self.config.install_dir.join(format!("php-{}", installed_version));
+        let ini_path = version_dir.join("php.ini");
+        if !ini_path.is_file() {
+            return Err(PhpVmError::Io(format!("PHP {} has no php.ini at {:?}", installed_version, ini_path)).into());
+        }
+        let php_exe = platform::get_php_executable_path(&version_dir);
+        let ini_arg = ini_path.to_string_lossy();
+        let output = platform::run_php_output(
+            &php_exe,
+            &["-c", &ini_arg, "-d", "display_startup_errors=1", "--version"],
+            platform::PHP_QUERY_TIMEOUT,
+        )?;
+        let issues = crate::ini::parse_startup_messages(&format!("{}\n{}", output.stderr, output.stdout));
+        if issues.is_empty() && !output.status.success() {
+            anyhow::bail!("PHP {} failed to start with {:?}: {}", installed_version, ini_path, output.stderr.trim());
+        }
+        Ok(issues)
+    }
+
+    /// `date.timezone` from an installed version's php.ini. None when it has no php.ini
+    /// or the setting is commented out, which makes PHP warn and fall back to UTC.
+    pub fn get_timezone(&self, version_str: &str) -> anyhow::Result<Option<String>> {
+        let installed_version = self.resolve_installed_variant(version_str)?;
+        let ini_path = self.config.install_dir.join(format!("php-{}", installed_version)).join("php.ini");
+        let content = match fs::read_to_string(&ini_path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", ini_path)),
+        };
+        Ok(crate::ini::get_directive(&content, "date.timezone").filter(|tz| !tz.is_empty()))
+    }
+
+    /// Set `date.timezone` in an installed version's php.ini, creating php.ini from
+    /// php.ini-development if missing. `timezone` must be an IANA name ("Europe/Lisbon");
+    /// returns it as spelled in the tz database.
+    pub fn set_timezone(&self, version_str: &str, timezone: &str) -> anyhow::Result<String> {
+        let timezone = crate::timezone::canonical_timezone(timezone)?;
+        let installed_version = self.resolve_installed_variant(version_str)?;
+        let version_dir = self.config.install_dir.join(format!("php-{}", installed_version));
+        let ini_path = version_dir.join("php.ini");
+        let template_path = version_dir.join("php.ini-development");
+        let base = if ini_path.exists() {
+            fs::read_to_string(&ini_path)?
+        } else if template_path.exists() {
+            fs::read_to_string(&template_path)?
+        } else {
+            String::new()
+        };
+        fs::write(&ini_path, crate::ini::set_directive(&base, "Date", "date.timezone", timezone))
+            .with_context(|| format!("Failed to write {:?}", ini_path))?;
+        logging::log_message("INFO", &format!("Set date.timezone to {} for PHP {}", timezone, installed_version));
+
+        // php.ini changed on purpose, so record a fresh checksum for verify_integrity
+        let mut state = PhpState::load()?;
+        if let Some(entry) = state.install_metadata.get_mut(&installed_version) {
+            entry.checksum = Some(Installer::calculate_checksum_with(&version_dir, self.config.checksum_threads)?);
+            state.save()?;
+        }
+        Ok(timezone.to_string())
+    }
+
+    /// Modules reported by `php -m` for an installed version ("8.3.0" or "8.3.0-nts"),
+    /// run from the version's own directory rather than the `current` shim
+    pub fn loaded_modules(&self, version_str: &str) -> anyhow::Result<Vec<String>> {
+        let installed_version = self.resolve_installed_variant(version_str)?;
+        let version_dir = self.config.install_dir.join(format!("php-{}", installed_version));
+        let php_exe = platform::get_php_executable_path(&version_dir);
+
+        let output = platform::run_php(&php_exe, &["-m"], platform::PHP_QUERY_TIMEOUT)
+            .with_context(|| format!("Could not list modules for PHP {}", installed_version))?;
+        let modules = platform::parse_module_list(&output);
+        if modules.is_empty() {
+            anyhow::bail!("PHP {} reported no modules", installed_version);
+        }
+        Ok(modules)
+    }
+
+    /// Compare the modules two installed versions load
+    pub fn diff_modules(&self, from: &str, to: &str) -> anyhow::Result<ModuleDiff> {
+        let from_version = self.resolve_installed_variant(from)?;
+        let to_version = self.resolve_installed_variant(to)?;
+        let from_modules = self.loaded_modules(&from_version)?;
+        let to_modules = self.loaded_modules(&to_version)?;
+        Ok(ModuleDiff::between(&from_version, &from_modules, &to_version, &to_modules))
+    }
+
+    /// Download the extension DLL matching an installed version's PHP branch, TS/NTS
+    /// variant and compiler into its `ext` directory, and enable it in its php.ini
+    /// (created from php.ini-development if missing). Returns the DLL path.
+    pub async fn install_extension(&self, version_str: &str, provider: &dyn ExtensionProvider) -> anyhow::Result<std::path::PathBuf> {
+        let installed_version = self.resolve_installed_variant(version_str)?;
+        let mut state = PhpState::load()?;
+        let metadata = state
+            .get_metadata(&installed_version)
+            .cloned()
+            .ok_or_else(|| PhpVmError::NotInstalled(format!("Version {} is not installed", installed_version)))?;
+
+        let target = ExtensionTarget::from_metadata(&metadata)?;
+        let url = provider.download_url(&target)?;
+        logging::log_message("INFO", &format!("Installing {} for PHP {} from {}", provider.name(), installed_version, url));
+
+        let downloaded = Downloader::new()?
+            .download_file(&url, None, None)
+            .await
+            .with_context(|| format!("Failed to download {} from: {}", provider.name(), url))?
+            .path;
+        let bytes = fs::read(&downloaded)?;
+        if let Err(e) = extension::ensure_x64_dll(&bytes) {
+            // Don't keep an unusable file (e.g. an HTML error page) in the cache
+            let _ = fs::remove_file(&downloaded);
+            return Err(e.context(format!("{} download from {} was rejected", provider.name(), url)));
+        }
+
+        let ext_dir = metadata.install_path.join("ext");
+        fs::create_dir_all(&ext_dir)?;
+        let dll_path = ext_dir.join(provider.dll_file_name());
+        fs::write(&dll_path, &bytes)?;
+
+        enable_extension_in_ini(&metadata.install_path, provider.name(), &dll_path, provider.is_zend_extension())?;
+
+        // The install changed on purpose, so record a fresh checksum for verify_integrity
+        if let Some(entry) = state.install_metadata.get_mut(&installed_version) {
+            entry.checksum = Some(Installer::calculate_checksum_with(&metadata.install_path, self.config.checksum_threads)?);
+        }
+        state.save()?;
+        Ok(dll_path)
+    }
+
+    /// Slim an installed version down to the bundled extensions in `keep`: the other
+    /// libraries in its `ext` directory are deleted and commented out in its php.ini.
+    /// Only reinstalling the version brings them back.
+    pub fn strip_extensions(&self, version_str: &str, keep: &[String]) -> anyhow::Result<extension::StripReport> {
+        let installed_version = self.resolve_installed_variant(version_str)?;
+        let mut state = PhpState::load()?;
+        let install_path = state
+            .get_metadata(&installed_version)
+            .map(|metadata| metadata.install_path.clone())
+            .ok_or_else(|| PhpVmError::NotInstalled(format!("Version {} is not installed", installed_version)))?;
+
+        let report = extension::strip_extensions(&install_path, keep)?;
+        if report.removed.is_empty() {
+            return Ok(report);
+        }
+        logging::log_message(
+            "WARN",
+            &format!(
+                "Removed {} from PHP {} ({} freed); reinstall to get them back",
+                report.removed.join(", "),
+                installed_version,
+                platform::format_bytes(report.freed_bytes)
+            ),
+        );
+
+        // The install changed on purpose, so record a fresh checksum for verify_integrity
+        if let Some(entry) = state.install_metadata.get_mut(&installed_version) {
+            entry.checksum = Some(Installer::calculate_checksum_with(&install_path, self.config.checksum_threads)?);
+        }
+        state.save()?;
+        Ok(report)
+    }
+
+    /// Set up what a project's `.phpvmrc` asks for (see `project::ProjectConfig`): install
+    /// the version if it's missing, switch to it, and enable the listed extensions.
+    /// Bundled extensions are enabled from the install's `ext` directory; ones phpvm can
+    /// download (Xdebug) are installed first. Safe to run again: finished steps are skipped.
+    pub async fn apply_project_config(&self, dir: &std::path::Path, progress_callback: Option<crate::download::ProgressCallback>) -> anyhow::Result<crate::project::ProjectApplyReport> {
+        let project = crate::project::ProjectConfig::load(dir)?.ok_or_else(|| {
+            PhpVmError::InvalidArgument(format!("No {} in {:?}", crate::project::PROJECT_CONFIG_FILE, dir))
+        })?;
+        let thread_safe = project.thread_safe().or(self.config.default_thread_safe).unwrap_or(true);
+        let version = format!("{}-{}", project.version, if thread_safe { "ts" } else { "nts" });
+        let mut steps = Vec::new();
+
+        if self.list_installed()?.contains(&version) {
+            steps.push(format!("PHP {} is already installed", version));
+        } else {
+            self.install(&project.version, Some(thread_safe), false, progress_callback).await?;
+            steps.push(format!("Installed PHP {}", version));
+        }
+
+        if self.get_active()?.as_deref() == Some(version.as_str()) {
+            steps.push(format!("PHP {} is already active", version));
+        } else {
+            let outcome = self.switch_checked(&version).await?;
+            steps.push(format!("Switched to PHP {}", version));
+            steps.extend(outcome.path_warning);
+        }
+
+        let install_path = self.config.install_dir.join(format!("php-{}", version));
+        let mut ini_changed = false;
+        for name in &project.extensions {
+            let zend = ZEND_EXTENSIONS.contains(&name.as_str());
+            let bundled = bundled_extension_file(&install_path.join("ext"), name);
+            let enabled = match (bundled, name.as_str()) {
+                (Some(dll_path), _) => {
+                    let changed = enable_extension_in_ini(&install_path, name, &dll_path, zend)?;
+                    ini_changed |= changed;
+                    changed
+                }
+                (None, "xdebug") if extension_already_enabled(&install_path, name, true)? => false,
+                (None, "xdebug") => {
+                    self.install_extension(&version, &XdebugProvider).await?;
+                    true
+                }
+                (None, _) => {
+                    return Err(PhpVmError::NotInstalled(format!(
+                        "Extension {} isn't bundled with PHP {} and phpvm can't download it",
+                        name, version
+                    ))
+                    .into())
+                }
+            };
+            steps.push(if enabled {
+                format!("Enabled {}", name)
+            } else {
+                format!("{} is already enabled", name)
+            });
+        }
+
+        if ini_changed {
+            // php.ini changed on purpose, so record a fresh checksum for verify_integrity
+            let mut state = PhpState::load()?;
+            if let Some(entry) = state.install_metadata.get_mut(&version) {
+                entry.checksum = Some(Installer::calculate_checksum_with(&install_path, self.config.checksum_threads)?);
+                state.save()?;
+            }
+        }
+
+        for step in &steps {
+            logging::log_message("INFO", &format!("Project {:?}: {}", dir, step));
+        }
+        Ok(crate::project::ProjectApplyReport { version, steps })
+    }
+
+    /// Compiler, architecture and thread safety of an installed build, as reported by
+    /// `php -i` when it runs, otherwise from what was recorded at install time
+    pub fn get_build_info(&self, version_str: &str) -> anyhow::Result<BuildInfo> {
+        let installed_version = self.resolve_installed_variant(version_str)?;
+        let (php_version, variant) = installed_version
+            .rsplit_once('-')
+            .unwrap_or((installed_version.as_str(), "ts"));
+        let metadata = PhpState::load()?.get_metadata(&installed_version).cloned();
+
+        let version_dir = self.config.install_dir.join(format!("php-{}", installed_version));
+        let php_exe = platform::get_php_executable_path(&version_dir);
+        let (reported_compiler, reported_arch) = match platform::query_php_build(&php_exe) {
+            Ok(build) => (build.compiler, Some(build.arch)),
+            Err(e) => {
+                logging::log_message("WARN", &format!("Could not query build info for PHP {}: {:#}", installed_version, e));
+                (None, None)
+            }
+        };
+
+        let source = metadata.as_ref().map(|m| m.source.as_str()).unwrap_or_default();
+        let compiler = reported_compiler
+            .or_else(|| metadata.as_ref().and_then(|m| m.compiler.clone()))
+            .or_else(|| extension::compiler_tag_from_url(source));
+        let arch = reported_arch.unwrap_or_else(|| {
+            // phpvm installs x64 builds unless the archive said otherwise
+            if source.contains("-x86") { "x86" } else { "x64" }.to_string()
+        });
+
+        Ok(BuildInfo {
+            php_version: php_version.to_string(),
+            compiler,
+            arch,
+            thread_safe: variant == "ts",
+        })
+    }
+
+    pub async fn install_xdebug(&self, version_str: &str) -> anyhow::Result<std::path::PathBuf> {
+        self.install_extension(version_str, &XdebugProvider).await
+    }
+
+    /// PHP executable to hand to IDEs: the `current` shim's copy when there is one, so
+    /// the IDE follows later switches, otherwise the active version's own executable
+    pub fn ide_php_executable(&self) -> anyhow::Result<std::path::PathBuf> {
+        let active = self
+            .get_active()?
+            .ok_or_else(|| PhpVmError::NotInstalled("No PHP version is active".to_string()))?;
+        let current_dir = platform::get_current_path()
+            .parent()
+            .map(|dir| dir.to_path_buf())
+            .ok_or_else(|| anyhow::anyhow!("Invalid current path"))?;
+        // php.bat can't be used by IDEs, but the Copy/Junction strategies put php.exe next to it
+        let current_exe = if cfg!(target_os = "windows") {
+            current_dir.join("php.exe")
+        } else {
+            platform::get_current_path()
+        };
+        if current_exe.exists() {
+            return Ok(current_exe);
+        }
+        Ok(platform::get_php_executable_path(&self.config.install_dir.join(format!("php-{}", active))))
+    }
+
+    /// Set VS Code's `php.validate.executablePath` (user settings) to the active PHP.
+    /// settings.json is backed up first; undo with `unregister_from_vscode`.
+    pub fn register_with_vscode(&self) -> anyhow::Result<std::path::PathBuf> {
+        let settings = crate::ide::vscode_settings_path()
+            .ok_or_else(|| anyhow::anyhow!("Could not locate the VS Code settings directory"))?;
+        crate::ide::register_with_vscode_at(&settings, &self.ide_php_executable()?)?;
+        Ok(settings)
+    }
+
+    /// Put back the setting `register_with_vscode` replaced (or remove it). Returns false
+    /// if there was nothing of phpvm's to undo.
+    pub fn unregister_from_vscode(&self) -> anyhow::Result<bool> {
+        let settings = crate::ide::vscode_settings_path()
+            .ok_or_else(|| anyhow::anyhow!("Could not locate the VS Code settings directory"))?;
+        crate::ide::unregister_from_vscode_at(&settings)
+    }
+
+    /// Make `name` the active profile (see `config::get_profile_directory`): from now on
+    /// installs, state and the `current` shim are the profile's. The PATH entry moves to
+    /// the profile's `current` directory. `$PHPVM_PROFILE`, if set, still wins for this process.
+    pub fn switch_profile(&mut self, name: &str) -> anyhow::Result<()> {
+        config::validate_profile_name(name)?;
+        if !config::list_profiles().iter().any(|profile| profile == name) {
+            return Err(PhpVmError::InvalidArgument(format!("Profile {} doesn't exist", name)).into());
+        }
+        let old_current_dir = platform::get_current_path().parent().map(|dir| dir.to_path_buf());
+
+        let mut config = config::Config::load()?;
+        config.active_profile = (name != config::DEFAULT_PROFILE).then(|| name.to_string());
+        config.save()?;
+        self.config = config::Config::load()?;
+        self.installer = Installer::new()?;
+        logging::log_message("INFO", &format!("Switched to profile {}", config::get_active_profile()));
+
+        let new_current_dir = platform::get_current_path().parent().map(|dir| dir.to_path_buf());
+        if self.config.manage_path && old_current_dir != new_current_dir {
+            if let Some(old) = old_current_dir {
+                if let Err(e) = platform::remove_from_path(&old) {
+                    logging::log_message("WARN", &format!("Failed to remove {:?} from PATH: {:#}", old, e));
+                }
+            }
+            if self.get_active()?.is_some() {
+                self.ensure_path_set()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bring an existing PHP directory (XAMPP's `php`, a hand-extracted zip, ...) under
+    /// management. The version, variant and compiler come from its `php -i`; when that
+    /// doesn't say, the version comes from `php -v` and the variant from
+    /// `thread_safe_hint`. The directory is copied into the install dir rather than
+    /// linked: it costs the space of one install, but the original keeps working on its
+    /// own and removing the adopted version can never delete it.
+    /// Returns the registered version, e.g. "8.2.12-ts".
+    pub fn adopt_external(&self, path: &std::path::Path, thread_safe_hint: Option<bool>) -> anyhow::Result<String> {
+        let source_dir = path.to_path_buf();
+        let php_exe = platform::get_php_executable_path(&source_dir);
+        if !php_exe.is_file() {
+            return Err(PhpVmError::InvalidArgument(format!("No PHP executable found at {:?}", php_exe)).into());
+        }
+        if let (Ok(source), Ok(install_dir)) = (source_dir.canonicalize(), self.config.install_dir.canonicalize()) {
+            if source.starts_with(&install_dir) {
+                return Err(PhpVmError::InvalidArgument(format!("{:?} is already managed by phpvm", path)).into());
+            }
+        }
+
+        let (base_version, thread_safe, compiler) = match platform::query_php_build(&php_exe) {
+            Ok(build) => {
+                if thread_safe_hint.is_some_and(|hint| hint != build.thread_safe) {
+                    logging::log_message(
+                        "WARN",
+                        &format!(
+                            "{:?} reports thread safety {}, ignoring the hint",
+                            php_exe,
+                            if build.thread_safe { "enabled" } else { "disabled" }
+                        ),
+                    );
+                }
+                (build.php_version, build.thread_safe, build.compiler)
+            }
+            Err(e) => {
+                // `php -v` still names the version; the variant has to come from the hint
+                let banner = platform::run_php(&php_exe, &["-v"], platform::PHP_QUERY_TIMEOUT)
+                    .with_context(|| format!("{:?} doesn't run", php_exe))?;
+                let base_version = platform::parse_php_version_banner(&banner)
+                    .ok_or_else(|| anyhow::anyhow!("Could not read a PHP version from {:?} -v: {}", php_exe, banner.trim()))?;
+                let thread_safe = thread_safe_hint.ok_or_else(|| {
+                    PhpVmError::InvalidArgument(format!(
+                        "Could not tell whether {:?} is thread safe ({:#}); say which variant it is",
+                        php_exe, e
+                    ))
+                })?;
+                (base_version, thread_safe, None)
+            }
+        };
+        let version = format!("{}-{}", base_version, if thread_safe { "ts" } else { "nts" });
+
+        let mut state = PhpState::load()?;
+        if state.installed_versions.contains(&version) {
+            return Err(PhpVmError::AlreadyInstalled(format!("PHP {} is already installed", version)).into());
+        }
+        let target_dir = self.config.install_dir.join(format!("php-{}", version));
+        if target_dir.exists() {
+            return Err(PhpVmError::AlreadyInstalled(format!("{:?} already exists", target_dir)).into());
+        }
+
+        fs::create_dir_all(&self.config.install_dir)?;
+        let size = platform::tree_size(&source_dir).with_context(|| format!("Failed to read {:?}", source_dir))?;
+        platform::ensure_space(&self.config.install_dir, size)?;
+        if let Err(e) = platform::copy_tree(&source_dir, &target_dir) {
+            let _ = fs::remove_dir_all(&target_dir);
+            return Err(anyhow::Error::new(e).context(format!("Failed to copy {:?} to {:?}", source_dir, target_dir)));
+        }
+
+        let metadata = crate::state::InstallMetadata {
+            version: version.clone(),
+            install_path: target_dir.clone(),
+            installed_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs().to_string())
+                .unwrap_or_default(),
+            checksum: Some(Installer::calculate_checksum_with(&target_dir, self.config.checksum_threads)?),
+            source: "adopted".to_string(),
+            compiler,
+        };
+        state.add_version(version.clone(), metadata);
+        state.save()?;
+        logging::log_message("INFO", &format!("Adopted PHP {} from {:?} ({})", version, source_dir, platform::format_bytes(size)));
+        Ok(version)
+    }
+
+    /// Move an install to the newest patch of its branch: install it (same TS/NTS variant),
+    /// bring over php.ini and extra extensions (`migrate_install_settings`), carry over a
+    /// pin, switch to it if the old patch was the active version and, with `remove_old`,
+    /// remove the old patch. `version_str` is anything `switch` accepts, e.g. "8.2.10-nts"
+    /// or "8.2".
+    pub async fn upgrade(&self, version_str: &str, remove_old: bool, progress_callback: Option<crate::download::ProgressCallback>) -> anyhow::Result<UpgradeReport> {
+        validate_version_spec(version_str)?;
+        let state = PhpState::load()?;
+        let from = resolve_switch_target(version_str, &state.installed_versions, None)?;
+
+        let provider = Provider::with_providers(self.config.providers.clone())?;
+        let versions = provider.fetch_available_versions().await?;
+        let Some(update) = Provider::find_patch_updates(&versions, std::slice::from_ref(&from)).into_iter().next() else {
+            return Ok(UpgradeReport {
+                steps: vec![format!("PHP {} is already the latest patch of its branch", from)],
+                to: from.clone(),
+                from,
+            });
+        };
+        let to = format!("{}-{}", update.latest, update.variant);
+        let mut steps = Vec::new();
+
+        match self.install(&update.latest, Some(update.variant == "ts"), false, progress_callback).await {
+            Ok(()) => steps.push(format!("Installed PHP {}", to)),
+            Err(e) if matches!(e.downcast_ref::<PhpVmError>(), Some(PhpVmError::AlreadyInstalled(_))) => {
+                steps.push(format!("PHP {} was already installed", to))
+            }
+            Err(e) => return Err(e),
+        }
+
+        let old_dir = self.config.install_dir.join(format!("php-{}", from));
+        let new_dir = self.config.install_dir.join(format!("php-{}", to));
+        let migrated = migrate_install_settings(&old_dir, &new_dir)?;
+        if !migrated.is_empty() {
+            // The copied files are part of the install now, so verify_integrity must expect them
+            let checksum = Installer::calculate_checksum_with(&new_dir, self.config.checksum_threads)?;
+            let mut state = PhpState::load()?;
+            if let Some(metadata) = state.install_metadata.get_mut(&to) {
+                metadata.checksum = Some(checksum);
+                state.save()?;
+            }
+        }
+        steps.extend(migrated);
+
+        if state.is_pinned(&from) {
+            self.pin_version(&to)?;
+            steps.push(format!("Pinned PHP {} like {}", to, from));
+        }
+
+        // Upgrading an inactive install must not change which PHP is in use
+        if state.active_version.as_deref() == Some(from.as_str()) {
+            let outcome = self.switch_checked(&to).await?;
+            steps.push(format!("Switched to PHP {}", to));
+            steps.extend(outcome.path_warning);
+        }
+
+        if remove_old {
+            self.remove(&from, false).await?;
+            steps.push(format!("Removed PHP {}", from));
+        }
+
+        for step in &steps {
+            logging::log_message("INFO", &format!("Upgrade {} -> {}: {}", from, to, step));
+        }
+        Ok(UpgradeReport { from, to, steps })
+    }
+
+    /// Variant used when an install doesn't specify one: Some(true) for TS, Some(false)
+    /// for NTS, None if the user hasn't chosen
+    pub fn get_default_variant(&self) -> Option<bool> {
+        self.config.default_thread_safe
+    }
+
+    /// Save the default variant to config.json; None clears it
+    pub fn set_default_variant(&mut self, thread_safe: Option<bool>) -> anyhow::Result<()> {
+        let mut config = config::Config::load()?;
+        config.default_thread_safe = thread_safe;
+        config.save()?;
+        self.config.default_thread_safe = thread_safe;
+        Ok(())
+    }
+
+    /// Lifetime install/switch/remove/download counters, read from the local stats.json
+    pub fn get_usage_stats(&self) -> crate::stats::UsageReport {
+        crate::stats::UsageStats::load().report()
+    }
+
+    /// The active install with its variant ("8.3.0-ts"), from state.json
+    pub fn get_active(&self) -> anyhow::Result<Option<String>> {
+        let state = PhpState::load()?;
+        Ok(state.active_version)
+    }
+
+    /// `get_active` split into base version, variant and install directory: the one
+    /// place to read the active version from, rather than config.json's base-only copy
+    pub fn get_active_detailed(&self) -> anyhow::Result<Option<ActiveVersion>> {
+        let state = PhpState::load()?;
+        let Some(version) = state.active_version.clone() else { return Ok(None) };
+        let (base_version, variant) = match version.rsplit_once('-') {
+            Some((base, variant)) if variant == "ts" || variant == "nts" => (base.to_string(), variant.to_string()),
+            _ => {
+                // Unsuffixed entries predate variants and are migrated on startup
+                let thread_safe = platform::query_php_build(&platform::get_php_executable_path(
+                    &self.config.install_dir.join(format!("php-{}", version)),
+                ))
+                .map(|build| build.thread_safe)
+                .unwrap_or(true);
+                (version.clone(), if thread_safe { "ts" } else { "nts" }.to_string())
+            }
+        };
+        let install_path = state
+            .get_metadata(&version)
+            .map(|metadata| metadata.install_path.clone())
+            .unwrap_or_else(|| self.config.install_dir.join(format!("php-{}", version)));
+        Ok(Some(ActiveVersion { version, base_version, variant, install_path }))
+    }
+    
+    pub fn get_version_metadata(&self, version_str: &str) -> anyhow::Result<Option<crate::state::InstallMetadata>> {
+        let state = PhpState::load()?;
+        Ok(state.get_metadata(version_str).cloned())
+    }
+
+    /// Recompute the checksum of an installed version (e.g. "8.3.0-ts") and compare it to
+    /// the one recorded at install time. Returns false if the files have changed. A
+    /// checksum from before the "v2:" format can't be checked reliably, so it's replaced
+    /// by a fresh one that later checks compare against.
+    pub fn verify_integrity(&self, version_str: &str) -> anyhow::Result<bool> {
+        let mut state = PhpState::load()?;
+        let metadata = state
+            .get_metadata(version_str)
+            .ok_or_else(|| anyhow::Error::new(PhpVmError::NotInstalled(format!("Version {} is not installed", version_str))))?;
+        let expected = metadata
+            .checksum
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("No checksum recorded for PHP {}", version_str))?;
+
+        if !metadata.install_path.exists() {
+            anyhow::bail!("Installation directory does not exist: {:?}", metadata.install_path);
+        }
+
+        if !Installer::is_current_checksum(expected) {
+            let legacy_matches = Installer::calculate_legacy_checksum(&metadata.install_path)? == expected;
+            let fresh = Installer::calculate_checksum_with(&metadata.install_path, self.config.checksum_threads)?;
+            logging::log_message(
+                if legacy_matches { "INFO" } else { "WARN" },
+                &format!(
+                    "PHP {} had a checksum in the old format ({}); recorded a new one to check against from now on",
+                    version_str,
+                    if legacy_matches { "it matched" } else { "it can't be checked reliably" }
+                ),
+            );
+            if let Some(entry) = state.install_metadata.get_mut(version_str) {
+                entry.checksum = Some(fresh);
+            }
+            state.save()?;
+            return Ok(true);
+        }
+
+        let actual = Installer::calculate_checksum_with(&metadata.install_path, self.config.checksum_threads)?;
+        let intact = actual == expected;
+        if intact {
+            logging::log_message("INFO", &format!("Integrity check passed for PHP {}", version_str));
+        } else {
+            logging::log_message("WARN", &format!("Integrity check failed for PHP {}: expected {}, got {}", version_str, expected, actual));
+        }
+        Ok(intact)
+    }
+
+    /// Everything `list_installed`, `get_active`, `list_available` and `is_path_configured`
+    /// report, in one call. Failing to fetch available versions doesn't fail the report.
+    pub async fn status_report(&self) -> anyhow::Result<StatusReport> {
+        let active = self.get_active()?;
+        let installed = self
+            .list_installed()?
+            .into_iter()
+            .map(|version| InstalledStatus {
+                variant: version.rsplit_once('-').map(|(_, variant)| variant.to_string()).unwrap_or_default(),
+                active: active.as_deref() == Some(version.as_str()),
+                path: self.config.install_dir.join(format!("php-{}", version)),
+                version,
+            })
+            .collect();
+        let (available, available_error) = match self.list_available().await {
+            Ok(available) => (available, None),
+            Err(e) => (Vec::new(), Some(format!("{:#}", e))),
+        };
+        Ok(StatusReport {
+            schema_version: STATUS_REPORT_SCHEMA_VERSION,
+            installed,
+            active,
+            available,
+            available_error,
+            path_configured: self.is_path_configured().unwrap_or(false),
+            install_dir: self.config.install_dir.clone(),
+        })
+    }
+
+    /// Available versions, filtered and ordered by the saved `available_filter`
+    /// (by default all of them, newest first)
+    pub async fn list_available(&self) -> anyhow::Result<Vec<String>> {
+        self.list_available_filtered(&self.config.available_filter).await
+    }
+
+    /// `list_available` for this OS and CPU: the same list where phpvm's downloads run,
+    /// otherwise an empty one and the reason, without fetching anything
+    pub async fn list_installable(&self) -> anyhow::Result<InstallableVersions> {
+        let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+        let reason = platform::install_unsupported_reason(os, arch);
+        let versions = match &reason {
+            Some(reason) => {
+                logging::log_message("DEBUG", &format!("No installable versions: {}", reason));
+                Vec::new()
+            }
+            None => self.list_available().await?,
+        };
+        Ok(InstallableVersions { os: os.to_string(), arch: arch.to_string(), versions, reason })
+    }
+
+    /// Available versions after applying `filter` to the full fetched list
+    pub async fn list_available_filtered(&self, filter: &config::AvailableFilter) -> anyhow::Result<Vec<String>> {
+        logging::log_message("DEBUG", &format!("Fetching available PHP versions ({:?})", filter));
+        let provider = Provider::with_providers(self.config.providers.clone())?;
+        let versions = provider.get_top_versions(None).await?;
+        let installed = self.list_installed()?;
+        let version_strings: Vec<String> = Provider::filter_available(versions, filter, &installed, &crate::date::today_iso())
+            .into_iter()
+            .map(|v| v.version)
+            .collect();
+        logging::log_message("DEBUG", &format!("Found {} available versions", version_strings.len()));
+        Ok(version_strings)
+    }
+
+    /// Save how `list_available` filters and orders versions to config.json
+    pub fn set_available_filter(&mut self, filter: config::AvailableFilter) -> anyhow::Result<()> {
+        let mut config = config::Config::load()?;
+        config.available_filter = filter.clone();
+        config.save()?;
+        self.config.available_filter = filter;
+        Ok(())
+    }
+
+    /// How `list_available` currently filters and orders versions, as saved in config.json
+    pub fn get_available_filter(&self) -> config::AvailableFilter {
+        self.config.available_filter.clone()
+    }
+
+    /// New patches and moved EOL dates the last changed version list brought, compared
+    /// with the list cached before it. Purely local: reading it never fetches anything.
+    pub fn get_last_version_delta(&self) -> Option<crate::provider::VersionDelta> {
+        crate::provider::VersionDelta::load()
+    }
+    
+    /// Available versions bucketed by major.minor branch, with EOL and installed-update flags
+    pub async fn list_available_grouped(&self) -> anyhow::Result<Vec<crate::provider::BranchInfo>> {
+        let provider = Provider::with_providers(self.config.providers.clone())?;
+        let versions = provider.fetch_available_versions().await?;
+        let installed = self.list_installed()?;
+        Ok(Provider::group_by_branch(&versions, &installed, &crate::date::today_iso()))
+    }
+    
+    /// Available versions whose archive is already cached, so installing them needs no download
+    pub async fn list_cache_ready_versions(&self) -> anyhow::Result<Vec<CacheReadyVersion>> {
+        let provider = Provider::with_providers(self.config.providers.clone())?;
+        let versions = provider.fetch_available_versions().await?;
+        let downloader = crate::download::Downloader::new()?;
+        Ok(cache_ready_versions(&versions, |url| downloader.is_cached(url)))
+    }
+
+    /// Delete cached archives that don't belong to any version (see `cache_owner_versions`):
+    /// any patch of a listed branch (and, with `remove_eol`, only supported ones) or an
+    /// installed or pinned version, so older patches and prefetched archives stay. Every
+    /// URL an install tries counts, mirrors included. Fails rather than going by the
+    /// built-in version list when no provider answers. `clear_all_cache` in the GUI removes
+    /// everything instead.
+    pub async fn clean_orphaned_cache(&self, remove_eol: bool) -> anyhow::Result<crate::download::CacheCleanReport> {
+        let provider = Provider::with_providers(self.config.providers.clone())?;
+        let versions = provider
+            .fetch_provider_versions()
+            .await
+            .context("Can't tell which cached archives are orphaned without the version list")?;
+        let filter = config::AvailableFilter { include_eol: !remove_eol, ..Default::default() };
+        let versions = Provider::filter_available(versions, &filter, &[], &crate::date::today_iso());
+        let state = PhpState::load()?;
+        let recorded: Vec<String> = state.installed_versions.iter().chain(&state.pinned_versions).cloned().collect();
+        let downloader = crate::download::Downloader::new()?;
+
+        let mut keep = std::collections::HashSet::new();
+        let mut claim = |url: &str| {
+            // cached_path also moves legacy-named files to the name that's kept
+            if let Some(name) = downloader.cached_path(url).file_name() {
+                keep.insert(name.to_string_lossy().to_string());
+            }
+        };
+        for version in cache_owner_versions(&versions, &recorded) {
+            for thread_safe in [true, false] {
+                let plan = self.installer.plan(&version, thread_safe);
+                for (url, _) in self.installer.download_attempts(&plan, None)? {
+                    claim(&url);
+                }
+            }
+        }
+        for info in &versions {
+            if let Some(url) = &info.download_url {
+                claim(url);
+                for mirror in &self.config.download_mirrors {
+                    if let Some(mirrored) = Provider::mirrored_url(url, mirror) {
+                        claim(&mirrored);
+                    }
+                }
+            }
+        }
+
+        let cache_dir = config::get_base_directory().join("cache");
+        let report = crate::download::remove_cache_files_except(&cache_dir, &keep)?;
+        logging::log_message(
+            "INFO",
+            &format!("Removed {} orphaned cache file(s), freeing {}", report.removed.len(), platform::format_bytes(report.freed_bytes)),
+        );
+        Ok(report)
+    }
+
+    /// Every published patch of a branch (not just the newest), newest first
+    pub async fn list_branch_patches(&self, major: u8, minor: u8) -> anyhow::Result<Vec<crate::provider::VersionInfo>> {
+        let provider = Provider::with_providers(self.config.providers.clone())?;
+        provider.list_branch_patches(major, minor).await
+    }
+
+    /// php.net release notes for a version ("8.3.0" or "8.3.0-nts")
+    pub async fn get_changelog(&self, version_str: &str) -> anyhow::Result<String> {
+        validate_version_str(version_str)?;
+        let base = version_str.split('-').next().unwrap_or(version_str);
+        let version = PhpVersion::from_string(base)?;
+        let provider = Provider::with_providers(self.config.providers.clone())?;
+        provider.fetch_changelog(version.major, version.minor, version.patch).await
+    }
+
+    /// Installed versions that have a newer patch release in the same branch
+    pub async fn check_patch_updates(&self) -> anyhow::Result<Vec<crate::provider::PatchUpdate>> {
+        let provider = Provider::with_providers(self.config.providers.clone())?;
+        let versions = provider.fetch_available_versions().await?;
+        let state = PhpState::load()?;
+        let installed: Vec<String> = self
+            .list_installed()?
+            .into_iter()
+            .filter(|v| !state.is_pinned(v))
+            .collect();
+        let updates = Provider::find_patch_updates(&versions, &installed);
+        logging::log_message("INFO", &format!("Found {} patch updates for installed versions", updates.len()));
+        Ok(updates)
+    }
+    
+    /// The provider's `VersionInfo` for a version like "8.3.0", with the EOL dates and
+    /// (TS) download URL filled in from built-in data when the provider has none, or
+    /// can't be reached. `checksum` is only set when the provider published one.
+    /// None if `version_str` isn't a version.
+    pub async fn get_version_info(&self, version_str: &str) -> anyhow::Result<Option<crate::provider::VersionInfo>> {
+        let provider = Provider::with_providers(self.config.providers.clone())?;
+        let versions = match provider.fetch_available_versions().await {
+            Ok(versions) => versions,
+            Err(e) => {
+                logging::log_message("WARN", &format!("Using built-in data for PHP {}: {:#}", version_str, e));
+                Vec::new()
+            }
+        };
+        
+        // Try to find version in fetched list
+        if let Some(mut info) = versions.into_iter().find(|v| v.version == version_str) {
+            // Ensure EOL date is populated even if parsing failed
+            if info.eol_date.is_none() {
+                if let Ok(version) = PhpVersion::from_string(version_str) {
+                    info.eol_date = Provider::get_eol_date(version.major, version.minor);
+                }
+            }
+            if info.active_support_until.is_none() {
+                if let Ok(version) = PhpVersion::from_string(version_str) {
+                    info.active_support_until = Provider::get_active_support_date(version.major, version.minor);
+                }
+            }
+            // Ensure download URL is populated
+            if info.download_url.is_none() {
+                if let Ok(version) = PhpVersion::from_string(version_str) {
+                    info.download_url = Some(Provider::generate_download_url(version_str, version.major, version.minor, true));
+                }
+            }
+            return Ok(Some(info));
+        }
+        
+        // Version not in fetched list - create VersionInfo with EOL date from get_eol_date
+        if let Ok(version) = PhpVersion::from_string(version_str) {
+            let eol_date = Provider::get_eol_date(version.major, version.minor);
+            let download_url = Some(Provider::generate_download_url(version_str, version.major, version.minor, true));
+            
+            Ok(Some(crate::provider::VersionInfo {
+                version: version_str.to_string(),
+                release_date: None,
+                eol_date,
+                active_support_until: Provider::get_active_support_date(version.major, version.minor),
+                download_url,
+                checksum: None,
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    /// A `Provider` for the configured sources. Callers sharing the manager behind a
+    /// lock take this and release the lock before probing URLs.
+    pub fn provider(&self) -> anyhow::Result<Provider> {
+        Provider::with_providers(self.config.providers.clone())
+    }
+
+    /// Whether a download URL currently answers with a 2xx (cached per URL)
+    pub async fn is_download_available(&self, url: &str) -> anyhow::Result<bool> {
+        Ok(self.provider()?.is_url_available(url).await)
+    }
+
+    /// Probe, right now, the URLs an install of this version and variant would download
+    /// from, in the order it tries them (see `get_download_url`), stopping at the first
+    /// that answers. The single-version counterpart of `check_versions_online`, with
+    /// status codes and sizes to paste into a bug report.
+    pub async fn check_version_downloadable(&self, version_str: &str, thread_safe: Option<bool>) -> anyhow::Result<DownloadCheck> {
+        let urls = self.get_download_url(version_str, thread_safe)?;
+        Ok(probe_download_urls(&self.provider()?, &urls).await)
+    }
+
+    /// Check download availability for many versions at once, probing all URLs in parallel
+    pub async fn check_versions_online(&self, version_strs: &[String]) -> anyhow::Result<std::collections::HashMap<String, bool>> {
+        Ok(check_versions_online_with(&self.provider()?, version_strs).await)
+    }
+    
+    pub fn is_path_configured(&self) -> anyhow::Result<bool> {
+        let current_path = platform::get_current_path();
+        let current_dir = current_path.parent()
             .ok_or_else(|| anyhow::anyhow!("Invalid current path"))?;
         platform::is_path_set(&current_dir.to_path_buf())
     }
     
+    /// Check what `php` actually runs rather than what the registry says: a php earlier
+    /// on PATH, or a PATH change this process hasn't picked up, makes it `Shadowed` or
+    /// `NotFound` even when `is_path_configured` is true
+    pub fn resolve_php(&self) -> anyhow::Result<PhpResolution> {
+        let current_path = platform::get_current_path();
+        let shim_dir = current_path.parent()
+            .ok_or_else(|| anyhow::anyhow!("Invalid current path"))?
+            .to_path_buf();
+        let resolved = platform::resolve_php_on_path();
+        let status = match resolved.as_deref().and_then(|php| php.parent()) {
+            None => PhpResolutionStatus::NotFound,
+            Some(dir) if is_same_dir(dir, &shim_dir) => PhpResolutionStatus::Active,
+            Some(_) => PhpResolutionStatus::Shadowed,
+        };
+        tracing::debug!("php on PATH resolves to {:?} ({:?})", resolved, status);
+        Ok(PhpResolution { status, resolved, shim_dir })
+    }
+
+    /// Run the php on PATH and report its version and whether phpvm manages it: the
+    /// ground truth behind "your shell runs PHP 8.1.2 from C:\laragon\...", which can
+    /// differ from the active version when another php comes first
+    pub fn get_effective_php(&self) -> anyhow::Result<EffectivePhp> {
+        let resolution = self.resolve_php()?;
+        let active = self.get_active()?;
+        let Some(path) = resolution.resolved else {
+            return Ok(EffectivePhp { path: None, version: None, managed: false, active });
+        };
+        let version = platform::php_version_of(&path);
+        let in_install_dir = match (path.canonicalize(), self.config.install_dir.canonicalize()) {
+            (Ok(php), Ok(install_dir)) => php.starts_with(install_dir),
+            _ => path.starts_with(&self.config.install_dir),
+        };
+        let managed = resolution.status == PhpResolutionStatus::Active || in_install_dir;
+        logging::log_message("DEBUG", &format!("php on PATH is {:?} ({:?}, managed: {})", path, version, managed));
+        Ok(EffectivePhp { path: Some(path), version, managed, active })
+    }
+
+    /// `backup::restore_all`, then reload the restored config and rebuild the `current`
+    /// shim for the restored active version, since backups don't contain it
+    pub async fn restore_all(&mut self, src_zip: &std::path::Path) -> anyhow::Result<crate::backup::RestoreReport> {
+        let report = crate::backup::restore_all(src_zip)?;
+        self.config = config::Config::load()?;
+        self.installer = Installer::new()?;
+        if let Some(active) = self.get_active()? {
+            if let Err(e) = self.switch(&active).await {
+                logging::log_message("WARN", &format!("Restored PHP {} but couldn't activate it: {:#}", active, e));
+            }
+        }
+        Ok(report)
+    }
+
+    /// Remove everything phpvm created: every profile's installed versions, `current` shim,
+    /// PATH entry and state, then the profiles, config, stats and logs, plus cached downloads
+    /// unless `keep_downloads`.
+    /// `install_dir` and `download_cache` may be shared folders, so only what phpvm put
+    /// in them goes, and each is removed only if that leaves it empty.
+    /// The running executable is never deleted, even if it lives under the phpvm directory.
+    /// Individual failures are collected in the report rather than aborting the reset.
+    pub fn uninstall_all(&self, keep_downloads: bool) -> anyhow::Result<UninstallReport> {
+        logging::log_message("WARN", &format!("Uninstalling everything (keep_downloads: {})", keep_downloads));
+
+        let mut report = UninstallReport::default();
+        let running_exe = std::env::current_exe().ok();
+        let protected = running_exe.as_deref();
+
+        // These live in phpvm's own directories
+        let mut targets = Vec::new();
+        for profile in config::list_profiles() {
+            let profile_dir = config::profile_directory(&profile);
+            let current_dir = profile_dir.join("current");
+            match platform::remove_from_path(&current_dir) {
+                Ok(()) => report.removed.push(format!("PATH entry {}", current_dir.display())),
+                Err(e) => report.errors.push(format!("PATH entry {}: {}", current_dir.display(), e)),
+            }
+
+            let state_path = profile_dir.join("state.json");
+            let recorded = fs::read_to_string(&state_path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<PhpState>(&content).ok())
+                .map(|state| state.installed_versions)
+                .unwrap_or_default();
+            remove_owned_entries(
+                &config::profile_install_dir(&profile),
+                &|name, is_dir| is_install_entry(name, is_dir, &recorded),
+                protected,
+                &mut report,
+            );
+            targets.extend([current_dir, state_path]);
+        }
+
+        let base_dir = config::get_base_directory();
+
+        if keep_downloads {
+            report.kept.push(self.config.download_cache.display().to_string());
+        } else {
+            // Archives go to the base directory's cache, whatever download_cache says
+            let mut cache_dirs = vec![self.config.download_cache.clone(), base_dir.join("cache")];
+            cache_dirs.dedup();
+            for cache_dir in cache_dirs {
+                remove_owned_entries(&cache_dir.join("versions"), &|name, is_dir| !is_dir && name.ends_with(".json"), protected, &mut report);
+                remove_owned_entries(&cache_dir.join("changelogs"), &|name, is_dir| !is_dir && name.ends_with(".txt"), protected, &mut report);
+                remove_owned_entries(&cache_dir.join("checksums"), &|name, is_dir| !is_dir && is_cache_file_name(name), protected, &mut report);
+                remove_owned_entries(&cache_dir, &|name, is_dir| !is_dir && is_cache_file_name(name), protected, &mut report);
+            }
+        }
+
+        targets.extend([base_dir.join("profiles"), config::get_config_path(), config::get_stats_path()]);
+        if let Some(logs_dir) = config::get_log_path().parent() {
+            targets.push(logs_dir.to_path_buf());
+        }
+
+        for target in targets {
+            if !target.exists() {
+                continue;
+            }
+            match platform::remove_tree_except(&target, protected) {
+                Ok(true) => report.removed.push(target.display().to_string()),
+                Ok(false) => report.kept.push(format!(
+                    "{} (contains the running executable)",
+                    target.display()
+                )),
+                Err(e) => report.errors.push(format!("{}: {}", target.display(), e)),
+            }
+        }
+
+        // Drop the base directory only if nothing else (kept cache, the exe) is left in it
+        if base_dir.exists() && fs::remove_dir(&base_dir).is_ok() {
+            report.removed.push(base_dir.display().to_string());
+        }
+
+        Ok(report)
+    }
+
     pub fn ensure_path_set(&self) -> anyhow::Result<()> {
+        if !self.config.manage_path {
+            return Ok(());
+        }
         let current_path = platform::get_current_path();
         let current_dir = current_path.parent()
             .ok_or_else(|| anyhow::anyhow!("Invalid current path"))?;
@@ -338,3 +2018,380 @@ impl PhpManager {
             .context("Failed to add PHP to PATH")
     }
 }
+
+/// Whether `version` ("8.3.0-nts") is the name part of an install directory phpvm
+/// creates: a valid version with its variant
+fn is_install_dir_version(version: &str) -> bool {
+    validate_version_str(version).is_ok() && (version.ends_with("-ts") || version.ends_with("-nts"))
+}
+
+/// Install directories ("php-8.3.0-nts", or any `recorded` version) and the staging and
+/// replaced copies installs leave next to them
+fn is_install_entry(name: &str, is_dir: bool, recorded: &[String]) -> bool {
+    is_dir
+        && [".staging-php-", ".replaced-php-", "php-"].iter().any(|prefix| {
+            name.strip_prefix(prefix)
+                .is_some_and(|version| is_install_dir_version(version) || recorded.iter().any(|v| v == version))
+        })
+}
+
+/// Names `Downloader` gives cached archives: a hex hash of the URL (32 digits, or up to
+/// 16 for the legacy key)
+fn is_cache_file_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 32 && name.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Delete the entries of `dir` that `owned(name, is_dir)` says phpvm created, then `dir`
+/// itself if nothing else is left in it. For `uninstall_all`.
+fn remove_owned_entries(
+    dir: &std::path::Path,
+    owned: &dyn Fn(&str, bool) -> bool,
+    protected: Option<&std::path::Path>,
+    report: &mut UninstallReport,
+) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+        Err(e) => {
+            report.errors.push(format!("{}: {}", dir.display(), e));
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if !owned(&entry.file_name().to_string_lossy(), is_dir) {
+            continue;
+        }
+        match platform::remove_tree_except(&path, protected) {
+            Ok(true) => report.removed.push(path.display().to_string()),
+            Ok(false) => report.kept.push(format!("{} (contains the running executable)", path.display())),
+            Err(e) => report.errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    if fs::remove_dir(dir).is_ok() {
+        report.removed.push(dir.display().to_string());
+    } else if dir.exists() {
+        report.kept.push(format!("{} (has files phpvm didn't create)", dir.display()));
+    }
+}
+
+/// Make the `current` shim run `php_exe` from `version_dir`, using the configured
+/// switch strategy on Windows
+pub(crate) fn point_current_at(config: &config::Config, php_exe: &std::path::Path, version_dir: &std::path::Path) -> anyhow::Result<()> {
+    let current_path = platform::get_current_path();
+    let current_dir = current_path.parent()
+        .ok_or_else(|| anyhow::anyhow!("Invalid current path"))?;
+    fs::create_dir_all(current_dir)?;
+
+    #[cfg(target_os = "windows")]
+    {
+        // Drop whatever the previous switch left behind, whichever strategy it used
+        clear_current_dir(current_dir);
+
+        match config.switch_strategy {
+            config::SwitchStrategy::Copy => copy_php_into_current(php_exe, version_dir, current_dir)?,
+            config::SwitchStrategy::Junction => link_current_junction(version_dir, current_dir)?,
+            config::SwitchStrategy::BatOnly | config::SwitchStrategy::WindowsAppsAlias => {}
+        }
+
+        // php.bat for command-line use, written with every strategy
+        let batch_content = platform::php_shim_content(php_exe, current_dir);
+        fs::write(&current_path, batch_content)?;
+
+        update_windows_apps_launcher(config.switch_strategy, Some(php_exe));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // Only the Windows strategies need the whole directory
+        let _ = (config, version_dir);
+        // On Unix, create a symlink or shell script
+        if current_path.exists() || current_path.is_symlink() {
+            fs::remove_file(&current_path)?;
+        }
+        std::os::unix::fs::symlink(php_exe, &current_path)?;
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(&current_path)?.permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(&current_path, perms)?;
+    }
+    Ok(())
+}
+
+/// Whether two paths name the same directory, resolving links and (on Windows) case
+fn is_same_dir(a: &std::path::Path, b: &std::path::Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Bundled extensions php.ini loads with `zend_extension=` instead of `extension=`
+const ZEND_EXTENSIONS: [&str; 2] = ["opcache", "xdebug"];
+
+/// An extension's library in an install's `ext` directory: `php_<name>.dll` in Windows
+/// builds, `<name>.so` elsewhere
+fn bundled_extension_file(ext_dir: &std::path::Path, name: &str) -> Option<std::path::PathBuf> {
+    [format!("php_{}.dll", name), format!("{}.so", name)]
+        .into_iter()
+        .map(|file| ext_dir.join(file))
+        .find(|path| path.is_file())
+}
+
+/// Whether an install's php.ini has an active line loading `name` and the library it
+/// names exists. Relative names are looked up in the install's `ext` directory.
+fn extension_already_enabled(install_path: &std::path::Path, name: &str, zend: bool) -> anyhow::Result<bool> {
+    let ini_path = install_path.join("php.ini");
+    if !ini_path.exists() {
+        return Ok(false);
+    }
+    let Some(library) = extension::enabled_library(&fs::read_to_string(&ini_path)?, name, zend) else {
+        return Ok(false);
+    };
+    let library = std::path::Path::new(&library);
+    let ext_dir = install_path.join("ext");
+    Ok(if library.is_absolute() {
+        library.is_file()
+    } else {
+        // PHP also tries the bare name as php_<name>.dll / <name>.so
+        ext_dir.join(library).is_file() || bundled_extension_file(&ext_dir, &crate::ini::extension_name(&library.to_string_lossy())).is_some()
+    })
+}
+
+/// Point an install's php.ini at an extension DLL, creating php.ini from
+/// php.ini-development if missing. Returns false if php.ini already had it enabled.
+fn enable_extension_in_ini(install_path: &std::path::Path, name: &str, dll_path: &std::path::Path, zend: bool) -> anyhow::Result<bool> {
+    let ini_path = install_path.join("php.ini");
+    let template_path = install_path.join("php.ini-development");
+    let current = if ini_path.exists() { Some(fs::read_to_string(&ini_path)?) } else { None };
+    let base = match &current {
+        Some(content) => content.clone(),
+        None if template_path.exists() => fs::read_to_string(&template_path)?,
+        None => String::new(),
+    };
+    let ini_content = extension::enable_in_ini(&base, name, &dll_path.to_string_lossy(), zend);
+    if current.as_deref() == Some(ini_content.as_str()) {
+        return Ok(false);
+    }
+    fs::write(&ini_path, ini_content)?;
+    logging::log_message("INFO", &format!("Enabled {} in {:?}", name, ini_path));
+    Ok(true)
+}
+
+/// Carry a user's setup from one install to another: the old php.ini (with paths into
+/// the old install pointed at the new one) and extension files in `ext` that the new
+/// install doesn't ship. A php.ini already in the new install is kept as php.ini.bak.
+/// Returns a line per step taken.
+pub fn migrate_install_settings(old_dir: &std::path::Path, new_dir: &std::path::Path) -> anyhow::Result<Vec<String>> {
+    let mut steps = Vec::new();
+
+    let old_ini = old_dir.join("php.ini");
+    if old_ini.is_file() {
+        let new_ini = new_dir.join("php.ini");
+        if new_ini.exists() {
+            fs::rename(&new_ini, new_dir.join("php.ini.bak"))
+                .with_context(|| format!("Failed to back up {:?}", new_ini))?;
+            steps.push("Backed up the new install's php.ini to php.ini.bak".to_string());
+        }
+        let content = fs::read_to_string(&old_ini).with_context(|| format!("Failed to read {:?}", old_ini))?;
+        let rewritten = content.replace(&*old_dir.to_string_lossy(), &new_dir.to_string_lossy());
+        fs::write(&new_ini, &rewritten).with_context(|| format!("Failed to write {:?}", new_ini))?;
+        steps.push(if rewritten == content {
+            "Copied php.ini".to_string()
+        } else {
+            "Copied php.ini, pointing paths at the new install".to_string()
+        });
+    }
+
+    let old_ext = old_dir.join("ext");
+    let new_ext = new_dir.join("ext");
+    if let Ok(entries) = fs::read_dir(&old_ext) {
+        fs::create_dir_all(&new_ext)?;
+        for entry in entries.flatten() {
+            let target = new_ext.join(entry.file_name());
+            if !entry.path().is_file() || target.exists() {
+                continue;
+            }
+            fs::copy(entry.path(), &target).with_context(|| format!("Failed to copy {:?}", entry.path()))?;
+            steps.push(format!("Copied extension {}", entry.file_name().to_string_lossy()));
+        }
+    }
+    Ok(steps)
+}
+
+/// What `upgrade` did
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpgradeReport {
+    /// The install upgraded from, e.g. "8.2.10-nts"
+    pub from: String,
+    /// The install upgraded to, e.g. "8.2.30-nts"; same as `from` if it was already the latest
+    pub to: String,
+    pub steps: Vec<String>,
+}
+
+/// The installed version a switch spec refers to. An exact "8.2.5-ts" must be installed
+/// as is; "8.2.5", "8.2" or "8" (optionally with "-ts"/"-nts") picks the newest matching
+/// patch. Without a variant in the spec, `preferred_variant` wins when both builds are
+/// installed, else TS does. A branch spec ("8.2") whose newest patch lacks the preferred
+/// build is NotInstalled, naming the build that's there; an exact "8.2.5" names one
+/// patch, so its only installed build is used.
+pub fn resolve_switch_target(spec: &str, installed: &[String], preferred_variant: Option<&str>) -> anyhow::Result<String> {
+    if installed.iter().any(|v| v == spec) {
+        return Ok(spec.to_string());
+    }
+
+    let (base, wanted_variant) = match spec.rsplit_once('-') {
+        Some((base, variant)) if variant == "ts" || variant == "nts" => (base, Some(variant)),
+        _ => (spec, None),
+    };
+    let wanted: Vec<&str> = base.split('.').collect();
+
+    let best = installed
+        .iter()
+        .filter_map(|v| {
+            let (version, variant) = v.rsplit_once('-').filter(|(_, variant)| *variant == "ts" || *variant == "nts")?;
+            if wanted_variant.is_some_and(|wanted| wanted != variant) {
+                return None;
+            }
+            // Compare component-wise so "8.2" never matches 8.20.x
+            let matches = wanted.len() == 3 && version == base
+                || wanted.len() < 3 && version.split('.').take(wanted.len()).eq(wanted.iter().copied());
+            if !matches {
+                return None;
+            }
+            let parsed = PhpVersion::from_string(version).ok()?;
+            let variant_rank = if Some(variant) == preferred_variant {
+                2
+            } else if variant == "ts" {
+                1
+            } else {
+                0
+            };
+            // Releases sort above pre-releases of the same patch
+            Some(((parsed.major, parsed.minor, parsed.patch, parsed.suffix.is_none()), variant_rank, v))
+        })
+        .max_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+    let (_, variant_rank, best) = best.ok_or_else(|| {
+        anyhow::Error::new(PhpVmError::NotInstalled(format!("No installed version matches {}", spec)))
+    })?;
+    if let (None, Some(preferred), true) = (wanted_variant, preferred_variant, variant_rank < 2 && wanted.len() < 3) {
+        let (patch, variant) = best.rsplit_once('-').unwrap_or((best, ""));
+        let preferred = preferred.to_uppercase();
+        return Err(PhpVmError::NotInstalled(format!(
+            "PHP {} is installed only as {}, not {}; switch to {:?} or install its {} build",
+            patch,
+            variant.to_uppercase(),
+            preferred,
+            best,
+            preferred
+        ))
+        .into());
+    }
+    Ok(best.clone())
+}
+
+/// Remove the php.exe/DLL copies and `php` junction a previous switch may have created
+#[cfg(target_os = "windows")]
+fn clear_current_dir(current_dir: &std::path::Path) {
+    let junction = current_dir.join("php");
+    if junction.is_dir() {
+        // remove_dir removes the junction itself, never the version directory behind it
+        if let Err(e) = fs::remove_dir(&junction) {
+            tracing::warn!("Failed to remove junction {:?}: {}", junction, e);
+        }
+    }
+
+    let php_exe_in_current = current_dir.join("php.exe");
+    if php_exe_in_current.exists() {
+        let _ = fs::remove_file(&php_exe_in_current);
+    }
+
+    if let Ok(entries) = fs::read_dir(current_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_dll = path
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("dll"))
+                .unwrap_or(false);
+            if path.is_file() && is_dll {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+}
+
+/// `SwitchStrategy::WindowsAppsAlias`: point the WindowsApps launcher at `php_exe`, or
+/// remove it when deactivating or using another strategy. Failures only lose the
+/// shortcut (`current` on PATH still works), so they're logged, not returned.
+#[cfg(target_os = "windows")]
+fn update_windows_apps_launcher(strategy: config::SwitchStrategy, php_exe: Option<&std::path::Path>) {
+    let Some(dir) = platform::windows_apps_dir() else { return };
+    match php_exe.filter(|_| strategy == config::SwitchStrategy::WindowsAppsAlias) {
+        Some(php_exe) => match platform::write_windows_apps_launcher(&dir, php_exe, std::env::var_os(platform::get_path_env_var()).as_deref()) {
+            Ok(launcher) => logging::log_message("INFO", &format!("Wrote launcher {:?}", launcher)),
+            Err(e) => logging::log_message("WARN", &format!("No WindowsApps launcher, relying on PATH: {:#}", e)),
+        },
+        None => {
+            if let Err(e) = platform::remove_windows_apps_launcher(&dir) {
+                logging::log_message("WARN", &format!("{:#}", e));
+            }
+        }
+    }
+}
+
+/// `SwitchStrategy::Copy`: IDEs (like VS Code) expect php.exe directly in the current
+/// directory, e.g. C:\Users\...\phpvm\current\php.exe. Copying works without admin
+/// privileges; PHP needs its DLLs next to it, so those are copied too.
+#[cfg(target_os = "windows")]
+fn copy_php_into_current(php_exe: &std::path::Path, version_dir: &std::path::Path, current_dir: &std::path::Path) -> anyhow::Result<()> {
+    let php_exe_in_current = current_dir.join("php.exe");
+    fs::copy(php_exe, &php_exe_in_current)
+        .context("Failed to copy php.exe to current directory for IDE compatibility")?;
+    tracing::info!("Copied php.exe to current directory for IDE compatibility: {:?}", php_exe_in_current);
+
+    if let Ok(entries) = fs::read_dir(version_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_dll = path
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("dll"))
+                .unwrap_or(false);
+            if let (true, true, Some(file_name)) = (path.is_file(), is_dll, path.file_name()) {
+                let dll_in_current = current_dir.join(file_name);
+                if let Err(e) = fs::copy(&path, &dll_in_current) {
+                    tracing::warn!("Failed to copy DLL {:?} to current directory: {}", path, e);
+                } else {
+                    tracing::info!("Copied DLL {:?} to current directory", file_name);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `SwitchStrategy::Junction`: `current\php` -> version directory. Junctions, unlike
+/// symlinks, don't need admin rights or developer mode.
+#[cfg(target_os = "windows")]
+fn link_current_junction(version_dir: &std::path::Path, current_dir: &std::path::Path) -> anyhow::Result<()> {
+    let junction = current_dir.join("php");
+    let output = std::process::Command::new("cmd")
+        .arg("/C")
+        .arg("mklink")
+        .arg("/J")
+        .arg(&junction)
+        .arg(version_dir)
+        .output()
+        .context("Failed to run mklink")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to create junction {:?} -> {:?}: {}",
+            junction,
+            version_dir,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    tracing::info!("Linked {:?} to {:?}", junction, version_dir);
+    Ok(())
+}
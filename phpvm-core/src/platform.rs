@@ -1,5 +1,8 @@
 use crate::config;
-use std::path::PathBuf;
+use crate::error::PhpVmError;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 
 #[cfg(target_os = "windows")]
 pub fn get_php_executable_path(version_dir: &PathBuf) -> PathBuf {
@@ -13,12 +16,12 @@ pub fn get_php_executable_path(version_dir: &PathBuf) -> PathBuf {
 
 #[cfg(target_os = "windows")]
 pub fn get_current_path() -> PathBuf {
-    config::get_base_directory().join("current").join("php.bat")
+    config::get_profile_directory().join("current").join("php.bat")
 }
 
 #[cfg(not(target_os = "windows"))]
 pub fn get_current_path() -> PathBuf {
-    config::get_base_directory().join("current").join("php")
+    config::get_profile_directory().join("current").join("php")
 }
 
 pub fn get_path_env_var() -> &'static str {
@@ -32,53 +35,688 @@ pub fn get_path_env_var() -> &'static str {
     }
 }
 
+/// Quote a path for a .bat file: wrap it in double quotes and escape `%`,
+/// which cmd.exe expands even inside quotes
+pub fn quote_batch_path(path: &Path) -> String {
+    format!("\"{}\"", path.to_string_lossy().replace('%', "%%"))
+}
+
+/// Content of the `current\php.bat` shim that forwards to `php_exe`.
+///
+/// When php.exe lives under the phpvm base directory the path is written relative to the
+/// shim (`%~dp0`), so cmd.exe resolves the user profile path itself. Otherwise the absolute
+/// path is written; if it contains non-ASCII characters the shim temporarily switches the
+/// console to UTF-8, since cmd.exe decodes batch files with the OEM code page.
+pub fn php_shim_content(php_exe: &Path, shim_dir: &Path) -> String {
+    let relative = shim_dir
+        .parent()
+        .and_then(|base_dir| php_exe.strip_prefix(base_dir).ok());
+
+    let target = match relative {
+        Some(relative) => {
+            let relative = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+                .join("\\");
+            format!("\"%~dp0..\\{}\"", relative.replace('%', "%%"))
+        }
+        None => quote_batch_path(php_exe),
+    };
+
+    let lines = if target.is_ascii() {
+        vec!["@echo off".to_string(), format!("{} %*", target)]
+    } else {
+        vec![
+            "@echo off".to_string(),
+            "setlocal".to_string(),
+            "for /f \"tokens=2 delims=:.\" %%c in ('chcp') do set \"PHPVM_CP=%%c\"".to_string(),
+            "chcp 65001 >nul".to_string(),
+            format!("{} %*", target),
+            "set \"PHPVM_RC=%ERRORLEVEL%\"".to_string(),
+            "chcp %PHPVM_CP% >nul".to_string(),
+            "exit /b %PHPVM_RC%".to_string(),
+        ]
+    };
+
+    // cmd.exe expects CRLF line endings
+    let mut content = lines.join("\r\n");
+    content.push_str("\r\n");
+    content
+}
+
+/// First line of the launcher `write_windows_apps_launcher` writes, so only phpvm's own
+/// file is ever replaced or removed
+pub const WINDOWS_APPS_LAUNCHER_MARKER: &str = "@rem Written by phpvm on every switch; edits are lost";
+
+/// `%LOCALAPPDATA%\Microsoft\WindowsApps`, where Windows keeps app execution aliases and
+/// which it puts on each user's PATH (Windows 10 1709 and later)
+pub fn windows_apps_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("Microsoft").join("WindowsApps"))
+}
+
+/// Write `dir\php.bat` running `php_exe`. Fails without writing when `dir` doesn't exist,
+/// isn't on `path_var` (the PATH terminals start with) or holds a php.bat phpvm didn't write.
+pub fn write_windows_apps_launcher(dir: &Path, php_exe: &Path, path_var: Option<&std::ffi::OsStr>) -> anyhow::Result<PathBuf> {
+    if !dir.is_dir() {
+        anyhow::bail!("{:?} doesn't exist", dir);
+    }
+    let on_path = path_var
+        .map(|path| std::env::split_paths(path).any(|entry| same_path_entry(&entry, dir)))
+        .unwrap_or(false);
+    if !on_path {
+        anyhow::bail!("{:?} isn't on PATH", dir);
+    }
+    let launcher = dir.join("php.bat");
+    if launcher.exists() && !is_phpvm_launcher(&launcher) {
+        anyhow::bail!("{:?} wasn't written by phpvm; leaving it alone", launcher);
+    }
+    let content = format!("{}\r\n{}", WINDOWS_APPS_LAUNCHER_MARKER, php_shim_content(php_exe, dir));
+    std::fs::write(&launcher, content).with_context(|| format!("Failed to write {:?}", launcher))?;
+    Ok(launcher)
+}
+
+/// Remove the launcher `write_windows_apps_launcher` left in `dir`, if there is one.
+/// Returns whether it was there.
+pub fn remove_windows_apps_launcher(dir: &Path) -> anyhow::Result<bool> {
+    let launcher = dir.join("php.bat");
+    if !is_phpvm_launcher(&launcher) {
+        return Ok(false);
+    }
+    std::fs::remove_file(&launcher).with_context(|| format!("Failed to remove {:?}", launcher))?;
+    Ok(true)
+}
+
+fn is_phpvm_launcher(path: &Path) -> bool {
+    std::fs::read_to_string(path)
+        .map(|content| content.lines().next() == Some(WINDOWS_APPS_LAUNCHER_MARKER))
+        .unwrap_or(false)
+}
+
+/// PATH entries compare case-insensitively and without a trailing separator on Windows
+fn same_path_entry(entry: &Path, dir: &Path) -> bool {
+    let normalize = |path: &Path| path.to_string_lossy().trim_end_matches(['\\', '/']).to_string();
+    if cfg!(target_os = "windows") {
+        normalize(entry).eq_ignore_ascii_case(&normalize(dir))
+    } else {
+        normalize(entry) == normalize(dir)
+    }
+}
+
+/// Shell flavour for generated activation scripts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    Cmd,
+    PowerShell,
+    Bash,
+}
+
+impl ShellKind {
+    pub fn parse(name: &str) -> anyhow::Result<Self> {
+        match name.to_lowercase().as_str() {
+            "cmd" | "bat" | "batch" => Ok(ShellKind::Cmd),
+            "powershell" | "pwsh" | "ps1" => Ok(ShellKind::PowerShell),
+            "bash" | "sh" | "zsh" => Ok(ShellKind::Bash),
+            _ => Err(PhpVmError::InvalidArgument(format!(
+                "Unsupported shell: {} (expected cmd, powershell or bash)",
+                name
+            ))
+            .into()),
+        }
+    }
+
+    pub fn script_extension(&self) -> &'static str {
+        match self {
+            ShellKind::Cmd => "bat",
+            ShellKind::PowerShell => "ps1",
+            ShellKind::Bash => "sh",
+        }
+    }
+}
+
+/// Script that prepends `php_dir` to PATH for the current shell session only.
+/// It must be sourced (`call` for cmd, `.` for PowerShell/bash) to affect the calling shell.
+pub fn activation_script(php_dir: &Path, label: &str, shell: ShellKind) -> String {
+    let dir = php_dir.to_string_lossy();
+    match shell {
+        ShellKind::Cmd => {
+            let lines = [
+                "@echo off".to_string(),
+                format!("rem Activate PHP {} for this cmd session: call this script", label),
+                format!("set \"PATH={};%PATH%\"", dir.replace('%', "%%")),
+                format!("echo Using PHP {} in this session", label),
+            ];
+            let mut content = lines.join("\r\n");
+            content.push_str("\r\n");
+            content
+        }
+        ShellKind::PowerShell => format!(
+            "# Activate PHP {} for this PowerShell session: . .\\<this script>\n\
+             $env:PATH = '{}' + [IO.Path]::PathSeparator + $env:PATH\n\
+             Write-Host 'Using PHP {} in this session'\n",
+            label,
+            dir.replace('\'', "''"),
+            label
+        ),
+        ShellKind::Bash => format!(
+            "# Activate PHP {} for this shell session: source <this script>\n\
+             export PATH='{}':\"$PATH\"\n\
+             echo 'Using PHP {} in this session'\n",
+            label,
+            dir.replace('\'', "'\\''"),
+            label
+        ),
+    }
+}
+
+/// Pull the "Loaded Configuration File" path out of `php --ini` output.
+/// Returns None when PHP reports "(none)".
+pub fn parse_loaded_ini_path(output: &str) -> Option<PathBuf> {
+    output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Loaded Configuration File:"))
+        .map(str::trim)
+        .filter(|path| !path.is_empty() && *path != "(none)")
+        .map(PathBuf::from)
+}
+
+/// Command for a PHP executable that doesn't flash a console window from the GUI
+fn php_command(php_exe: &Path) -> std::process::Command {
+    #[allow(unused_mut)]
+    let mut command = std::process::Command::new(php_exe);
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+    command
+}
+
+/// Run `php --ini` and return the php.ini it reports as loaded
+pub fn query_loaded_ini(php_exe: &Path) -> anyhow::Result<Option<PathBuf>> {
+    let output = run_php(php_exe, &["--ini"], PHP_QUERY_TIMEOUT)?;
+    Ok(parse_loaded_ini_path(&output))
+}
+
+/// How long `php -m` may take before the version is reported as hung
+pub const PHP_QUERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Run a PHP executable with `args`, killing it if it runs longer than `timeout`.
+/// Fails if it can't be launched, times out, or exits unsuccessfully.
+pub fn run_php(php_exe: &Path, args: &[&str], timeout: std::time::Duration) -> anyhow::Result<String> {
+    let output = run_php_output(php_exe, args, timeout)?;
+    if !output.status.success() {
+        let detail = output.stderr.trim();
+        let detail = if detail.is_empty() { output.stdout.trim() } else { detail };
+        anyhow::bail!("{:?} {} exited with {}: {}", php_exe, args.join(" "), output.status, detail);
+    }
+    Ok(output.stdout)
+}
+
+/// What a PHP run printed, and how it exited
+pub struct PhpOutput {
+    pub status: std::process::ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Like `run_php`, but an unsuccessful exit isn't an error: both streams are returned
+/// either way, for callers interested in what PHP complained about
+pub fn run_php_output(php_exe: &Path, args: &[&str], timeout: std::time::Duration) -> anyhow::Result<PhpOutput> {
+    use std::io::Read;
+    use std::process::Stdio;
+
+    if !php_exe.is_file() {
+        anyhow::bail!("PHP executable not found at {:?}", php_exe);
+    }
+    let mut child = php_command(php_exe)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to launch {:?}: {}", php_exe, e))?;
+
+    // Drain the pipes on threads so a chatty process can't block on a full pipe
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buffer = String::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_string(&mut buffer);
+            }
+            buffer
+        })
+    };
+    let stdout = drain(child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let stderr = drain(child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+
+    let started = std::time::Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            anyhow::bail!("{:?} did not finish within {} seconds", php_exe, timeout.as_secs());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    };
+
+    Ok(PhpOutput {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Module names from `php -m` output, sorted and without the section headers.
+/// Zend extensions listed in both sections appear once.
+pub fn parse_module_list(output: &str) -> Vec<String> {
+    let mut modules: Vec<String> = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| {
+            let is_header = line.starts_with('[') && line.ends_with(']');
+            !line.is_empty() && !is_header
+        })
+        .map(str::to_string)
+        .collect();
+    modules.sort_by_key(|m| m.to_lowercase());
+    modules.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+    modules
+}
+
+/// Compiler tag ("vs16", "vc15", ...) and architecture ("x64") from `php -i` output,
+/// e.g. "Compiler => Visual C++ 2019" and "Architecture => x64"
+pub fn parse_php_build_info(output: &str) -> (Option<String>, Option<String>) {
+    let value = |key: &str| {
+        output.lines().find_map(|line| {
+            let (k, v) = line.split_once("=>")?;
+            (k.trim() == key).then(|| v.trim().to_string())
+        })
+    };
+    // Windows builds name the Visual Studio release; older ones also say "MSVC15"
+    let compiler = value("Compiler").and_then(|compiler| {
+        [("2022", "vs17"), ("2019", "vs16"), ("2017", "vc15"), ("2015", "vc14"), ("2012", "vc11")]
+            .into_iter()
+            .find(|(year, _)| compiler.contains(&format!("Visual C++ {}", year)))
+            .map(|(_, tag)| tag.to_string())
+    });
+    (compiler, value("Architecture").filter(|a| !a.is_empty()))
+}
+
+/// Free bytes available to the current user on the volume holding `path`. The path
+/// doesn't have to exist yet; its nearest existing ancestor is used.
+pub fn available_space(path: &Path) -> std::io::Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(path);
+    fs2::available_space(existing)
+}
+
+/// Fail with "Insufficient disk space" unless `needed` bytes are free at `path`. If the
+/// free space can't be determined, the check is skipped rather than blocking the caller.
+pub fn ensure_space(path: &Path, needed: u64) -> anyhow::Result<()> {
+    match available_space(path) {
+        Ok(available) if available < needed => Err(PhpVmError::Io(format!(
+            "Insufficient disk space in {:?}: need ~{}, have {}",
+            path,
+            format_bytes(needed),
+            format_bytes(available)
+        ))
+        .into()),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            crate::logging::log_message("WARN", &format!("Could not determine free space for {:?}: {}", path, e));
+            Ok(())
+        }
+    }
+}
+
+/// "512 B", "1.5 KB", "32.0 MB", ...
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Whether `php -i` output says the build is thread safe ("Thread Safety => enabled")
+pub fn parse_php_thread_safety(output: &str) -> Option<bool> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.split_once("=>")?;
+        if key.trim() != "Thread Safety" {
+            return None;
+        }
+        match value.trim() {
+            "enabled" => Some(true),
+            "disabled" => Some(false),
+            _ => None,
+        }
+    })
+}
+
+/// The PHP version in the first line of `php -v` output ("PHP 8.2.12 (cli) ..." -> "8.2.12")
+pub fn parse_php_version_banner(output: &str) -> Option<String> {
+    let version = output.lines().next()?.trim().strip_prefix("PHP ")?.split_whitespace().next()?;
+    numeric_php_version(version)
+}
+
+/// Drop suffixes like "-dev" or "RC1" builds append, keeping major.minor.patch
+fn numeric_php_version(version: &str) -> Option<String> {
+    let numeric: String = version.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+    (numeric.split('.').filter(|part| !part.is_empty()).count() == 3).then_some(numeric)
+}
+
+/// How an installed PHP was built, e.g. for showing "VS16 x64 TS"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub php_version: String,
+    /// Lowercase compiler tag ("vs16"), if known
+    pub compiler: Option<String>,
+    pub arch: String,
+    pub thread_safe: bool,
+}
+
+/// Version, compiler, architecture and thread safety from `php -i` output. None unless it
+/// has both "PHP Version" and "Thread Safety"; builds that don't print "Architecture"
+/// (non-Windows ones) are taken to match this machine.
+pub fn parse_php_info(output: &str) -> Option<BuildInfo> {
+    let php_version = output.lines().find_map(|line| {
+        let (key, value) = line.split_once("=>")?;
+        (key.trim() == "PHP Version").then(|| numeric_php_version(value.trim()))?
+    })?;
+    let thread_safe = parse_php_thread_safety(output)?;
+    let (compiler, arch) = parse_php_build_info(output);
+    Some(BuildInfo {
+        php_version,
+        compiler,
+        arch: arch.unwrap_or_else(|| host_arch().to_string()),
+        thread_safe,
+    })
+}
+
+/// This machine's architecture in PHP's naming ("x64", "x86", "arm64")
+pub fn host_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        other => other,
+    }
+}
+
+/// Why phpvm can't install PHP on this OS and CPU (`std::env::consts` names), or None
+/// when it can. Installs are the 64-bit Windows builds from windows.php.net, which
+/// Windows on ARM runs under emulation.
+pub fn install_unsupported_reason(os: &str, arch: &str) -> Option<String> {
+    match (os, arch) {
+        ("windows", "x86_64" | "aarch64") => None,
+        ("windows", arch) => Some(format!(
+            "phpvm installs the 64-bit Windows builds of PHP, which don't run on {} Windows",
+            arch
+        )),
+        (os, _) => Some(format!(
+            "phpvm installs the Windows builds of PHP from windows.php.net; there are no {} builds to install. \
+             Install PHP with your package manager and add it with adopt instead.",
+            os
+        )),
+    }
+}
+
+/// Run `php -i` and read how the build was made. Fails when PHP doesn't run or its
+/// output lacks the version or thread safety, rather than guessing.
+pub fn query_php_build(php_exe: &Path) -> anyhow::Result<BuildInfo> {
+    let output = run_php(php_exe, &["-i"], PHP_QUERY_TIMEOUT).with_context(|| format!("{:?} doesn't run", php_exe))?;
+    parse_php_info(&output).ok_or_else(|| {
+        anyhow::anyhow!("{:?} -i doesn't report its PHP version and thread safety", php_exe)
+    })
+}
+
+/// Total size in bytes of the files under `path`, following symlinks
+pub fn tree_size(path: &Path) -> std::io::Result<u64> {
+    let metadata = std::fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        total += tree_size(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+/// Copy the directory tree `src` to `dst` (created if missing). Symlinks are followed,
+/// so the copy doesn't depend on anything outside `src`.
+pub fn copy_tree(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let target = dst.join(entry.file_name());
+        if std::fs::metadata(entry.path())?.is_dir() {
+            copy_tree(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete `path` (file or directory tree) except for `protected` and the directories
+/// leading to it, e.g. the running executable of a portable install.
+/// Symlinks and junctions are removed without following them.
+/// Returns true if `path` is completely gone.
+pub fn remove_tree_except(path: &Path, protected: Option<&Path>) -> std::io::Result<bool> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(m) => m,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(true),
+        Err(e) => return Err(e),
+    };
+    let canonical = |p: &Path| std::fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+
+    let is_link = metadata.file_type().is_symlink() || is_junction(path);
+    if !metadata.is_dir() || is_link {
+        if protected.is_some_and(|p| canonical(p) == canonical(path)) && !is_link {
+            return Ok(false);
+        }
+        if metadata.is_dir() {
+            std::fs::remove_dir(path)?;
+        } else {
+            std::fs::remove_file(path)?;
+        }
+        return Ok(true);
+    }
+
+    match protected {
+        Some(p) if canonical(p).starts_with(canonical(path)) => {
+            let mut all_removed = true;
+            for entry in std::fs::read_dir(path)? {
+                all_removed &= remove_tree_except(&entry?.path(), protected)?;
+            }
+            if all_removed {
+                std::fs::remove_dir(path)?;
+            }
+            Ok(all_removed)
+        }
+        _ => {
+            std::fs::remove_dir_all(path)?;
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn is_junction(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+    std::fs::symlink_metadata(path)
+        .map(|m| m.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn is_junction(_path: &Path) -> bool {
+    false
+}
+
+/// Whether the process runs with administrator rights (an elevated token on Windows,
+/// effective uid 0 on Unix). Only reads the process token, so it's cheap to call.
+#[cfg(target_os = "windows")]
+pub fn is_elevated() -> bool {
+    use winapi::um::handleapi::CloseHandle;
+    use winapi::um::processthreadsapi::{GetCurrentProcess, OpenProcessToken};
+    use winapi::um::securitybaseapi::GetTokenInformation;
+    use winapi::um::winnt::{TokenElevation, HANDLE, TOKEN_ELEVATION, TOKEN_QUERY};
+
+    // SAFETY: the token handle is only used while open and closed exactly once; the
+    // buffer passed to GetTokenInformation is a TOKEN_ELEVATION of the size given.
+    unsafe {
+        let mut token: HANDLE = std::ptr::null_mut();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return false;
+        }
+        let mut elevation: TOKEN_ELEVATION = std::mem::zeroed();
+        let mut size = std::mem::size_of::<TOKEN_ELEVATION>() as u32;
+        let ok = GetTokenInformation(
+            token,
+            TokenElevation,
+            &mut elevation as *mut TOKEN_ELEVATION as *mut _,
+            size,
+            &mut size,
+        );
+        CloseHandle(token);
+        ok != 0 && elevation.TokenIsElevated != 0
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn is_elevated() -> bool {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// File names `php` resolves to within one PATH directory, in lookup order (the usual
+/// PATHEXT order on Windows)
+#[cfg(target_os = "windows")]
+const PHP_COMMAND_NAMES: &[&str] = &["php.com", "php.exe", "php.bat", "php.cmd"];
+
+#[cfg(not(target_os = "windows"))]
+const PHP_COMMAND_NAMES: &[&str] = &["php"];
+
+/// The first file named one of `names` in the directories of a PATH value, searched in order
+pub fn find_on_path(path_value: &std::ffi::OsStr, names: &[&str]) -> Option<PathBuf> {
+    std::env::split_paths(path_value)
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .find(|candidate| candidate.is_file())
+}
+
+/// What `php` runs in this process: the first php on the live PATH. Registry changes only
+/// reach processes started after them, so this can disagree with `is_path_set`.
+pub fn resolve_php_on_path() -> Option<PathBuf> {
+    find_on_path(&std::env::var_os("PATH")?, PHP_COMMAND_NAMES)
+}
+
+/// The php PATH resolves to and the version its `php -v` reports: what a command run
+/// from this process really gets, whoever installed it. None when there is no php on
+/// PATH or it doesn't run.
+pub fn active_php_version() -> Option<(PathBuf, String)> {
+    let php = resolve_php_on_path()?;
+    let version = php_version_of(&php)?;
+    Some((php, version))
+}
+
+/// The version a PHP executable reports in its `php -v` banner
+pub fn php_version_of(php_exe: &Path) -> Option<String> {
+    let output = run_php(php_exe, &["-v"], PHP_QUERY_TIMEOUT).ok()?;
+    parse_php_version_banner(&output)
+}
+
+/// Whether a Windows PATH entry refers to `dir`: case-insensitive, ignoring surrounding
+/// whitespace, quotes and trailing separators
+fn is_same_path_entry(entry: &str, dir: &str) -> bool {
+    let normalize = |p: &str| {
+        p.trim()
+            .trim_matches('"')
+            .trim_end_matches(['\\', '/'])
+            .replace('/', "\\")
+            .to_lowercase()
+    };
+    let entry = normalize(entry);
+    !entry.is_empty() && entry == normalize(dir)
+}
+
+/// Drop every entry for `dir` (however it was cased or spelled) from a `;`-separated PATH
+pub fn remove_path_entry(path_value: &str, dir: &str) -> String {
+    path_value
+        .split(';')
+        .filter(|entry| !entry.trim().is_empty() && !is_same_path_entry(entry, dir))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Put `dir` first in a `;`-separated PATH exactly once, dropping stale duplicates of it
+pub fn prepend_path_entry(path_value: &str, dir: &str) -> String {
+    let rest = remove_path_entry(path_value, dir);
+    if rest.is_empty() {
+        dir.to_string()
+    } else {
+        format!("{};{}", dir, rest)
+    }
+}
+
+/// Error for a PATH change that failed. Access denied gets an actionable message, since
+/// on locked-down machines policy blocks writes to the user environment.
+pub fn path_update_error(error: std::io::Error, dir: &Path, action: &str) -> anyhow::Error {
+    if error.kind() != std::io::ErrorKind::PermissionDenied {
+        return anyhow::Error::new(error).context(action.to_string());
+    }
+    let message = if cfg!(target_os = "windows") {
+        format!(
+            "PATH is managed by group policy ({}); add {} to your PATH manually or run phpvm as administrator",
+            error,
+            dir.display()
+        )
+    } else {
+        format!("The shell profile can't be written ({}); add {} to your PATH manually", error, dir.display())
+    };
+    PhpVmError::Io(message).into()
+}
+
 #[cfg(target_os = "windows")]
 pub fn add_to_path(current_dir: &PathBuf) -> anyhow::Result<()> {
-    use anyhow::Context;
     use winreg::enums::*;
     use winreg::RegKey;
     use std::ffi::CString;
     use std::ptr;
 
+    // Created if it's missing, as it can be on freshly provisioned accounts
     let current_user = RegKey::predef(HKEY_CURRENT_USER);
-    let environment = current_user
-        .open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)
-        .context("Failed to open Environment registry key")?;
+    let (environment, _) = current_user
+        .create_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)
+        .map_err(|e| path_update_error(e, current_dir, "Failed to open Environment registry key"))?;
 
     let path_value: String = environment
         .get_value("Path")
         .unwrap_or_else(|_| String::new());
 
     let current_str = current_dir.to_string_lossy().to_string();
+    let new_path = prepend_path_entry(&path_value, &current_str);
     
-    // Check if already in PATH (exact match or as part of a path)
-    let path_entries: Vec<&str> = path_value.split(';').collect();
-    let already_in_path = path_entries.iter().any(|entry| {
-        entry.trim().eq_ignore_ascii_case(&current_str)
-    });
-    
-    if !already_in_path {
-        // Remove any existing phpvm entries to avoid duplicates
-        let cleaned_path: Vec<&str> = path_entries
-            .iter()
-            .filter(|entry| {
-                let entry_trimmed = entry.trim();
-                // Keep entries that don't look like phpvm current directory
-                !entry_trimmed.contains("phpvm") || !entry_trimmed.contains("current")
-            })
-            .copied()
-            .collect();
-        
-        let new_path = if cleaned_path.is_empty() || cleaned_path.iter().all(|s| s.trim().is_empty()) {
-            current_str
-        } else {
-            // Add to the beginning of PATH for priority
-            format!("{};{}", current_str, cleaned_path.join(";"))
-        };
-        
+    if new_path != path_value {
         environment
             .set_value("Path", &new_path)
-            .context("Failed to set Path in registry")?;
+            .map_err(|e| path_update_error(e, current_dir, "Failed to set Path in registry"))?;
         
         // Broadcast WM_SETTINGCHANGE to notify Windows of environment change
         unsafe {
@@ -120,7 +758,8 @@ pub fn add_to_path(current_dir: &PathBuf) -> anyhow::Result<()> {
 
     if !content.contains(&current_str) {
         let new_content = format!("{}\n{}", content, current_str);
-        fs::write(&rc_file, new_content)?;
+        fs::write(&rc_file, new_content)
+            .map_err(|e| path_update_error(e, current_dir, &format!("Failed to write {:?}", rc_file)))?;
     }
 
     Ok(())
@@ -128,29 +767,27 @@ pub fn add_to_path(current_dir: &PathBuf) -> anyhow::Result<()> {
 
 #[cfg(target_os = "windows")]
 pub fn remove_from_path(current_dir: &PathBuf) -> anyhow::Result<()> {
-    use anyhow::Context;
     use winreg::enums::*;
     use winreg::RegKey;
 
     let current_user = RegKey::predef(HKEY_CURRENT_USER);
-    let environment = current_user
-        .open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)
-        .context("Failed to open Environment registry key")?;
+    let environment = match current_user.open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE) {
+        Ok(environment) => environment,
+        // No user environment, so nothing to remove
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(path_update_error(e, current_dir, "Failed to open Environment registry key")),
+    };
 
     let path_value: String = environment
         .get_value("Path")
         .unwrap_or_else(|_| String::new());
 
     let current_str = current_dir.to_string_lossy().to_string();
-    if path_value.contains(&current_str) {
-        let new_path = path_value
-            .split(';')
-            .filter(|p| !p.contains(&current_str))
-            .collect::<Vec<_>>()
-            .join(";");
+    let new_path = remove_path_entry(&path_value, &current_str);
+    if new_path != path_value {
         environment
             .set_value("Path", &new_path)
-            .context("Failed to update Path in registry")?;
+            .map_err(|e| path_update_error(e, current_dir, "Failed to update Path in registry"))?;
     }
 
     Ok(())
@@ -176,7 +813,8 @@ pub fn remove_from_path(current_dir: &PathBuf) -> anyhow::Result<()> {
 
     if content.contains(&current_str) {
         let new_content = content.replace(&format!("{}\n", current_str), "");
-        fs::write(&rc_file, new_content)?;
+        fs::write(&rc_file, new_content)
+            .map_err(|e| path_update_error(e, current_dir, &format!("Failed to write {:?}", rc_file)))?;
     }
 
     Ok(())
@@ -184,21 +822,22 @@ pub fn remove_from_path(current_dir: &PathBuf) -> anyhow::Result<()> {
 
 #[cfg(target_os = "windows")]
 pub fn is_path_set(current_dir: &PathBuf) -> anyhow::Result<bool> {
-    use anyhow::Context;
     use winreg::enums::*;
     use winreg::RegKey;
 
     let current_user = RegKey::predef(HKEY_CURRENT_USER);
-    let environment = current_user
-        .open_subkey_with_flags("Environment", KEY_READ)
-        .context("Failed to open Environment registry key")?;
+    let environment = match current_user.open_subkey_with_flags("Environment", KEY_READ) {
+        Ok(environment) => environment,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(anyhow::Error::new(e).context("Failed to open Environment registry key")),
+    };
 
     let path_value: String = environment
         .get_value("Path")
         .unwrap_or_else(|_| String::new());
 
     let current_str = current_dir.to_string_lossy().to_string();
-    Ok(path_value.contains(&current_str))
+    Ok(path_value.split(';').any(|entry| is_same_path_entry(entry, &current_str)))
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -259,6 +898,306 @@ mod tests {
         assert_eq!(env_var, "PATH");
     }
 
+    #[test]
+    fn test_php_shim_content_relative_path_with_spaces() {
+        let base_dir = PathBuf::from("/Users/Jane Doe/AppData/Local/phpvm");
+        let php_exe = base_dir.join("versions").join("php-8.3.0-ts").join("php.exe");
+        let content = php_shim_content(&php_exe, &base_dir.join("current"));
+
+        assert_eq!(
+            content,
+            "@echo off\r\n\"%~dp0..\\versions\\php-8.3.0-ts\\php.exe\" %*\r\n"
+        );
+        // The user profile path is resolved by cmd.exe, never written into the file
+        assert!(!content.contains("Jane Doe"));
+    }
+
+    #[test]
+    fn test_php_shim_content_absolute_path() {
+        let php_exe = PathBuf::from("/opt/php builds/100%/php.exe");
+        let content = php_shim_content(&php_exe, &PathBuf::from("/phpvm/current"));
+
+        assert!(content.starts_with("@echo off\r\n"));
+        assert!(content.contains("\"/opt/php builds/100%%/php.exe\" %*"));
+        assert!(!content.contains("chcp"));
+    }
+
+    #[test]
+    fn test_php_shim_content_non_ascii_path() {
+        let php_exe = PathBuf::from("/Users/José Müller/php/php.exe");
+        let content = php_shim_content(&php_exe, &PathBuf::from("/phpvm/current"));
+
+        assert!(content.contains("chcp 65001 >nul"));
+        assert!(content.contains("\"/Users/José Müller/php/php.exe\" %*"));
+        assert!(content.ends_with("exit /b %PHPVM_RC%\r\n"));
+    }
+
+    #[test]
+    fn test_prepend_path_entry_removes_stale_duplicates() {
+        let current = r"C:\Users\dev\phpvm\current";
+        let path_value = r"C:\Windows\system32;c:\users\dev\PHPVM\current\;C:\tools\phpvm-helper\current-bin;C:\Users\dev\phpvm\current;C:\Users\dev\phpvm\versions\php-8.2.0-ts";
+
+        let new_path = prepend_path_entry(path_value, current);
+        let entries: Vec<&str> = new_path.split(';').collect();
+        assert_eq!(entries.iter().filter(|e| is_same_path_entry(e, current)).count(), 1);
+        assert_eq!(entries[0], current);
+        // Unrelated entries, including ones that merely mention phpvm, are kept in order
+        assert_eq!(
+            &entries[1..],
+            &[
+                r"C:\Windows\system32",
+                r"C:\tools\phpvm-helper\current-bin",
+                r"C:\Users\dev\phpvm\versions\php-8.2.0-ts"
+            ]
+        );
+
+        // Already first and unique: unchanged
+        assert_eq!(prepend_path_entry(&new_path, current), new_path);
+        assert_eq!(prepend_path_entry("", current), current);
+    }
+
+    #[test]
+    fn test_remove_tree_except_keeps_protected_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let base = temp_dir.path().join("phpvm");
+        std::fs::create_dir_all(base.join("versions/php-8.3.0-ts")).unwrap();
+        std::fs::create_dir_all(base.join("app")).unwrap();
+        std::fs::write(base.join("versions/php-8.3.0-ts/php.exe"), "php").unwrap();
+        std::fs::write(base.join("app/phpvm-gui.exe"), "gui").unwrap();
+        std::fs::write(base.join("app/readme.txt"), "notes").unwrap();
+        std::fs::write(base.join("state.json"), "{}").unwrap();
+
+        let exe = base.join("app/phpvm-gui.exe");
+        assert!(!remove_tree_except(&base, Some(&exe)).unwrap());
+        assert!(exe.exists());
+        assert!(!base.join("app/readme.txt").exists());
+        assert!(!base.join("versions").exists());
+        assert!(!base.join("state.json").exists());
+
+        // Without a protected path inside it, the whole tree goes
+        assert!(remove_tree_except(&base, Some(temp_dir.path().join("elsewhere.exe").as_path())).unwrap());
+        assert!(!base.exists());
+        assert!(remove_tree_except(&base, None).unwrap());
+    }
+
+    #[test]
+    fn test_windows_apps_launcher_only_touches_its_own_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let apps = temp_dir.path().join("WindowsApps");
+        let php_exe = temp_dir.path().join("versions").join("php-8.3.0-nts").join("php.exe");
+        let path_var = std::env::join_paths([temp_dir.path().join("bin"), apps.clone()]).unwrap();
+
+        assert!(write_windows_apps_launcher(&apps, &php_exe, Some(&path_var)).is_err());
+        std::fs::create_dir(&apps).unwrap();
+        let elsewhere = std::env::join_paths([temp_dir.path().join("bin")]).unwrap();
+        assert!(write_windows_apps_launcher(&apps, &php_exe, Some(&elsewhere)).is_err());
+        assert!(write_windows_apps_launcher(&apps, &php_exe, None).is_err());
+
+        let launcher = write_windows_apps_launcher(&apps, &php_exe, Some(&path_var)).unwrap();
+        let content = std::fs::read_to_string(&launcher).unwrap();
+        assert!(content.starts_with(WINDOWS_APPS_LAUNCHER_MARKER));
+        assert!(content.contains("php-8.3.0-nts"));
+        assert!(remove_windows_apps_launcher(&apps).unwrap());
+        assert!(!launcher.exists());
+        assert!(!remove_windows_apps_launcher(&apps).unwrap());
+
+        // Someone else's php.bat is neither overwritten nor removed
+        std::fs::write(&launcher, "@echo off\r\nC:\\xampp\\php\\php.exe %*\r\n").unwrap();
+        assert!(write_windows_apps_launcher(&apps, &php_exe, Some(&path_var)).is_err());
+        assert!(!remove_windows_apps_launcher(&apps).unwrap());
+        assert!(launcher.exists());
+    }
+
+    #[test]
+    fn test_parse_loaded_ini_path() {
+        let output = "Configuration File (php.ini) Path: \r\n\
+                      Loaded Configuration File:         C:\\phpvm\\versions\\php-8.3.0-ts\\php.ini\r\n\
+                      Scan for additional .ini files in: (none)\r\n";
+        assert_eq!(
+            parse_loaded_ini_path(output),
+            Some(PathBuf::from("C:\\phpvm\\versions\\php-8.3.0-ts\\php.ini"))
+        );
+
+        let output = "Configuration File (php.ini) Path: /etc/php\nLoaded Configuration File:         (none)\n";
+        assert_eq!(parse_loaded_ini_path(output), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_elevated_matches_uid() {
+        let output = std::process::Command::new("id").arg("-u").output().unwrap();
+        let uid = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        assert_eq!(is_elevated(), uid == "0");
+    }
+
+    #[test]
+    fn test_parse_php_build_info() {
+        let output = "phpinfo()\nPHP Version => 8.3.0\n\nSystem => Windows NT HOST 10.0 build 19045 (Windows 10) AMD64\n\
+                      Build Date => Nov 21 2023 17:49:44\nCompiler => Visual C++ 2019\nArchitecture => x64\n";
+        assert_eq!(parse_php_build_info(output), (Some("vs16".to_string()), Some("x64".to_string())));
+
+        let output = "Compiler => MSVC15 (Visual C++ 2017)\nArchitecture => x86\n";
+        assert_eq!(parse_php_build_info(output), (Some("vc15".to_string()), Some("x86".to_string())));
+
+        // Linux builds report gcc/clang, which has no tag
+        assert_eq!(parse_php_build_info("Compiler => GCC 12.2.0\n"), (None, None));
+    }
+
+    #[test]
+    fn test_path_update_error_explains_access_denied() {
+        let dir = Path::new("/home/me/.phpvm/current");
+        let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "Access is denied");
+        let error = PhpVmError::from(path_update_error(denied, dir, "Failed to set Path in registry"));
+        assert_eq!(error.code(), "IO");
+        assert!(error.message().contains("/home/me/.phpvm/current"));
+        assert!(error.message().contains("manually"));
+
+        let other = std::io::Error::new(std::io::ErrorKind::Other, "disk on fire");
+        let error = path_update_error(other, dir, "Failed to set Path in registry");
+        assert_eq!(error.to_string(), "Failed to set Path in registry");
+    }
+
+    #[test]
+    fn test_format_bytes_and_space_check() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(32 * 1024 * 1024), "32.0 MB");
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        // Works for a directory that doesn't exist yet
+        let target = temp_dir.path().join("versions").join("php-8.3.0-ts");
+        assert!(available_space(&target).unwrap() > 0);
+        assert!(ensure_space(&target, 1).is_ok());
+        let error = ensure_space(&target, u64::MAX).unwrap_err();
+        assert!(error.to_string().starts_with("Insufficient disk space"), "{}", error);
+    }
+
+    #[test]
+    fn test_find_on_path_takes_the_first_match() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let empty = tempfile::tempdir().unwrap();
+        std::fs::write(first.path().join("php.bat"), "").unwrap();
+        std::fs::write(second.path().join("php.exe"), "").unwrap();
+        std::fs::create_dir(empty.path().join("php.exe")).unwrap();
+
+        let path_value = std::env::join_paths([empty.path(), second.path(), first.path()]).unwrap();
+        let names = ["php.exe", "php.bat"];
+        // Directory order wins over name order, and directories named like php are skipped
+        assert_eq!(find_on_path(&path_value, &names), Some(second.path().join("php.exe")));
+
+        let path_value = std::env::join_paths([first.path(), second.path()]).unwrap();
+        assert_eq!(find_on_path(&path_value, &names), Some(first.path().join("php.bat")));
+        assert_eq!(find_on_path(&std::env::join_paths([empty.path()]).unwrap(), &names), None);
+    }
+
+    #[test]
+    fn test_parse_php_thread_safety() {
+        assert_eq!(parse_php_thread_safety("PHP Version => 8.2.0\r\nThread Safety => disabled\r\n"), Some(false));
+        assert_eq!(parse_php_thread_safety("Thread Safety => enabled\n"), Some(true));
+        assert_eq!(parse_php_thread_safety("PHP Version => 8.2.0\n"), None);
+        assert_eq!(
+            parse_php_version_banner("PHP 8.2.12 (cli) (built: Oct 24 2023 21:15:15) (ZTS Visual C++ 2019 x64)\r\n").as_deref(),
+            Some("8.2.12")
+        );
+        assert_eq!(parse_php_version_banner("PHP 8.4.0-dev (cli)\n").as_deref(), Some("8.4.0"));
+        assert_eq!(parse_php_version_banner("Zend Engine v4.2.0\n"), None);
+    }
+
+    #[test]
+    fn test_install_unsupported_reason() {
+        assert_eq!(install_unsupported_reason("windows", "x86_64"), None);
+        assert_eq!(install_unsupported_reason("windows", "aarch64"), None);
+        assert!(install_unsupported_reason("windows", "x86").unwrap().contains("64-bit"));
+        for os in ["linux", "macos", "freebsd"] {
+            assert!(install_unsupported_reason(os, "x86_64").unwrap().contains(os));
+        }
+    }
+
+    #[test]
+    fn test_parse_php_info() {
+        let output = "phpinfo()\r\nPHP Version => 8.3.0RC6\r\n\r\nCompiler => Visual C++ 2019\r\nArchitecture => x86\r\n\
+                      Thread Safety => disabled\r\n";
+        assert_eq!(
+            parse_php_info(output),
+            Some(BuildInfo {
+                php_version: "8.3.0".to_string(),
+                compiler: Some("vs16".to_string()),
+                arch: "x86".to_string(),
+                thread_safe: false,
+            })
+        );
+
+        let linux = parse_php_info("PHP Version => 8.1.2\nCompiler => GCC 12.2.0\nThread Safety => enabled\n").unwrap();
+        assert_eq!((linux.compiler, linux.arch.as_str(), linux.thread_safe), (None, host_arch(), true));
+
+        assert_eq!(parse_php_info("Thread Safety => enabled\n"), None);
+        assert_eq!(parse_php_info("PHP Version => 8.1.2\n"), None);
+    }
+
+    #[test]
+    fn test_parse_module_list() {
+        let output = "[PHP Modules]\r\nCore\r\ncurl\r\nmbstring\r\nXdebug\r\n\r\n[Zend Modules]\r\nXdebug\r\n\r\n";
+        assert_eq!(parse_module_list(output), vec!["Core", "curl", "mbstring", "Xdebug"]);
+        assert!(parse_module_list("").is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_php_reports_failures() {
+        use std::os::unix::fs::PermissionsExt;
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let script = |name: &str, body: &str| {
+            let path = temp_dir.path().join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            path
+        };
+        let timeout = std::time::Duration::from_secs(5);
+
+        let ok = script("ok", "echo '[PHP Modules]'; echo Core");
+        assert_eq!(parse_module_list(&run_php(&ok, &["-m"], timeout).unwrap()), vec!["Core"]);
+
+        let broken = script("broken", "echo 'Unable to load dynamic library' >&2; exit 1");
+        let err = run_php(&broken, &["-m"], timeout).unwrap_err().to_string();
+        assert!(err.contains("Unable to load dynamic library"));
+
+        let hung = script("hung", "sleep 10");
+        let err = run_php(&hung, &["-m"], std::time::Duration::from_millis(200)).unwrap_err().to_string();
+        assert!(err.contains("did not finish"));
+
+        assert!(run_php(&temp_dir.path().join("missing"), &["-m"], timeout).is_err());
+    }
+
+    #[test]
+    fn test_remove_path_entry() {
+        let current = r"C:\Users\dev\phpvm\current";
+        let path_value = r"C:\Users\dev\phpvm\current;C:\Windows;C:/Users/dev/phpvm/current/";
+        assert_eq!(remove_path_entry(path_value, current), r"C:\Windows");
+    }
+
+    #[test]
+    fn test_activation_script() {
+        let php_dir = PathBuf::from("/Users/O'Brien/phpvm/versions/php-8.3.0-nts");
+
+        let cmd = activation_script(&php_dir, "8.3.0-nts", ShellKind::Cmd);
+        assert!(cmd.contains("set \"PATH=/Users/O'Brien/phpvm/versions/php-8.3.0-nts;%PATH%\"\r\n"));
+
+        let ps = activation_script(&php_dir, "8.3.0-nts", ShellKind::PowerShell);
+        assert!(ps.contains("$env:PATH = '/Users/O''Brien/phpvm/versions/php-8.3.0-nts' + [IO.Path]::PathSeparator + $env:PATH"));
+
+        let bash = activation_script(&php_dir, "8.3.0-nts", ShellKind::Bash);
+        assert!(bash.contains("export PATH='/Users/O'\\''Brien/phpvm/versions/php-8.3.0-nts':\"$PATH\""));
+    }
+
+    #[test]
+    fn test_shell_kind_parse() {
+        assert_eq!(ShellKind::parse("cmd").unwrap(), ShellKind::Cmd);
+        assert_eq!(ShellKind::parse("PowerShell").unwrap(), ShellKind::PowerShell);
+        assert_eq!(ShellKind::parse("bash").unwrap(), ShellKind::Bash);
+        assert!(ShellKind::parse("fish").is_err());
+    }
+
     #[test]
     fn test_get_current_path() {
         let current_path = get_current_path();
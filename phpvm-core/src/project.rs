@@ -0,0 +1,163 @@
+use crate::error::PhpVmError;
+use crate::version::{validate_version_spec, validate_version_str, PhpVersion};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Project file `apply_project_config` reads, e.g.
+///
+/// ```json
+/// { "version": "8.3.0", "variant": "nts", "extensions": ["curl", "mbstring", "xdebug"] }
+/// ```
+pub const PROJECT_CONFIG_FILE: &str = ".phpvmrc";
+
+/// Plain-text version file other version managers also read: one line such as "8.1",
+/// "8.1.27" or "8.1.27-nts"
+pub const PHP_VERSION_FILE: &str = ".php-version";
+
+/// The PHP setup a project asks for in its `.phpvmrc` (JSON). Keys phpvm doesn't know
+/// are ignored, so files written for newer versions still load.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectConfig {
+    /// Exact version, e.g. "8.3.0"
+    pub version: String,
+    /// "ts" or "nts"; the configured default variant (else TS) when missing
+    #[serde(default)]
+    pub variant: Option<String>,
+    /// Extensions to enable in php.ini: bundled ones by name ("curl", "opcache"),
+    /// plus downloadable ones phpvm knows ("xdebug")
+    #[serde(default)]
+    pub extensions: Vec<String>,
+}
+
+impl ProjectConfig {
+    /// Read `dir/.phpvmrc`. Ok(None) when the directory has none.
+    pub fn load(dir: &Path) -> anyhow::Result<Option<Self>> {
+        let path = dir.join(PROJECT_CONFIG_FILE);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+        };
+        let config = Self::parse(&content).with_context(|| format!("Invalid {:?}", path))?;
+        Ok(Some(config))
+    }
+
+    /// Parse and validate `.phpvmrc` content
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        let config: ProjectConfig =
+            serde_json::from_str(content).map_err(|e| PhpVmError::InvalidArgument(format!("Not valid .phpvmrc JSON: {}", e)))?;
+
+        validate_version_str(&config.version)?;
+        PhpVersion::from_string(&config.version)?;
+        if config.version.ends_with("-ts") || config.version.ends_with("-nts") {
+            return Err(PhpVmError::InvalidArgument(format!(
+                "Put the variant of {:?} in \"variant\" instead of the version",
+                config.version
+            ))
+            .into());
+        }
+        if let Some(variant) = config.variant.as_deref().filter(|v| *v != "ts" && *v != "nts") {
+            return Err(PhpVmError::InvalidArgument(format!("variant must be \"ts\" or \"nts\", not {:?}", variant)).into());
+        }
+        if let Some(name) = config
+            .extensions
+            .iter()
+            .find(|name| name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        {
+            return Err(PhpVmError::InvalidArgument(format!("Invalid extension name {:?}", name)).into());
+        }
+        Ok(config)
+    }
+
+    /// Whether the project asks for the thread-safe build; None leaves it to the caller
+    pub fn thread_safe(&self) -> Option<bool> {
+        self.variant.as_deref().map(|variant| variant == "ts")
+    }
+}
+
+/// The version a directory's project asks for, as a switch spec ("8.1", "8.3.0-nts").
+/// Looks in `dir` and then each parent for `.phpvmrc` (which wins) or `.php-version`;
+/// Ok(None) when none of them has either file.
+pub fn detect_project_version(dir: &Path) -> anyhow::Result<Option<String>> {
+    for dir in dir.ancestors() {
+        if let Some(config) = ProjectConfig::load(dir)? {
+            return Ok(Some(match config.variant {
+                Some(variant) => format!("{}-{}", config.version, variant),
+                None => config.version,
+            }));
+        }
+        let path = dir.join(PHP_VERSION_FILE);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+        };
+        let spec = parse_php_version_file(&content).with_context(|| format!("Invalid {:?}", path))?;
+        return Ok(Some(spec));
+    }
+    Ok(None)
+}
+
+/// The version in `.php-version` content: the first line that isn't blank or a `#` comment
+pub fn parse_php_version_file(content: &str) -> anyhow::Result<String> {
+    let spec = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .ok_or_else(|| PhpVmError::InvalidArgument(".php-version is empty".to_string()))?;
+    validate_version_spec(spec)?;
+    Ok(spec.to_string())
+}
+
+/// What `apply_project_config` did
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectApplyReport {
+    /// The install the project now uses, e.g. "8.3.0-nts"
+    pub version: String,
+    pub steps: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_project_config() {
+        let config = ProjectConfig::parse(
+            r#"{ "version": "8.3.0", "variant": "nts", "extensions": ["curl", "xdebug"], "from_the_future": true }"#,
+        )
+        .unwrap();
+        assert_eq!(config.version, "8.3.0");
+        assert_eq!(config.thread_safe(), Some(false));
+        assert_eq!(config.extensions, vec!["curl", "xdebug"]);
+
+        let minimal = ProjectConfig::parse(r#"{ "version": "7.4.33" }"#).unwrap();
+        assert_eq!(minimal.thread_safe(), None);
+        assert!(minimal.extensions.is_empty());
+
+        assert!(ProjectConfig::parse(r#"{ "variant": "ts" }"#).is_err());
+        assert!(ProjectConfig::parse(r#"{ "version": "8.3" }"#).is_err());
+        assert!(ProjectConfig::parse(r#"{ "version": "8.3.0-nts" }"#).is_err());
+        assert!(ProjectConfig::parse(r#"{ "version": "8.3.0", "variant": "zts" }"#).is_err());
+        assert!(ProjectConfig::parse(r#"{ "version": "8.3.0", "extensions": ["../evil"] }"#).is_err());
+    }
+
+    #[test]
+    fn test_detect_project_version_walks_up() {
+        let root = tempfile::tempdir().unwrap();
+        let nested = root.path().join("app").join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(detect_project_version(&nested).unwrap(), None);
+
+        std::fs::write(root.path().join(PHP_VERSION_FILE), "# pinned for CI\n8.1\n").unwrap();
+        assert_eq!(detect_project_version(&nested).unwrap().as_deref(), Some("8.1"));
+
+        std::fs::write(root.path().join("app").join(PROJECT_CONFIG_FILE), r#"{ "version": "8.3.0", "variant": "nts" }"#).unwrap();
+        assert_eq!(detect_project_version(&nested).unwrap().as_deref(), Some("8.3.0-nts"));
+
+        std::fs::write(nested.join(PHP_VERSION_FILE), "latest\n").unwrap();
+        assert!(detect_project_version(&nested).is_err());
+        assert!(parse_php_version_file("\n  \n").is_err());
+    }
+}
@@ -1,29 +1,311 @@
+use crate::config::{self, ProviderConfig, ProviderKind};
+use crate::error::PhpVmError;
 use crate::version::PhpVersion;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use regex::Regex;
 use anyhow::Context;
+use futures::StreamExt;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const RELEASES_URL: &str = "https://windows.php.net/downloads/releases/";
+const ARCHIVES_URL: &str = "https://windows.php.net/downloads/releases/archives/";
+
+/// Compiler tags windows.php.net publishes archives under, the only ones an install
+/// may ask for explicitly
+pub const KNOWN_COMPILER_TAGS: [&str; 6] = ["vs17", "vs16", "vc15", "VC15", "VC14", "VC11"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VersionInfo {
     pub version: String,
     pub release_date: Option<String>,
     pub eol_date: Option<String>,
+    /// End of active support (bug fixes); only security fixes land after this, until `eol_date`
+    #[serde(default)]
+    pub active_support_until: Option<String>,
     pub download_url: Option<String>,
     pub checksum: Option<String>,
 }
 
+/// What probing one download URL found
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UrlProbe {
+    pub url: String,
+    /// Status of the HEAD (or ranged GET) response; None when none came back
+    pub status_code: Option<u16>,
+    /// Answered with a 2xx
+    pub reachable: bool,
+    /// Archive size in bytes, if the server said
+    pub content_length: Option<u64>,
+    /// Why there was no response at all, e.g. a DNS or TLS failure
+    pub error: Option<String>,
+}
+
+/// Where a branch is in its support lifecycle on a given day. `days_left` counts down to
+/// the end of security support (the EOL date).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum EolStatus {
+    /// Bug and security fixes
+    Supported { days_left: i64 },
+    /// Active support has ended; only security fixes until EOL
+    SecurityOnly { days_left: i64 },
+    Ended { days_ago: i64 },
+}
+
+/// Versions of one major.minor branch, newest patch first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BranchInfo {
+    pub major: u8,
+    pub minor: u8,
+    pub eol_date: Option<String>,
+    pub is_eol: bool,
+    pub latest_patch: String,
+    pub all_patches: Vec<String>,
+    pub installed: Vec<String>, // installed variants in this branch, e.g. "8.2.10-ts"
+    pub update_available: bool, // an installed version is behind latest_patch
+}
+
+/// A newer patch release in the same branch as an installed version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchUpdate {
+    pub installed: String, // base version, e.g. "8.2.10"
+    pub latest: String,    // e.g. "8.2.30"
+    pub variant: String,   // "ts" or "nts"
+}
+
+const VERSIONLOG_URL: &str = "https://versionlog.com/php/";
+/// php.net keeps one ChangeLog page per major version, e.g. ChangeLog-8.php
+const CHANGELOG_BASE_URL: &str = "https://www.php.net/";
+
+/// How long a HEAD availability result is trusted before the URL is probed again
+const AVAILABILITY_TTL: Duration = Duration::from_secs(60 * 60);
+const AVAILABILITY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Shared across Provider instances since commands create a fresh Provider per call
+fn availability_cache() -> &'static Mutex<HashMap<String, (bool, Instant)>> {
+    static CACHE: OnceLock<Mutex<HashMap<String, (bool, Instant)>>> = OnceLock::new();
+    CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Versions parsed from a provider page, kept with the validators the server sent so
+/// the next fetch can be a conditional request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedVersionList {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix seconds of the last fetch or successful revalidation
+    fetched_at: u64,
+    versions: Vec<VersionInfo>,
+}
+
+impl CachedVersionList {
+    fn path(url: &str) -> std::path::PathBuf {
+        config::get_base_directory()
+            .join("cache")
+            .join("versions")
+            .join(format!("{}.json", crate::download::Downloader::cache_key(url)))
+    }
+
+    fn load(url: &str) -> Option<Self> {
+        let content = std::fs::read_to_string(Self::path(url)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    fn save(&self, url: &str) {
+        let path = Self::path(url);
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, serde_json::to_string(self).unwrap_or_default()));
+        if let Err(e) = result {
+            crate::logging::log_message("WARN", &format!("Failed to cache version list for {}: {}", url, e));
+        }
+    }
+}
+
+/// Response to a conditional GET of a provider page
+enum PageFetch {
+    /// 304: the versions parsed last time are still current
+    NotModified(Vec<VersionInfo>),
+    /// A full response, with the validators to store alongside what's parsed from it
+    Fetched {
+        response: reqwest::Response,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Below this share of versions with dates parsed from the page, the versionlog.com
+/// selectors are assumed to have broken
+pub const SCRAPE_HEALTH_MIN_RATIO: f64 = 0.5;
+
+/// How well the last versionlog.com scrape went: how many of the versions found had
+/// a release or EOL date in their table row. A site redesign shows up here as a drop
+/// in `dated` instead of silently falling back to built-in dates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrapeHealth {
+    /// Unix seconds of the scrape
+    pub checked_at: u64,
+    pub total: usize,
+    pub dated: usize,
+}
+
+impl ScrapeHealth {
+    fn path() -> std::path::PathBuf {
+        config::get_base_directory().join("cache").join("versions").join("versionlog-health.json")
+    }
+
+    /// Result of the last scrape, if there was one
+    pub fn load() -> Option<Self> {
+        let content = std::fs::read_to_string(Self::path()).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Whether version metadata (release and EOL dates) may be stale, i.e. the page
+    /// yielded no versions or too few of them had dates
+    pub fn is_degraded(&self) -> bool {
+        self.total == 0 || (self.dated as f64) < (self.total as f64) * SCRAPE_HEALTH_MIN_RATIO
+    }
+
+    /// Log a warning if the scrape looks broken and keep the result for diagnostics
+    fn record(&self, url: &str) {
+        if self.is_degraded() {
+            crate::logging::log_message(
+                "WARN",
+                &format!(
+                    "Only {} of {} versions from {} had dates; the page layout may have changed and version metadata may be stale",
+                    self.dated, self.total, url
+                ),
+            );
+        }
+        let path = Self::path();
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, serde_json::to_string(self).unwrap_or_default()));
+        if let Err(e) = result {
+            crate::logging::log_message("WARN", &format!("Failed to save scrape health: {}", e));
+        }
+    }
+}
+
+/// An EOL date that moved between two fetches of a version list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EolChange {
+    pub version: String,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// What changed in a provider's version list when its disk cache was last refreshed
+/// with a different list: patches that appeared and EOL dates that moved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionDelta {
+    /// The provider page the list came from
+    pub source: String,
+    /// Unix seconds of the fetch that found the change
+    pub checked_at: u64,
+    /// Unix seconds of the fetch the new list was compared against
+    pub previous_fetched_at: u64,
+    /// Newest first
+    pub added: Vec<String>,
+    pub eol_changes: Vec<EolChange>,
+}
+
+impl VersionDelta {
+    fn path() -> std::path::PathBuf {
+        config::get_base_directory().join("cache").join("versions").join("last-delta.json")
+    }
+
+    /// The last change seen, if any refresh has found one
+    pub fn load() -> Option<Self> {
+        let content = std::fs::read_to_string(Self::path()).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    /// Compare two lists of the same page. Versions that disappeared aren't reported;
+    /// providers drop old ones as a matter of course.
+    pub fn between(source: &str, previous_fetched_at: u64, previous: &[VersionInfo], current: &[VersionInfo]) -> Self {
+        let before: HashMap<&str, &VersionInfo> = previous.iter().map(|v| (v.version.as_str(), v)).collect();
+        let mut added = Vec::new();
+        let mut eol_changes = Vec::new();
+        for version in current {
+            match before.get(version.version.as_str()) {
+                None => added.push(version.version.clone()),
+                Some(old) if old.eol_date != version.eol_date => eol_changes.push(EolChange {
+                    version: version.version.clone(),
+                    from: old.eol_date.clone(),
+                    to: version.eol_date.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        let newest_first = |a: &String, b: &String| {
+            PhpVersion::from_string(b).unwrap_or_default().cmp(&PhpVersion::from_string(a).unwrap_or_default())
+        };
+        added.sort_by(newest_first);
+        eol_changes.sort_by(|a, b| newest_first(&a.version, &b.version));
+        VersionDelta { source: source.to_string(), checked_at: unix_now(), previous_fetched_at, added, eol_changes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.eol_changes.is_empty()
+    }
+
+    /// Log the change and keep it for `load`. Empty deltas are dropped so the last
+    /// real change stays visible.
+    fn record(&self) {
+        if self.is_empty() {
+            return;
+        }
+        crate::logging::log_message(
+            "INFO",
+            &format!(
+                "Version list from {} changed: {} new ({}), {} EOL date(s) moved",
+                self.source,
+                self.added.len(),
+                self.added.join(", "),
+                self.eol_changes.len()
+            ),
+        );
+        let path = Self::path();
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, serde_json::to_string(self).unwrap_or_default()));
+        if let Err(e) = result {
+            crate::logging::log_message("WARN", &format!("Failed to save version list changes: {}", e));
+        }
+    }
+}
+
 pub struct Provider {
     client: reqwest::Client,
+    providers: Vec<ProviderConfig>,
 }
 
 impl Provider {
     pub fn new() -> anyhow::Result<Self> {
+        Self::with_providers(config::default_providers())
+    }
+
+    /// Create a provider that tries the given sources in order before the hardcoded fallback list
+    pub fn with_providers(providers: Vec<ProviderConfig>) -> anyhow::Result<Self> {
         Ok(Provider {
             client: reqwest::Client::builder()
                 .user_agent("phpvm/0.1.0")
                 .timeout(std::time::Duration::from_secs(30))
                 .build()?,
+            providers,
         })
     }
 
@@ -52,30 +334,127 @@ impl Provider {
         }
     }
 
+    /// A compiler tag given by the user, checked against `KNOWN_COMPILER_TAGS` since it
+    /// goes straight into the download URL
+    pub fn validate_compiler_tag(tag: &str) -> Result<&'static str, PhpVmError> {
+        KNOWN_COMPILER_TAGS.iter().copied().find(|known| *known == tag).ok_or_else(|| {
+            PhpVmError::InvalidArgument(format!(
+                "Unknown compiler tag {:?} (expected one of {})",
+                tag,
+                KNOWN_COMPILER_TAGS.join(", ")
+            ))
+        })
+    }
+
     /// Check if version should use archives URL (versions below 7.4)
     pub(crate) fn is_archived_version(major: u8, minor: u8) -> bool {
         major < 7 || (major == 7 && minor < 4)
     }
 
+    /// Base directory a version's archive is published under
+    pub(crate) fn get_base_url(major: u8, minor: u8) -> &'static str {
+        if Self::is_archived_version(major, minor) {
+            ARCHIVES_URL
+        } else {
+            RELEASES_URL
+        }
+    }
+
+    /// Where a superseded patch of a current branch lives: windows.php.net moves every
+    /// release except the newest of each branch into `archives/` under the same file name.
+    /// None for branches that are archived entirely (their primary URL is already there).
+    pub fn archived_download_url(version_str: &str, major: u8, minor: u8, thread_safe: bool) -> Option<String> {
+        if Self::is_archived_version(major, minor) {
+            return None;
+        }
+        Some(Self::build_download_url(
+            ARCHIVES_URL,
+            version_str,
+            Self::get_vs_version(major, minor),
+            thread_safe,
+        ))
+    }
+
+    /// Build an archive URL for a specific base URL and compiler tag
+    /// TS:  php-{version}-Win32-{vs}-x64.zip
+    /// NTS: php-{version}-nts-Win32-{vs}-x64.zip (nts comes AFTER version, BEFORE Win32)
+    fn build_download_url(base_url: &str, version_str: &str, vs_version: &str, thread_safe: bool) -> String {
+        let nts_infix = if thread_safe { "" } else { "-nts" };
+        format!("{}php-{}{}-Win32-{}-x64.zip", base_url, version_str, nts_infix, vs_version)
+    }
+
     /// Generate download URL for a PHP version
     /// Older versions (< 7.4) are in archives directory and use VC format (capital)
     /// Newer versions (>= 7.4) are in releases directory and use vs format (lowercase)
-    pub fn generate_download_url(version_str: &str, major: u8, minor: u8) -> String {
-        let vs_version = Self::get_vs_version(major, minor);
-        
-        if Self::is_archived_version(major, minor) {
-            // Older versions: use archives directory and VC format (capital)
-            format!(
-                "https://windows.php.net/downloads/releases/archives/php-{}-Win32-{}-x64.zip",
-                version_str, vs_version
-            )
-        } else {
-            // Newer versions: use releases directory and vs format (lowercase)
-            format!(
-                "https://windows.php.net/downloads/releases/php-{}-Win32-{}-x64.zip",
-                version_str, vs_version
-            )
+    /// This is the single source of truth for archive URLs.
+    pub fn generate_download_url(version_str: &str, major: u8, minor: u8, thread_safe: bool) -> String {
+        Self::build_download_url(
+            Self::get_base_url(major, minor),
+            version_str,
+            Self::get_vs_version(major, minor),
+            thread_safe,
+        )
+    }
+
+    /// `generate_download_url` with `compiler_tag` in place of the one the branch is
+    /// normally built with
+    pub fn download_url_for_compiler(version_str: &str, major: u8, minor: u8, thread_safe: bool, compiler_tag: &str) -> String {
+        Self::build_download_url(Self::get_base_url(major, minor), version_str, compiler_tag, thread_safe)
+    }
+
+    /// `url` with its scheme and host swapped for `mirror`, which may carry a path prefix:
+    /// "https://windows.php.net/downloads/releases/php-8.3.0-Win32-vs16-x64.zip" on
+    /// "https://cdn.example.com/php" is "https://cdn.example.com/php/downloads/releases/...".
+    /// None if `url` isn't an absolute http(s) URL.
+    pub fn mirrored_url(url: &str, mirror: &str) -> Option<String> {
+        let rest = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"))?;
+        let path = rest.find('/').map(|i| &rest[i..]).unwrap_or("/");
+        Some(format!("{}{}", mirror.trim_end_matches('/'), path))
+    }
+
+    /// The URL from `generate_download_url` followed by URLs some releases were
+    /// published under with a neighbouring compiler tag
+    pub fn candidate_download_urls(version_str: &str, major: u8, minor: u8, thread_safe: bool) -> Vec<String> {
+        let mut urls = vec![Self::generate_download_url(version_str, major, minor, thread_safe)];
+        let fallback = match Self::get_vs_version(major, minor) {
+            // For vs17, try vs16 as fallback
+            "vs17" => Some(("https://windows.php.net/downloads/releases/", "vs16")),
+            // For vs16, try vc15 as fallback
+            "vs16" => Some(("https://windows.php.net/downloads/releases/", "vc15")),
+            // For VC15 (PHP 7.2-7.3), try VC14 as fallback
+            "VC15" => Some(("https://windows.php.net/downloads/releases/archives/", "VC14")),
+            _ => None,
+        };
+        if let Some((base_url, vs_version)) = fallback {
+            urls.push(Self::build_download_url(base_url, version_str, vs_version, thread_safe));
+        }
+        urls
+    }
+
+    /// Every URL an install of this variant tries, in order: the generated one, the
+    /// `archives/` copy of a superseded patch, then the neighbouring compiler tag
+    pub fn install_download_urls(version_str: &str, major: u8, minor: u8, thread_safe: bool) -> Vec<String> {
+        let mut urls = Self::candidate_download_urls(version_str, major, minor, thread_safe);
+        if let Some(archived) = Self::archived_download_url(version_str, major, minor, thread_safe) {
+            urls.insert(1, archived);
+        }
+        urls
+    }
+
+    /// The URLs an install with an explicit compiler tag tries: the branch's directory,
+    /// then the `archives/` copy. No other tag is tried, the caller picked this one.
+    pub fn install_download_urls_for_compiler(
+        version_str: &str,
+        major: u8,
+        minor: u8,
+        thread_safe: bool,
+        compiler_tag: &str,
+    ) -> Vec<String> {
+        let mut urls = vec![Self::download_url_for_compiler(version_str, major, minor, thread_safe, compiler_tag)];
+        if !Self::is_archived_version(major, minor) {
+            urls.push(Self::build_download_url(ARCHIVES_URL, version_str, compiler_tag, thread_safe));
         }
+        urls
     }
 
     // EOL dates for major.minor versions (security support end dates)
@@ -84,7 +463,7 @@ impl Provider {
     // Source: https://versionlog.com/php/
     // Last updated: Dec 18, 2025
     // 
-    // When updating, ensure EOL dates match the fallback list in fetch_available_versions()
+    // When updating, ensure EOL dates match the fallback list in fallback_versions()
     pub fn get_eol_date(major: u8, minor: u8) -> Option<String> {
         match (major, minor) {
             (8, 5) => Some("2029-12-31".to_string()), // PHP 8.5 EOL: Dec 31, 2029
@@ -103,21 +482,69 @@ impl Provider {
         }
     }
 
+    // End of active support (bug fixes) per branch; security fixes continue until get_eol_date.
+    // Source: https://www.php.net/supported-versions.php
+    pub fn get_active_support_date(major: u8, minor: u8) -> Option<String> {
+        match (major, minor) {
+            (8, 5) => Some("2027-12-31".to_string()),
+            (8, 4) => Some("2026-12-31".to_string()),
+            (8, 3) => Some("2025-12-31".to_string()),
+            (8, 2) => Some("2024-12-31".to_string()),
+            (8, 1) => Some("2023-11-25".to_string()),
+            (8, 0) => Some("2022-11-26".to_string()),
+            (7, 4) => Some("2021-11-28".to_string()),
+            (7, 3) => Some("2020-12-06".to_string()),
+            (7, 2) => Some("2019-11-30".to_string()),
+            (7, 1) => Some("2018-12-01".to_string()),
+            (7, 0) => Some("2018-01-04".to_string()),
+            (5, 6) => Some("2017-01-19".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Support status of a branch on `today` ("YYYY-MM-DD"), or None if its EOL date is unknown.
+    /// A branch whose active support date is unknown counts as supported until EOL.
+    pub fn eol_status(major: u8, minor: u8, today: &str) -> Option<EolStatus> {
+        let eol_date = Self::get_eol_date(major, minor)?;
+        let days_left = crate::date::days_between(today, &eol_date)?;
+        if days_left < 0 {
+            return Some(EolStatus::Ended { days_ago: -days_left });
+        }
+        let active_ended = Self::get_active_support_date(major, minor)
+            .is_some_and(|active_until| crate::date::is_before(&active_until, today));
+        if active_ended {
+            Some(EolStatus::SecurityOnly { days_left })
+        } else {
+            Some(EolStatus::Supported { days_left })
+        }
+    }
+
     /// Fetch version information from versionlog.com/php/
     /// Parses the HTML table to extract latest patch versions, release dates, and EOL dates
-    async fn fetch_versions_from_versionlog(&self) -> anyhow::Result<Vec<VersionInfo>> {
-        let url = "https://versionlog.com/php/";
+    async fn fetch_versions_from_versionlog(&self, url: &str) -> anyhow::Result<Vec<VersionInfo>> {
         tracing::info!("Fetching PHP versions from: {}", url);
         
-        let response = self
-            .client
-            .get(url)
-            .send()
+        let (response, etag, last_modified) = match self
+            .fetch_page(url)
             .await
-            .with_context(|| format!("Failed to fetch versionlog.com page from {}", url))?;
-        
-        let html: String = response.text().await.with_context(|| "Failed to read response body")?;
+            .with_context(|| format!("Failed to fetch versionlog.com page from {}", url))?
+        {
+            PageFetch::NotModified(versions) => return Ok(versions),
+            PageFetch::Fetched { response, etag, last_modified } => (response, etag, last_modified),
+        };
         
+        let html = Self::read_body_lenient(response).await?;
+        let (versions, health) = Self::parse_versionlog_html(&html);
+        health.record(url);
+
+        tracing::info!("Found {} PHP versions from versionlog.com", versions.len());
+        Self::remember_version_list(url, etag, last_modified, &versions);
+        Ok(versions)
+    }
+
+    /// Parse the versionlog.com PHP table: latest patch per branch with its dates, plus
+    /// how many branch rows the date selectors still matched
+    fn parse_versionlog_html(html: &str) -> (Vec<VersionInfo>, ScrapeHealth) {
         // Strategy: Find all version numbers in format X.Y.Z, group by major.minor, take latest patch
         let version_regex = Regex::new(r#"(\d+)\.(\d+)\.(\d+)"#).unwrap();
         
@@ -125,12 +552,12 @@ impl Provider {
         let mut latest_patches: HashMap<(u8, u8), (u8, String)> = HashMap::new();
         
         // Find all version numbers in the HTML
-        for cap in version_regex.captures_iter(&html) {
+        for cap in version_regex.captures_iter(html) {
             let major: u8 = cap[1].parse().unwrap_or(0);
             let minor: u8 = cap[2].parse().unwrap_or(0);
             let patch: u8 = cap[3].parse().unwrap_or(0);
             
-            if major > 0 && minor >= 0 && patch > 0 {
+            if major > 0 && patch > 0 {
                 let version_str = format!("{}.{}.{}", major, minor, patch);
                 let key = (major, minor);
                 
@@ -150,6 +577,7 @@ impl Provider {
         // Now try to extract EOL dates from table rows
         // Look for table rows that contain version links and EOL dates
         let mut versions_map: HashMap<String, VersionInfo> = HashMap::new();
+        let mut dated = 0;
         
         for ((major, minor), (_, version_str)) in latest_patches.iter() {
             // Try to find the table row for this major.minor version
@@ -161,83 +589,38 @@ impl Provider {
             };
             
             let mut eol_date: Option<String> = None;
+            let mut active_support_until: Option<String> = None;
             let mut release_date: Option<String> = None;
             
-            if let Some(row_cap) = row_regex.captures(&html) {
+            if let Some(row_cap) = row_regex.captures(html) {
                 let row_content = row_cap.get(0).map(|m| m.as_str()).unwrap_or("");
                 
-                // Try to extract EOL date - look for "End of security fixes" followed by date
-                // Dates can be in format "December 31, 2029" or "Dec 31, 2029"
-                let eol_patterns = vec![
-                    r#"End of security fixes[^<]*([A-Za-z]+\s+\d{1,2},\s+\d{4})"#,
-                    r#"End of security fixes[^<]*(\d{4}-\d{2}-\d{2})"#,
-                ];
-                
-                for pattern in &eol_patterns {
-                    if let Ok(eol_regex) = Regex::new(*pattern) {
-                        if let Some(eol_cap) = eol_regex.captures(row_content) {
-                            if let Some(date_match) = eol_cap.get(1) {
-                                let date_str = date_match.as_str().trim();
-                                if !date_str.is_empty() {
-                                    eol_date = Self::parse_date_to_iso(date_str).or_else(|| {
-                                        // Try parsing as ISO date directly
-                                        if date_str.len() == 10 && date_str.matches('-').count() == 2 {
-                                            Some(date_str.to_string())
-                                        } else {
-                                            None
-                                        }
-                                    });
-                                    if eol_date.is_some() {
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                
-                // Try to extract release date from "Latest patch release"
-                let release_patterns = vec![
-                    r#"Latest patch release[^<]*([A-Za-z]+\s+\d{1,2},\s+\d{4})"#,
-                    r#"Latest patch release[^<]*(\d{4}-\d{2}-\d{2})"#,
-                ];
-                
-                for pattern in &release_patterns {
-                    if let Ok(release_regex) = Regex::new(*pattern) {
-                        if let Some(release_cap) = release_regex.captures(row_content) {
-                            if let Some(date_match) = release_cap.get(1) {
-                                let date_str = date_match.as_str().trim();
-                                if !date_str.is_empty() {
-                                    release_date = Self::parse_date_to_iso(date_str).or_else(|| {
-                                        if date_str.len() == 10 && date_str.matches('-').count() == 2 {
-                                            Some(date_str.to_string())
-                                        } else {
-                                            None
-                                        }
-                                    });
-                                    if release_date.is_some() {
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+                // Dates can be in format "December 31, 2029", "Dec 31, 2029" or "2029-12-31"
+                eol_date = Self::extract_row_date(row_content, "End of security fixes");
+                active_support_until = Self::extract_row_date(row_content, "End of active support");
+                release_date = Self::extract_row_date(row_content, "Latest patch release");
+            }
+            if eol_date.is_some() || release_date.is_some() {
+                dated += 1;
             }
             
             // Use EOL from get_eol_date if we couldn't parse it from HTML
             if eol_date.is_none() {
                 eol_date = Self::get_eol_date(*major, *minor);
             }
+            if active_support_until.is_none() {
+                active_support_until = Self::get_active_support_date(*major, *minor);
+            }
             
             // Generate download URL for this version
             // This ensures versions from versionlog.com are marked as "online" and can be installed
-            let download_url = Some(Self::generate_download_url(&version_str, *major, *minor));
+            let download_url = Some(Self::generate_download_url(version_str, *major, *minor, true));
             
             versions_map.insert(version_str.clone(), VersionInfo {
                 version: version_str.clone(),
                 release_date,
                 eol_date,
+                active_support_until,
                 download_url,
                 checksum: None,
             });
@@ -251,151 +634,494 @@ impl Provider {
             let vb = PhpVersion::from_string(&b.version).unwrap_or_default();
             vb.cmp(&va)
         });
-        
-        tracing::info!("Found {} PHP versions from versionlog.com", versions.len());
-        Ok(versions)
+
+        let health = ScrapeHealth { checked_at: unix_now(), total: versions.len(), dated };
+        (versions, health)
+    }
+
+    /// GET a provider page, revalidating the cached version list for it with
+    /// `If-None-Match`/`If-Modified-Since` when the server gave validators last time
+    async fn fetch_page(&self, url: &str) -> anyhow::Result<PageFetch> {
+        let cached = CachedVersionList::load(url);
+        let mut request = self.client.get(url);
+        if let Some(cached) = &cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        let response = request.send().await?;
+
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            if let Some(mut cached) = cached {
+                tracing::info!("Version list from {} not modified, using cached copy", url);
+                cached.fetched_at = unix_now();
+                cached.save(url);
+                return Ok(PageFetch::NotModified(cached.versions));
+            }
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(reqwest::header::ETAG);
+        let last_modified = header(reqwest::header::LAST_MODIFIED);
+        Ok(PageFetch::Fetched { response, etag, last_modified })
+    }
+
+    /// Store what was parsed from a page for conditional requests later, recording what
+    /// changed since the stored list. Pages served without validators aren't stored,
+    /// since they can only ever be fetched in full.
+    fn remember_version_list(url: &str, etag: Option<String>, last_modified: Option<String>, versions: &[VersionInfo]) {
+        if (etag.is_none() && last_modified.is_none()) || versions.is_empty() {
+            return;
+        }
+        if let Some(previous) = CachedVersionList::load(url) {
+            VersionDelta::between(url, previous.fetched_at, &previous.versions, versions).record();
+        }
+        CachedVersionList { etag, last_modified, fetched_at: unix_now(), versions: versions.to_vec() }.save(url);
     }
     
-    /// Parse date string like "December 31, 2029" to ISO format "2029-12-31"
-    fn parse_date_to_iso(date_str: &str) -> Option<String> {
-        // Simple date parsing for common formats
-        // Format: "December 31, 2029" or "Dec 31, 2029"
-        let month_map: HashMap<&str, &str> = [
-            ("january", "01"), ("february", "02"), ("march", "03"),
-            ("april", "04"), ("may", "05"), ("june", "06"),
-            ("july", "07"), ("august", "08"), ("september", "09"),
-            ("october", "10"), ("november", "11"), ("december", "12"),
-            ("jan", "01"), ("feb", "02"), ("mar", "03"),
-            ("apr", "04"), ("may", "05"), ("jun", "06"),
-            ("jul", "07"), ("aug", "08"), ("sep", "09"),
-            ("oct", "10"), ("nov", "11"), ("dec", "12"),
-        ].iter().cloned().collect();
-        
-        let date_lower = date_str.to_lowercase().trim().to_string();
-        let parts: Vec<&str> = date_lower.split_whitespace().collect();
-        
-        if parts.len() >= 3 {
-            let month_name = parts[0];
-            let day = parts[1].trim_end_matches(',');
-            let year = parts[2];
-            
-            if let Some(month_num) = month_map.get(month_name) {
-                if let (Ok(day_num), Ok(year_num)) = (day.parse::<u8>(), year.parse::<u16>()) {
-                    if day_num >= 1 && day_num <= 31 && year_num >= 2000 && year_num <= 2100 {
-                        return Some(format!("{}-{}-{:02}", year_num, month_num, day_num));
-                    }
-                }
+    /// Date following `label` in a versionlog.com table row, as "YYYY-MM-DD"
+    fn extract_row_date(row_content: &str, label: &str) -> Option<String> {
+        let patterns = [
+            // Lazy, so the whole month name is captured rather than its last letter
+            format!(r#"{}[^<]*?([A-Za-z]+\.?\s+\d{{1,2}},?\s+\d{{4}})"#, regex::escape(label)),
+            format!(r#"{}[^<]*?(\d{{1,2}}\s+[A-Za-z]+\.?,?\s+\d{{4}})"#, regex::escape(label)),
+            format!(r#"{}[^<]*?(\d{{4}}-\d{{2}}-\d{{2}})"#, regex::escape(label)),
+        ];
+        for pattern in &patterns {
+            let Ok(regex) = Regex::new(pattern) else { continue };
+            let Some(date_match) = regex.captures(row_content).and_then(|cap| cap.get(1)) else { continue };
+            if let Some(date) = Self::parse_date_to_iso(date_match.as_str()) {
+                return Some(date);
             }
         }
-        
         None
     }
 
-    async fn fetch_versions_from_php_net(&self) -> anyhow::Result<Vec<VersionInfo>> {
-        // Fetch the Windows PHP downloads page
-        let url = "https://windows.php.net/downloads/releases/";
-        tracing::info!("Fetching PHP versions from: {}", url);
+    /// Read a response body without failing on bad encoding, so a slightly broken or
+    /// mis-labelled mirror page still yields version numbers
+    async fn read_body_lenient(response: reqwest::Response) -> anyhow::Result<String> {
+        let url = response.url().to_string();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let bytes = response.bytes().await.with_context(|| "Failed to read response body")?;
+
+        let (body, lossy) = Self::decode_body(&bytes, content_type.as_deref());
+        if lossy {
+            crate::logging::log_message(
+                "WARN",
+                &format!("Response from {} is not valid {}; decoded lossily", url, content_type.as_deref().unwrap_or("UTF-8")),
+            );
+        }
+        Ok(body)
+    }
+
+    /// Decode a body using the `Content-Type` charset when it's a single-byte Latin
+    /// charset, UTF-8 otherwise. Returns the text and whether invalid bytes were replaced.
+    fn decode_body(bytes: &[u8], content_type: Option<&str>) -> (String, bool) {
+        let charset = content_type
+            .and_then(|ct| {
+                ct.split(';')
+                    .filter_map(|param| param.trim().split_once('='))
+                    .find(|(key, _)| key.trim().eq_ignore_ascii_case("charset"))
+                    .map(|(_, value)| value.trim().trim_matches('"').to_lowercase())
+            })
+            .unwrap_or_default();
+
+        // Every byte is a valid character in these; windows-1252 only differs from
+        // Latin-1 in 0x80-0x9F, which never matters for version numbers or dates
+        if matches!(charset.as_str(), "iso-8859-1" | "latin1" | "latin-1" | "windows-1252" | "us-ascii") {
+            return (bytes.iter().map(|&b| b as char).collect(), false);
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_string(), false),
+            Err(_) => (String::from_utf8_lossy(bytes).into_owned(), true),
+        }
+    }
+
+    /// Parse a date in versionlog.com text to ISO format "2029-12-31". Reads "December 31,
+    /// 2029", "Dec. 31, 2029", "31 December 2029" and an ISO date anywhere in the text,
+    /// with any spacing. None unless it's a real calendar date, rather than a guess.
+    fn parse_date_to_iso(date_str: &str) -> Option<String> {
+        const MONTHS: [&str; 12] = [
+            "january", "february", "march", "april", "may", "june",
+            "july", "august", "september", "october", "november", "december",
+        ];
+        let to_iso = |year: &str, month: u32, day: &str| -> Option<String> {
+            let year: i64 = year.parse().ok().filter(|year| (2000..=2100).contains(year))?;
+            let day = ["st", "nd", "rd", "th"].iter().find_map(|suffix| day.strip_suffix(suffix)).unwrap_or(day);
+            let day: u32 = day.parse().ok()?;
+            // Feb 30 and the like come back as a different day
+            let real = crate::date::civil_from_days(crate::date::days_from_civil(year, month, day)) == (year, month, day);
+            real.then(|| format!("{}-{:02}-{:02}", year, month, day))
+        };
+
+        if let Some(cap) = Regex::new(r"(?:^|\D)(\d{4})-(\d{2})-(\d{2})(?:\D|$)").ok()?.captures(date_str) {
+            let month: u32 = cap[2].parse().ok().filter(|month| (1..=12).contains(month))?;
+            return to_iso(&cap[1], month, &cap[3]);
+        }
+
+        // Full names and abbreviations ("dec", "sept"), with any trailing period dropped
+        let month_of = |token: &str| {
+            MONTHS
+                .iter()
+                .position(|name| token.len() >= 3 && name.starts_with(token))
+                .map(|index| index as u32 + 1)
+        };
+        let lower = date_str.to_lowercase().replace(',', " ");
+        let tokens: Vec<&str> = lower.split_whitespace().map(|token| token.trim_end_matches('.')).collect();
+        tokens.windows(3).find_map(|window| match (month_of(window[0]), month_of(window[1])) {
+            (Some(month), None) => to_iso(window[2], month, window[1]),
+            (None, Some(month)) => to_iso(window[2], month, window[0]),
+            _ => None,
+        })
+    }
+
+    /// Fetch versions from a windows.php.net style directory listing (the official site or a mirror).
+    /// When `verify_checksum` is set, checksums are read from the listing's `sha256sum.txt`.
+    async fn fetch_versions_from_directory_listing(&self, url: &str, verify_checksum: bool) -> anyhow::Result<Vec<VersionInfo>> {
+        let base_url = if url.ends_with('/') { url.to_string() } else { format!("{}/", url) };
+        tracing::info!("Fetching PHP versions from: {}", base_url);
         
-        let response = self
-            .client
-            .get(url)
-            .send()
+        let (response, etag, last_modified) = match self
+            .fetch_page(&base_url)
             .await
-            .with_context(|| format!("Failed to fetch PHP releases page from {}", url))?;
+            .with_context(|| format!("Failed to fetch PHP releases page from {}", base_url))?
+        {
+            PageFetch::NotModified(versions) => return Ok(versions),
+            PageFetch::Fetched { response, etag, last_modified } => (response, etag, last_modified),
+        };
         
-        let html: String = response.text().await.with_context(|| "Failed to read response body")?;
-        
-        // Regex to match PHP version files: 
-        // - php-8.4.0-Win32-vs17-x64.zip (PHP 8.4+)
-        // - php-8.3.0-Win32-vs16-x64.zip (PHP 8.0-8.3)
-        // - php-7.4.33-Win32-vc15-x64.zip (PHP 7.x)
-        // Pattern: php-{major}.{minor}.{patch}-Win32-{vs_version|vc_version}-x64
-        // Extract both version and VS/VC version from filename
-        let version_regex = Regex::new(r"php-(\d+)\.(\d+)\.(\d+)(?:-RC\d+)?-Win32-(vs\d+|vc\d+)-x64(?:-nts)?\.zip").unwrap();
+        let status = response.status();
+        if !status.is_success() {
+            return Err(PhpVmError::Network(format!("HTTP error {} when fetching PHP releases page from {}", status, base_url)).into());
+        }
         
-        // Collect all unique versions (using HashMap to avoid duplicates)
-        let mut versions_set: HashMap<String, VersionInfo> = HashMap::new();
+        // Scan the listing as it arrives; the archives page lists hundreds of files
+        let mut parser = DirectoryListingParser::new();
+        let mut body = response.bytes_stream();
+        while let Some(chunk) = body.next().await {
+            parser.feed(&chunk.with_context(|| "Failed to read response body")?);
+        }
         
-        for cap in version_regex.captures_iter(&html) {
-            let major: u8 = cap[1].parse().unwrap_or(0);
-            let minor: u8 = cap[2].parse().unwrap_or(0);
-            let patch: u8 = cap[3].parse().unwrap_or(0);
-            let vs_version = cap.get(4).map(|m| m.as_str()).unwrap_or("vc15");
-            
-            // Skip invalid versions
-            if major == 0 || minor == 0 || patch == 0 {
-                continue;
+        let checksums = if verify_checksum {
+            match self.fetch_checksums(&base_url).await {
+                Ok(checksums) => checksums,
+                Err(e) => {
+                    tracing::warn!("Failed to fetch checksums from {}: {}", base_url, e);
+                    HashMap::new()
+                }
             }
-            
-            // Only process thread-safe builds (non-nts), skip nts files
-            // The regex will match both, but we only want the TS version for the main download URL
-            let version_str = format!("{}.{}.{}", major, minor, patch);
-            
-            // Only add if we haven't seen this exact version before
-            // Prefer vs17 over vs16 if both exist (newer versions)
-            if !versions_set.contains_key(&version_str) {
-                let eol_date = Self::get_eol_date(major, minor);
-                versions_set.insert(version_str.clone(), VersionInfo {
-                    version: version_str.clone(),
-                    release_date: None, // We don't have release dates from the page
-                    eol_date,
-                    download_url: Some(format!("{}php-{}-Win32-{}-x64.zip", url, version_str, vs_version)),
-                    checksum: None,
-                });
-            } else {
-                // If version already exists, update if we found a newer VS version (vs17 > vs16)
-                if vs_version == "vs17" {
-                    if let Some(existing) = versions_set.get_mut(&version_str) {
-                        if existing.download_url.as_ref().map(|u| u.contains("vs16")).unwrap_or(false) {
-                            existing.download_url = Some(format!("{}php-{}-Win32-{}-x64.zip", url, version_str, vs_version));
+        } else {
+            HashMap::new()
+        };
+        
+        let versions = parser.finish(&base_url, &checksums);
+        tracing::info!("Found {} PHP versions from {}", versions.len(), base_url);
+        Self::remember_version_list(&base_url, etag, last_modified, &versions);
+        Ok(versions)
+    }
+
+    /// Every published patch of one branch, newest first. Reads both the releases
+    /// directory (newest patch of each supported branch) and the archives directory
+    /// (everything else), so older patches such as 8.1.10 are included.
+    pub async fn list_branch_patches(&self, major: u8, minor: u8) -> anyhow::Result<Vec<VersionInfo>> {
+        let mut patches: HashMap<String, VersionInfo> = HashMap::new();
+        let mut errors = Vec::new();
+
+        for base_url in [RELEASES_URL, ARCHIVES_URL] {
+            match self.fetch_versions_from_directory_listing(base_url, false).await {
+                Ok(versions) => {
+                    for info in versions {
+                        let in_branch = PhpVersion::from_string(&info.version)
+                            .map(|v| v.major == major && v.minor == minor)
+                            .unwrap_or(false);
+                        // The releases directory wins if a file is listed in both
+                        if in_branch && !patches.contains_key(&info.version) {
+                            patches.insert(info.version.clone(), info);
                         }
                     }
                 }
+                Err(e) => {
+                    crate::logging::log_message("WARN", &format!("Failed to list {}: {}", base_url, e));
+                    errors.push(e);
+                }
             }
         }
-        
-        let mut versions: Vec<VersionInfo> = versions_set.into_values().collect();
-        
-        // Sort by version (newest first)
-        versions.sort_by(|a, b| {
+
+        if patches.is_empty() {
+            if let Some(e) = errors.into_iter().next() {
+                return Err(e.context(format!("Failed to list PHP {}.{} releases", major, minor)));
+            }
+        }
+
+        let mut patches: Vec<VersionInfo> = patches.into_values().collect();
+        patches.sort_by(|a, b| {
             let va = PhpVersion::from_string(&a.version).unwrap_or_default();
             let vb = PhpVersion::from_string(&b.version).unwrap_or_default();
             vb.cmp(&va)
         });
-        
-        tracing::info!("Found {} PHP versions from PHP.net", versions.len());
-        Ok(versions)
+        Ok(patches)
     }
 
-    pub async fn fetch_available_versions(&self) -> anyhow::Result<Vec<VersionInfo>> {
-        // Try to fetch dynamically from versionlog.com first (most reliable for EOL dates and latest patches)
-        match self.fetch_versions_from_versionlog().await {
-            Ok(versions) if !versions.is_empty() => {
-                tracing::info!("Successfully fetched {} versions from versionlog.com", versions.len());
-                return Ok(versions);
+    /// php.net ChangeLog page covering a major version. Current and archived branches
+    /// share the page; PHP 3 and earlier (and the never released PHP 6) have none.
+    pub fn changelog_url(major: u8) -> Option<String> {
+        match major {
+            4 | 5 | 7.. => Some(format!("{}ChangeLog-{}.php", CHANGELOG_BASE_URL, major)),
+            _ => None,
+        }
+    }
+
+    fn changelog_cache_path(version: &str) -> std::path::PathBuf {
+        config::get_base_directory()
+            .join("cache")
+            .join("changelogs")
+            .join(format!("{}.txt", version))
+    }
+
+    /// Release notes for one patch release as plain text: a "Version X.Y.Z" heading and
+    /// release date, then one line per extension ("Core:") followed by its "- " entries.
+    ///
+    /// Notes never change once published, so they're cached on disk forever. Versions
+    /// php.net has no notes for get a "No changelog available" message instead of an error.
+    pub async fn fetch_changelog(&self, major: u8, minor: u8, patch: u8) -> anyhow::Result<String> {
+        let version = format!("{}.{}.{}", major, minor, patch);
+        let unavailable = format!("No changelog available for PHP {}.", version);
+
+        let cache_path = Self::changelog_cache_path(&version);
+        if let Ok(notes) = std::fs::read_to_string(&cache_path) {
+            return Ok(notes);
+        }
+
+        let Some(url) = Self::changelog_url(major) else {
+            return Ok(unavailable);
+        };
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch changelog from {}", url))?;
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(unavailable);
+        }
+        if !status.is_success() {
+            return Err(PhpVmError::Network(format!("HTTP error {} when fetching changelog from {}", status, url)).into());
+        }
+        let html = Self::read_body_lenient(response).await?;
+
+        let Some(notes) = Self::extract_changelog_section(&html, &version) else {
+            crate::logging::log_message("INFO", &format!("No changelog section for PHP {} in {}", version, url));
+            return Ok(unavailable);
+        };
+        if let Some(parent) = cache_path.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent).and_then(|_| std::fs::write(&cache_path, &notes)) {
+                crate::logging::log_message("WARN", &format!("Failed to cache changelog for PHP {}: {}", version, e));
             }
-            Ok(_) => {
-                tracing::warn!("Fetched empty version list from versionlog.com, trying PHP.net");
+        }
+        Ok(notes)
+    }
+
+    /// Pull the `<section class="version" id="X.Y.Z">` block for a version out of a
+    /// php.net ChangeLog page and flatten it to text
+    fn extract_changelog_section(html: &str, version: &str) -> Option<String> {
+        let start_re = Regex::new(&format!(r#"<section[^>]*\bid=["']{}["'][^>]*>"#, regex::escape(version))).ok()?;
+        let start = start_re.find(html)?.end();
+        let end = html[start..].find("</section>").map(|i| start + i).unwrap_or(html.len());
+
+        static TAG_RE: OnceLock<Regex> = OnceLock::new();
+        let tag_re = TAG_RE.get_or_init(|| Regex::new(r"(?s)<!--.*?-->|<(/?)([a-zA-Z0-9]+)[^>]*>").unwrap());
+
+        let mut lines: Vec<String> = Vec::new();
+        let mut prefix = String::new();
+        let mut text = String::new();
+        let mut depth = 0usize;
+        let flush = |lines: &mut Vec<String>, prefix: &str, text: &mut String| {
+            let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !collapsed.is_empty() {
+                lines.push(format!("{}{}", prefix, Self::decode_entities(&collapsed)));
             }
-            Err(e) => {
-                tracing::warn!("Failed to fetch versions from versionlog.com: {}, trying PHP.net", e);
+            text.clear();
+        };
+
+        let section = &html[start..end];
+        let mut last = 0;
+        for caps in tag_re.captures_iter(section) {
+            let tag = caps.get(0).unwrap();
+            text.push_str(&section[last..tag.start()]);
+            last = tag.end();
+
+            let closing = caps.get(1).is_some_and(|m| m.as_str() == "/");
+            let name = caps.get(2).map(|m| m.as_str().to_lowercase()).unwrap_or_default();
+            match (closing, name.as_str()) {
+                (false, "ul") => {
+                    flush(&mut lines, &prefix, &mut text);
+                    depth += 1;
+                }
+                (true, "ul") => {
+                    flush(&mut lines, &prefix, &mut text);
+                    depth = depth.saturating_sub(1);
+                }
+                (false, "li") => {
+                    flush(&mut lines, &prefix, &mut text);
+                    // Top-level items name the extension; nested ones are the changes
+                    prefix = if depth <= 1 { String::new() } else { format!("{}- ", "  ".repeat(depth - 2)) };
+                }
+                (_, "h1" | "h2" | "h3" | "h4" | "p" | "br" | "b" | "li") => {
+                    flush(&mut lines, &prefix, &mut text);
+                }
+                _ => {}
             }
         }
+        text.push_str(&section[last..]);
+        flush(&mut lines, &prefix, &mut text);
+
+        if lines.is_empty() {
+            return None;
+        }
+        Some(lines.join("\n"))
+    }
+
+    fn decode_entities(text: &str) -> String {
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#039;", "'")
+            .replace("&#39;", "'")
+            .replace("&nbsp;", " ")
+            .replace("&amp;", "&")
+    }
+
+    /// Fetch `sha256sum.txt` from a release directory, keyed by archive file name.
+    /// A directory without one (404) publishes no checksums, which isn't an error.
+    async fn fetch_checksums(&self, base_url: &str) -> anyhow::Result<HashMap<String, String>> {
+        let url = format!("{}sha256sum.txt", base_url);
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to fetch checksums from {}", url))?;
+        
+        let status = response.status();
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(HashMap::new());
+        }
+        if !status.is_success() {
+            return Err(PhpVmError::Network(format!("HTTP error {} when fetching checksums from {}", status, url)).into());
+        }
+        
+        let body = Self::read_body_lenient(response).await?;
+        Ok(Self::parse_sha256sums(&body))
+    }
+
+    /// Published SHA256 of an archive, from `sha256sum.txt` next to it. None when the
+    /// directory has no checksum file or doesn't list the archive; an error when the
+    /// checksum file couldn't be fetched (offline, server error), so callers can tell
+    /// "nothing published" from "couldn't check".
+    pub async fn checksum_for_url(&self, url: &str) -> anyhow::Result<Option<String>> {
+        let Some((base_url, file_name)) = url.rsplit_once('/') else { return Ok(None) };
+        let checksums = self.fetch_checksums(&format!("{}/", base_url)).await?;
+        Ok(checksums.get(file_name).cloned())
+    }
+
+    /// The configured provider a download URL comes from: the one whose `url` it's under,
+    /// or the `official` provider for windows.php.net release and archive downloads.
+    /// None for URLs no provider serves (e.g. an explicit source URL).
+    pub fn provider_for_download(&self, url: &str) -> Option<&ProviderConfig> {
+        self.providers
+            .iter()
+            .find(|provider| url.starts_with(&format!("{}/", provider.url.trim_end_matches('/'))))
+            .or_else(|| {
+                self.providers
+                    .iter()
+                    .find(|provider| provider.kind() == ProviderKind::Official && url.starts_with(RELEASES_URL))
+            })
+    }
+
+    /// Parse a checksum listing in either `sha256sum` format (`<hash> *<file>`)
+    /// or BSD format (`SHA256(<file>)= <hash>`)
+    pub(crate) fn parse_sha256sums(content: &str) -> HashMap<String, String> {
+        let bsd_regex = Regex::new(r"^SHA256\(([^)]+)\)\s*=\s*([0-9a-fA-F]{64})$").unwrap();
+        let gnu_regex = Regex::new(r"^([0-9a-fA-F]{64})\s+\*?(\S+)$").unwrap();
         
-        // Fallback to PHP.net if versionlog.com fails
-        match self.fetch_versions_from_php_net().await {
-            Ok(versions) if !versions.is_empty() => {
-                tracing::info!("Successfully fetched {} versions from PHP.net", versions.len());
-                return Ok(versions);
+        let mut checksums = HashMap::new();
+        for line in content.lines().map(str::trim) {
+            if let Some(cap) = bsd_regex.captures(line) {
+                checksums.insert(cap[1].to_string(), cap[2].to_lowercase());
+            } else if let Some(cap) = gnu_regex.captures(line) {
+                checksums.insert(cap[2].to_string(), cap[1].to_lowercase());
             }
-            Ok(_) => {
-                tracing::warn!("Fetched empty version list from PHP.net, using hardcoded fallback");
+        }
+        checksums
+    }
+
+    /// Try a single configured provider
+    async fn fetch_from_provider(&self, provider: &ProviderConfig) -> anyhow::Result<Vec<VersionInfo>> {
+        match provider.kind() {
+            ProviderKind::Versionlog => self.fetch_versions_from_versionlog(&provider.url).await,
+            ProviderKind::DirectoryListing => {
+                self.fetch_versions_from_directory_listing(&provider.url, provider.verify_checksum).await
             }
-            Err(e) => {
-                tracing::warn!("Failed to fetch versions from PHP.net: {}, using hardcoded fallback", e);
+            ProviderKind::Official => {
+                // versionlog.com is the most reliable source for EOL dates and latest patches
+                match self.fetch_versions_from_versionlog(VERSIONLOG_URL).await {
+                    Ok(versions) if !versions.is_empty() => return Ok(versions),
+                    Ok(_) => {
+                        tracing::warn!("Fetched empty version list from versionlog.com, trying {}", provider.url);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Failed to fetch versions from versionlog.com: {}, trying {}", e, provider.url);
+                    }
+                }
+                self.fetch_versions_from_directory_listing(&provider.url, provider.verify_checksum).await
             }
         }
-        
+    }
+
+    pub async fn fetch_available_versions(&self) -> anyhow::Result<Vec<VersionInfo>> {
+        match self.fetch_provider_versions().await {
+            Ok(versions) => Ok(versions),
+            Err(_) => Ok(Self::fallback_versions()),
+        }
+    }
+
+    /// Like `fetch_available_versions`, but fails instead of using the built-in list
+    /// when no provider returns any versions
+    pub async fn fetch_provider_versions(&self) -> anyhow::Result<Vec<VersionInfo>> {
+        // Try each configured provider in order
+        for provider in &self.providers {
+            match self.fetch_from_provider(provider).await {
+                Ok(versions) if !versions.is_empty() => {
+                    tracing::info!("Successfully fetched {} versions from provider '{}'", versions.len(), provider.name);
+                    return Ok(versions);
+                }
+                Ok(_) => {
+                    tracing::warn!("Fetched empty version list from provider '{}', trying next provider", provider.name);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to fetch versions from provider '{}': {}, trying next provider", provider.name, e);
+                }
+            }
+        }
+
+        Err(PhpVmError::Network("No version provider returned any versions".to_string()).into())
+    }
+
+    fn fallback_versions() -> Vec<VersionInfo> {
         // Fallback to hardcoded list if fetching fails
         // 
         // IMPORTANT: This list must be kept up-to-date with the latest patch releases!
@@ -425,17 +1151,17 @@ impl Provider {
             ("5.6.40", Some("2019-01-10"), Some("2018-12-31")), // PHP 5.6 - Latest patch (Jan 10, 2019), EOL: Dec 31, 2018 (ended)
         ];
 
-        Ok(versions
+        versions
             .into_iter()
             .map(|(v, release, eol)| {
                 // Parse version to determine VS version for download URL
                 let parts: Vec<&str> = v.split('.').collect();
-                let major: u8 = parts.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
+                let major: u8 = parts.first().and_then(|s| s.parse().ok()).unwrap_or(0);
                 let minor: u8 = parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
                 
                 // Generate download URL so versions are marked as "online"
-                let download_url = if major > 0 && minor >= 0 {
-                    Some(Self::generate_download_url(v, major, minor))
+                let download_url = if major > 0 {
+                    Some(Self::generate_download_url(v, major, minor, true))
                 } else {
                     None
                 };
@@ -444,58 +1170,495 @@ impl Provider {
                     version: v.to_string(),
                     release_date: release.map(|s| s.to_string()),
                     eol_date: eol.map(|s| s.to_string()),
+                    active_support_until: Self::get_active_support_date(major, minor),
                     download_url,
                     checksum: None,
                 }
             })
-            .collect())
+            .collect()
     }
 
-    pub async fn get_top_versions(&self, limit: usize) -> anyhow::Result<Vec<VersionInfo>> {
-        let mut versions = self.fetch_available_versions().await?;
-        
-        // Sort by version (newest first)
+    /// Fetched versions, newest first; `limit` None keeps all of them
+    pub async fn get_top_versions(&self, limit: Option<usize>) -> anyhow::Result<Vec<VersionInfo>> {
+        Ok(Self::top_versions(self.fetch_available_versions().await?, limit))
+    }
+
+    /// Sort newest first and cut to `limit`. With None nothing is dropped, so old
+    /// branches stay listed however many patches and sources the list holds.
+    pub fn top_versions(mut versions: Vec<VersionInfo>, limit: Option<usize>) -> Vec<VersionInfo> {
         versions.sort_by(|a, b| {
             let va = PhpVersion::from_string(&a.version).unwrap_or_default();
             let vb = PhpVersion::from_string(&b.version).unwrap_or_default();
             vb.cmp(&va)
         });
-        
-        versions.truncate(limit);
-        Ok(versions)
+        if let Some(limit) = limit {
+            versions.truncate(limit);
+        }
+        versions
     }
 
-    pub fn detect_thread_safe_from_url(url: &str) -> Option<bool> {
-        // Windows PHP downloads: TS = Thread Safe, NTS = Non-Thread Safe
-        // URLs typically contain: php-{version}-Win32-vs16-x64.zip (TS)
-        // or: php-{version}-Win32-vs16-x64-nts.zip (NTS)
-        if url.contains("-nts") || url.contains("NTS") {
-            Some(false)
-        } else if url.contains("-ts") || url.contains("TS") {
-            Some(true)
-        } else {
-            // Default: most Windows builds are TS
-            Some(true)
+    /// Apply a version list filter: drop EOL branches (relative to `today`) and branches
+    /// without an install as asked, sort by version and cut to the limit. `installed`
+    /// holds variant strings as stored in state (e.g. "8.2.10-ts").
+    pub fn filter_available(versions: Vec<VersionInfo>, filter: &crate::config::AvailableFilter, installed: &[String], today: &str) -> Vec<VersionInfo> {
+        let installed_branches: Vec<(u8, u8)> = installed
+            .iter()
+            .filter_map(|v| PhpVersion::from_string(v.split('-').next().unwrap_or(v)).ok())
+            .map(|v| (v.major, v.minor))
+            .collect();
+
+        let mut kept: Vec<(PhpVersion, VersionInfo)> = versions
+            .into_iter()
+            .filter_map(|info| PhpVersion::from_string(&info.version).ok().map(|version| (version, info)))
+            .filter(|(version, info)| {
+                if filter.only_installed_branches && !installed_branches.contains(&(version.major, version.minor)) {
+                    return false;
+                }
+                if filter.include_eol {
+                    return true;
+                }
+                let eol_date = info.eol_date.clone().or_else(|| Self::get_eol_date(version.major, version.minor));
+                !eol_date.is_some_and(|eol| crate::date::is_before(&eol, today))
+            })
+            .collect();
+
+        kept.sort_by(|a, b| if filter.ascending { a.0.cmp(&b.0) } else { b.0.cmp(&a.0) });
+        if let Some(limit) = filter.limit {
+            kept.truncate(limit);
+        }
+        kept.into_iter().map(|(_, info)| info).collect()
+    }
+
+    /// Bucket versions by major.minor branch (newest branch first), marking EOL
+    /// branches relative to `today` and installed versions behind the latest patch.
+    /// `installed` holds variant strings as stored in state (e.g. "8.2.10-ts").
+    pub fn group_by_branch(versions: &[VersionInfo], installed: &[String], today: &str) -> Vec<BranchInfo> {
+        let mut branches: HashMap<(u8, u8), Vec<(PhpVersion, &VersionInfo)>> = HashMap::new();
+        for info in versions {
+            if let Ok(version) = PhpVersion::from_string(&info.version) {
+                branches.entry((version.major, version.minor)).or_default().push((version, info));
+            }
+        }
+
+        let mut result: Vec<BranchInfo> = branches
+            .into_iter()
+            .map(|((major, minor), mut patches)| {
+                patches.sort_by(|a, b| b.0.cmp(&a.0));
+                let (latest, latest_info) = &patches[0];
+
+                let eol_date = latest_info
+                    .eol_date
+                    .clone()
+                    .or_else(|| Self::get_eol_date(major, minor));
+                let is_eol = eol_date
+                    .as_deref()
+                    .map(|eol| crate::date::is_before(eol, today))
+                    .unwrap_or(false);
+
+                let branch_installed: Vec<String> = installed
+                    .iter()
+                    .filter(|v| {
+                        PhpVersion::from_string(v.split('-').next().unwrap_or(v))
+                            .map(|iv| iv.major == major && iv.minor == minor)
+                            .unwrap_or(false)
+                    })
+                    .cloned()
+                    .collect();
+                let update_available = branch_installed.iter().any(|v| {
+                    PhpVersion::from_string(v.split('-').next().unwrap_or(v))
+                        .map(|iv| iv.patch < latest.patch)
+                        .unwrap_or(false)
+                });
+
+                BranchInfo {
+                    major,
+                    minor,
+                    eol_date,
+                    is_eol,
+                    latest_patch: latest.to_string(),
+                    all_patches: patches.iter().map(|(v, _)| v.to_string()).collect(),
+                    installed: branch_installed,
+                    update_available,
+                }
+            })
+            .collect();
+
+        result.sort_by_key(|b| std::cmp::Reverse((b.major, b.minor)));
+        result
+    }
+
+    /// Find installed versions with a newer patch in the same major.minor branch.
+    /// Cross-branch upgrades (e.g. 8.2 -> 8.3) are never suggested.
+    pub fn find_patch_updates(versions: &[VersionInfo], installed: &[String]) -> Vec<PatchUpdate> {
+        let available: Vec<PhpVersion> = versions
+            .iter()
+            .filter_map(|info| PhpVersion::from_string(&info.version).ok())
+            .filter(|v| v.suffix.is_none())
+            .collect();
+
+        let mut updates = Vec::new();
+        for installed_version in installed {
+            let (base, variant) = match installed_version.rsplit_once('-') {
+                Some((base, variant)) if variant == "ts" || variant == "nts" => (base, variant),
+                _ => (installed_version.as_str(), "ts"),
+            };
+            let current = match PhpVersion::from_string(base) {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+
+            let latest = available
+                .iter()
+                .filter(|v| v.major == current.major && v.minor == current.minor)
+                .max();
+            if let Some(latest) = latest {
+                if latest.patch > current.patch {
+                    updates.push(PatchUpdate {
+                        installed: current.to_string(),
+                        latest: latest.to_string(),
+                        variant: variant.to_string(),
+                    });
+                }
+            }
+        }
+        updates
+    }
+
+    /// Check whether a download URL is actually reachable (2xx on HEAD).
+    /// Results are cached per URL for `AVAILABILITY_TTL`; network failures are not cached.
+    pub async fn is_url_available(&self, url: &str) -> bool {
+        if let Some((available, checked_at)) = availability_cache().lock().unwrap().get(url) {
+            if checked_at.elapsed() < AVAILABILITY_TTL {
+                return *available;
+            }
+        }
+
+        let available = match self.probe_url(url).await {
+            Ok(available) => available,
+            Err(e) => {
+                tracing::warn!("Availability check failed for {}: {}", url, e);
+                return false;
+            }
+        };
+
+        availability_cache()
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), (available, Instant::now()));
+        available
+    }
+
+    /// Check several URLs concurrently, returning availability keyed by URL
+    pub async fn check_urls_available(&self, urls: &[String]) -> HashMap<String, bool> {
+        let checks = urls.iter().map(|url| async move {
+            (url.clone(), self.is_url_available(url).await)
+        });
+        futures::future::join_all(checks).await.into_iter().collect()
+    }
+
+    async fn probe_url(&self, url: &str) -> anyhow::Result<bool> {
+        Ok(self.probe_response(url).await?.status().is_success())
+    }
+
+    /// HEAD `url`, or a single-byte ranged GET for mirrors that reject HEAD
+    async fn probe_response(&self, url: &str) -> anyhow::Result<reqwest::Response> {
+        let response = self
+            .client
+            .head(url)
+            .timeout(AVAILABILITY_TIMEOUT)
+            .send()
+            .await
+            .with_context(|| format!("Failed to send HEAD request to {}", url))?;
+
+        if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED {
+            return self
+                .client
+                .get(url)
+                .header(reqwest::header::RANGE, "bytes=0-0")
+                .timeout(AVAILABILITY_TIMEOUT)
+                .send()
+                .await
+                .with_context(|| format!("Failed to send GET request to {}", url));
+        }
+        Ok(response)
+    }
+
+    /// Probe one download URL now, bypassing the availability cache, and report the
+    /// HTTP status and archive size for triaging a failing install
+    pub async fn probe_download(&self, url: &str) -> UrlProbe {
+        let response = match self.probe_response(url).await {
+            Ok(response) => response,
+            Err(e) => {
+                return UrlProbe {
+                    url: url.to_string(),
+                    status_code: None,
+                    reachable: false,
+                    content_length: None,
+                    error: Some(format!("{:#}", e)),
+                }
+            }
+        };
+        let header = |name: reqwest::header::HeaderName| {
+            response.headers().get(name).and_then(|value| value.to_str().ok()).map(str::to_string)
+        };
+        // A ranged GET answers with "Content-Range: bytes 0-0/<size>"
+        let content_length = match response.status() {
+            reqwest::StatusCode::PARTIAL_CONTENT => {
+                header(reqwest::header::CONTENT_RANGE).and_then(|range| range.rsplit('/').next()?.parse().ok())
+            }
+            _ => header(reqwest::header::CONTENT_LENGTH).and_then(|length| length.parse().ok()),
+        };
+        UrlProbe {
+            url: url.to_string(),
+            status_code: Some(response.status().as_u16()),
+            reachable: response.status().is_success(),
+            content_length,
+            error: None,
         }
     }
 
+    /// Thread safety of a windows.php.net build from its URL. Only the file name is
+    /// looked at, with the same rule as `detect_thread_safe_from_filename`.
+    pub fn detect_thread_safe_from_url(url: &str) -> Option<bool> {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let filename = path.rsplit('/').next().unwrap_or(path);
+        Self::detect_thread_safe_from_filename(filename)
+    }
+
+    /// Thread safety of a windows.php.net build from its file name. NTS builds carry an
+    /// `nts` part (php-8.3.0-nts-Win32-vs16-x64.zip); TS builds have no marker at all
+    /// (php-8.3.0-Win32-vs16-x64.zip), so anything without `nts` is TS. Always `Some`.
     pub fn detect_thread_safe_from_filename(filename: &str) -> Option<bool> {
-        // Check filename for TS/NTS indicators
-        let lower = filename.to_lowercase();
-        if lower.contains("-nts") || lower.contains("_nts") {
-            Some(false)
-        } else if lower.contains("-ts") || lower.contains("_ts") {
-            Some(true)
-        } else {
-            None
+        let is_nts = filename
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .any(|part| part.eq_ignore_ascii_case("nts"));
+        Some(!is_nts)
+    }
+}
+
+/// Bytes kept from the end of one chunk for the next, so a file name split across chunks
+/// still matches; comfortably more than the longest "php-X.Y.Z-RCn-Win32-vsNN-x64-nts.zip"
+const LISTING_OVERLAP: usize = 128;
+
+/// Incremental scan of a windows.php.net style directory listing: `feed` the body chunk by
+/// chunk as it arrives, then `finish`. Only ASCII file names are matched, so the page's
+/// encoding doesn't matter, and at most one chunk plus the overlap is held.
+struct DirectoryListingParser {
+    // Pattern: php-{major}.{minor}.{patch}-Win32-{vs_version|vc_version}-x64, e.g.
+    // php-8.4.0-Win32-vs17-x64.zip, php-8.3.0-Win32-vs16-x64.zip, php-7.4.33-Win32-vc15-x64.zip.
+    // Archived releases use an upper-case tag (VC15, VC14, VC11)
+    file_regex: regex::bytes::Regex,
+    /// Unscanned tail of the body so far
+    pending: Vec<u8>,
+    /// Version -> thread-safe archive file name, preferring vs17 over vs16 builds
+    files: HashMap<String, String>,
+}
+
+impl DirectoryListingParser {
+    fn new() -> Self {
+        DirectoryListingParser {
+            file_regex: regex::bytes::Regex::new(r"php-(\d+)\.(\d+)\.(\d+)(?:-RC\d+)?-Win32-((?i:vs|vc)\d+)-x64(?:-nts)?\.zip").unwrap(),
+            pending: Vec::new(),
+            files: HashMap::new(),
         }
     }
+
+    fn feed(&mut self, chunk: &[u8]) {
+        self.pending.extend_from_slice(chunk);
+
+        let mut scanned = 0;
+        for cap in self.file_regex.captures_iter(&self.pending) {
+            scanned = cap.get(0).map_or(scanned, |m| m.end());
+            // The pattern only matches ASCII, so these can't fail
+            let text = |i: usize| std::str::from_utf8(&cap[i]).unwrap_or_default();
+            let major: u8 = text(1).parse().unwrap_or(0);
+            let minor: u8 = text(2).parse().unwrap_or(0);
+            let patch: u8 = text(3).parse().unwrap_or(0);
+            let vs_version = text(4);
+
+            // Skip invalid versions (8.0.x and X.Y.0 releases are valid)
+            if major == 0 {
+                continue;
+            }
+
+            let version_str = format!("{}.{}.{}", major, minor, patch);
+            let file_name = format!("php-{}-Win32-{}-x64.zip", version_str, vs_version);
+            match self.files.get_mut(&version_str) {
+                None => {
+                    self.files.insert(version_str, file_name);
+                }
+                // Prefer vs17 over vs16 if both exist
+                Some(existing) if vs_version == "vs17" && existing.contains("vs16") => *existing = file_name,
+                Some(_) => {}
+            }
+        }
+
+        let keep_from = scanned.max(self.pending.len().saturating_sub(LISTING_OVERLAP));
+        self.pending.drain(..keep_from);
+    }
+
+    /// The versions seen, newest first, with download URLs under `base_url`
+    fn finish(self, base_url: &str, checksums: &HashMap<String, String>) -> Vec<VersionInfo> {
+        let mut versions: Vec<(PhpVersion, VersionInfo)> = self
+            .files
+            .into_iter()
+            .filter_map(|(version_str, file_name)| {
+                let version = PhpVersion::from_string(&version_str).ok()?;
+                let info = VersionInfo {
+                    eol_date: Provider::get_eol_date(version.major, version.minor),
+                    active_support_until: Provider::get_active_support_date(version.major, version.minor),
+                    release_date: None, // We don't have release dates from the page
+                    download_url: Some(format!("{}{}", base_url, file_name)),
+                    checksum: checksums.get(&file_name).cloned(),
+                    version: version_str,
+                };
+                Some((version, info))
+            })
+            .collect();
+
+        // Sort by version (newest first)
+        versions.sort_by(|a, b| b.0.cmp(&a.0));
+        versions.into_iter().map(|(_, info)| info).collect()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_version_delta_between() {
+        let info = |version: &str, eol: Option<&str>| VersionInfo {
+            version: version.to_string(),
+            release_date: None,
+            eol_date: eol.map(str::to_string),
+            active_support_until: None,
+            download_url: None,
+            checksum: None,
+        };
+        let previous = vec![info("8.3.0", Some("2026-11-23")), info("8.2.12", Some("2025-12-08")), info("7.4.33", None)];
+        let current = vec![
+            info("8.3.1", Some("2027-12-31")),
+            info("8.3.0", Some("2027-12-31")),
+            info("8.2.13", Some("2025-12-08")),
+            info("8.2.12", Some("2025-12-08")),
+        ];
+
+        let delta = VersionDelta::between("https://example.test/", 42, &previous, &current);
+        assert_eq!(delta.added, vec!["8.3.1", "8.2.13"]);
+        assert_eq!(
+            delta.eol_changes,
+            vec![EolChange { version: "8.3.0".to_string(), from: Some("2026-11-23".to_string()), to: Some("2027-12-31".to_string()) }]
+        );
+        assert_eq!(delta.previous_fetched_at, 42);
+        assert!(VersionDelta::between("https://example.test/", 42, &current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_top_versions_without_limit_keeps_every_branch() {
+        // 30 versions: three patches each of ten branches, 5.6 through 8.3
+        let branches = [(5, 6), (7, 0), (7, 1), (7, 2), (7, 3), (7, 4), (8, 0), (8, 1), (8, 2), (8, 3)];
+        let versions: Vec<VersionInfo> = branches
+            .iter()
+            .flat_map(|(major, minor)| (1..=3).map(move |patch| format!("{}.{}.{}", major, minor, patch)))
+            .map(|version| VersionInfo {
+                version,
+                release_date: None,
+                eol_date: None,
+                active_support_until: None,
+                download_url: None,
+                checksum: None,
+            })
+            .collect();
+        assert_eq!(versions.len(), 30);
+
+        let all = Provider::top_versions(versions.clone(), None);
+        assert_eq!(all.len(), 30);
+        assert_eq!(all[0].version, "8.3.3");
+        for (major, minor) in branches {
+            let latest = format!("{}.{}.3", major, minor);
+            assert!(all.iter().any(|v| v.version == latest), "{} was dropped", latest);
+        }
+
+        // An explicit limit still applies, newest first
+        let top: Vec<String> = Provider::top_versions(versions, Some(20)).into_iter().map(|v| v.version).collect();
+        assert_eq!(top.len(), 20);
+        assert!(!top.contains(&"5.6.3".to_string()));
+    }
+
+    #[test]
+    fn test_filter_available() {
+        use crate::config::AvailableFilter;
+
+        let info = |version: &str, eol: &str| VersionInfo {
+            version: version.to_string(),
+            release_date: None,
+            eol_date: Some(eol.to_string()),
+            active_support_until: None,
+            download_url: None,
+            checksum: None,
+        };
+        let versions = vec![
+            info("8.2.10", "2026-12-31"),
+            info("7.4.33", "2022-11-28"),
+            info("8.3.0", "2027-12-31"),
+            info("8.1.27", "2025-12-31"),
+        ];
+        let installed = vec!["8.2.5-nts".to_string(), "7.4.30-ts".to_string()];
+        let filtered = |filter: AvailableFilter| -> Vec<String> {
+            Provider::filter_available(versions.clone(), &filter, &installed, "2026-01-15")
+                .into_iter()
+                .map(|v| v.version)
+                .collect()
+        };
+
+        // Defaults keep everything, newest first, with no cap
+        assert_eq!(filtered(AvailableFilter::default()), vec!["8.3.0", "8.2.10", "8.1.27", "7.4.33"]);
+        assert_eq!(
+            filtered(AvailableFilter { include_eol: false, ..Default::default() }),
+            vec!["8.3.0", "8.2.10"]
+        );
+        assert_eq!(
+            filtered(AvailableFilter { only_installed_branches: true, ascending: true, ..Default::default() }),
+            vec!["7.4.33", "8.2.10"]
+        );
+        assert_eq!(filtered(AvailableFilter { limit: Some(1), ascending: true, ..Default::default() }), vec!["7.4.33"]);
+    }
+
+    #[test]
+    fn test_mirrored_url() {
+        let url = "https://windows.php.net/downloads/releases/php-8.3.0-Win32-vs16-x64.zip";
+        assert_eq!(
+            Provider::mirrored_url(url, "https://php-mirror.example.com/").as_deref(),
+            Some("https://php-mirror.example.com/downloads/releases/php-8.3.0-Win32-vs16-x64.zip")
+        );
+        assert_eq!(
+            Provider::mirrored_url(url, "http://cdn.example.com/php").as_deref(),
+            Some("http://cdn.example.com/php/downloads/releases/php-8.3.0-Win32-vs16-x64.zip")
+        );
+        assert_eq!(Provider::mirrored_url("C:/archives/php.zip", "https://cdn.example.com"), None);
+    }
+
+    #[test]
+    fn test_provider_for_download() {
+        let provider = Provider::with_providers(vec![
+            ProviderConfig { name: "official".to_string(), url: "https://mirror.example.com/php".to_string(), verify_checksum: true },
+            ProviderConfig { name: "internal".to_string(), url: "https://php.corp.local/releases/".to_string(), verify_checksum: false },
+        ])
+        .unwrap();
+        let name = |url: &str| provider.provider_for_download(url).map(|p| p.name.clone());
+
+        assert_eq!(name("https://php.corp.local/releases/php-8.3.0-Win32-vs16-x64.zip").as_deref(), Some("internal"));
+        assert_eq!(name("https://mirror.example.com/php/php-8.3.0-Win32-vs16-x64.zip").as_deref(), Some("official"));
+        // Archives move out of the listing, but they're still the official provider's
+        assert_eq!(
+            name("https://windows.php.net/downloads/releases/archives/php-8.1.0-Win32-vs16-x64.zip").as_deref(),
+            Some("official")
+        );
+        assert_eq!(name("https://php.corp.local/releases-old/php-8.3.0.zip"), None);
+        assert_eq!(name("http://127.0.0.1:8080/php-8.3.0.zip"), None);
+    }
+
     #[test]
     fn test_get_vs_version() {
         // Test PHP 8.4+ (vs17)
@@ -546,29 +1709,112 @@ mod tests {
     #[test]
     fn test_generate_download_url() {
         // Test newer versions (>= 7.4) - use releases directory
-        let url = Provider::generate_download_url("8.2.0", 8, 2);
+        let url = Provider::generate_download_url("8.2.0", 8, 2, true);
         assert!(url.contains("php-8.2.0-Win32-vs16-x64.zip"));
         
-        let url = Provider::generate_download_url("8.4.0", 8, 4);
+        let url = Provider::generate_download_url("8.4.0", 8, 4, true);
         assert!(url.contains("php-8.4.0-Win32-vs17-x64.zip"));
         
-        let url = Provider::generate_download_url("7.4.33", 7, 4);
+        let url = Provider::generate_download_url("7.4.33", 7, 4, true);
         assert!(url.contains("php-7.4.33-Win32-vc15-x64.zip"));
         
         // Test older versions (< 7.4) - use archives directory
-        let url = Provider::generate_download_url("7.3.33", 7, 3);
+        let url = Provider::generate_download_url("7.3.33", 7, 3, true);
         assert!(url.contains("php-7.3.33-Win32-VC15-x64.zip"));
         assert!(url.contains("archives"));
         
-        let url = Provider::generate_download_url("7.0.33", 7, 0);
+        let url = Provider::generate_download_url("7.0.33", 7, 0, true);
         assert!(url.contains("php-7.0.33-Win32-VC14-x64.zip"));
         assert!(url.contains("archives"));
         
-        let url = Provider::generate_download_url("5.6.40", 5, 6);
+        let url = Provider::generate_download_url("5.6.40", 5, 6, true);
         assert!(url.contains("php-5.6.40-Win32-VC11-x64.zip"));
         assert!(url.contains("archives"));
     }
 
+    #[test]
+    fn test_generate_download_url_nts() {
+        // NTS infix goes after the version and before Win32
+        assert_eq!(
+            Provider::generate_download_url("8.4.0", 8, 4, false),
+            "https://windows.php.net/downloads/releases/php-8.4.0-nts-Win32-vs17-x64.zip"
+        );
+        assert_eq!(
+            Provider::generate_download_url("8.2.0", 8, 2, false),
+            "https://windows.php.net/downloads/releases/php-8.2.0-nts-Win32-vs16-x64.zip"
+        );
+        assert_eq!(
+            Provider::generate_download_url("7.4.33", 7, 4, false),
+            "https://windows.php.net/downloads/releases/php-7.4.33-nts-Win32-vc15-x64.zip"
+        );
+        
+        // Archived versions keep the capital VC tag with the same infix placement
+        assert_eq!(
+            Provider::generate_download_url("7.3.33", 7, 3, false),
+            "https://windows.php.net/downloads/releases/archives/php-7.3.33-nts-Win32-VC15-x64.zip"
+        );
+        assert_eq!(
+            Provider::generate_download_url("7.0.33", 7, 0, false),
+            "https://windows.php.net/downloads/releases/archives/php-7.0.33-nts-Win32-VC14-x64.zip"
+        );
+        assert_eq!(
+            Provider::generate_download_url("5.6.40", 5, 6, false),
+            "https://windows.php.net/downloads/releases/archives/php-5.6.40-nts-Win32-VC11-x64.zip"
+        );
+        
+        // TS URLs never contain the infix
+        assert!(!Provider::generate_download_url("7.3.33", 7, 3, true).contains("-nts"));
+    }
+
+    #[test]
+    fn test_candidate_download_urls() {
+        let urls = Provider::candidate_download_urls("8.4.0", 8, 4, true);
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0], Provider::generate_download_url("8.4.0", 8, 4, true));
+        assert!(urls[1].ends_with("php-8.4.0-Win32-vs16-x64.zip"));
+
+        let urls = Provider::candidate_download_urls("7.3.33", 7, 3, false);
+        assert!(urls[1].ends_with("archives/php-7.3.33-nts-Win32-VC14-x64.zip"));
+
+        assert_eq!(Provider::candidate_download_urls("5.6.40", 5, 6, true).len(), 1);
+    }
+
+    #[test]
+    fn test_install_download_urls_try_archives_before_other_compilers() {
+        let urls = Provider::install_download_urls("8.2.10", 8, 2, false);
+        assert_eq!(
+            urls,
+            vec![
+                "https://windows.php.net/downloads/releases/php-8.2.10-nts-Win32-vs16-x64.zip",
+                "https://windows.php.net/downloads/releases/archives/php-8.2.10-nts-Win32-vs16-x64.zip",
+                "https://windows.php.net/downloads/releases/php-8.2.10-nts-Win32-vc15-x64.zip",
+            ]
+        );
+        // Whole branches in archives/ have no separate archived copy
+        assert_eq!(Provider::install_download_urls("7.3.33", 7, 3, true), Provider::candidate_download_urls("7.3.33", 7, 3, true));
+    }
+
+    #[test]
+    fn test_explicit_compiler_tag() {
+        assert_eq!(Provider::validate_compiler_tag("vs16").unwrap(), "vs16");
+        assert_eq!(Provider::validate_compiler_tag("VC15").unwrap(), "VC15");
+        for bad in ["vs15", "VS16", "", "vs16/../../evil", "vs16-x64.zip?"] {
+            assert_eq!(Provider::validate_compiler_tag(bad).unwrap_err().code(), "INVALID_ARGUMENT", "{:?}", bad);
+        }
+
+        assert_eq!(
+            Provider::install_download_urls_for_compiler("7.4.33", 7, 4, true, "vs16"),
+            vec![
+                "https://windows.php.net/downloads/releases/php-7.4.33-Win32-vs16-x64.zip",
+                "https://windows.php.net/downloads/releases/archives/php-7.4.33-Win32-vs16-x64.zip",
+            ]
+        );
+        assert_eq!(
+            Provider::install_download_urls_for_compiler("7.2.34", 7, 2, false, "VC14"),
+            vec!["https://windows.php.net/downloads/releases/archives/php-7.2.34-nts-Win32-VC14-x64.zip"]
+        );
+    }
+
     #[test]
     fn test_get_eol_date() {
         // Test known EOL dates
@@ -590,6 +1836,327 @@ mod tests {
         assert_eq!(Provider::get_eol_date(6, 0), None);
     }
 
+    #[test]
+    fn test_extract_row_date() {
+        let row = r#"<tr><td><a href="/php/8.3/">PHP 8.3</a></td>
+            <td>Latest patch release: Dec 18, 2025</td>
+            <td>End of active support: December 31, 2025</td>
+            <td>End of security fixes: 2027-12-31</td></tr>"#;
+        assert_eq!(Provider::extract_row_date(row, "End of active support"), Some("2025-12-31".to_string()));
+        assert_eq!(Provider::extract_row_date(row, "End of security fixes"), Some("2027-12-31".to_string()));
+        assert_eq!(Provider::extract_row_date(row, "Latest patch release"), Some("2025-12-18".to_string()));
+        assert_eq!(Provider::extract_row_date("<tr><td>PHP 9.0</td></tr>", "End of active support"), None);
+
+        let row = "<td>End of active support: 31 December 2025</td><td>End of security fixes: Dec.  31,  2027</td>";
+        assert_eq!(Provider::extract_row_date(row, "End of active support"), Some("2025-12-31".to_string()));
+        assert_eq!(Provider::extract_row_date(row, "End of security fixes"), Some("2027-12-31".to_string()));
+    }
+
+    #[test]
+    fn test_parse_date_to_iso_formats() {
+        let parsed = |text: &str| Provider::parse_date_to_iso(text);
+        assert_eq!(parsed("December 31, 2029").as_deref(), Some("2029-12-31"));
+        assert_eq!(parsed("Dec 31, 2029").as_deref(), Some("2029-12-31"));
+        // Trailing period after an abbreviated month
+        assert_eq!(parsed("Dec. 31, 2029").as_deref(), Some("2029-12-31"));
+        assert_eq!(parsed("Sept. 5, 2024").as_deref(), Some("2024-09-05"));
+        // Day first
+        assert_eq!(parsed("31 December 2029").as_deref(), Some("2029-12-31"));
+        assert_eq!(parsed("8 Jun. 2023").as_deref(), Some("2023-06-08"));
+        assert_eq!(parsed("1st March, 2026").as_deref(), Some("2026-03-01"));
+        // Extra whitespace
+        assert_eq!(parsed("  Nov \t 26 ,\n 2023 ").as_deref(), Some("2023-11-26"));
+        // ISO date inside other text
+        assert_eq!(parsed("EOL: 2029-12-31 (estimated)").as_deref(), Some("2029-12-31"));
+        assert_eq!(parsed("Released on Nov 21, 2023 at 10:00").as_deref(), Some("2023-11-21"));
+
+        // Unparseable or impossible dates are None, not a wrong date
+        for text in ["", "TBD", "December 2029", "Feb 30, 2025", "2025-13-01", "Dec 31, 29", "Dec 32, 2029", "Foo 12, 2029", "12345-12-31"] {
+            assert_eq!(parsed(text), None, "{:?}", text);
+        }
+        assert_eq!(parsed("Feb 29, 2024").as_deref(), Some("2024-02-29"));
+    }
+
+    #[test]
+    fn test_decode_body() {
+        let page = b"<a href=\"php-8.3.29-Win32-vs16-x64.zip\">caf\xe9</a>";
+
+        // Mislabelled (or unlabelled) Latin-1 still yields the version, lossily
+        let (text, lossy) = Provider::decode_body(page, Some("text/html; charset=UTF-8"));
+        assert!(lossy);
+        assert!(text.contains("php-8.3.29-Win32"));
+        assert!(text.contains('\u{FFFD}'));
+
+        let (text, lossy) = Provider::decode_body(page, Some("text/html; charset=\"ISO-8859-1\""));
+        assert!(!lossy);
+        assert!(text.ends_with("caf\u{e9}</a>"));
+
+        let (text, lossy) = Provider::decode_body("8.4.16 – ok".as_bytes(), None);
+        assert!(!lossy);
+        assert_eq!(text, "8.4.16 – ok");
+    }
+
+    #[test]
+    fn test_parse_directory_listing() {
+        let html = r#"
+            <a href="/downloads/releases/archives/php-8.1.10-Win32-vs16-x64.zip">php-8.1.10-Win32-vs16-x64.zip</a>
+            <a href="/downloads/releases/archives/php-8.1.10-nts-Win32-vs16-x64.zip">php-8.1.10-nts-Win32-vs16-x64.zip</a>
+            <a href="/downloads/releases/archives/php-8.1.0-Win32-vs16-x64.zip">php-8.1.0-Win32-vs16-x64.zip</a>
+            <a href="/downloads/releases/archives/php-8.0.30-Win32-vs16-x64.zip">php-8.0.30-Win32-vs16-x64.zip</a>
+            <a href="/downloads/releases/archives/php-7.3.5-Win32-VC15-x64.zip">php-7.3.5-Win32-VC15-x64.zip</a>
+        "#;
+        let mut parser = DirectoryListingParser::new();
+        parser.feed(html.as_bytes());
+        let versions = parser.finish(ARCHIVES_URL, &HashMap::new());
+        let names: Vec<&str> = versions.iter().map(|v| v.version.as_str()).collect();
+        assert_eq!(names, vec!["8.1.10", "8.1.0", "8.0.30", "7.3.5"]);
+        assert_eq!(
+            versions[3].download_url.as_deref(),
+            Some("https://windows.php.net/downloads/releases/archives/php-7.3.5-Win32-VC15-x64.zip")
+        );
+    }
+
+    #[test]
+    fn test_directory_listing_parser_handles_split_chunks() {
+        let html = r#"<a href="php-8.3.10-Win32-vs16-x64.zip">php-8.3.10-Win32-vs16-x64.zip</a>
+            <a href="php-8.3.10-Win32-vs17-x64.zip">php-8.3.10-Win32-vs17-x64.zip</a>
+            <a href="php-8.2.9-nts-Win32-vs16-x64.zip">php-8.2.9-nts-Win32-vs16-x64.zip</a>
+            <a href="php-8.2.9-Win32-vs16-x64.zip">php-8.2.9-Win32-vs16-x64.zip</a>
+            <a href="php-7.4.33-Win32-vc15-x64.zip">php-7.4.33-Win32-vc15-x64.zip</a>"#;
+        let files = |versions: Vec<VersionInfo>| -> Vec<(String, Option<String>)> {
+            versions.into_iter().map(|v| (v.version, v.download_url)).collect()
+        };
+
+        let mut parser = DirectoryListingParser::new();
+        parser.feed(html.as_bytes());
+        let whole = files(parser.finish(RELEASES_URL, &HashMap::new()));
+        let names: Vec<&str> = whole.iter().map(|(version, _)| version.as_str()).collect();
+        assert_eq!(names, vec!["8.3.10", "8.2.9", "7.4.33"]);
+        assert_eq!(
+            whole[0].1.as_deref(),
+            Some("https://windows.php.net/downloads/releases/php-8.3.10-Win32-vs17-x64.zip")
+        );
+
+        // Every chunk size splits some file name; the result must not change
+        for size in [1, 3, 7, 16, 50, 129] {
+            let mut parser = DirectoryListingParser::new();
+            for chunk in html.as_bytes().chunks(size) {
+                parser.feed(chunk);
+                assert!(parser.pending.len() <= LISTING_OVERLAP + size);
+            }
+            assert_eq!(files(parser.finish(RELEASES_URL, &HashMap::new())), whole, "chunk size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_archived_download_url() {
+        assert_eq!(
+            Provider::archived_download_url("8.1.10", 8, 1, false).as_deref(),
+            Some("https://windows.php.net/downloads/releases/archives/php-8.1.10-nts-Win32-vs16-x64.zip")
+        );
+        assert_eq!(Provider::archived_download_url("7.3.33", 7, 3, true), None);
+    }
+
+    #[test]
+    fn test_changelog_url() {
+        assert_eq!(Provider::changelog_url(8).as_deref(), Some("https://www.php.net/ChangeLog-8.php"));
+        assert_eq!(Provider::changelog_url(5).as_deref(), Some("https://www.php.net/ChangeLog-5.php"));
+        assert_eq!(Provider::changelog_url(6), None);
+        assert_eq!(Provider::changelog_url(3), None);
+    }
+
+    #[test]
+    fn test_extract_changelog_section() {
+        let html = r#"
+<section class="version" id="8.3.1"><!-- {{{ 8.3.1 -->
+<h3>Version 8.3.1</h3>
+<b><time class='releasedate' datetime='2023-12-21'>21 Dec 2023</time></b>
+<ul><li>Core:
+<ul>
+  <li>Fixed bug <a href="https://github.com/php/php-src/issues/12758">GH-12758</a> (gc_status() &amp; friends).</li>
+  <li>Fixed oss-fuzz #54325
+    (Stack-use-after-return with
+    &lt;generator&gt;).</li>
+</ul></li>
+<li>Date:
+<ul>
+  <li>Fixed &quot;relative&quot; times.</li>
+</ul></li>
+</ul>
+<!-- }}} --></section>
+<section class="version" id="8.3.0"><h3>Version 8.3.0</h3><ul><li>Core:<ul><li>Other</li></ul></li></ul></section>
+"#;
+        assert_eq!(
+            Provider::extract_changelog_section(html, "8.3.1").unwrap(),
+            "Version 8.3.1\n21 Dec 2023\nCore:\n- Fixed bug GH-12758 (gc_status() & friends).\n\
+             - Fixed oss-fuzz #54325 (Stack-use-after-return with <generator>).\nDate:\n- Fixed \"relative\" times."
+        );
+        assert_eq!(
+            Provider::extract_changelog_section(html, "8.3.0").unwrap(),
+            "Version 8.3.0\nCore:\n- Other"
+        );
+        // 8.3.10 must not match the 8.3.1 section, and unknown versions have none
+        assert_eq!(Provider::extract_changelog_section(html, "8.3.10"), None);
+    }
+
+    #[test]
+    fn test_parse_sha256sums() {
+        let hash = "a".repeat(64);
+        let content = format!(
+            "SHA256(php-8.3.0-Win32-vs16-x64.zip)= {}\n{}  php-8.2.0-Win32-vs16-x64.zip\n{} *php-8.1.0-Win32-vs16-x64.zip\nnot a checksum line\n",
+            hash.to_uppercase(), hash, hash
+        );
+        let checksums = Provider::parse_sha256sums(&content);
+
+        assert_eq!(checksums.len(), 3);
+        assert_eq!(checksums.get("php-8.3.0-Win32-vs16-x64.zip"), Some(&hash));
+        assert_eq!(checksums.get("php-8.2.0-Win32-vs16-x64.zip"), Some(&hash));
+        assert_eq!(checksums.get("php-8.1.0-Win32-vs16-x64.zip"), Some(&hash));
+    }
+
+    #[test]
+    fn test_fallback_versions_have_download_urls() {
+        let versions = Provider::fallback_versions();
+        assert!(!versions.is_empty());
+        assert!(versions.iter().all(|v| v.download_url.is_some()));
+    }
+
+    #[test]
+    fn test_group_by_branch() {
+        let info = |version: &str, eol: Option<&str>| VersionInfo {
+            version: version.to_string(),
+            release_date: None,
+            eol_date: eol.map(|s| s.to_string()),
+            active_support_until: None,
+            download_url: None,
+            checksum: None,
+        };
+        let versions = vec![
+            info("8.2.10", None),
+            info("8.3.29", Some("2027-12-31")),
+            info("8.2.30", Some("2026-12-31")),
+            info("7.4.33", Some("2022-11-28")),
+        ];
+        let installed = vec!["8.2.10-ts".to_string(), "8.3.29-nts".to_string()];
+
+        let branches = Provider::group_by_branch(&versions, &installed, "2026-06-01");
+        assert_eq!(branches.len(), 3);
+
+        assert_eq!((branches[0].major, branches[0].minor), (8, 3));
+        assert!(!branches[0].is_eol);
+        assert!(!branches[0].update_available);
+
+        assert_eq!((branches[1].major, branches[1].minor), (8, 2));
+        assert_eq!(branches[1].latest_patch, "8.2.30");
+        assert_eq!(branches[1].all_patches, vec!["8.2.30", "8.2.10"]);
+        assert_eq!(branches[1].installed, vec!["8.2.10-ts"]);
+        assert!(branches[1].update_available);
+
+        assert_eq!((branches[2].major, branches[2].minor), (7, 4));
+        assert!(branches[2].is_eol);
+        assert!(branches[2].installed.is_empty());
+    }
+
+    #[test]
+    fn test_parse_versionlog_html_reports_scrape_health() {
+        let html = r#"<table>
+            <tr><td><a href="/php/8.3/">PHP 8.3</a></td><td>8.3.29</td>
+                <td>Latest patch release: December 18, 2025</td>
+                <td>End of security fixes: December 31, 2027</td></tr>
+            <tr><td><a href="/php/8.2/">PHP 8.2</a></td><td>8.2.30</td>
+                <td>Latest patch release: 2025-12-18</td></tr>
+        </table>"#;
+        let (versions, health) = Provider::parse_versionlog_html(html);
+        assert_eq!(versions.iter().map(|v| v.version.as_str()).collect::<Vec<_>>(), vec!["8.3.29", "8.2.30"]);
+        assert_eq!(versions[0].release_date.as_deref(), Some("2025-12-18"));
+        assert_eq!((health.total, health.dated), (2, 2));
+        assert!(!health.is_degraded());
+
+        // Redesigned page: versions are still found, but no row matches the selectors
+        let redesigned = r#"<div class="release" data-branch="8.3">8.3.29 <time>2025-12-18</time></div>
+            <div class="release" data-branch="8.2">8.2.30 <time>2025-12-18</time></div>"#;
+        let (versions, health) = Provider::parse_versionlog_html(redesigned);
+        assert_eq!(versions.len(), 2);
+        // Built-in EOL dates still fill in
+        assert_eq!(versions[0].eol_date.as_deref(), Some("2027-12-31"));
+        assert_eq!((health.total, health.dated), (2, 0));
+        assert!(health.is_degraded());
+
+        assert!(Provider::parse_versionlog_html("<html></html>").1.is_degraded());
+    }
+
+    #[test]
+    fn test_detect_thread_safe_from_real_filenames() {
+        let cases = [
+            ("php-8.3.0-Win32-vs16-x64.zip", true),
+            ("php-8.3.0-nts-Win32-vs16-x64.zip", false),
+            ("php-8.4.1-Win32-vs17-x64.zip", true),
+            ("php-8.4.1-nts-Win32-vs17-x64.zip", false),
+            ("php-7.4.33-Win32-vc15-x64.zip", true),
+            ("php-7.4.33-nts-Win32-vc15-x64.zip", false),
+            ("php-debug-pack-8.3.0-nts-Win32-vs16-x64.zip", false),
+            ("php-devel-pack-8.3.0-Win32-vs16-x64.zip", true),
+        ];
+        for (filename, thread_safe) in cases {
+            assert_eq!(Provider::detect_thread_safe_from_filename(filename), Some(thread_safe), "{}", filename);
+            for url in [
+                format!("https://windows.php.net/downloads/releases/{}", filename),
+                format!("https://windows.php.net/downloads/releases/archives/{}?mirror=1", filename),
+            ] {
+                assert_eq!(Provider::detect_thread_safe_from_url(&url), Some(thread_safe), "{}", url);
+            }
+        }
+        // Only the file name counts, not the host or directories
+        assert_eq!(
+            Provider::detect_thread_safe_from_url("https://mirror.example/nts/php-8.3.0-Win32-vs16-x64.zip"),
+            Some(true)
+        );
+        assert_eq!(Provider::detect_thread_safe_from_filename("PHP-8.3.0-NTS-WIN32-VS16-X64.ZIP"), Some(false));
+    }
+
+    #[test]
+    fn test_find_patch_updates_same_branch_only() {
+        let info = |version: &str| VersionInfo {
+            version: version.to_string(),
+            release_date: None,
+            eol_date: None,
+            active_support_until: None,
+            download_url: None,
+            checksum: None,
+        };
+        let versions = vec![info("8.3.29"), info("8.2.30"), info("8.2.15")];
+        let installed = vec![
+            "8.2.10-ts".to_string(),
+            "8.2.10-nts".to_string(),
+            "8.3.29-ts".to_string(),
+            "8.1.2-ts".to_string(),
+        ];
+
+        let updates = Provider::find_patch_updates(&versions, &installed);
+        assert_eq!(
+            updates,
+            vec![
+                PatchUpdate { installed: "8.2.10".to_string(), latest: "8.2.30".to_string(), variant: "ts".to_string() },
+                PatchUpdate { installed: "8.2.10".to_string(), latest: "8.2.30".to_string(), variant: "nts".to_string() },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_is_url_available_uses_cache() {
+        let url = "https://phpvm.invalid/php-8.3.0-Win32-vs16-x64.zip";
+        availability_cache()
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), (true, Instant::now()));
+
+        let provider = Provider::new().unwrap();
+        assert!(provider.is_url_available(url).await);
+
+        let results = provider.check_urls_available(&[url.to_string()]).await;
+        assert_eq!(results.get(url), Some(&true));
+    }
+
     #[test]
     fn test_provider_new() {
         let _provider = Provider::new().unwrap();
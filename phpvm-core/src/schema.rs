@@ -0,0 +1,93 @@
+use serde_json::{Map, Value};
+
+/// Schema of config.json this version writes. Files without a `schema_version` are
+/// version 1, written before the field existed.
+pub const CONFIG_SCHEMA_VERSION: u32 = 2;
+
+/// Schema of state.json this version writes; see `CONFIG_SCHEMA_VERSION`
+pub const STATE_SCHEMA_VERSION: u32 = 2;
+
+/// `schema_version` for files that have none
+pub(crate) fn legacy_schema_version() -> u32 {
+    1
+}
+
+/// One upgrade step, from the version it's indexed at to the next. Gets the file's JSON
+/// object and the defaults of the current schema.
+type Step = fn(&mut Map<String, Value>, &Map<String, Value>);
+
+/// v1 -> v2: write out fields added after v1 (mirrors, switch strategy, timeouts, pins)
+/// with the values serde would have defaulted them to, so the file says what's in effect
+fn fill_missing_defaults(object: &mut Map<String, Value>, defaults: &Map<String, Value>) {
+    for (key, value) in defaults {
+        if !object.contains_key(key) {
+            object.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+const CONFIG_STEPS: [Step; 1] = [fill_missing_defaults];
+const STATE_STEPS: [Step; 1] = [fill_missing_defaults];
+
+/// Upgrade config.json content to `CONFIG_SCHEMA_VERSION` in place. `defaults` is a
+/// default config serialized. Returns whether anything changed; files from a newer
+/// phpvm are left alone.
+pub fn migrate_config(value: &mut Value, defaults: &Value) -> anyhow::Result<bool> {
+    migrate(value, defaults, &CONFIG_STEPS, CONFIG_SCHEMA_VERSION, "config.json")
+}
+
+/// Upgrade state.json content to `STATE_SCHEMA_VERSION` in place; see `migrate_config`
+pub fn migrate_state(value: &mut Value, defaults: &Value) -> anyhow::Result<bool> {
+    migrate(value, defaults, &STATE_STEPS, STATE_SCHEMA_VERSION, "state.json")
+}
+
+fn migrate(value: &mut Value, defaults: &Value, steps: &[Step], current: u32, file: &str) -> anyhow::Result<bool> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow::anyhow!("{} is not a JSON object", file))?;
+    let defaults = defaults.as_object().cloned().unwrap_or_default();
+    let from = match object.get("schema_version") {
+        None => legacy_schema_version(),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v >= 1)
+            .ok_or_else(|| anyhow::anyhow!("{} has an invalid schema_version: {}", file, version))?,
+    };
+    if from >= current {
+        return Ok(false);
+    }
+
+    for step in &steps[(from - 1) as usize..(current - 1) as usize] {
+        step(object, &defaults);
+    }
+    object.insert("schema_version".to_string(), Value::from(current));
+    crate::logging::log_message("INFO", &format!("Upgraded {} from schema {} to {}", file, from, current));
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_migrate_fills_defaults_and_keeps_values() {
+        let defaults = json!({ "schema_version": 2, "manage_path": true, "update_mirrors": [] });
+        let mut value = json!({ "manage_path": false, "install_dir": "/opt/phpvm/versions" });
+        assert!(migrate_config(&mut value, &defaults).unwrap());
+        assert_eq!(
+            value,
+            json!({ "schema_version": 2, "manage_path": false, "update_mirrors": [], "install_dir": "/opt/phpvm/versions" })
+        );
+
+        // Current and newer files are left as they are
+        assert!(!migrate_config(&mut value, &defaults).unwrap());
+        let mut newer = json!({ "schema_version": 7 });
+        assert!(!migrate_state(&mut newer, &defaults).unwrap());
+        assert_eq!(newer, json!({ "schema_version": 7 }));
+
+        assert!(migrate_state(&mut json!({ "schema_version": "two" }), &defaults).is_err());
+        assert!(migrate_state(&mut json!([]), &defaults).is_err());
+    }
+}
@@ -5,10 +5,19 @@ use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PhpState {
+    /// Layout of this file; older ones are upgraded by `load`. See `schema::migrate_state`.
+    #[serde(default = "crate::schema::legacy_schema_version")]
+    pub schema_version: u32,
     pub installed_versions: Vec<String>,
+    /// The install the `current` shim runs, with its variant ("8.3.0-ts"). The one source
+    /// of truth for the active version; `Config::active_version` only mirrors its base.
     pub active_version: Option<String>,
     pub last_known_good: Option<String>,
     pub install_metadata: HashMap<String, InstallMetadata>,
+    /// Installed versions (e.g. "8.1.34-nts") to keep as they are: skipped by pruning
+    /// and by patch update checks
+    #[serde(default)]
+    pub pinned_versions: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -18,6 +27,10 @@ pub struct InstallMetadata {
     pub installed_at: String,
     pub checksum: Option<String>,
     pub source: String,
+    /// Lowercase compiler tag the build was made with ("vs16", "vc15", ...).
+    /// Missing for installs made before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiler: Option<String>,
 }
 
 impl PhpState {
@@ -25,7 +38,13 @@ impl PhpState {
         let state_path = config::get_state_path();
         if state_path.exists() {
             let content = std::fs::read_to_string(&state_path)?;
-            Ok(serde_json::from_str(&content)?)
+            let mut value: serde_json::Value = serde_json::from_str(&content)?;
+            let migrated = crate::schema::migrate_state(&mut value, &serde_json::to_value(PhpState::default())?)?;
+            let state: PhpState = serde_json::from_value(value)?;
+            if migrated {
+                state.save()?;
+            }
+            Ok(state)
         } else {
             Ok(PhpState::default())
         }
@@ -54,6 +73,52 @@ impl PhpState {
         if self.active_version.as_deref() == Some(version) {
             self.active_version = self.last_known_good.clone();
         }
+        if self.last_known_good.as_deref() == Some(version) {
+            self.last_known_good = None;
+        }
+        self.pinned_versions.retain(|v| v != version);
+    }
+
+    /// Move every reference to `old` (list, active, last known good, pin, metadata)
+    /// over to `new`, whose files now live in `install_path`
+    pub fn rename_version(&mut self, old: &str, new: &str, install_path: &std::path::Path) {
+        let rename = |v: &mut String| {
+            if v == old {
+                *v = new.to_string();
+            }
+        };
+        self.installed_versions.iter_mut().for_each(rename);
+        if !self.installed_versions.iter().any(|v| v == new) {
+            self.installed_versions.push(new.to_string());
+        }
+        self.active_version.iter_mut().for_each(rename);
+        self.last_known_good.iter_mut().for_each(rename);
+        self.pinned_versions.iter_mut().for_each(rename);
+        if let Some(mut metadata) = self.install_metadata.remove(old) {
+            metadata.version = new.to_string();
+            metadata.install_path = install_path.to_path_buf();
+            self.install_metadata.insert(new.to_string(), metadata);
+        }
+    }
+
+    /// Returns false if it was already pinned
+    pub fn pin_version(&mut self, version: &str) -> bool {
+        if self.is_pinned(version) {
+            return false;
+        }
+        self.pinned_versions.push(version.to_string());
+        true
+    }
+
+    /// Returns false if it wasn't pinned
+    pub fn unpin_version(&mut self, version: &str) -> bool {
+        let before = self.pinned_versions.len();
+        self.pinned_versions.retain(|v| v != version);
+        self.pinned_versions.len() != before
+    }
+
+    pub fn is_pinned(&self, version: &str) -> bool {
+        self.pinned_versions.iter().any(|v| v == version)
     }
 
     pub fn set_active(&mut self, version: String) {
@@ -71,10 +136,12 @@ impl PhpState {
 impl Default for PhpState {
     fn default() -> Self {
         PhpState {
+            schema_version: crate::schema::STATE_SCHEMA_VERSION,
             installed_versions: Vec::new(),
             active_version: None,
             last_known_good: None,
             install_metadata: HashMap::new(),
+            pinned_versions: Vec::new(),
         }
     }
 }
@@ -102,6 +169,7 @@ mod tests {
             installed_at: "2024-01-01".to_string(),
             checksum: Some("abc123".to_string()),
             source: "official".to_string(),
+            compiler: None,
         };
 
         state.add_version("8.2.0".to_string(), metadata.clone());
@@ -120,6 +188,7 @@ mod tests {
             installed_at: "2024-01-01".to_string(),
             checksum: None,
             source: "official".to_string(),
+            compiler: None,
         };
         let metadata2 = InstallMetadata {
             version: "8.2.0".to_string(),
@@ -127,6 +196,7 @@ mod tests {
             installed_at: "2024-01-02".to_string(),
             checksum: None,
             source: "official".to_string(),
+            compiler: None,
         };
 
         state.add_version("8.2.0".to_string(), metadata1);
@@ -147,6 +217,7 @@ mod tests {
             installed_at: "2024-01-01".to_string(),
             checksum: None,
             source: "official".to_string(),
+            compiler: None,
         };
 
         state.add_version("8.2.0".to_string(), metadata);
@@ -157,6 +228,13 @@ mod tests {
         assert!(state.install_metadata.get("8.2.0").is_none());
         // Active version should be reset to last_known_good
         assert_eq!(state.active_version, None);
+
+        // A removed version is never kept as the one to fall back to
+        state.set_active("8.1.0".to_string());
+        state.set_active("8.3.0".to_string());
+        state.remove_version("8.1.0");
+        assert_eq!(state.active_version, Some("8.3.0".to_string()));
+        assert_eq!(state.last_known_good, None);
     }
 
     #[test]
@@ -181,6 +259,7 @@ mod tests {
             installed_at: "2024-01-01".to_string(),
             checksum: Some("abc123".to_string()),
             source: "official".to_string(),
+            compiler: None,
         };
 
         state.add_version("8.2.0".to_string(), metadata.clone());
@@ -198,6 +277,7 @@ mod tests {
             installed_at: "2024-01-01".to_string(),
             checksum: None,
             source: "official".to_string(),
+            compiler: None,
         };
         let metadata2 = InstallMetadata {
             version: "8.1.0".to_string(),
@@ -205,6 +285,7 @@ mod tests {
             installed_at: "2024-01-01".to_string(),
             checksum: None,
             source: "official".to_string(),
+            compiler: None,
         };
 
         state.add_version("8.1.0".to_string(), metadata2);
@@ -216,4 +297,28 @@ mod tests {
         state.remove_version("8.2.0");
         assert_eq!(state.active_version, Some("8.1.0".to_string()));
     }
+
+    #[test]
+    fn test_pin_and_unpin() {
+        let mut state = PhpState::default();
+        assert!(state.pin_version("8.1.34-nts"));
+        assert!(!state.pin_version("8.1.34-nts"));
+        assert!(state.is_pinned("8.1.34-nts"));
+        assert!(!state.is_pinned("8.1.34-ts"));
+
+        assert!(state.unpin_version("8.1.34-nts"));
+        assert!(!state.unpin_version("8.1.34-nts"));
+
+        // Removing a version drops its pin
+        state.pin_version("8.2.0-ts");
+        state.remove_version("8.2.0-ts");
+        assert!(state.pinned_versions.is_empty());
+    }
+
+    #[test]
+    fn test_state_without_pins_deserializes() {
+        let json = r#"{"installed_versions":["8.3.0-ts"],"active_version":null,"last_known_good":null,"install_metadata":{}}"#;
+        let state: PhpState = serde_json::from_str(json).unwrap();
+        assert!(state.pinned_versions.is_empty());
+    }
 }
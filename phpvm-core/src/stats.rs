@@ -0,0 +1,135 @@
+use crate::config;
+use crate::logging;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Lifetime usage counters kept in `stats.json`, for the user's own information.
+/// Nothing here is ever sent over the network.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageStats {
+    pub installs: u64,
+    pub switches: u64,
+    pub removals: u64,
+    /// Bytes fetched by live downloads (cache hits don't count)
+    pub bytes_downloaded: u64,
+    /// Number of switches to each version, e.g. "8.3.0-nts"
+    pub switches_by_version: BTreeMap<String, u64>,
+}
+
+/// `UsageStats` plus what's derived from it, as returned to the GUI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageReport {
+    #[serde(flatten)]
+    pub stats: UsageStats,
+    pub most_used_version: Option<String>,
+}
+
+pub enum UsageEvent<'a> {
+    Install,
+    Switch(&'a str),
+    Remove,
+    Downloaded(u64),
+}
+
+/// Serializes read-modify-write cycles within the process so increments aren't lost
+static STATS_LOCK: Mutex<()> = Mutex::new(());
+
+impl UsageStats {
+    /// Missing or unreadable stats start over from zero rather than failing
+    pub fn load() -> Self {
+        let path = config::get_stats_path();
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return UsageStats::default();
+        };
+        serde_json::from_str(&content).unwrap_or_else(|e| {
+            logging::log_message("WARN", &format!("Ignoring corrupt {:?}: {}", path, e));
+            UsageStats::default()
+        })
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = config::get_stats_path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Write then rename, so a crash mid-write can't leave a truncated file
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&temp_path, &path)?;
+        Ok(())
+    }
+
+    pub fn apply(&mut self, event: UsageEvent) {
+        match event {
+            UsageEvent::Install => self.installs += 1,
+            UsageEvent::Switch(version) => {
+                self.switches += 1;
+                *self.switches_by_version.entry(version.to_string()).or_insert(0) += 1;
+            }
+            UsageEvent::Remove => self.removals += 1,
+            UsageEvent::Downloaded(bytes) => self.bytes_downloaded += bytes,
+        }
+    }
+
+    /// Version switched to most often (the lowest version string on a tie)
+    pub fn most_used_version(&self) -> Option<&str> {
+        self.switches_by_version
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(version, _)| version.as_str())
+    }
+
+    pub fn report(self) -> UsageReport {
+        let most_used_version = self.most_used_version().map(str::to_string);
+        UsageReport { stats: self, most_used_version }
+    }
+}
+
+/// Count an event. Failures are only logged: stats must never break the operation
+/// being counted.
+pub fn record(event: UsageEvent) {
+    let _guard = STATS_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let mut stats = UsageStats::load();
+    stats.apply(event);
+    if let Err(e) = stats.save() {
+        logging::log_message("WARN", &format!("Failed to save usage stats: {}", e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_and_most_used_version() {
+        let mut stats = UsageStats::default();
+        assert_eq!(stats.most_used_version(), None);
+
+        stats.apply(UsageEvent::Install);
+        stats.apply(UsageEvent::Switch("8.3.0-nts"));
+        stats.apply(UsageEvent::Switch("8.2.10-ts"));
+        stats.apply(UsageEvent::Switch("8.3.0-nts"));
+        stats.apply(UsageEvent::Downloaded(1024));
+        stats.apply(UsageEvent::Downloaded(2048));
+        stats.apply(UsageEvent::Remove);
+
+        assert_eq!(stats.installs, 1);
+        assert_eq!(stats.switches, 3);
+        assert_eq!(stats.removals, 1);
+        assert_eq!(stats.bytes_downloaded, 3072);
+        assert_eq!(stats.most_used_version(), Some("8.3.0-nts"));
+
+        let report = serde_json::to_value(stats.report()).unwrap();
+        assert_eq!(report["most_used_version"], "8.3.0-nts");
+        assert_eq!(report["switches"], 3);
+    }
+
+    #[test]
+    fn test_old_or_partial_file_deserializes() {
+        let stats: UsageStats = serde_json::from_str(r#"{ "installs": 4 }"#).unwrap();
+        assert_eq!(stats.installs, 4);
+        assert!(stats.switches_by_version.is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::error::PhpVmError;
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -9,6 +11,27 @@ pub struct PhpVersion {
     pub suffix: Option<String>,
 }
 
+/// Reject anything but "X.Y.Z" with an optional pre-release ("-rc1", "-beta2") and/or
+/// variant ("-ts", "-nts") suffix. Version strings become directory names under the
+/// install dir, so this must run before any path is built from user input.
+pub fn validate_version_str(s: &str) -> anyhow::Result<()> {
+    let re = regex::Regex::new(r"^\d{1,3}\.\d{1,3}\.\d{1,3}(-(alpha|beta|rc|RC)\d+)?(-(ts|nts))?$")?;
+    if !re.is_match(s) {
+        return Err(PhpVmError::InvalidVersion(format!("Invalid version: {:?}", s)).into());
+    }
+    Ok(())
+}
+
+/// Like `validate_version_str`, but also accepts a bare branch ("8.2") or major ("8"),
+/// optionally with a variant ("8.2-nts"), for commands that pick the newest match
+pub fn validate_version_spec(s: &str) -> anyhow::Result<()> {
+    let re = regex::Regex::new(r"^\d{1,3}(\.\d{1,3})?(-(ts|nts))?$")?;
+    if re.is_match(s) {
+        return Ok(());
+    }
+    validate_version_str(s)
+}
+
 impl PhpVersion {
     pub fn new(major: u8, minor: u8, patch: u8) -> Self {
         PhpVersion {
@@ -22,13 +45,14 @@ impl PhpVersion {
     pub fn from_string(s: &str) -> anyhow::Result<Self> {
         let parts: Vec<&str> = s.split('.').collect();
         if parts.len() < 3 {
-            anyhow::bail!("Invalid version format: {}", s);
+            return Err(PhpVmError::InvalidVersion(format!("Invalid version format: {}", s)).into());
         }
 
-        let major = parts[0].parse()?;
-        let minor = parts[1].parse()?;
+        let invalid = || PhpVmError::InvalidVersion(format!("Invalid version format: {}", s));
+        let major = parts[0].parse().with_context(invalid)?;
+        let minor = parts[1].parse().with_context(invalid)?;
         let patch_parts: Vec<&str> = parts[2].split('-').collect();
-        let patch = patch_parts[0].parse()?;
+        let patch = patch_parts[0].parse().with_context(invalid)?;
         let suffix = if patch_parts.len() > 1 {
             Some(patch_parts[1..].join("-"))
         } else {
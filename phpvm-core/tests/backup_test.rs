@@ -0,0 +1,101 @@
+/**
+ * Integration test for backing up and restoring the base directory
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+use phpvm_core::backup::{backup_all, restore_all, BACKUP_MANIFEST_FILE};
+use phpvm_core::config::{Config, PHPVM_HOME_ENV};
+use phpvm_core::{InstallMetadata, PhpState, PhpVmError};
+use std::fs;
+use std::io::Write;
+use tempfile::TempDir;
+
+#[test]
+fn test_backup_and_restore_to_another_base_directory() {
+    let old_home = TempDir::new().unwrap();
+    let new_home = TempDir::new().unwrap();
+    let backups = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, old_home.path());
+
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let install_path = config.install_dir.join("php-8.3.0-ts");
+    fs::create_dir_all(&install_path).unwrap();
+    fs::write(install_path.join("php.ini"), "memory_limit=1G").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::write(install_path.join("php"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(install_path.join("php"), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    fs::create_dir_all(old_home.path().join("cache")).unwrap();
+    fs::write(old_home.path().join("cache").join("php.zip"), "archive").unwrap();
+    fs::create_dir_all(old_home.path().join("logs")).unwrap();
+    fs::write(old_home.path().join("logs").join("phpvm.log"), "log line").unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    state.add_version(
+        "8.3.0-ts".to_string(),
+        InstallMetadata {
+            version: "8.3.0-ts".to_string(),
+            install_path: install_path.clone(),
+            installed_at: "2024-01-01".to_string(),
+            checksum: None,
+            source: "official".to_string(),
+            compiler: None,
+        },
+    );
+    state.save().unwrap();
+
+    // A backup written inside the base directory must not include itself
+    let inside = old_home.path().join("before-reset.zip");
+    let report = backup_all(&inside, false, false).unwrap();
+    let archive = zip::ZipArchive::new(fs::File::open(&inside).unwrap()).unwrap();
+    let names: Vec<&str> = archive.file_names().collect();
+    assert!(names.contains(&"config.json") && names.contains(&"state.json") && names.contains(&"logs/phpvm.log"));
+    assert!(names.contains(&BACKUP_MANIFEST_FILE));
+    assert!(!names.iter().any(|name| name.starts_with("versions/") || name.starts_with("cache/") || name.contains(".zip")));
+    assert_eq!(report.files, names.len() - 1);
+
+    // Nor one written into it earlier
+    let full = backups.path().join("full.zip");
+    backup_all(&full, true, true).unwrap();
+    let archive = zip::ZipArchive::new(fs::File::open(&full).unwrap()).unwrap();
+    assert!(!archive.file_names().any(|name| name == "before-reset.zip"));
+
+    std::env::set_var(PHPVM_HOME_ENV, new_home.path());
+    let report = restore_all(&full).unwrap();
+    assert!(report.rewritten_paths >= 3, "install_dir, download_cache and one install_path");
+    assert_eq!(
+        fs::read_to_string(new_home.path().join("versions").join("php-8.3.0-ts").join("php.ini")).unwrap(),
+        "memory_limit=1G"
+    );
+    assert!(new_home.path().join("cache").join("php.zip").exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let restored_php = new_home.path().join("versions").join("php-8.3.0-ts").join("php");
+        assert_eq!(fs::metadata(restored_php).unwrap().permissions().mode() & 0o777, 0o755);
+    }
+
+    let restored = Config::load().unwrap();
+    assert_eq!(restored.install_dir, new_home.path().join("versions"));
+    assert!(!restored.manage_path);
+    let state = PhpState::load().unwrap();
+    assert_eq!(
+        state.get_metadata("8.3.0-ts").unwrap().install_path,
+        new_home.path().join("versions").join("php-8.3.0-ts")
+    );
+
+    // Archives without state.json are refused before anything is written
+    let bogus = backups.path().join("bogus.zip");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&bogus).unwrap());
+    zip.start_file("config.json", zip::write::FileOptions::default()).unwrap();
+    zip.write_all(b"{\"manage_path\": true}").unwrap();
+    zip.finish().unwrap();
+    let error = PhpVmError::from(restore_all(&bogus).unwrap_err());
+    assert!(matches!(error, PhpVmError::InvalidArgument(_)), "{:?}", error);
+    assert!(!Config::load().unwrap().manage_path);
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
@@ -0,0 +1,86 @@
+/**
+ * Integration test for per-provider checksum verification of downloaded archives
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+mod common;
+
+use common::synthetic_php_archive;
+use phpvm_core::config::{Config, ProviderConfig, PHPVM_HOME_ENV};
+use phpvm_core::install::Installer;
+use phpvm_core::{PhpState, PhpVersion, PhpVmError};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn use_mirror(url: String, verify_checksum: bool) {
+    let mut config = Config::load().unwrap();
+    config.providers = vec![ProviderConfig { name: "mirror".to_string(), url, verify_checksum }];
+    config.save().unwrap();
+}
+
+#[tokio::test]
+async fn test_verify_checksum_is_enforced_or_skipped_per_provider() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let archive = synthetic_php_archive();
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/mirror/php-8.3.0-nts.zip"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(archive.clone()))
+        .mount(&server)
+        .await;
+    let mirror_url = format!("{}/mirror/", server.uri());
+    let source_url = format!("{}php-8.3.0-nts.zip", mirror_url);
+    let version = PhpVersion::from_string("8.3.0").unwrap();
+    let (version, source_url) = (&version, &source_url);
+    let install = |force_reinstall: bool| async move {
+        Installer::new()
+            .unwrap()
+            .install_version(version, Some(source_url), Some(false), force_reinstall, None)
+            .await
+    };
+
+    // verify_checksum on, but the mirror publishes no sha256sum.txt
+    use_mirror(mirror_url.clone(), true);
+    let error = PhpVmError::from(install(false).await.unwrap_err());
+    assert_eq!(error.code(), "CHECKSUM_MISMATCH", "{}", error.message());
+    assert!(error.message().contains("provider 'mirror'"), "{}", error.message());
+    assert!(PhpState::load().unwrap().installed_versions.is_empty());
+
+    // verify_checksum off: installs without asking for a checksum
+    let checksum_requests = || async {
+        let requests = server.received_requests().await.unwrap();
+        requests.iter().filter(|r| r.url.path().ends_with("sha256sum.txt")).count()
+    };
+    let before = checksum_requests().await;
+    use_mirror(mirror_url.clone(), false);
+    install(false).await.unwrap();
+    assert!(PhpState::load().unwrap().installed_versions.contains(&"8.3.0-nts".to_string()));
+    assert_eq!(checksum_requests().await, before);
+
+    // verify_checksum on with a published checksum that matches (the cached archive is checked too)
+    Mock::given(method("GET"))
+        .and(path("/mirror/sha256sum.txt"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            "{} *php-8.3.0-nts.zip\n",
+            hex::encode(Sha256::digest(&archive))
+        )))
+        .mount(&server)
+        .await;
+    use_mirror(mirror_url, true);
+    install(true).await.unwrap();
+
+    // With the checksums out of reach, the cached archive is trusted by the hash it was verified against
+    server.reset().await;
+    Mock::given(method("GET"))
+        .and(path("/mirror/sha256sum.txt"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    install(true).await.unwrap();
+    assert_eq!(checksum_requests().await, 0);
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
@@ -0,0 +1,53 @@
+/**
+ * Helpers shared by the integration test binaries
+ */
+use std::io::Write;
+
+const ARCHIVE_FILES: [&str; 4] = ["php.exe", "bin/php", "php8ts.dll", "php.ini-development"];
+
+/// Tiny stand-in for a PHP release archive in the format the platform extracts
+/// (zip on Windows, tar.gz elsewhere). Carries both layouts so
+/// `get_php_executable_path` finds an executable either way.
+#[cfg(target_os = "windows")]
+pub fn synthetic_php_archive() -> Vec<u8> {
+    let mut buffer = std::io::Cursor::new(Vec::new());
+    {
+        let mut zip = zip::ZipWriter::new(&mut buffer);
+        let options = zip::write::FileOptions::default();
+        for name in ARCHIVE_FILES {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(fake_contents(name).as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+    buffer.into_inner()
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn synthetic_php_archive() -> Vec<u8> {
+    let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+    let mut tar = tar::Builder::new(encoder);
+    for name in ARCHIVE_FILES {
+        let content = fake_contents(name);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        tar.append_data(&mut header, name, content.as_bytes()).unwrap();
+    }
+    let mut encoder = tar.into_inner().unwrap();
+    encoder.flush().unwrap();
+    encoder.finish().unwrap()
+}
+
+/// What `synthetic_php_archive` puts in the file at `name`
+fn fake_contents(name: &str) -> String {
+    format!("fake {}", name)
+}
+
+/// Contents of the php executable `get_php_executable_path` finds in an extracted
+/// `synthetic_php_archive`
+#[allow(dead_code)]
+pub fn fake_php_contents() -> String {
+    fake_contents(if cfg!(target_os = "windows") { "php.exe" } else { "bin/php" })
+}
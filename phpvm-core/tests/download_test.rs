@@ -0,0 +1,35 @@
+/**
+ * Integration tests for Downloader timeouts
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+use phpvm_core::config::PHPVM_HOME_ENV;
+use phpvm_core::download::Downloader;
+use phpvm_core::PhpVmError;
+use std::time::Duration;
+use tempfile::TempDir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_stalled_download_times_out_and_leaves_no_cache_file() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/php-8.3.0-Win32-vs16-x64.zip"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 16]).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let downloader = Downloader::with_timeouts(Duration::from_secs(1), Duration::from_millis(300)).unwrap();
+    let url = format!("{}/php-8.3.0-Win32-vs16-x64.zip", server.uri());
+    let error = downloader.download_file(&url, None, None).await.unwrap_err();
+
+    let error = PhpVmError::from(error);
+    assert_eq!(error.code(), "NETWORK");
+    assert!(error.message().contains("stalled"), "{}", error.message());
+    assert!(!downloader.is_cached(&url));
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
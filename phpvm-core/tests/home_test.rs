@@ -0,0 +1,39 @@
+/**
+ * Integration tests for the PHPVM_HOME override
+ * Runs in its own test binary, so pointing PHPVM_HOME at a temp dir
+ * doesn't leak into other test files
+ */
+use phpvm_core::config::{self, Config, PHPVM_HOME_ENV};
+use phpvm_core::{get_current_path, PhpState};
+use tempfile::TempDir;
+
+#[test]
+fn test_phpvm_home_relocates_all_paths() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    assert_eq!(config::get_base_directory(), temp_dir.path());
+    assert!(config::get_config_path().starts_with(temp_dir.path()));
+    assert!(config::get_state_path().starts_with(temp_dir.path()));
+    assert!(config::get_log_path().starts_with(temp_dir.path()));
+    assert!(get_current_path().starts_with(temp_dir.path()));
+
+    // Config and state round-trip inside the override directory
+    let config = Config::load().unwrap();
+    assert!(temp_dir.path().join("config.json").exists());
+    assert!(config.install_dir.starts_with(temp_dir.path()));
+    assert!(config.download_cache.starts_with(temp_dir.path()));
+
+    let mut state = PhpState::load().unwrap();
+    state.set_active("8.3.0-ts".to_string());
+    state.save().unwrap();
+    assert!(temp_dir.path().join("state.json").exists());
+    assert_eq!(PhpState::load().unwrap().active_version, Some("8.3.0-ts".to_string()));
+
+    // An empty value falls back to the default location
+    std::env::set_var(PHPVM_HOME_ENV, "");
+    assert!(config::get_base_directory().ends_with("phpvm"));
+    assert_ne!(config::get_base_directory(), temp_dir.path());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
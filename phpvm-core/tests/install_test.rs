@@ -0,0 +1,104 @@
+/**
+ * Integration tests for the install -> extract -> state -> remove flow
+ * Runs against a mock HTTP server with PHPVM_HOME pointed at a temp dir,
+ * so nothing touches the network or the real phpvm directory
+ */
+mod common;
+
+use common::synthetic_php_archive;
+use phpvm_core::config::{Config, PHPVM_HOME_ENV};
+use phpvm_core::download::Downloader;
+use phpvm_core::install::Installer;
+use phpvm_core::{get_php_executable_path, PhpManager, PhpState, PhpVersion};
+use tempfile::TempDir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Path component of a planned download URL, e.g. "/downloads/releases/php-8.3.0-nts-Win32-vs16-x64.zip"
+fn url_path(url: &str) -> String {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    without_scheme
+        .find('/')
+        .map(|i| without_scheme[i..].to_string())
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn test_install_and_remove_variants_against_mock_server() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let server = MockServer::start().await;
+    let manager = PhpManager::new().unwrap();
+    let installer = Installer::new().unwrap();
+    let version = PhpVersion::from_string("8.3.0").unwrap();
+
+    for (thread_safe, variant) in [(false, "nts"), (true, "ts")] {
+        // Serve the archive only at the path the provider would use for this variant,
+        // so a divergence between installer and provider URLs fails the install
+        let plan = manager.plan_install("8.3.0", Some(thread_safe)).unwrap();
+        let archive_path = url_path(&plan.download_url);
+        Mock::given(method("GET"))
+            .and(path(archive_path.as_str()))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(synthetic_php_archive()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let source_url = format!("{}{}", server.uri(), archive_path);
+        let install_path = installer
+            .install_version(&version, Some(&source_url), Some(thread_safe), false, None)
+            .await
+            .unwrap();
+
+        assert_eq!(install_path, plan.install_path);
+        assert!(install_path.ends_with(format!("php-8.3.0-{}", variant)));
+        assert!(get_php_executable_path(&install_path).exists());
+
+        let state = PhpState::load().unwrap();
+        let version_with_variant = format!("8.3.0-{}", variant);
+        assert!(state.installed_versions.contains(&version_with_variant));
+        let metadata = state.get_metadata(&version_with_variant).unwrap();
+        assert_eq!(metadata.install_path, install_path);
+        assert!(metadata.checksum.is_some());
+        assert_eq!(metadata.compiler, Some(plan.compiler_tag.to_lowercase()));
+        assert!(manager.verify_integrity(&version_with_variant).unwrap());
+    }
+
+    let mut installed = manager.list_installed().unwrap();
+    installed.sort();
+    assert_eq!(installed, vec!["8.3.0-nts", "8.3.0-ts"]);
+
+    // Removing one variant leaves the other untouched
+    manager.remove("8.3.0-nts", false).await.unwrap();
+    let state = PhpState::load().unwrap();
+    assert_eq!(state.installed_versions, vec!["8.3.0-ts".to_string()]);
+    assert!(state.get_metadata("8.3.0-nts").is_none());
+    assert!(!temp_dir.path().join("versions").join("php-8.3.0-nts").exists());
+    assert!(temp_dir.path().join("versions").join("php-8.3.0-ts").exists());
+
+    manager.remove("8.3.0-ts", false).await.unwrap();
+    assert!(PhpState::load().unwrap().installed_versions.is_empty());
+    assert!(!temp_dir.path().join("versions").join("php-8.3.0-ts").exists());
+
+    // With keep_archives_after_install off, the archive leaves the cache once installed
+    let mut config = Config::load().unwrap();
+    config.keep_archives_after_install = false;
+    config.save().unwrap();
+    Mock::given(method("GET"))
+        .and(path("/one-off/php-8.3.0-nts.zip"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(synthetic_php_archive()))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let source_url = format!("{}/one-off/php-8.3.0-nts.zip", server.uri());
+    Installer::new()
+        .unwrap()
+        .install_version(&version, Some(&source_url), Some(false), false, None)
+        .await
+        .unwrap();
+    assert!(get_php_executable_path(&temp_dir.path().join("versions").join("php-8.3.0-nts")).exists());
+    assert!(!Downloader::new().unwrap().is_cached(&source_url));
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
@@ -0,0 +1,1174 @@
+/**
+ * Integration tests for PhpManager's view of the install directory
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+use phpvm_core::config::PHPVM_HOME_ENV;
+use phpvm_core::{get_php_executable_path, PhpManager, PhpState};
+use std::fs;
+use std::sync::Mutex;
+use tempfile::TempDir;
+
+/// Tests in this binary share the PHPVM_HOME environment variable
+static HOME_LOCK: Mutex<()> = Mutex::new(());
+
+#[test]
+fn test_list_installed_reports_but_keeps_broken_installs() {
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let manager = PhpManager::new().unwrap();
+    let install_dir = temp_dir.path().join("versions");
+
+    // A complete install and one whose extraction hasn't produced the executable yet
+    let complete = install_dir.join("php-8.3.0-ts");
+    let exe = get_php_executable_path(&complete);
+    fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    fs::write(&exe, "fake php").unwrap();
+
+    let partial = install_dir.join("php-8.2.0-nts");
+    fs::create_dir_all(&partial).unwrap();
+    fs::write(partial.join("php8.dll"), "fake dll").unwrap();
+    // Not named like an install nor recorded: someone else's folder
+    let unrelated = install_dir.join("php-docs");
+    fs::create_dir_all(&unrelated).unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    state.installed_versions.push("8.2.0-nts".to_string());
+    state.save().unwrap();
+
+    assert_eq!(manager.list_installed().unwrap(), vec!["8.3.0-ts".to_string()]);
+    assert!(partial.exists(), "list_installed must not delete a directory missing php.exe");
+
+    let broken = manager.find_broken_installs().unwrap();
+    assert_eq!(broken.len(), 1);
+    assert_eq!(broken[0].version, "8.2.0-nts");
+    assert_eq!(broken[0].path, partial);
+    assert!(broken[0].in_state);
+    assert!(partial.exists());
+
+    let pruned = manager.prune_broken_installs().unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert!(!partial.exists());
+    assert!(complete.exists());
+    assert!(!PhpState::load().unwrap().installed_versions.contains(&"8.2.0-nts".to_string()));
+    assert!(manager.find_broken_installs().unwrap().is_empty());
+    assert!(unrelated.exists());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_force_remove_active_switches_away_first() {
+    use phpvm_core::config::Config;
+    use phpvm_core::platform::get_current_path;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    // Keep switch away from the real shell rc files
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let manager = PhpManager::new().unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    for version in ["8.1.0-nts", "8.2.0-nts", "8.3.0-nts"] {
+        let exe = get_php_executable_path(&temp_dir.path().join("versions").join(format!("php-{}", version)));
+        fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        fs::write(&exe, "fake php").unwrap();
+        state.installed_versions.push(version.to_string());
+    }
+    state.save().unwrap();
+    let version_dir = |version: &str| temp_dir.path().join("versions").join(format!("php-{}", version));
+
+    runtime.block_on(manager.switch("8.1.0-nts")).unwrap();
+    runtime.block_on(manager.switch("8.2.0-nts")).unwrap();
+
+    // Without force the active version is still refused
+    let err = runtime.block_on(manager.remove("8.2.0-nts", false)).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<phpvm_core::PhpVmError>(),
+        Some(phpvm_core::PhpVmError::ActiveVersionInUse(_))
+    ));
+    assert!(version_dir("8.2.0-nts").exists());
+
+    // Last known good wins over the newer 8.3.0
+    runtime.block_on(manager.remove("8.2.0-nts", true)).unwrap();
+    assert!(!version_dir("8.2.0-nts").exists());
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.1.0-nts"));
+    assert!(fs::read_link(get_current_path()).unwrap().starts_with(version_dir("8.1.0-nts")));
+
+    runtime.block_on(manager.remove("8.1.0-nts", true)).unwrap();
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.3.0-nts"));
+
+    // Removing the last install deactivates PHP instead of leaving a dangling shim
+    runtime.block_on(manager.remove("8.3.0-nts", true)).unwrap();
+    assert_eq!(manager.get_active().unwrap(), None);
+    assert!(fs::symlink_metadata(get_current_path()).is_err());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_build_info_falls_back_to_install_metadata() {
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let manager = PhpManager::new().unwrap();
+
+    // Not a runnable PHP, so nothing comes from `php -i`
+    let install_path = temp_dir.path().join("versions").join("php-8.3.0-nts");
+    let exe = get_php_executable_path(&install_path);
+    fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    fs::write(&exe, "fake php").unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    state.add_version(
+        "8.3.0-nts".to_string(),
+        phpvm_core::InstallMetadata {
+            version: "8.3.0-nts".to_string(),
+            install_path,
+            installed_at: "0".to_string(),
+            checksum: None,
+            source: "https://windows.php.net/downloads/releases/php-8.3.0-nts-Win32-vs16-x64.zip".to_string(),
+            compiler: Some("vs16".to_string()),
+        },
+    );
+    state.save().unwrap();
+
+    let info = manager.get_build_info("8.3.0").unwrap();
+    assert_eq!(
+        info,
+        phpvm_core::manager::BuildInfo {
+            php_version: "8.3.0".to_string(),
+            compiler: Some("vs16".to_string()),
+            arch: "x64".to_string(),
+            thread_safe: false,
+        }
+    );
+    assert!(manager.get_build_info("8.2.0").is_err());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_resolve_switch_target() {
+    use phpvm_core::manager::resolve_switch_target;
+    let installed: Vec<String> = ["8.2.5-ts", "8.2.10-nts", "8.20.1-ts", "8.3.0-ts", "8.3.0-nts", "7.4.33-nts"]
+        .iter()
+        .map(|v| v.to_string())
+        .collect();
+
+    // The newest 8.2 patch is only installed as NTS: fine without a preference, an error
+    // naming the missing build with a TS one, and 8.20 isn't 8.2
+    assert_eq!(resolve_switch_target("8.2", &installed, None).unwrap(), "8.2.10-nts");
+    assert_eq!(resolve_switch_target("8.2", &installed, Some("nts")).unwrap(), "8.2.10-nts");
+    let err = resolve_switch_target("8.2", &installed, Some("ts")).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<phpvm_core::PhpVmError>(),
+        Some(phpvm_core::PhpVmError::NotInstalled(_))
+    ));
+    assert_eq!(
+        err.to_string(),
+        "PHP 8.2.10 is installed only as NTS, not TS; switch to \"8.2.10-nts\" or install its TS build"
+    );
+    // An exact patch installed in one build is that build, whatever the preference
+    assert_eq!(resolve_switch_target("8.2.10", &installed, Some("ts")).unwrap(), "8.2.10-nts");
+    assert_eq!(resolve_switch_target("8.3.0", &installed, Some("nts")).unwrap(), "8.3.0-nts");
+    // An explicit variant narrows the choice
+    assert_eq!(resolve_switch_target("8.2-ts", &installed, None).unwrap(), "8.2.5-ts");
+    // Both builds of the newest patch: the active kind first, else TS
+    assert_eq!(resolve_switch_target("8.3", &installed, Some("nts")).unwrap(), "8.3.0-nts");
+    assert_eq!(resolve_switch_target("8", &installed, None).unwrap(), "8.20.1-ts");
+    assert_eq!(resolve_switch_target("8.3.0", &installed, None).unwrap(), "8.3.0-ts");
+    assert_eq!(resolve_switch_target("7.4.33-nts", &installed, None).unwrap(), "7.4.33-nts");
+
+    let err = resolve_switch_target("8.4", &installed, None).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<phpvm_core::PhpVmError>(),
+        Some(phpvm_core::PhpVmError::NotInstalled(_))
+    ));
+    assert_eq!(err.to_string(), "No installed version matches 8.4");
+    assert!(resolve_switch_target("8.2.5-nts", &installed, None).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_switch_to_branch_picks_newest_patch() {
+    use phpvm_core::config::Config;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let manager = PhpManager::new().unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    for version in ["8.2.5-ts", "8.2.10-nts"] {
+        let exe = get_php_executable_path(&temp_dir.path().join("versions").join(format!("php-{}", version)));
+        fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        fs::write(&exe, "fake php").unwrap();
+        state.installed_versions.push(version.to_string());
+    }
+    state.save().unwrap();
+
+    runtime.block_on(manager.switch("8.2")).unwrap();
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.2.10-nts"));
+    runtime.block_on(manager.switch("8.2-ts")).unwrap();
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.2.5-ts"));
+    assert!(runtime.block_on(manager.switch("8.1")).is_err());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_pinned_installs_are_not_pruned() {
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let manager = PhpManager::new().unwrap();
+
+    // Two broken installs (no executable), one of them pinned
+    let install_dir = temp_dir.path().join("versions");
+    let pinned = install_dir.join("php-8.1.34-nts");
+    let unpinned = install_dir.join("php-8.2.0-nts");
+    for dir in [&pinned, &unpinned] {
+        fs::create_dir_all(dir).unwrap();
+    }
+    let mut state = PhpState::load().unwrap();
+    state.installed_versions.extend(["8.1.34-nts".to_string(), "8.2.0-nts".to_string()]);
+    state.pin_version("8.1.34-nts");
+    state.save().unwrap();
+
+    let pruned = manager.prune_broken_installs().unwrap();
+    assert_eq!(pruned.len(), 1);
+    assert_eq!(pruned[0].version, "8.2.0-nts");
+    assert!(pinned.exists());
+    assert!(!unpinned.exists());
+
+    // Still reported, so the user can see it needs attention
+    assert_eq!(manager.find_broken_installs().unwrap().len(), 1);
+
+    manager.unpin_version("8.1.34").unwrap();
+    assert!(PhpState::load().unwrap().pinned_versions.is_empty());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_module_diff_between() {
+    let modules = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+    let diff = phpvm_core::manager::ModuleDiff::between(
+        "8.1.0-ts",
+        &modules(&["Core", "curl", "mysqli"]),
+        "8.2.0-ts",
+        &modules(&["Core", "Curl", "random"]),
+    );
+    assert_eq!(diff.added, vec!["random"]);
+    assert_eq!(diff.removed, vec!["mysqli"]);
+}
+
+#[test]
+fn test_manager_rejects_path_traversal_versions() {
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let manager = PhpManager::new().unwrap();
+
+    // A directory the traversal would otherwise reach from the install dir
+    let outside = temp_dir.path().join("php-outside");
+    fs::create_dir_all(&outside).unwrap();
+
+    for version in ["../php-outside", "8.3.0-../../php-outside", "/tmp", "8.3.0-ts\\..\\.."] {
+        assert!(runtime.block_on(manager.remove(version, true)).is_err());
+        assert!(runtime.block_on(manager.switch(version)).is_err());
+        assert!(manager.plan_install(version, Some(true)).is_err());
+        assert!(manager.loaded_modules(version).is_err());
+    }
+    assert!(outside.exists());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_cache_ready_versions() {
+    use phpvm_core::manager::{cache_ready_versions, CacheReadyVersion};
+    use phpvm_core::provider::Provider;
+    use phpvm_core::VersionInfo;
+
+    let info = |version: &str| VersionInfo {
+        version: version.to_string(),
+        release_date: None,
+        eol_date: None,
+        active_support_until: None,
+        download_url: None,
+        checksum: None,
+    };
+    let versions = vec![info("8.3.0"), info("8.2.10"), info("7.4.33")];
+    let cached = [
+        Provider::generate_download_url("8.3.0", 8, 3, false),
+        // Superseded patches are found under archives/ too
+        Provider::archived_download_url("8.2.10", 8, 2, true).unwrap(),
+    ];
+
+    let ready = cache_ready_versions(&versions, |url| cached.iter().any(|c| c == url));
+    assert_eq!(
+        ready,
+        vec![
+            CacheReadyVersion { version: "8.3.0".to_string(), ts: false, nts: true },
+            CacheReadyVersion { version: "8.2.10".to_string(), ts: true, nts: false },
+        ]
+    );
+}
+
+#[test]
+fn test_cache_owner_versions_cover_every_patch() {
+    use phpvm_core::manager::cache_owner_versions;
+    use phpvm_core::VersionInfo;
+
+    let info = |version: &str| VersionInfo {
+        version: version.to_string(),
+        release_date: None,
+        eol_date: None,
+        active_support_until: None,
+        download_url: None,
+        checksum: None,
+    };
+    // The list names only the newest patch of each branch
+    let listed = vec![info("8.3.2"), info("8.2.1")];
+    let recorded = ["7.4.33-nts".to_string(), "8.3.1-ts".to_string()];
+
+    let owners: Vec<String> = cache_owner_versions(&listed, &recorded).iter().map(|v| v.to_string()).collect();
+    assert_eq!(owners, vec!["7.4.33", "8.2.0", "8.2.1", "8.3.0", "8.3.1", "8.3.2"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_switch_succeeds_when_path_cannot_be_written() {
+    use phpvm_core::platform::get_current_path;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path().join("phpvm"));
+    let (old_home, old_shell) = (std::env::var_os("HOME"), std::env::var_os("SHELL"));
+    std::env::set_var("HOME", temp_dir.path());
+    std::env::set_var("SHELL", "/bin/bash");
+    // A directory where the rc file should be makes writing it fail
+    fs::create_dir_all(temp_dir.path().join(".bashrc")).unwrap();
+
+    let manager = PhpManager::new().unwrap();
+    let version_dir = temp_dir.path().join("phpvm").join("versions").join("php-8.3.0-nts");
+    let exe = get_php_executable_path(&version_dir);
+    fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    fs::write(&exe, "fake php").unwrap();
+    let mut state = PhpState::load().unwrap();
+    state.installed_versions.push("8.3.0-nts".to_string());
+    state.save().unwrap();
+
+    let switched = tokio::runtime::Runtime::new().unwrap().block_on(manager.switch("8.3.0-nts"));
+    // Setting PATH explicitly still reports the failure
+    let path_set = manager.ensure_path_set();
+    for (key, value) in [("HOME", old_home), ("SHELL", old_shell)] {
+        match value {
+            Some(value) => std::env::set_var(key, value),
+            None => std::env::remove_var(key),
+        }
+    }
+    // The switch goes through and says what went wrong with PATH
+    let warning = switched.unwrap().path_warning.unwrap();
+    assert!(warning.contains("could not be added to PATH"), "{}", warning);
+    assert!(path_set.is_err());
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.3.0-nts"));
+    assert!(fs::read_link(get_current_path()).unwrap().starts_with(&version_dir));
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_get_download_url_lists_fallbacks_and_mirrors() {
+    use phpvm_core::config::Config;
+    use phpvm_core::provider::Provider;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.download_mirrors = vec!["https://mirror.example/php".to_string()];
+    config.save().unwrap();
+
+    let urls = PhpManager::new().unwrap().get_download_url("8.2.10", Some(false)).unwrap();
+    let official = Provider::install_download_urls("8.2.10", 8, 2, false);
+    assert_eq!(urls.primary, official[0]);
+    assert_eq!(urls.fallbacks.len(), official.len() * 2 - 1);
+    assert_eq!(urls.fallbacks[..official.len() - 1], official[1..]);
+    assert_eq!(
+        urls.fallbacks[official.len() - 1],
+        "https://mirror.example/php/downloads/releases/php-8.2.10-nts-Win32-vs16-x64.zip"
+    );
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_legacy_unsuffixed_installs_are_migrated() {
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let install_dir = temp_dir.path().join("versions");
+
+    // Can't be run, so it's assumed to be TS
+    let legacy = install_dir.join("php-8.2.0");
+    let exe = get_php_executable_path(&legacy);
+    fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    fs::write(&exe, "fake php").unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    state.installed_versions.push("8.2.0".to_string());
+    state.active_version = Some("8.2.0".to_string());
+    state.pinned_versions.push("8.2.0".to_string());
+    state.save().unwrap();
+
+    let manager = PhpManager::new().unwrap();
+    assert!(!legacy.exists());
+    assert!(get_php_executable_path(&install_dir.join("php-8.2.0-ts")).exists());
+    assert_eq!(manager.list_installed().unwrap(), vec!["8.2.0-ts".to_string()]);
+    let state = PhpState::load().unwrap();
+    assert_eq!(state.installed_versions, vec!["8.2.0-ts".to_string()]);
+    assert_eq!(state.active_version.as_deref(), Some("8.2.0-ts"));
+    assert!(state.is_pinned("8.2.0-ts"));
+    #[cfg(unix)]
+    assert_eq!(
+        fs::read_link(phpvm_core::platform::get_current_path()).unwrap(),
+        get_php_executable_path(&install_dir.join("php-8.2.0-ts"))
+    );
+    // Nothing left to do the second time
+    assert!(manager.migrate_legacy_installs().unwrap().is_empty());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_legacy_install_variant_comes_from_php_info() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let legacy = temp_dir.path().join("versions").join("php-7.4.33");
+    let exe = get_php_executable_path(&legacy);
+    fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    fs::write(&exe, "#!/bin/sh\necho 'PHP Version => 7.4.33'\necho 'Thread Safety => disabled'\n").unwrap();
+    fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let manager = PhpManager::new().unwrap();
+    assert_eq!(manager.list_installed().unwrap(), vec!["7.4.33-nts".to_string()]);
+    assert!(PhpState::load().unwrap().installed_versions.contains(&"7.4.33-nts".to_string()));
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_default_variant_is_saved_and_preferred_by_branch_switch() {
+    use phpvm_core::config::Config;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let mut manager = PhpManager::new().unwrap();
+    assert_eq!(manager.get_default_variant(), None);
+    assert!(manager.plan_install("8.3.0", None).unwrap().thread_safe);
+
+    manager.set_default_variant(Some(false)).unwrap();
+    assert_eq!(Config::load().unwrap().default_thread_safe, Some(false));
+    assert!(!manager.plan_install("8.3.0", None).unwrap().thread_safe);
+    assert!(manager.plan_install("8.3.0", Some(true)).unwrap().thread_safe);
+
+    let mut state = PhpState::load().unwrap();
+    for version in ["8.2.10-ts", "8.2.10-nts"] {
+        let exe = get_php_executable_path(&temp_dir.path().join("versions").join(format!("php-{}", version)));
+        fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        fs::write(&exe, "fake php").unwrap();
+        state.installed_versions.push(version.to_string());
+    }
+    state.active_version = Some("8.2.10-ts".to_string());
+    state.save().unwrap();
+
+    // The configured default wins over the variant in use
+    runtime.block_on(manager.switch("8.2")).unwrap();
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.2.10-nts"));
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_rebuild_state_from_disk() {
+    use phpvm_core::config::{get_state_path, Config};
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let manager = PhpManager::new().unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    for version in ["8.2.10-nts", "8.3.0-ts"] {
+        let exe = get_php_executable_path(&temp_dir.path().join("versions").join(format!("php-{}", version)));
+        fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        fs::write(&exe, "fake php").unwrap();
+        state.installed_versions.push(version.to_string());
+    }
+    state.save().unwrap();
+    runtime.block_on(manager.switch("8.2.10-nts")).unwrap();
+    // Not an install: no executable
+    fs::create_dir_all(temp_dir.path().join("versions").join("php-8.1.0-ts")).unwrap();
+
+    fs::write(get_state_path(), "{ corrupted").unwrap();
+    assert!(PhpState::load().is_err());
+
+    let rebuilt = manager.rebuild_state_from_disk().unwrap();
+    assert_eq!(rebuilt.installed_versions, vec!["8.2.10-nts".to_string(), "8.3.0-ts".to_string()]);
+    assert_eq!(rebuilt.active_version.as_deref(), Some("8.2.10-nts"));
+    let metadata = rebuilt.get_metadata("8.3.0-ts").unwrap();
+    assert_eq!(metadata.source, "unknown");
+    assert!(metadata.checksum.is_some());
+
+    let state = PhpState::load().unwrap();
+    assert_eq!(state.installed_versions, rebuilt.installed_versions);
+    assert_eq!(fs::read_to_string(get_state_path().with_extension("json.bak")).unwrap(), "{ corrupted");
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_switch_checked_rolls_back_when_new_version_doesnt_run() {
+    use phpvm_core::config::Config;
+    use phpvm_core::platform::get_current_path;
+    use std::os::unix::fs::PermissionsExt;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let manager = PhpManager::new().unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    let working_exe = get_php_executable_path(&temp_dir.path().join("versions").join("php-8.2.10-nts"));
+    fs::create_dir_all(working_exe.parent().unwrap()).unwrap();
+    fs::write(&working_exe, "#!/bin/sh\necho 'PHP 8.2.10 (cli)'\n").unwrap();
+    fs::set_permissions(&working_exe, fs::Permissions::from_mode(0o755)).unwrap();
+    // Not executable, like a php.exe whose runtime DLLs are missing
+    let broken_exe = get_php_executable_path(&temp_dir.path().join("versions").join("php-8.3.0-nts"));
+    fs::create_dir_all(broken_exe.parent().unwrap()).unwrap();
+    fs::write(&broken_exe, "fake php").unwrap();
+    state.installed_versions = vec!["8.2.10-nts".to_string(), "8.3.0-nts".to_string()];
+    state.save().unwrap();
+
+    runtime.block_on(manager.switch_checked("8.2.10-nts")).unwrap();
+    assert_eq!(manager.verify_runtime("8.2.10-nts").unwrap(), "PHP 8.2.10 (cli)");
+
+    let error = runtime.block_on(manager.switch_checked("8.3.0-nts")).unwrap_err().to_string();
+    assert!(error.contains("8.3.0-nts"), "{}", error);
+    assert!(error.contains("switched back to PHP 8.2.10-nts"), "{}", error);
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.2.10-nts"));
+    assert_eq!(fs::read_link(get_current_path()).unwrap(), working_exe);
+    assert_ne!(PhpState::load().unwrap().last_known_good.as_deref(), Some("8.3.0-nts"));
+
+    // Nothing to go back to: PHP ends up deactivated
+    runtime.block_on(manager.switch("8.2.10-nts")).unwrap();
+    let mut state = PhpState::load().unwrap();
+    state.active_version = None;
+    state.save().unwrap();
+    assert!(runtime.block_on(manager.switch_checked("8.3.0-nts")).is_err());
+    assert_eq!(manager.get_active().unwrap(), None);
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_status_report() {
+    use phpvm_core::config::{Config, ProviderConfig};
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    // Nothing listens there, so available versions can't be fetched
+    config.providers = vec![ProviderConfig {
+        name: "mirror".to_string(),
+        url: "http://127.0.0.1:9/releases/".to_string(),
+        verify_checksum: false,
+    }];
+    config.save().unwrap();
+    let manager = PhpManager::new().unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    for version in ["8.2.10-nts", "8.3.0-ts"] {
+        let exe = get_php_executable_path(&temp_dir.path().join("versions").join(format!("php-{}", version)));
+        fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        fs::write(&exe, "fake php").unwrap();
+        state.installed_versions.push(version.to_string());
+    }
+    state.save().unwrap();
+    runtime.block_on(manager.switch("8.3.0-ts")).unwrap();
+
+    let report = runtime.block_on(manager.status_report()).unwrap();
+    assert_eq!(report.active.as_deref(), Some("8.3.0-ts"));
+    let installed: Vec<_> = report.installed.iter().map(|i| (i.version.as_str(), i.variant.as_str(), i.active)).collect();
+    assert_eq!(installed, vec![("8.2.10-nts", "nts", false), ("8.3.0-ts", "ts", true)]);
+    assert_eq!(report.installed[1].path, temp_dir.path().join("versions").join("php-8.3.0-ts"));
+
+    let json = serde_json::to_value(&report).unwrap();
+    for field in ["schema_version", "installed", "active", "available", "available_error", "path_configured", "install_dir"] {
+        assert!(json.get(field).is_some(), "missing {}", field);
+    }
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_lint_ini_reports_startup_messages() {
+    use phpvm_core::ini::{IniIssueKind, IniIssueSeverity};
+    use std::os::unix::fs::PermissionsExt;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let manager = PhpManager::new().unwrap();
+
+    let version_dir = temp_dir.path().join("versions").join("php-8.3.0-nts");
+    let exe = get_php_executable_path(&version_dir);
+    fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    // Stands in for PHP reading a broken php.ini given with -c
+    fs::write(
+        &exe,
+        "#!/bin/sh\n\
+         echo \"PHP:  syntax error, unexpected '=' in $2 on line 3\" >&2\n\
+         echo \"PHP Warning:  PHP Startup: Unable to load dynamic library 'redis.so' in Unknown on line 0\" >&2\n\
+         echo 'PHP 8.3.0 (cli)'\n",
+    )
+    .unwrap();
+    fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+    let mut state = PhpState::load().unwrap();
+    state.installed_versions.push("8.3.0-nts".to_string());
+    state.save().unwrap();
+
+    // No php.ini yet
+    assert!(manager.lint_ini("8.3.0").is_err());
+
+    fs::write(version_dir.join("php.ini"), "[PHP]\nmemory_limit = 128M\n= broken\n").unwrap();
+    let issues = manager.lint_ini("8.3.0").unwrap();
+    assert_eq!(issues.len(), 2);
+    assert_eq!((issues[0].severity, issues[0].kind, issues[0].line), (IniIssueSeverity::Error, IniIssueKind::Syntax, Some(3)));
+    assert_eq!((issues[1].severity, issues[1].kind), (IniIssueSeverity::Warning, IniIssueKind::ExtensionLoad));
+    assert_eq!(issues[1].extension.as_deref(), Some("redis"));
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_migrate_install_settings_for_upgrade() {
+    use phpvm_core::manager::migrate_install_settings;
+
+    let temp_dir = TempDir::new().unwrap();
+    let old_dir = temp_dir.path().join("php-8.2.10-nts");
+    let new_dir = temp_dir.path().join("php-8.2.30-nts");
+    fs::create_dir_all(old_dir.join("ext")).unwrap();
+    fs::create_dir_all(new_dir.join("ext")).unwrap();
+    fs::write(
+        old_dir.join("php.ini"),
+        format!("memory_limit=512M\nextension_dir=\"{}\"\n", old_dir.join("ext").display()),
+    )
+    .unwrap();
+    fs::write(old_dir.join("ext").join("php_curl.dll"), "old bundled").unwrap();
+    fs::write(old_dir.join("ext").join("php_xdebug.dll"), "user added").unwrap();
+    fs::write(new_dir.join("ext").join("php_curl.dll"), "new bundled").unwrap();
+    fs::write(new_dir.join("php.ini"), "fresh").unwrap();
+
+    let steps = migrate_install_settings(&old_dir, &new_dir).unwrap();
+    assert_eq!(steps.len(), 3, "{:?}", steps);
+
+    let ini = fs::read_to_string(new_dir.join("php.ini")).unwrap();
+    assert!(ini.contains("memory_limit=512M"));
+    assert!(ini.contains(&new_dir.join("ext").display().to_string()));
+    assert!(!ini.contains("8.2.10"));
+    assert_eq!(fs::read_to_string(new_dir.join("php.ini.bak")).unwrap(), "fresh");
+    // Only extensions the new install doesn't ship are copied
+    assert_eq!(fs::read_to_string(new_dir.join("ext").join("php_xdebug.dll")).unwrap(), "user added");
+    assert_eq!(fs::read_to_string(new_dir.join("ext").join("php_curl.dll")).unwrap(), "new bundled");
+
+    // Nothing to carry over from a bare install
+    let bare = temp_dir.path().join("php-7.4.33-ts");
+    fs::create_dir_all(&bare).unwrap();
+    assert!(migrate_install_settings(&bare, &new_dir).unwrap().is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_adopt_external_copies_install_into_management() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path().join("phpvm"));
+    let manager = PhpManager::new().unwrap();
+
+    let external = temp_dir.path().join("xampp").join("php");
+    let exe = get_php_executable_path(&external);
+    fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    fs::write(
+        &exe,
+        "#!/bin/sh\n\
+         if [ \"$1\" = \"-i\" ]; then printf 'PHP Version => 8.1.2\\nThread Safety => disabled\\n'; else echo 'PHP 8.1.2 (cli) (built: Jan 18 2022)'; fi\n",
+    )
+    .unwrap();
+    fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::write(external.join("php.ini"), "memory_limit=256M\n").unwrap();
+
+    // The detected variant wins over a wrong hint
+    let version = manager.adopt_external(&external, Some(true)).unwrap();
+    assert_eq!(version, "8.1.2-nts");
+    assert!(manager.list_installed().unwrap().contains(&version));
+    let metadata = manager.get_version_metadata(&version).unwrap().unwrap();
+    assert_eq!(metadata.source, "adopted");
+    assert_eq!(fs::read_to_string(metadata.install_path.join("php.ini")).unwrap(), "memory_limit=256M\n");
+    assert!(manager.verify_integrity(&version).unwrap());
+
+    // It's a copy: the original stays put and removing the adopted version leaves it alone
+    assert!(manager.adopt_external(&external, None).is_err());
+    tokio::runtime::Runtime::new().unwrap().block_on(manager.remove(&version, false)).unwrap();
+    assert!(exe.is_file());
+
+    // Not a PHP directory
+    assert!(manager.adopt_external(temp_dir.path(), None).is_err());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_apply_project_config_switches_and_enables_extensions() {
+    use phpvm_core::config::Config;
+    use phpvm_core::install::Installer;
+    use phpvm_core::InstallMetadata;
+    use std::os::unix::fs::PermissionsExt;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path().join("phpvm"));
+
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let manager = PhpManager::new().unwrap();
+
+    let version_dir = temp_dir.path().join("phpvm").join("versions").join("php-8.3.0-nts");
+    let exe = get_php_executable_path(&version_dir);
+    fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    fs::write(&exe, "#!/bin/sh\necho 'PHP 8.3.0 (cli)'\n").unwrap();
+    fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+    fs::create_dir_all(version_dir.join("ext")).unwrap();
+    fs::write(version_dir.join("ext").join("curl.so"), "fake curl").unwrap();
+    fs::write(version_dir.join("php.ini-development"), "[PHP]\nmemory_limit = 128M\n").unwrap();
+    let mut state = PhpState::load().unwrap();
+    state.add_version(
+        "8.3.0-nts".to_string(),
+        InstallMetadata {
+            version: "8.3.0-nts".to_string(),
+            install_path: version_dir.clone(),
+            installed_at: "0".to_string(),
+            checksum: Some(Installer::calculate_checksum(&version_dir).unwrap()),
+            source: "test".to_string(),
+            compiler: None,
+        },
+    );
+    state.save().unwrap();
+
+    let project = temp_dir.path().join("project");
+    fs::create_dir_all(&project).unwrap();
+    assert!(runtime.block_on(manager.apply_project_config(&project, None)).is_err());
+
+    fs::write(
+        project.join(".phpvmrc"),
+        r#"{ "version": "8.3.0", "variant": "nts", "extensions": ["curl"], "editor": "ignored" }"#,
+    )
+    .unwrap();
+    let report = runtime.block_on(manager.apply_project_config(&project, None)).unwrap();
+    assert_eq!(report.version, "8.3.0-nts");
+    assert_eq!(
+        report.steps,
+        vec!["PHP 8.3.0-nts is already installed", "Switched to PHP 8.3.0-nts", "Enabled curl"]
+    );
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.3.0-nts"));
+    let ini = fs::read_to_string(version_dir.join("php.ini")).unwrap();
+    assert!(ini.contains("memory_limit = 128M"));
+    assert!(ini.contains(&format!("extension=\"{}\"", version_dir.join("ext").join("curl.so").display())));
+    assert!(manager.verify_integrity("8.3.0-nts").unwrap());
+
+    // Running it again changes nothing
+    let report = runtime.block_on(manager.apply_project_config(&project, None)).unwrap();
+    assert_eq!(report.steps[1..], ["PHP 8.3.0-nts is already active", "curl is already enabled"]);
+
+    // Neither bundled nor downloadable
+    fs::write(project.join(".phpvmrc"), r#"{ "version": "8.3.0", "variant": "nts", "extensions": ["mongodb"] }"#).unwrap();
+    assert!(runtime.block_on(manager.apply_project_config(&project, None)).is_err());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_get_version_info_falls_back_when_offline() {
+    use phpvm_core::config::{Config, ProviderConfig};
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let mut config = Config::load().unwrap();
+    // Nothing listens there, so the provider can't be reached
+    config.providers = vec![ProviderConfig {
+        name: "mirror".to_string(),
+        url: "http://127.0.0.1:9/releases/".to_string(),
+        verify_checksum: false,
+    }];
+    config.save().unwrap();
+    let manager = PhpManager::new().unwrap();
+
+    let info = runtime.block_on(manager.get_version_info("8.2.10")).unwrap().unwrap();
+    assert_eq!(info.version, "8.2.10");
+    assert_eq!(
+        info.download_url.as_deref(),
+        Some(phpvm_core::provider::Provider::generate_download_url("8.2.10", 8, 2, true).as_str())
+    );
+    assert!(info.eol_date.is_some());
+    assert_eq!(info.checksum, None);
+
+    assert!(runtime.block_on(manager.get_version_info("not-a-version")).unwrap().is_none());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_shell_activate_leaves_global_state_alone() {
+    use phpvm_core::config::Config;
+    use phpvm_core::platform::ShellKind;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let manager = PhpManager::new().unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    for version in ["8.1.27-nts", "8.3.0-ts"] {
+        let exe = get_php_executable_path(&temp_dir.path().join("versions").join(format!("php-{}", version)));
+        fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        fs::write(&exe, "fake php").unwrap();
+        state.installed_versions.push(version.to_string());
+    }
+    state.save().unwrap();
+    runtime.block_on(manager.switch("8.3.0-ts")).unwrap();
+    let shim = fs::read(phpvm_core::get_current_path()).unwrap();
+
+    let info = manager.shell_activate("8.1", Some(ShellKind::Bash)).unwrap();
+    assert_eq!(info.version, "8.1.27-nts");
+    assert_eq!(
+        info.path_dir,
+        get_php_executable_path(&temp_dir.path().join("versions").join("php-8.1.27-nts")).parent().unwrap()
+    );
+    assert!(info.script.unwrap().contains(&info.path_dir.to_string_lossy().to_string()));
+    assert_eq!(manager.shell_activate("8.1.27", None).unwrap().script, None);
+
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.3.0-ts"));
+    assert_eq!(fs::read(phpvm_core::get_current_path()).unwrap(), shim);
+    assert!(manager.shell_activate("7.4", None).is_err());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_effective_php_reports_what_path_runs() {
+    use phpvm_core::config::Config;
+    use std::os::unix::fs::PermissionsExt;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let manager = PhpManager::new().unwrap();
+
+    // Another php ahead of everything else on PATH, like a Laragon install
+    let other_dir = temp_dir.path().join("laragon").join("php");
+    fs::create_dir_all(&other_dir).unwrap();
+    let other_php = other_dir.join("php");
+    fs::write(&other_php, "#!/bin/sh\necho 'PHP 8.1.2 (cli) (built: Jan  1 2022)'\n").unwrap();
+    fs::set_permissions(&other_php, fs::Permissions::from_mode(0o755)).unwrap();
+    let old_path = std::env::var_os("PATH").unwrap_or_default();
+    let mut dirs = vec![other_dir.clone()];
+    dirs.extend(std::env::split_paths(&old_path));
+    std::env::set_var("PATH", std::env::join_paths(dirs).unwrap());
+
+    let effective = manager.get_effective_php();
+    let installed = phpvm_core::platform::active_php_version();
+
+    // One of phpvm's own installs put on PATH by hand still counts as managed
+    let managed_php = get_php_executable_path(&temp_dir.path().join("versions").join("php-8.3.0-nts"));
+    fs::create_dir_all(managed_php.parent().unwrap()).unwrap();
+    fs::write(&managed_php, "#!/bin/sh\necho 'PHP 8.3.0 (cli)'\n").unwrap();
+    fs::set_permissions(&managed_php, fs::Permissions::from_mode(0o755)).unwrap();
+    let mut dirs = vec![managed_php.parent().unwrap().to_path_buf()];
+    dirs.extend(std::env::split_paths(&old_path));
+    std::env::set_var("PATH", std::env::join_paths(dirs).unwrap());
+    let managed = manager.get_effective_php();
+    std::env::set_var("PATH", &old_path);
+
+    let effective = effective.unwrap();
+    assert_eq!(effective.path.as_deref(), Some(other_php.as_path()));
+    assert_eq!(effective.version.as_deref(), Some("8.1.2"));
+    assert!(!effective.managed);
+    assert_eq!(effective.active, None);
+    assert_eq!(installed, Some((other_php, "8.1.2".to_string())));
+    let managed = managed.unwrap();
+    assert_eq!(managed.version.as_deref(), Some("8.3.0"));
+    assert!(managed.managed);
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[cfg(not(target_os = "windows"))]
+#[test]
+fn test_list_installable_is_empty_off_windows() {
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let manager = PhpManager::new().unwrap();
+
+    // Returns before fetching anything, so this needs no network
+    let installable = tokio::runtime::Runtime::new().unwrap().block_on(manager.list_installable()).unwrap();
+    assert_eq!(installable.os, std::env::consts::OS);
+    assert!(installable.versions.is_empty());
+    assert!(installable.reason.unwrap().contains("windows.php.net"));
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_active_version_is_reported_consistently() {
+    use phpvm_core::config::Config;
+    use std::os::unix::fs::PermissionsExt;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let manager = PhpManager::new().unwrap();
+
+    let mut state = PhpState::load().unwrap();
+    for version in ["8.2.10-nts", "8.3.0-ts"] {
+        let php_exe = get_php_executable_path(&temp_dir.path().join("versions").join(format!("php-{}", version)));
+        fs::create_dir_all(php_exe.parent().unwrap()).unwrap();
+        fs::write(&php_exe, "#!/bin/sh\necho 'PHP 8.3.0 (cli)'\n").unwrap();
+        fs::set_permissions(&php_exe, fs::Permissions::from_mode(0o755)).unwrap();
+        state.installed_versions.push(version.to_string());
+    }
+    state.save().unwrap();
+    assert_eq!(manager.get_active_detailed().unwrap(), None);
+
+    runtime.block_on(manager.switch("8.3.0-ts")).unwrap();
+    let active = manager.get_active_detailed().unwrap().unwrap();
+    assert_eq!(active.version, "8.3.0-ts");
+    assert_eq!(active.base_version, "8.3.0");
+    assert_eq!(active.variant, "ts");
+    assert_eq!(active.install_path, temp_dir.path().join("versions").join("php-8.3.0-ts"));
+    assert_eq!(Config::load().unwrap().active_version.as_deref(), Some("8.3.0"));
+
+    // Forcing the active version out switches away, and config.json follows state.json
+    runtime.block_on(manager.remove("8.3.0-ts", true)).unwrap();
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.2.10-nts"));
+    assert_eq!(Config::load().unwrap().active_version.as_deref(), Some("8.2.10"));
+    runtime.block_on(manager.remove("8.2.10-nts", true)).unwrap();
+    assert_eq!(manager.get_active_detailed().unwrap(), None);
+    assert_eq!(Config::load().unwrap().active_version, None);
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_timezone_is_set_in_php_ini() {
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let manager = PhpManager::new().unwrap();
+
+    let version_dir = temp_dir.path().join("versions").join("php-8.3.0-nts");
+    let php_exe = get_php_executable_path(&version_dir);
+    fs::create_dir_all(php_exe.parent().unwrap()).unwrap();
+    fs::write(&php_exe, "fake php").unwrap();
+    fs::write(version_dir.join("php.ini-development"), "[Date]\n;date.timezone =\n").unwrap();
+    let mut state = PhpState::load().unwrap();
+    state.add_version(
+        "8.3.0-nts".to_string(),
+        phpvm_core::InstallMetadata {
+            version: "8.3.0-nts".to_string(),
+            install_path: version_dir.clone(),
+            installed_at: "0".to_string(),
+            checksum: Some(phpvm_core::install::Installer::calculate_checksum(&version_dir).unwrap()),
+            source: "test".to_string(),
+            compiler: None,
+        },
+    );
+    state.save().unwrap();
+
+    assert_eq!(manager.get_timezone("8.3.0").unwrap(), None);
+    assert!(manager.set_timezone("8.3.0", "Mars/Olympus_Mons").is_err());
+    assert!(!version_dir.join("php.ini").exists());
+
+    assert_eq!(manager.set_timezone("8.3.0", "europe/lisbon").unwrap(), "Europe/Lisbon");
+    assert_eq!(fs::read_to_string(version_dir.join("php.ini")).unwrap(), "[Date]\ndate.timezone = \"Europe/Lisbon\"\n");
+    assert_eq!(manager.get_timezone("8.3.0-nts").unwrap().as_deref(), Some("Europe/Lisbon"));
+    assert!(manager.get_timezone("7.4.33").is_err());
+    // The php.ini edit is recorded, so it isn't reported as tampering
+    assert!(manager.verify_integrity("8.3.0-nts").unwrap());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_uninstall_all_only_removes_what_phpvm_created() {
+    use phpvm_core::config::Config;
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path().join("phpvm"));
+    let old_home = std::env::var_os("HOME");
+    std::env::set_var("HOME", temp_dir.path());
+
+    // install_dir and download_cache pointed at folders that hold other things too
+    let tools = temp_dir.path().join("tools");
+    let shared_cache = temp_dir.path().join("shared-cache");
+    let mut config = Config::load().unwrap();
+    config.install_dir = tools.clone();
+    config.download_cache = shared_cache.clone();
+    config.save().unwrap();
+    for dir in ["php-8.3.0-nts", ".staging-php-8.2.0-ts", "composer", "php-cs-fixer"] {
+        fs::create_dir_all(tools.join(dir)).unwrap();
+    }
+    fs::write(tools.join("notes.txt"), "mine").unwrap();
+    fs::create_dir_all(shared_cache.join("versions")).unwrap();
+    fs::write(shared_cache.join("0123456789abcdef0123456789abcdef"), "archive").unwrap();
+    fs::write(shared_cache.join("versions").join("list.json"), "{}").unwrap();
+    fs::create_dir_all(shared_cache.join("checksums")).unwrap();
+    fs::write(shared_cache.join("checksums").join("0123456789abcdef0123456789abcdef"), "ab").unwrap();
+    fs::write(shared_cache.join("report.pdf"), "mine").unwrap();
+    fs::write(phpvm_core::config::get_stats_path(), "{}").unwrap();
+
+    let report = PhpManager::new().unwrap().uninstall_all(false);
+    match old_home {
+        Some(home) => std::env::set_var("HOME", home),
+        None => std::env::remove_var("HOME"),
+    }
+    let report = report.unwrap();
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+    assert!(!tools.join("php-8.3.0-nts").exists());
+    assert!(!tools.join(".staging-php-8.2.0-ts").exists());
+    assert!(tools.join("composer").is_dir());
+    assert!(tools.join("php-cs-fixer").is_dir());
+    assert!(tools.join("notes.txt").exists());
+    assert!(!shared_cache.join("0123456789abcdef0123456789abcdef").exists());
+    assert!(!shared_cache.join("versions").exists());
+    assert!(!shared_cache.join("checksums").exists());
+    assert!(shared_cache.join("report.pdf").exists());
+    assert!(!temp_dir.path().join("phpvm").join("config.json").exists());
+    assert!(!temp_dir.path().join("phpvm").join("stats.json").exists());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
+
+#[test]
+fn test_verify_integrity_replaces_pre_v2_checksums() {
+    use phpvm_core::install::Installer;
+    use sha2::{Digest, Sha256};
+
+    let _home = HOME_LOCK.lock().unwrap();
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    let manager = PhpManager::new().unwrap();
+
+    let version_dir = temp_dir.path().join("versions").join("php-8.2.0-ts");
+    let php_exe = get_php_executable_path(&version_dir);
+    fs::create_dir_all(php_exe.parent().unwrap()).unwrap();
+    fs::write(version_dir.join("php.ini"), "memory_limit = 128M\n").unwrap();
+    // A checksum as releases before v2 recorded it (top-level file contents), which an
+    // edited php.ini no longer matches
+    let mut state = PhpState::load().unwrap();
+    state.add_version(
+        "8.2.0-ts".to_string(),
+        phpvm_core::InstallMetadata {
+            version: "8.2.0-ts".to_string(),
+            install_path: version_dir.clone(),
+            installed_at: "0".to_string(),
+            checksum: Some(hex::encode(Sha256::digest(b"memory_limit = 64M\n"))),
+            source: "test".to_string(),
+            compiler: None,
+        },
+    );
+    state.save().unwrap();
+    fs::write(&php_exe, "fake php").unwrap();
+
+    // Not reported as corrupted, but re-recorded in the current format
+    assert!(manager.verify_integrity("8.2.0-ts").unwrap());
+    let recorded = PhpState::load().unwrap().get_metadata("8.2.0-ts").unwrap().checksum.clone().unwrap();
+    assert!(Installer::is_current_checksum(&recorded));
+    assert_eq!(recorded, Installer::calculate_checksum(&version_dir).unwrap());
+
+    // Later checks compare against it
+    assert!(manager.verify_integrity("8.2.0-ts").unwrap());
+    fs::write(&php_exe, "tampered php").unwrap();
+    assert!(!manager.verify_integrity("8.2.0-ts").unwrap());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
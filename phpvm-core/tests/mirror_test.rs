@@ -0,0 +1,75 @@
+/**
+ * Integration test for falling back to download mirrors when the original host fails
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+mod common;
+
+use common::synthetic_php_archive;
+use phpvm_core::config::{Config, PHPVM_HOME_ENV};
+use phpvm_core::install::Installer;
+use phpvm_core::{PhpState, PhpVersion, PhpVmError};
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_download_falls_back_to_mirror_with_same_path() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let archive_path = "/downloads/releases/php-8.3.0-nts-Win32-vs16-x64.zip";
+    let primary = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&primary)
+        .await;
+    let mirror = MockServer::start().await;
+    let archive = synthetic_php_archive();
+    Mock::given(method("GET"))
+        .and(path(archive_path))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(archive.clone()))
+        .mount(&mirror)
+        .await;
+
+    let mut config = Config::load().unwrap();
+    config.download_mirrors = vec![format!("{}/", mirror.uri())];
+    config.save().unwrap();
+
+    let version = PhpVersion::from_string("8.3.0").unwrap();
+    let source_url = format!("{}{}", primary.uri(), archive_path);
+    let install = || async {
+        Installer::new()
+            .unwrap()
+            .install_version(&version, Some(&source_url), Some(false), true, None)
+            .await
+    };
+
+    // The mirror's checksum still has to match
+    let bad_checksum = Mock::given(method("GET"))
+        .and(path("/downloads/releases/sha256sum.txt"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!("{} *php-8.3.0-nts-Win32-vs16-x64.zip\n", "0".repeat(64))))
+        .mount_as_scoped(&mirror)
+        .await;
+    let error = PhpVmError::from(install().await.unwrap_err());
+    assert_eq!(error.code(), "CHECKSUM_MISMATCH", "{}", error.message());
+    drop(bad_checksum);
+
+    Mock::given(method("GET"))
+        .and(path("/downloads/releases/sha256sum.txt"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            "{} *php-8.3.0-nts-Win32-vs16-x64.zip\n",
+            hex::encode(Sha256::digest(&archive))
+        )))
+        .mount(&mirror)
+        .await;
+    install().await.unwrap();
+
+    // The install records the mirror it actually came from
+    let state = PhpState::load().unwrap();
+    let metadata = state.get_metadata("8.3.0-nts").unwrap();
+    assert_eq!(metadata.source, format!("{}{}", mirror.uri(), archive_path));
+    assert!(primary.received_requests().await.unwrap().iter().any(|r| r.url.path() == archive_path));
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
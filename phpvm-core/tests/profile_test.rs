@@ -0,0 +1,92 @@
+/**
+ * Integration test for profiles: separate installs, state and active version
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+use phpvm_core::config::{self, Config, PHPVM_HOME_ENV, PHPVM_PROFILE_ENV};
+use phpvm_core::{get_current_path, get_php_executable_path, PhpManager, PhpState};
+use std::fs;
+use tempfile::TempDir;
+
+fn fake_install(manager: &PhpManager, version: &str) {
+    let install_dir = Config::load().unwrap().install_dir;
+    let exe = get_php_executable_path(&install_dir.join(format!("php-{}", version)));
+    fs::create_dir_all(exe.parent().unwrap()).unwrap();
+    fs::write(&exe, "fake php").unwrap();
+    let mut state = PhpState::load().unwrap();
+    state.installed_versions.push(version.to_string());
+    state.save().unwrap();
+    assert!(manager.list_installed().unwrap().contains(&version.to_string()));
+}
+
+#[test]
+fn test_profiles_keep_installs_and_active_version_apart() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    std::env::remove_var(PHPVM_PROFILE_ENV);
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let mut config = Config::load().unwrap();
+    config.manage_path = false;
+    config.save().unwrap();
+    let mut manager = PhpManager::new().unwrap();
+
+    // The default profile keeps the flat layout
+    assert_eq!(config::get_active_profile(), config::DEFAULT_PROFILE);
+    assert_eq!(config::get_state_path(), temp_dir.path().join("state.json"));
+    fake_install(&manager, "8.2.10-nts");
+    runtime.block_on(manager.switch("8.2.10-nts")).unwrap();
+
+    config::create_profile("client-a").unwrap();
+    assert!(config::create_profile("client-a").is_err());
+    assert!(config::create_profile("../escape").is_err());
+    assert_eq!(config::list_profiles(), vec!["default".to_string(), "client-a".to_string()]);
+    assert!(manager.switch_profile("missing").is_err());
+
+    manager.switch_profile("client-a").unwrap();
+    let profile_dir = temp_dir.path().join("profiles").join("client-a");
+    assert_eq!(config::get_active_profile(), "client-a");
+    assert_eq!(Config::load().unwrap().install_dir, profile_dir.join("versions"));
+    assert!(manager.list_installed().unwrap().is_empty());
+    assert_eq!(manager.get_active().unwrap(), None);
+
+    fake_install(&manager, "8.3.0-ts");
+    runtime.block_on(manager.switch("8.3.0-ts")).unwrap();
+    assert!(get_current_path().starts_with(&profile_dir));
+    assert!(profile_dir.join("state.json").exists());
+
+    // The profile's install_dir isn't written back to config.json
+    let stored: serde_json::Value = serde_json::from_str(&fs::read_to_string(config::get_config_path()).unwrap()).unwrap();
+    assert_eq!(stored["install_dir"], temp_dir.path().join("versions").to_string_lossy().as_ref());
+    assert_eq!(stored["active_profile"], "client-a");
+
+    // $PHPVM_PROFILE overrides the saved profile for one process
+    std::env::set_var(PHPVM_PROFILE_ENV, "default");
+    assert_eq!(config::get_active_profile(), "default");
+    std::env::remove_var(PHPVM_PROFILE_ENV);
+
+    manager.switch_profile("default").unwrap();
+    assert_eq!(manager.list_installed().unwrap(), vec!["8.2.10-nts".to_string()]);
+    assert_eq!(manager.get_active().unwrap().as_deref(), Some("8.2.10-nts"));
+    assert!(Config::load().unwrap().active_profile.is_none());
+
+    // Uninstalling from another profile still removes every profile's installs and state
+    #[cfg(unix)]
+    {
+        manager.switch_profile("client-a").unwrap();
+        let old_home = std::env::var_os("HOME");
+        std::env::set_var("HOME", temp_dir.path());
+        let report = manager.uninstall_all(false);
+        match old_home {
+            Some(home) => std::env::set_var("HOME", home),
+            None => std::env::remove_var("HOME"),
+        }
+        let report = report.unwrap();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(!temp_dir.path().join("versions").join("php-8.2.10-nts").exists());
+        assert!(!temp_dir.path().join("state.json").exists());
+        assert!(!temp_dir.path().join("current").exists());
+        assert!(!temp_dir.path().join("profiles").exists());
+    }
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
@@ -0,0 +1,68 @@
+/**
+ * Integration tests for conditional revalidation of provider version lists
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+use phpvm_core::config::{ProviderConfig, PHPVM_HOME_ENV};
+use phpvm_core::provider::Provider;
+use tempfile::TempDir;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const LISTING: &str = r#"<a href="php-8.3.0-Win32-vs16-x64.zip">php-8.3.0-Win32-vs16-x64.zip</a>
+<a href="php-8.2.10-Win32-vs16-x64.zip">php-8.2.10-Win32-vs16-x64.zip</a>"#;
+
+#[tokio::test]
+async fn test_version_list_is_revalidated_with_conditional_requests() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let server = MockServer::start().await;
+    // Revalidation with the stored ETag: nothing changed
+    Mock::given(method("GET"))
+        .and(path("/releases/"))
+        .and(header("If-None-Match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/releases/"))
+        .respond_with(ResponseTemplate::new(200).insert_header("ETag", "\"v1\"").set_body_string(LISTING))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let provider = Provider::with_providers(vec![ProviderConfig {
+        name: "mirror".to_string(),
+        url: format!("{}/releases/", server.uri()),
+        verify_checksum: false,
+    }])
+    .unwrap();
+
+    let first = provider.fetch_available_versions().await.unwrap();
+    let second = provider.fetch_available_versions().await.unwrap();
+    let versions = |list: &[phpvm_core::VersionInfo]| list.iter().map(|v| v.version.clone()).collect::<Vec<_>>();
+    assert_eq!(versions(&first), vec!["8.3.0", "8.2.10"]);
+    assert_eq!(versions(&second), versions(&first));
+    assert_eq!(second[0].download_url, first[0].download_url);
+
+    // A server that sends no validators is simply fetched in full each time
+    Mock::given(method("GET"))
+        .and(path("/plain/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(LISTING))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let provider = Provider::with_providers(vec![ProviderConfig {
+        name: "mirror".to_string(),
+        url: format!("{}/plain/", server.uri()),
+        verify_checksum: false,
+    }])
+    .unwrap();
+    assert_eq!(provider.fetch_available_versions().await.unwrap().len(), 2);
+    assert_eq!(provider.fetch_available_versions().await.unwrap().len(), 2);
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
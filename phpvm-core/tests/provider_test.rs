@@ -14,25 +14,36 @@ fn test_provider_new_public_api() {
 #[test]
 fn test_generate_download_url_public_api() {
     // Test newer versions (>= 7.4)
-    let url = Provider::generate_download_url("8.2.0", 8, 2);
+    let url = Provider::generate_download_url("8.2.0", 8, 2, true);
     assert!(url.contains("php-8.2.0-Win32-vs16-x64.zip"));
     
-    let url = Provider::generate_download_url("8.4.0", 8, 4);
+    let url = Provider::generate_download_url("8.4.0", 8, 4, true);
     assert!(url.contains("php-8.4.0-Win32-vs17-x64.zip"));
     
-    let url = Provider::generate_download_url("7.4.33", 7, 4);
+    let url = Provider::generate_download_url("7.4.33", 7, 4, true);
     assert!(url.contains("php-7.4.33-Win32-vc15-x64.zip"));
     
     // Test older versions (< 7.4) - use archives directory
-    let url = Provider::generate_download_url("7.3.33", 7, 3);
+    let url = Provider::generate_download_url("7.3.33", 7, 3, true);
     assert!(url.contains("php-7.3.33-Win32-VC15-x64.zip"));
     assert!(url.contains("archives"));
     
-    let url = Provider::generate_download_url("5.6.40", 5, 6);
+    let url = Provider::generate_download_url("5.6.40", 5, 6, true);
     assert!(url.contains("php-5.6.40-Win32-VC11-x64.zip"));
     assert!(url.contains("archives"));
 }
 
+#[test]
+fn test_generate_download_url_nts_public_api() {
+    let url = Provider::generate_download_url("8.3.0", 8, 3, false);
+    assert!(url.ends_with("php-8.3.0-nts-Win32-vs16-x64.zip"));
+    assert!(!url.contains("archives"));
+    
+    let url = Provider::generate_download_url("7.2.34", 7, 2, false);
+    assert!(url.ends_with("php-7.2.34-nts-Win32-VC15-x64.zip"));
+    assert!(url.contains("archives"));
+}
+
 #[test]
 fn test_get_eol_date_public_api() {
     // Test known EOL dates
@@ -53,3 +64,93 @@ fn test_get_eol_date_public_api() {
     assert_eq!(Provider::get_eol_date(9, 0), None);
     assert_eq!(Provider::get_eol_date(6, 0), None);
 }
+
+#[test]
+fn test_eol_status_public_api() {
+    use phpvm_core::provider::EolStatus;
+
+    // 8.3: active support until 2025-12-31, security fixes until 2027-12-31
+    assert_eq!(Provider::eol_status(8, 3, "2025-12-01"), Some(EolStatus::Supported { days_left: 760 }));
+    assert_eq!(Provider::eol_status(8, 3, "2025-12-31"), Some(EolStatus::Supported { days_left: 730 }));
+    assert_eq!(Provider::eol_status(8, 3, "2027-11-19"), Some(EolStatus::SecurityOnly { days_left: 42 }));
+    assert_eq!(Provider::eol_status(8, 3, "2027-12-31"), Some(EolStatus::SecurityOnly { days_left: 0 }));
+    assert_eq!(Provider::eol_status(8, 3, "2028-01-01"), Some(EolStatus::Ended { days_ago: 1 }));
+    assert_eq!(Provider::eol_status(7, 4, "2024-11-28"), Some(EolStatus::Ended { days_ago: 731 }));
+    assert_eq!(Provider::eol_status(9, 0, "2025-01-01"), None);
+
+    let json = serde_json::to_value(EolStatus::SecurityOnly { days_left: 42 }).unwrap();
+    assert_eq!(json, serde_json::json!({ "status": "security_only", "days_left": 42 }));
+}
+
+#[tokio::test]
+async fn test_checksum_for_url_reads_sibling_sha256sum() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    let hash = "ab".repeat(32);
+    Mock::given(method("GET"))
+        .and(path("/releases/sha256sum.txt"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!("{} *php-8.3.0-Win32-vs16-x64.zip\n", hash)))
+        .mount(&server)
+        .await;
+
+    let provider = Provider::new().unwrap();
+    let url = format!("{}/releases/php-8.3.0-Win32-vs16-x64.zip", server.uri());
+    assert_eq!(provider.checksum_for_url(&url).await.unwrap(), Some(hash));
+
+    let unlisted = format!("{}/releases/php-8.2.0-Win32-vs16-x64.zip", server.uri());
+    assert_eq!(provider.checksum_for_url(&unlisted).await.unwrap(), None);
+    // No checksum file at all is not an error, just nothing to verify against
+    let missing = format!("{}/archives/php-8.3.0-Win32-vs16-x64.zip", server.uri());
+    assert_eq!(provider.checksum_for_url(&missing).await.unwrap(), None);
+
+    // A checksum file that can't be fetched is, so it isn't mistaken for none published
+    Mock::given(method("GET"))
+        .and(path("/broken/sha256sum.txt"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+    let unreachable = format!("{}/broken/php-8.3.0-Win32-vs16-x64.zip", server.uri());
+    assert!(provider.checksum_for_url(&unreachable).await.is_err());
+}
+
+#[tokio::test]
+async fn test_probe_download_reports_status_and_size() {
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let server = MockServer::start().await;
+    Mock::given(method("HEAD"))
+        .and(path("/php-8.3.0-Win32-vs16-x64.zip"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![0u8; 1234]))
+        .mount(&server)
+        .await;
+    // A mirror that rejects HEAD gets a single-byte ranged GET instead
+    Mock::given(method("HEAD"))
+        .and(path("/no-head.zip"))
+        .respond_with(ResponseTemplate::new(405))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/no-head.zip"))
+        .and(header("range", "bytes=0-0"))
+        .respond_with(ResponseTemplate::new(206).insert_header("content-range", "bytes 0-0/31337").set_body_bytes(vec![0u8]))
+        .mount(&server)
+        .await;
+
+    let provider = Provider::new().unwrap();
+    let probe = provider.probe_download(&format!("{}/php-8.3.0-Win32-vs16-x64.zip", server.uri())).await;
+    assert_eq!((probe.status_code, probe.reachable, probe.content_length), (Some(200), true, Some(1234)));
+
+    let probe = provider.probe_download(&format!("{}/no-head.zip", server.uri())).await;
+    assert_eq!((probe.status_code, probe.reachable, probe.content_length), (Some(206), true, Some(31337)));
+
+    let probe = provider.probe_download(&format!("{}/missing.zip", server.uri())).await;
+    assert_eq!((probe.status_code, probe.reachable), (Some(404), false));
+    assert_eq!(probe.error, None);
+
+    let probe = provider.probe_download("http://127.0.0.1:1/php.zip").await;
+    assert_eq!((probe.status_code, probe.reachable), (None, false));
+    assert!(probe.error.is_some());
+}
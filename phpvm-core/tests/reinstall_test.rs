@@ -0,0 +1,75 @@
+/**
+ * Integration test for force-reinstalling a variant that is already installed
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+mod common;
+
+use common::{fake_php_contents, synthetic_php_archive};
+use phpvm_core::config::PHPVM_HOME_ENV;
+use phpvm_core::install::Installer;
+use phpvm_core::{get_current_path, get_php_executable_path, PhpState, PhpVersion, PhpVmError};
+use std::fs;
+use tempfile::TempDir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_force_reinstall_replaces_complete_install() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/php-8.3.0-nts.zip"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(synthetic_php_archive()))
+        .mount(&server)
+        .await;
+    let source_url = format!("{}/php-8.3.0-nts.zip", server.uri());
+
+    // Complete installs of both variants, the NTS one with a leftover file
+    let versions_dir = temp_dir.path().join("versions");
+    for variant in ["ts", "nts"] {
+        let exe = get_php_executable_path(&versions_dir.join(format!("php-8.3.0-{}", variant)));
+        fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        fs::write(&exe, "old php").unwrap();
+    }
+    let stale_file = versions_dir.join("php-8.3.0-nts").join("stale.txt");
+    fs::write(&stale_file, "left over").unwrap();
+
+    let installer = Installer::new().unwrap();
+    let version = PhpVersion::from_string("8.3.0").unwrap();
+    let error = installer
+        .install_version(&version, Some(&source_url), Some(false), false, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(error.downcast_ref::<PhpVmError>(), Some(PhpVmError::AlreadyInstalled(_))));
+    assert!(stale_file.exists());
+
+    let install_path = installer
+        .install_version(&version, Some(&source_url), Some(false), true, None)
+        .await
+        .unwrap();
+    assert_eq!(install_path, versions_dir.join("php-8.3.0-nts"));
+    assert!(!stale_file.exists());
+    assert_eq!(fs::read_to_string(get_php_executable_path(&install_path)).unwrap(), fake_php_contents());
+    assert!(PhpState::load().unwrap().get_metadata("8.3.0-nts").is_some());
+
+    // The other variant is left alone
+    let ts_exe = get_php_executable_path(&versions_dir.join("php-8.3.0-ts"));
+    assert_eq!(fs::read_to_string(ts_exe).unwrap(), "old php");
+
+    // Reinstalling the active version points the shim at the new install
+    let mut state = PhpState::load().unwrap();
+    state.set_active("8.3.0-nts".to_string());
+    state.save().unwrap();
+    assert!(fs::symlink_metadata(get_current_path()).is_err());
+    installer
+        .install_version(&version, Some(&source_url), Some(false), true, None)
+        .await
+        .unwrap();
+    assert!(get_current_path().exists());
+    #[cfg(not(target_os = "windows"))]
+    assert_eq!(fs::read_to_string(get_current_path()).unwrap(), fake_php_contents());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
@@ -0,0 +1,54 @@
+/**
+ * Integration test for upgrading config.json and state.json written before schema versions
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+use phpvm_core::config::{Config, SwitchStrategy, PHPVM_HOME_ENV};
+use phpvm_core::schema::{CONFIG_SCHEMA_VERSION, STATE_SCHEMA_VERSION};
+use phpvm_core::PhpState;
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+const CONFIG_V1: &str = include_str!("fixtures/config_v1.json");
+const STATE_V1: &str = include_str!("fixtures/state_v1.json");
+
+#[test]
+fn test_v1_files_upgrade_to_current_schema() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+    fs::write(temp_dir.path().join("config.json"), CONFIG_V1).unwrap();
+    fs::write(temp_dir.path().join("state.json"), STATE_V1).unwrap();
+
+    let config = Config::load().unwrap();
+    assert_eq!(config.schema_version, CONFIG_SCHEMA_VERSION);
+    assert_eq!(config.install_dir, PathBuf::from("/home/dev/.local/share/phpvm/versions"));
+    assert_eq!(config.active_version.as_deref(), Some("8.2.10"));
+    assert_eq!(config.github_token.as_deref(), Some("ghp_example"));
+    assert!(!config.manage_path);
+    assert_eq!(config.switch_strategy, SwitchStrategy::default());
+    assert_eq!(config.download_idle_timeout_secs, Config::default().download_idle_timeout_secs);
+
+    // The upgrade is written back, with the defaulted fields spelled out
+    let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(temp_dir.path().join("config.json")).unwrap()).unwrap();
+    assert_eq!(saved["schema_version"], CONFIG_SCHEMA_VERSION);
+    assert_eq!(saved["manage_path"], false);
+    assert!(saved.get("download_mirrors").is_some());
+
+    let state = PhpState::load().unwrap();
+    assert_eq!(state.schema_version, STATE_SCHEMA_VERSION);
+    assert_eq!(state.installed_versions, vec!["8.2.10-nts", "8.3.0-ts"]);
+    assert_eq!(state.active_version.as_deref(), Some("8.2.10-nts"));
+    assert_eq!(state.last_known_good.as_deref(), Some("8.3.0-ts"));
+    assert!(state.pinned_versions.is_empty());
+    let metadata = state.get_metadata("8.2.10-nts").unwrap();
+    assert_eq!(metadata.checksum.as_deref(), Some("0f1e2d"));
+    assert_eq!(metadata.installed_at, "1700000000");
+    assert_eq!(metadata.compiler, None);
+    assert_eq!(state.get_metadata("8.3.0-ts").unwrap().source, "unknown");
+
+    let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(temp_dir.path().join("state.json")).unwrap()).unwrap();
+    assert_eq!(saved["schema_version"], STATE_SCHEMA_VERSION);
+    assert_eq!(PhpState::load().unwrap().installed_versions, state.installed_versions);
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
@@ -0,0 +1,70 @@
+/**
+ * Integration test for installs going through a staging directory
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+mod common;
+
+use common::{fake_php_contents, synthetic_php_archive};
+use phpvm_core::config::PHPVM_HOME_ENV;
+use phpvm_core::install::{staging_path_for, Installer};
+use phpvm_core::{get_php_executable_path, PhpState, PhpVersion};
+use std::fs;
+use tempfile::TempDir;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_failed_install_never_touches_final_path() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/corrupt/php-8.3.0-nts.zip"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"not an archive".to_vec()))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/good/php-8.3.0-nts.zip"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(synthetic_php_archive()))
+        .mount(&server)
+        .await;
+
+    let installer = Installer::new().unwrap();
+    let version = PhpVersion::from_string("8.3.0").unwrap();
+    let install_path = temp_dir.path().join("versions").join("php-8.3.0-nts");
+    let staging_path = staging_path_for(&install_path);
+
+    let corrupt_url = format!("{}/corrupt/php-8.3.0-nts.zip", server.uri());
+    assert!(installer
+        .install_version(&version, Some(&corrupt_url), Some(false), false, None)
+        .await
+        .is_err());
+    assert!(!install_path.exists());
+    assert!(!staging_path.exists());
+    assert!(PhpState::load().unwrap().get_metadata("8.3.0-nts").is_none());
+
+    // A staging directory left by a crashed install is cleared, not mistaken for an install
+    fs::create_dir_all(&staging_path).unwrap();
+    fs::write(staging_path.join("half-written.dll"), "partial").unwrap();
+    let good_url = format!("{}/good/php-8.3.0-nts.zip", server.uri());
+    let installed = installer
+        .install_version(&version, Some(&good_url), Some(false), false, None)
+        .await
+        .unwrap();
+    assert_eq!(installed, install_path);
+    assert!(!staging_path.exists());
+    assert!(!install_path.join("half-written.dll").exists());
+    assert_eq!(fs::read_to_string(get_php_executable_path(&install_path)).unwrap(), fake_php_contents());
+
+    // A failed force reinstall leaves the working install in place
+    let corrupt_url = format!("{}/corrupt/php-8.3.0-nts.zip", server.uri());
+    assert!(installer
+        .install_version(&version, Some(&corrupt_url), Some(false), true, None)
+        .await
+        .is_err());
+    assert_eq!(fs::read_to_string(get_php_executable_path(&install_path)).unwrap(), fake_php_contents());
+    assert!(!staging_path.exists());
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
@@ -23,6 +23,7 @@ fn test_state_add_version_public_api() {
         installed_at: "2024-01-01".to_string(),
         checksum: Some("abc123".to_string()),
         source: "official".to_string(),
+        compiler: None,
     };
 
     state.add_version("8.2.0".to_string(), metadata.clone());
@@ -41,6 +42,7 @@ fn test_state_remove_version_public_api() {
         installed_at: "2024-01-01".to_string(),
         checksum: None,
         source: "official".to_string(),
+        compiler: None,
     };
 
     state.add_version("8.2.0".to_string(), metadata);
@@ -73,6 +75,7 @@ fn test_state_get_metadata_public_api() {
         installed_at: "2024-01-01".to_string(),
         checksum: Some("abc123".to_string()),
         source: "official".to_string(),
+        compiler: None,
     };
 
     state.add_version("8.2.0".to_string(), metadata.clone());
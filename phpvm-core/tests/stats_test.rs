@@ -0,0 +1,34 @@
+/**
+ * Integration tests for the local usage stats file
+ * Runs in its own test binary with PHPVM_HOME pointed at a temp dir
+ */
+use phpvm_core::config::{self, PHPVM_HOME_ENV};
+use phpvm_core::stats::{self, UsageEvent, UsageStats};
+use tempfile::TempDir;
+
+#[test]
+fn test_record_survives_missing_and_corrupt_files() {
+    let temp_dir = TempDir::new().unwrap();
+    std::env::set_var(PHPVM_HOME_ENV, temp_dir.path());
+
+    // No stats.json yet
+    assert_eq!(UsageStats::load(), UsageStats::default());
+    stats::record(UsageEvent::Install);
+    stats::record(UsageEvent::Switch("8.3.0-nts"));
+    stats::record(UsageEvent::Downloaded(500));
+
+    let loaded = UsageStats::load();
+    assert_eq!(loaded.installs, 1);
+    assert_eq!(loaded.switches, 1);
+    assert_eq!(loaded.bytes_downloaded, 500);
+    assert!(config::get_stats_path().starts_with(temp_dir.path()));
+
+    // A corrupt file is replaced instead of breaking the operation being counted
+    std::fs::write(config::get_stats_path(), "{ not json").unwrap();
+    stats::record(UsageEvent::Remove);
+    let loaded = UsageStats::load();
+    assert_eq!(loaded.removals, 1);
+    assert_eq!(loaded.installs, 0);
+
+    std::env::remove_var(PHPVM_HOME_ENV);
+}
@@ -2,7 +2,8 @@
  * Integration tests for PhpVersion
  * Tests the public API of the version module
  */
-use phpvm_core::PhpVersion;
+use phpvm_core::version::{validate_version_spec, validate_version_str};
+use phpvm_core::{PhpVersion, PhpVmError};
 
 #[test]
 fn test_version_parsing_public_api() {
@@ -43,3 +44,41 @@ fn test_version_parsing_invalid_public_api() {
     assert!(PhpVersion::from_string("8.2").is_err());
     assert!(PhpVersion::from_string("invalid").is_err());
 }
+
+#[test]
+fn test_validate_version_str_accepts_versions() {
+    for version in ["8.3.0", "8.3.0-ts", "8.3.0-nts", "8.4.0-rc1", "8.4.0-RC2-nts", "8.5.0-beta1"] {
+        assert!(validate_version_str(version).is_ok(), "{} should be valid", version);
+    }
+}
+
+#[test]
+fn test_validate_version_str_rejects_paths() {
+    for version in [
+        "",
+        "..",
+        "../../something",
+        "8.3.0-../../x",
+        "8.3.0/../../x",
+        "8.3.0\\..\\x",
+        "/etc/passwd",
+        "C:\\Windows",
+        "8.3.0-ts/evil",
+        "8.3.0 -ts",
+        "8.3.0-ts\n",
+        "8.3",
+    ] {
+        let err = validate_version_str(version).unwrap_err();
+        assert_eq!(PhpVmError::from(err).code(), "INVALID_VERSION", "{:?} should be rejected", version);
+    }
+}
+
+#[test]
+fn test_validate_version_spec_accepts_branches() {
+    for spec in ["8", "8.2", "8.2-nts", "8.2.5", "8.2.5-ts"] {
+        assert!(validate_version_spec(spec).is_ok(), "{} should be valid", spec);
+    }
+    for spec in ["", "8.", "8.2-", "8.2/..", "../8.2", "8.2-rc1"] {
+        assert!(validate_version_spec(spec).is_err(), "{:?} should be rejected", spec);
+    }
+}
@@ -1,10 +1,14 @@
 use crate::app_state::AppState;
+use crate::progress::emit_progress;
 use crate::update;
 use phpvm_core::config;
+use phpvm_core::download::Downloader;
+use phpvm_core::error::PhpVmError;
+use phpvm_core::install::{ChecksumProgress, InstallOptions};
 use phpvm_core::platform;
+use phpvm_core::provider::Provider;
 use serde::{Deserialize, Serialize};
 use tauri::{Emitter, State};
-use tokio::sync::mpsc;
 
 #[derive(Serialize, Deserialize)]
 pub struct VersionStatus {
@@ -15,13 +19,42 @@ pub struct VersionStatus {
     pub install_path: Option<String>,
     pub release_date: Option<String>,
     pub eol_date: Option<String>,
+    pub active_support_until: Option<String>,
     pub thread_safe: Option<bool>, // true = TS, false = NTS, None = unknown
+    /// Compiler tag recorded at install time ("vs16"), for the variant `install_path` points at
+    pub compiler: Option<String>,
+    /// Whether any installed variant is pinned (kept out of pruning and update checks)
+    pub pinned: bool,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct PathStatus {
     pub is_set: bool,
     pub current_path: String,
+    /// "set", "not_set", or "unmanaged" when `manage_path` is off in config
+    pub status: String,
+}
+
+/// Everything a bug report needs, gathered without network calls
+#[derive(Serialize, Deserialize)]
+pub struct Diagnostics {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub base_dir: String,
+    /// Where `base_dir` came from: "phpvm_home", "data_local_dir", "home_dir" or "temp_dir"
+    pub base_dir_source: config::BaseDirSource,
+    pub install_dir: String,
+    pub installed_count: usize,
+    pub active_version: Option<String>,
+    pub path_status: String,
+    pub elevated: bool,
+    /// Result of the last versionlog.com scrape, if versions were fetched from there
+    pub scrape_health: Option<phpvm_core::provider::ScrapeHealth>,
+    /// Release/EOL dates may be stale because the last scrape found too few of them
+    pub version_metadata_stale: bool,
+    /// The fields above as a plain-text block to paste into an issue
+    pub summary: String,
 }
 
 #[derive(Deserialize)]
@@ -31,6 +64,16 @@ pub struct InstallVersionParams {
     // "ts" = thread safe, "nts" = non-thread safe
     // Frontend always sends "ts" or "nts", never null
     pub thread_safe: String,
+    /// Delete an existing install of this variant and install it again
+    #[serde(default)]
+    pub force_reinstall: bool,
+    /// Download the build made with this compiler ("vs16", "VC15", ...) instead of the
+    /// branch's usual one
+    #[serde(default)]
+    pub compiler_tag: Option<String>,
+    /// Keep only these bundled extensions and delete the rest (can't be undone)
+    #[serde(default)]
+    pub keep_extensions: Option<Vec<String>>,
 }
 
 #[tauri::command]
@@ -38,7 +81,7 @@ pub async fn install_version(
     app: tauri::AppHandle,
     state: State<'_, AppState>,
     params: InstallVersionParams,
-) -> Result<(), String> {
+) -> Result<(), PhpVmError> {
     let version = params.version;
     let thread_safe = params.thread_safe;
     // Log the received parameters for debugging
@@ -83,161 +126,934 @@ pub async fn install_version(
         eprintln!("  - ⚠ WARNING: thread_safe is None - will default to TS");
     }
     
-    // Create a channel for progress updates
-    let (tx, mut rx) = mpsc::unbounded_channel::<(u64, u64, f64)>();
-    let app_for_events = app.clone();
-    
-    // Spawn a task to listen for progress updates and emit events
-    tokio::spawn(async move {
-        let mut last_emitted = std::time::Instant::now();
-        while let Some((downloaded, total, speed_mbps)) = rx.recv().await {
-            let now = std::time::Instant::now();
-            
-            // Emit progress event every 100ms or when complete
-            if now.duration_since(last_emitted).as_millis() >= 100 || downloaded == total {
-                let percent = if total > 0 { (downloaded * 100) / total } else { 0 };
-                eprintln!("[Download Progress] Emitting: downloaded={}, total={}, speed={:.2} MB/s, percent={}%", 
-                    downloaded, total, speed_mbps, percent);
-                
-                let payload = serde_json::json!({
-                    "downloaded": downloaded,
-                    "total": total,
-                    "speed_mbps": speed_mbps,
-                    "percent": percent
-                });
-                
-                if let Err(e) = app_for_events.emit("download-progress", &payload) {
-                    eprintln!("[Download Progress] Failed to emit event: {}", e);
-                } else {
-                    eprintln!("[Download Progress] Event emitted successfully");
-                }
-                
-                last_emitted = now;
+    let progress = emit_progress(&app, "download-progress");
+
+    let manager = state.manager.lock().await;
+
+    // A cached archive needs no download UI: tell the frontend up front so it can go
+    // straight to the extraction phase (the progress callback won't fire for it)
+    let plan = manager.plan_install_with_compiler(&version, thread_safe_bool, params.compiler_tag.as_deref());
+    if let Ok(plan) = &plan {
+        let cached = Downloader::new()
+            .map(|downloader| downloader.is_cached(&plan.download_url))
+            .unwrap_or(false);
+        if cached {
+            let payload = serde_json::json!({
+                "version": version,
+                "variant": plan.variant,
+                "url": plan.download_url
+            });
+            if let Err(e) = app.emit("using-cache", &payload) {
+                eprintln!("[Install] Failed to emit using-cache event: {}", e);
             }
         }
-    });
-    
-    // Create progress callback that sends to channel
-    let last_sent = std::sync::Arc::new(std::sync::Mutex::new((0u64, std::time::Instant::now())));
-    let tx_clone = tx.clone();
-    let progress_callback: Box<dyn FnMut(u64, u64, f64) + Send + Sync> = Box::new(move |downloaded: u64, total: u64, speed_mbps: f64| {
-        eprintln!("[Download Progress Callback] Called with: downloaded={}, total={}, speed={:.2} MB/s", downloaded, total, speed_mbps);
-        let mut last = last_sent.lock().unwrap();
-        let now = std::time::Instant::now();
-        
-        // Send progress update every 100ms or when complete (or immediately if total > 0 and we haven't sent anything yet)
-        if now.duration_since(last.1).as_millis() >= 100 || downloaded == total || (total > 0 && last.0 == 0) {
-            eprintln!("[Download Progress Callback] Sending to channel: downloaded={}, total={}, speed={:.2} MB/s", downloaded, total, speed_mbps);
-            if let Err(e) = tx_clone.send((downloaded, total, speed_mbps)) {
-                eprintln!("[Download Progress Callback] Failed to send to channel: {}", e);
-            } else {
-                eprintln!("[Download Progress Callback] Successfully sent to channel");
+    }
+
+    // Hashing a large install takes a while; report it once per percent
+    let checksum_app = app.clone();
+    let checksum_progress: ChecksumProgress = Box::new(move |done, total| {
+        if done == 0 || done == total || done * 100 / total != (done - 1) * 100 / total {
+            let payload = serde_json::json!({ "done": done, "total": total });
+            if let Err(e) = checksum_app.emit("checksum-progress", &payload) {
+                eprintln!("[Install] Failed to emit checksum-progress event: {}", e);
             }
-            *last = (downloaded, now);
         }
     });
-    
+
+    let result = manager
+        .install_with_options(
+            &version,
+            thread_safe_bool,
+            params.force_reinstall,
+            Some(progress.callback()),
+            InstallOptions {
+                compiler_tag: params.compiler_tag,
+                keep_extensions: params.keep_extensions,
+                checksum_progress: Some(checksum_progress),
+            },
+        )
+        .await
+        .map_err(PhpVmError::from);
+    progress.finish(None).await;
+
+    // Reinstalling the active version refreshed the shim; let the UI pick up the new build
+    if let (Ok(()), Ok(plan)) = (&result, &plan) {
+        let installed = format!("{}-{}", version, plan.variant);
+        if manager.get_active().ok().flatten().as_deref() == Some(installed.as_str()) {
+            if let Err(e) = app.emit("version-switched", &serde_json::json!({ "version": installed })) {
+                eprintln!("[Install] Failed to emit version-switched event: {}", e);
+            }
+        }
+    }
+    result
+}
+
+#[tauri::command]
+pub async fn plan_install(
+    state: State<'_, AppState>,
+    params: InstallVersionParams,
+) -> Result<phpvm_core::install::InstallPlan, PhpVmError> {
+    let thread_safe = match params.thread_safe.as_str() {
+        "nts" => false,
+        "ts" => true,
+        other => {
+            return Err(PhpVmError::InvalidArgument(format!(
+                "Invalid thread_safe value: {:?} (expected \"ts\" or \"nts\")",
+                other
+            )))
+        }
+    };
     let manager = state.manager.lock().await;
-    let result = manager.install(&version, thread_safe_bool, Some(progress_callback)).await.map_err(|e| e.to_string());
-    
-    // Drop the sender to close the channel when done
-    drop(tx);
-    
+    manager.plan_install(&params.version, Some(thread_safe)).map_err(PhpVmError::from)
+}
+
+/// The URL an install would download first and its fallbacks, without any network access
+#[tauri::command]
+pub async fn get_download_url(
+    state: State<'_, AppState>,
+    version: String,
+    thread_safe: bool,
+) -> Result<phpvm_core::install::DownloadUrls, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.get_download_url(&version, Some(thread_safe)).map_err(PhpVmError::from)
+}
+
+/// Probe the URLs an install would download from now: status code and size of the one
+/// it would use, plus every probe made
+#[tauri::command]
+pub async fn check_version_downloadable(
+    state: State<'_, AppState>,
+    version: String,
+    thread_safe: bool,
+) -> Result<phpvm_core::manager::DownloadCheck, PhpVmError> {
+    // Probing can take seconds per URL; don't hold the manager meanwhile
+    let (provider, urls) = {
+        let manager = state.manager.lock().await;
+        let urls = manager
+            .get_download_url(&version, Some(thread_safe))
+            .map_err(PhpVmError::from)?;
+        (manager.provider().map_err(PhpVmError::from)?, urls)
+    };
+    Ok(phpvm_core::manager::probe_download_urls(&provider, &urls).await)
+}
+
+/// Download a version into the cache without installing it. Progress is reported
+/// through the same `download-progress` event as installs.
+#[tauri::command]
+pub async fn prefetch_version(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    params: InstallVersionParams,
+) -> Result<String, PhpVmError> {
+    let thread_safe = match params.thread_safe.as_str() {
+        "nts" => false,
+        "ts" => true,
+        other => {
+            return Err(PhpVmError::InvalidArgument(format!(
+                "Invalid thread_safe value: {:?} (expected \"ts\" or \"nts\")",
+                other
+            )))
+        }
+    };
+    let progress = emit_progress(&app, "download-progress");
+    let manager = state.manager.lock().await;
+    let result = manager
+        .prefetch(&params.version, Some(thread_safe), Some(progress.callback()))
+        .await
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(PhpVmError::from);
+    progress.finish(None).await;
     result
 }
 
+/// The default variant as "ts"/"nts", or null if the user hasn't picked one
+#[tauri::command]
+pub async fn get_default_variant(state: State<'_, AppState>) -> Result<Option<String>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    Ok(manager
+        .get_default_variant()
+        .map(|thread_safe| if thread_safe { "ts" } else { "nts" }.to_string()))
+}
+
+/// Set the variant bare installs and branch switches use ("ts" or "nts"); null clears it
+#[tauri::command]
+pub async fn set_default_variant(
+    state: State<'_, AppState>,
+    variant: Option<String>,
+) -> Result<(), PhpVmError> {
+    let thread_safe = match variant.as_deref() {
+        None => None,
+        Some("ts") => Some(true),
+        Some("nts") => Some(false),
+        Some(other) => {
+            return Err(PhpVmError::InvalidArgument(format!(
+                "Invalid variant: {:?} (expected \"ts\" or \"nts\")",
+                other
+            )))
+        }
+    };
+    let mut manager = state.manager.lock().await;
+    manager.set_default_variant(thread_safe).map_err(PhpVmError::from)
+}
+
+/// Opt-in: point VS Code's `php.validate.executablePath` at the active PHP.
+/// Returns the settings.json that was changed (a `.phpvm-backup` copy is kept).
+#[tauri::command]
+pub async fn register_with_vscode(state: State<'_, AppState>) -> Result<String, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager
+        .register_with_vscode()
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(PhpVmError::from)
+}
+
+/// Undo `register_with_vscode`, restoring the previous setting; false if there was nothing to undo
+#[tauri::command]
+pub async fn unregister_from_vscode(state: State<'_, AppState>) -> Result<bool, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.unregister_from_vscode().map_err(PhpVmError::from)
+}
+
+/// Executable to enter as a PhpStorm CLI interpreter (Settings > PHP > CLI Interpreter)
+#[tauri::command]
+pub async fn get_ide_interpreter_path(state: State<'_, AppState>) -> Result<String, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager
+        .ide_php_executable()
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(PhpVmError::from)
+}
+
+/// Free bytes on the volume holding `path` (the install directory when omitted)
+#[tauri::command]
+pub async fn get_free_space(path: Option<String>) -> Result<u64, PhpVmError> {
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => config::Config::load().map_err(PhpVmError::from)?.install_dir,
+    };
+    platform::available_space(&path).map_err(|e| PhpVmError::Io(format!("Failed to read free space for {:?}: {}", path, e)))
+}
+
+/// Recreate state.json from the version directories on disk (old file kept as state.json.bak)
+#[tauri::command]
+pub async fn rebuild_state(state: State<'_, AppState>) -> Result<phpvm_core::PhpState, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.rebuild_state_from_disk().map_err(PhpVmError::from)
+}
+
+/// Upgrade a version to the newest patch of its branch, keeping php.ini, extra extensions
+/// and its pin. Download progress is reported through `download-progress`.
+#[tauri::command]
+pub async fn upgrade_version(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    version: String,
+    remove_old: bool,
+) -> Result<phpvm_core::manager::UpgradeReport, PhpVmError> {
+    let progress = emit_progress(&app, "download-progress");
+    let manager = state.manager.lock().await;
+    let result = manager
+        .upgrade(&version, remove_old, Some(progress.callback()))
+        .await
+        .map_err(PhpVmError::from);
+    progress.finish(None).await;
+    result
+}
+
+/// The provider's raw release data for a version (dates, download URL, checksum), with
+/// built-in fallbacks filling in what the provider lacks
+#[tauri::command]
+pub async fn get_version_info(
+    state: State<'_, AppState>,
+    version: String,
+) -> Result<Option<phpvm_core::VersionInfo>, PhpVmError> {
+    phpvm_core::version::validate_version_str(&version).map_err(PhpVmError::from)?;
+    let manager = state.manager.lock().await;
+    manager
+        .get_version_info(&version)
+        .await
+        .map_err(PhpVmError::from)
+}
+
+/// The `.phpvmrc` in a directory, so the UI can offer to apply it; None if there is none
+#[tauri::command]
+pub async fn get_project_config(
+    dir: String,
+) -> Result<Option<phpvm_core::project::ProjectConfig>, PhpVmError> {
+    phpvm_core::project::ProjectConfig::load(std::path::Path::new(&dir)).map_err(PhpVmError::from)
+}
+
+/// Install, switch to and enable the extensions a directory's `.phpvmrc` asks for.
+/// Download progress is reported through `download-progress`.
+#[tauri::command]
+pub async fn apply_project_config(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    dir: String,
+) -> Result<phpvm_core::project::ProjectApplyReport, PhpVmError> {
+    let progress = emit_progress(&app, "download-progress");
+    let manager = state.manager.lock().await;
+    let result = manager
+        .apply_project_config(std::path::Path::new(&dir), Some(progress.callback()))
+        .await
+        .map_err(PhpVmError::from);
+    progress.finish(None).await;
+    result
+}
+
+/// Ask the user for a folder with the native picker; None if they cancelled
+#[tauri::command]
+pub async fn pick_folder(title: Option<String>) -> Result<Option<String>, PhpVmError> {
+    let mut dialog = rfd::AsyncFileDialog::new();
+    if let Some(title) = title {
+        dialog = dialog.set_title(title);
+    }
+    Ok(dialog
+        .pick_folder()
+        .await
+        .map(|folder| folder.path().to_string_lossy().to_string()))
+}
+
+/// Ask for a file to open (filtered to `extension`, e.g. "zip"); None if they cancelled
+#[tauri::command]
+pub async fn pick_file(title: Option<String>, extension: Option<String>) -> Result<Option<String>, PhpVmError> {
+    let mut dialog = rfd::AsyncFileDialog::new();
+    if let Some(title) = title {
+        dialog = dialog.set_title(title);
+    }
+    if let Some(extension) = extension {
+        dialog = dialog.add_filter(extension.to_uppercase(), &[extension.as_str()]);
+    }
+    Ok(dialog
+        .pick_file()
+        .await
+        .map(|file| file.path().to_string_lossy().to_string()))
+}
+
+/// Ask where to save a file, suggesting `file_name`; None if they cancelled
+#[tauri::command]
+pub async fn pick_save_file(title: Option<String>, file_name: String) -> Result<Option<String>, PhpVmError> {
+    let mut dialog = rfd::AsyncFileDialog::new().set_file_name(file_name);
+    if let Some(title) = title {
+        dialog = dialog.set_title(title);
+    }
+    Ok(dialog
+        .save_file()
+        .await
+        .map(|file| file.path().to_string_lossy().to_string()))
+}
+
+/// Zip the phpvm directory (config, state, logs; installs and cache when asked) to `dest`
+#[tauri::command]
+pub async fn backup_all(
+    dest: String,
+    include_versions: bool,
+    include_cache: bool,
+) -> Result<phpvm_core::backup::BackupReport, PhpVmError> {
+    tokio::task::spawn_blocking(move || {
+        phpvm_core::backup::backup_all(std::path::Path::new(&dest), include_versions, include_cache)
+    })
+    .await
+    .map_err(|e| PhpVmError::Other(format!("Backup task failed: {}", e)))?
+    .map_err(PhpVmError::from)
+}
+
+/// Restore a `backup_all` zip over the phpvm directory and reactivate its active version
+#[tauri::command]
+pub async fn restore_all(
+    state: State<'_, AppState>,
+    src: String,
+) -> Result<phpvm_core::backup::RestoreReport, PhpVmError> {
+    let mut manager = state.manager.lock().await;
+    manager
+        .restore_all(std::path::Path::new(&src))
+        .await
+        .map_err(PhpVmError::from)
+}
+
+/// Copy an existing PHP directory (e.g. XAMPP's) into phpvm as an installed version.
+/// `thread_safe` is only used when the build doesn't report its variant.
+#[tauri::command]
+pub async fn adopt_php(
+    state: State<'_, AppState>,
+    path: String,
+    thread_safe: Option<bool>,
+) -> Result<String, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager
+        .adopt_external(std::path::Path::new(&path), thread_safe)
+        .map_err(PhpVmError::from)
+}
+
+/// Startup errors/warnings PHP prints for a version's php.ini; empty if it's clean
+#[tauri::command]
+pub async fn lint_ini(
+    state: State<'_, AppState>,
+    version: String,
+) -> Result<Vec<phpvm_core::ini::IniIssue>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.lint_ini(&version).map_err(PhpVmError::from)
+}
+
+/// `date.timezone` in a version's php.ini; None when it isn't set
+#[tauri::command]
+pub async fn get_timezone(state: State<'_, AppState>, version: String) -> Result<Option<String>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.get_timezone(&version).map_err(PhpVmError::from)
+}
+
+/// Set `date.timezone` in a version's php.ini; returns the name as the tz database spells it
+#[tauri::command]
+pub async fn set_timezone(
+    state: State<'_, AppState>,
+    version: String,
+    timezone: String,
+) -> Result<String, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.set_timezone(&version, &timezone).map_err(PhpVmError::from)
+}
+
+/// IANA timezone names `set_timezone` accepts
+#[tauri::command]
+pub fn list_timezones() -> Vec<&'static str> {
+    phpvm_core::timezone::IANA_TIMEZONES.to_vec()
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProfileList {
+    /// "default" first, then the created profiles by name
+    pub profiles: Vec<String>,
+    pub active: String,
+}
+
+#[tauri::command]
+pub async fn list_profiles() -> Result<ProfileList, PhpVmError> {
+    Ok(ProfileList {
+        profiles: config::list_profiles(),
+        active: config::get_active_profile(),
+    })
+}
+
+/// Create an empty profile (its own installs, state and active version)
+#[tauri::command]
+pub async fn create_profile(name: String) -> Result<(), PhpVmError> {
+    config::create_profile(&name).map(|_| ()).map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn switch_profile(state: State<'_, AppState>, name: String) -> Result<(), PhpVmError> {
+    let mut manager = state.manager.lock().await;
+    manager.switch_profile(&name).map_err(PhpVmError::from)
+}
+
+/// Installed, active, available and PATH state in one call (see `StatusReport`)
+#[tauri::command]
+pub async fn get_status_report(
+    state: State<'_, AppState>,
+) -> Result<phpvm_core::manager::StatusReport, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.status_report().await.map_err(PhpVmError::from)
+}
+
 #[tauri::command]
 pub async fn remove_version(
     state: State<'_, AppState>,
     version: String,
-) -> Result<(), String> {
+    force: Option<bool>,
+) -> Result<(), PhpVmError> {
     let manager = state.manager.lock().await;
-    manager.remove(&version).await.map_err(|e| e.to_string())
+    manager.remove(&version, force.unwrap_or(false)).await.map_err(PhpVmError::from)
 }
 
 #[tauri::command]
 pub async fn switch_version(
     state: State<'_, AppState>,
     version: String,
-) -> Result<(), String> {
+) -> Result<phpvm_core::manager::SwitchOutcome, PhpVmError> {
     let manager = state.manager.lock().await;
-    manager.switch(&version).await.map_err(|e| e.to_string())
+    manager.switch_checked(&version).await.map_err(PhpVmError::from)
 }
 
+/// Switch without checking that the new version runs (no rollback)
 #[tauri::command]
-pub async fn list_installed(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+pub async fn switch_version_unchecked(
+    state: State<'_, AppState>,
+    version: String,
+) -> Result<phpvm_core::manager::SwitchOutcome, PhpVmError> {
     let manager = state.manager.lock().await;
-    manager.list_installed().map_err(|e| e.to_string())
+    manager.switch(&version).await.map_err(PhpVmError::from)
 }
 
 #[tauri::command]
-pub async fn list_available(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+pub async fn switch_variant(
+    state: State<'_, AppState>,
+    version: String,
+    thread_safe: bool,
+) -> Result<phpvm_core::manager::SwitchOutcome, PhpVmError> {
     let manager = state.manager.lock().await;
-    manager.list_available().await.map_err(|e| e.to_string())
+    manager.switch_variant(&version, thread_safe).await.map_err(PhpVmError::from)
 }
 
 #[tauri::command]
-pub async fn get_active(state: State<'_, AppState>) -> Result<Option<String>, String> {
+pub async fn write_activation_script(
+    state: State<'_, AppState>,
+    version: String,
+    shell: String,
+) -> Result<String, PhpVmError> {
+    let shell = platform::ShellKind::parse(&shell).map_err(PhpVmError::from)?;
     let manager = state.manager.lock().await;
-    manager.get_active().map_err(|e| e.to_string())
+    let script = manager
+        .generate_activation_script(&version, shell)
+        .map_err(PhpVmError::from)?;
+
+    let script_path = std::env::temp_dir().join(format!(
+        "phpvm-activate-{}.{}",
+        version,
+        shell.script_extension()
+    ));
+    std::fs::write(&script_path, script).map_err(|e| PhpVmError::Io(format!("Failed to write activation script: {}", e)))?;
+    Ok(script_path.to_string_lossy().to_string())
 }
 
+/// Directory to put first on PATH (and, for `shell`, a snippet doing it) to use a
+/// version in one terminal without changing the active version
 #[tauri::command]
-pub async fn get_install_path() -> Result<String, String> {
-    let config = config::Config::load().map_err(|e| e.to_string())?;
+pub async fn shell_activate(
+    state: State<'_, AppState>,
+    version: String,
+    shell: Option<String>,
+) -> Result<phpvm_core::manager::ActivationInfo, PhpVmError> {
+    let shell = shell
+        .as_deref()
+        .map(platform::ShellKind::parse)
+        .transpose()
+        .map_err(PhpVmError::from)?;
+    let manager = state.manager.lock().await;
+    manager.shell_activate(&version, shell).map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn list_installed(state: State<'_, AppState>) -> Result<Vec<String>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.list_installed().map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn verify_integrity(
+    state: State<'_, AppState>,
+    version: String,
+) -> Result<bool, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.verify_integrity(&version).map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn list_available(state: State<'_, AppState>) -> Result<Vec<String>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.list_available().await.map_err(PhpVmError::from)
+}
+
+/// Available versions this OS and CPU can install; elsewhere none, and the reason
+#[tauri::command]
+pub async fn list_installable(
+    state: State<'_, AppState>,
+) -> Result<phpvm_core::manager::InstallableVersions, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.list_installable().await.map_err(PhpVmError::from)
+}
+
+/// Available versions filtered and ordered by `options`, without saving them
+#[tauri::command]
+pub async fn list_available_filtered(
+    state: State<'_, AppState>,
+    options: phpvm_core::config::AvailableFilter,
+) -> Result<Vec<String>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.list_available_filtered(&options).await.map_err(PhpVmError::from)
+}
+
+/// The saved filter `list_available` applies
+#[tauri::command]
+pub async fn get_available_filter(
+    state: State<'_, AppState>,
+) -> Result<phpvm_core::config::AvailableFilter, PhpVmError> {
+    let manager = state.manager.lock().await;
+    Ok(manager.get_available_filter())
+}
+
+/// What the last refreshed version list added or changed, if anything
+#[tauri::command]
+pub async fn get_last_version_delta(
+    state: State<'_, AppState>,
+) -> Result<Option<phpvm_core::provider::VersionDelta>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    Ok(manager.get_last_version_delta())
+}
+
+/// Save the filter `list_available` applies from now on
+#[tauri::command]
+pub async fn set_available_filter(
+    state: State<'_, AppState>,
+    options: phpvm_core::config::AvailableFilter,
+) -> Result<(), PhpVmError> {
+    let mut manager = state.manager.lock().await;
+    manager.set_available_filter(options).map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn list_available_grouped(
+    state: State<'_, AppState>,
+) -> Result<Vec<phpvm_core::provider::BranchInfo>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.list_available_grouped().await.map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn list_branch_patches(
+    state: State<'_, AppState>,
+    major: u8,
+    minor: u8,
+) -> Result<Vec<phpvm_core::VersionInfo>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.list_branch_patches(major, minor).await.map_err(PhpVmError::from)
+}
+
+/// Available versions with an archive in the cache, per variant (unlike
+/// `list_cached_files`, which lists the raw cache files)
+#[tauri::command]
+pub async fn list_cache_ready_versions(
+    state: State<'_, AppState>,
+) -> Result<Vec<phpvm_core::manager::CacheReadyVersion>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.list_cache_ready_versions().await.map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn get_changelog(
+    state: State<'_, AppState>,
+    version: String,
+) -> Result<String, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.get_changelog(&version).await.map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn check_patch_updates(
+    state: State<'_, AppState>,
+) -> Result<Vec<phpvm_core::provider::PatchUpdate>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.check_patch_updates().await.map_err(PhpVmError::from)
+}
+
+/// Startup check that emits `patch-updates-available` when an installed version has a
+/// newer patch. Runs without the manager lock so a slow network can't block other commands.
+pub async fn notify_patch_updates(app: tauri::AppHandle) {
+    let updates = match find_patch_updates().await {
+        Ok(updates) => updates,
+        Err(e) => {
+            eprintln!("[Patch Updates] Check failed: {}", e);
+            return;
+        }
+    };
+    
+    if !updates.is_empty() {
+        if let Err(e) = app.emit("patch-updates-available", &updates) {
+            eprintln!("[Patch Updates] Failed to emit event: {}", e);
+        }
+    }
+}
+
+async fn find_patch_updates() -> anyhow::Result<Vec<phpvm_core::provider::PatchUpdate>> {
+    let state = phpvm_core::PhpState::load()?;
+    // Pinned versions aren't offered updates, same as `PhpManager::check_patch_updates`
+    let installed: Vec<String> = state
+        .installed_versions
+        .iter()
+        .filter(|v| !state.is_pinned(v))
+        .cloned()
+        .collect();
+    if installed.is_empty() {
+        return Ok(vec![]);
+    }
+    let config = config::Config::load()?;
+    let provider = Provider::with_providers(config.providers)?;
+    let versions = provider.fetch_available_versions().await?;
+    Ok(Provider::find_patch_updates(&versions, &installed))
+}
+
+/// Support status of a version's branch as of today, or None if its EOL date is unknown
+#[tauri::command]
+pub async fn get_eol_status(version: String) -> Result<Option<phpvm_core::provider::EolStatus>, PhpVmError> {
+    let base = version.split('-').next().unwrap_or(&version);
+    let parsed = phpvm_core::PhpVersion::from_string(base).map_err(PhpVmError::from)?;
+    Ok(Provider::eol_status(
+        parsed.major,
+        parsed.minor,
+        &phpvm_core::date::today_iso(),
+    ))
+}
+
+/// Keep an installed version out of pruning and patch update checks. Returns the
+/// pinned variant, e.g. "8.1.34-nts".
+#[tauri::command]
+pub async fn pin_version(state: State<'_, AppState>, version: String) -> Result<String, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.pin_version(&version).map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn unpin_version(state: State<'_, AppState>, version: String) -> Result<(), PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.unpin_version(&version).map_err(PhpVmError::from)
+}
+
+/// Compiler, architecture and TS/NTS of an installed version, e.g. for "VS16 x64 TS"
+#[tauri::command]
+pub async fn get_build_info(
+    state: State<'_, AppState>,
+    version: String,
+) -> Result<phpvm_core::manager::BuildInfo, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.get_build_info(&version).map_err(PhpVmError::from)
+}
+
+/// Installs, switches, removals, bytes downloaded and most-used version, from the
+/// local stats.json only
+#[tauri::command]
+pub async fn get_usage_stats(
+    state: State<'_, AppState>,
+) -> Result<phpvm_core::stats::UsageReport, PhpVmError> {
+    let manager = state.manager.lock().await;
+    Ok(manager.get_usage_stats())
+}
+
+#[tauri::command]
+pub async fn get_active(state: State<'_, AppState>) -> Result<Option<String>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.get_active().map_err(PhpVmError::from)
+}
+
+/// The active install as base version, variant and directory
+#[tauri::command]
+pub async fn get_active_detailed(
+    state: State<'_, AppState>,
+) -> Result<Option<phpvm_core::manager::ActiveVersion>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.get_active_detailed().map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn factory_reset(
+    state: State<'_, AppState>,
+    confirm: bool,
+    keep_downloads: bool,
+) -> Result<phpvm_core::manager::UninstallReport, PhpVmError> {
+    if !confirm {
+        return Err(PhpVmError::InvalidArgument(
+            "Factory reset must be confirmed".to_string(),
+        ));
+    }
+    let manager = state.manager.lock().await;
+    manager.uninstall_all(keep_downloads).map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn find_broken_installs(
+    state: State<'_, AppState>,
+) -> Result<Vec<phpvm_core::manager::BrokenInstall>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.find_broken_installs().map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn prune_broken_installs(
+    state: State<'_, AppState>,
+) -> Result<Vec<phpvm_core::manager::BrokenInstall>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.prune_broken_installs().map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn get_install_path() -> Result<String, PhpVmError> {
+    let config = config::Config::load().map_err(PhpVmError::from)?;
     Ok(config.install_dir.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-pub async fn get_log_path() -> Result<String, String> {
+pub async fn get_log_path() -> Result<String, PhpVmError> {
     let log_path = config::get_log_path();
     Ok(log_path.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-pub async fn get_current_dir() -> Result<String, String> {
+pub async fn read_log_tail(max_lines: usize) -> Result<Vec<String>, PhpVmError> {
+    phpvm_core::logging::read_log_tail(max_lines).map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn clear_log() -> Result<(), PhpVmError> {
+    phpvm_core::logging::clear_log().map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn get_log_files() -> Result<Vec<phpvm_core::logging::LogFileInfo>, PhpVmError> {
+    phpvm_core::logging::list_log_files().map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn purge_old_logs(keep: usize) -> Result<usize, PhpVmError> {
+    phpvm_core::logging::purge_old_logs(keep).map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn set_log_level(level: String) -> Result<(), PhpVmError> {
+    phpvm_core::logging::set_log_level(&level).map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn get_log_level() -> Result<String, PhpVmError> {
+    Ok(phpvm_core::logging::get_log_level().to_string())
+}
+
+#[tauri::command]
+pub async fn get_current_dir() -> Result<String, PhpVmError> {
     let current_path = platform::get_current_path();
     let current_dir = current_path
         .parent()
-        .ok_or_else(|| "Invalid current path".to_string())?;
+        .ok_or_else(|| PhpVmError::Other("Invalid current path".to_string()))?;
     Ok(current_dir.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-pub async fn check_path_status() -> Result<PathStatus, String> {
+pub async fn check_path_status() -> Result<PathStatus, PhpVmError> {
+    path_status()
+}
+
+/// What `php` on the app's PATH runs: phpvm's shim ("active"), another php ("shadowed")
+/// or nothing ("not_found")
+#[tauri::command]
+pub async fn check_php_resolution(
+    state: State<'_, AppState>,
+) -> Result<phpvm_core::manager::PhpResolution, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.resolve_php().map_err(PhpVmError::from)
+}
+
+/// Run the php on the app's PATH: its path, version, and whether phpvm manages it
+#[tauri::command]
+pub async fn get_effective_php(
+    state: State<'_, AppState>,
+) -> Result<phpvm_core::manager::EffectivePhp, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.get_effective_php().map_err(PhpVmError::from)
+}
+
+fn path_status() -> Result<PathStatus, PhpVmError> {
     let current_path = platform::get_current_path();
     let current_dir = current_path
         .parent()
-        .ok_or_else(|| "Invalid current path".to_string())?;
+        .ok_or_else(|| PhpVmError::Other("Invalid current path".to_string()))?;
 
     let current_dir_buf = current_dir.to_path_buf();
     let is_set = platform::is_path_set(&current_dir_buf).unwrap_or(false);
+    let managed = config::Config::load().map(|c| c.manage_path).unwrap_or(true);
+    let status = match (managed, is_set) {
+        (false, _) => "unmanaged",
+        (true, true) => "set",
+        (true, false) => "not_set",
+    };
 
     Ok(PathStatus {
         is_set,
         current_path: current_dir.to_string_lossy().to_string(),
+        status: status.to_string(),
     })
 }
 
+/// Whether phpvm runs elevated (admin on Windows, root on Unix), so the GUI can
+/// disable or warn about operations that need it before they fail midway
 #[tauri::command]
-pub async fn set_path() -> Result<(), String> {
+pub async fn check_elevation() -> Result<bool, PhpVmError> {
+    Ok(platform::is_elevated())
+}
+
+#[tauri::command]
+pub async fn get_diagnostics(state: State<'_, AppState>) -> Result<Diagnostics, PhpVmError> {
+    let manager = state.manager.lock().await;
+    let config = config::Config::load().map_err(PhpVmError::from)?;
+    // Report what can be read rather than failing the whole report
+    let installed_count = manager.list_installed().map(|v| v.len()).unwrap_or(0);
+    let active_version = manager.get_active().unwrap_or(None);
+    let path_status = path_status()
+        .map(|s| s.status)
+        .unwrap_or_else(|e| format!("unknown ({})", e.message()));
+
+    let (base_dir, base_dir_source) = config::resolve_base_directory();
+    let scrape_health = phpvm_core::provider::ScrapeHealth::load();
+    let version_metadata_stale = scrape_health.as_ref().is_some_and(|health| health.is_degraded());
+
+    let mut diagnostics = Diagnostics {
+        app_version: update::get_current_version(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        base_dir: base_dir.display().to_string(),
+        base_dir_source,
+        install_dir: config.install_dir.display().to_string(),
+        installed_count,
+        active_version,
+        path_status,
+        elevated: platform::is_elevated(),
+        scrape_health,
+        version_metadata_stale,
+        summary: String::new(),
+    };
+    diagnostics.summary = [
+        format!("phpvm version: {}", diagnostics.app_version),
+        format!("OS: {} ({})", diagnostics.os, diagnostics.arch),
+        format!("Base directory: {} ({:?})", diagnostics.base_dir, diagnostics.base_dir_source),
+        format!("Install directory: {}", diagnostics.install_dir),
+        format!("Installed versions: {}", diagnostics.installed_count),
+        format!("Active version: {}", diagnostics.active_version.as_deref().unwrap_or("none")),
+        format!("PATH: {}", diagnostics.path_status),
+        format!("Elevated: {}", diagnostics.elevated),
+        match &diagnostics.scrape_health {
+            Some(health) => format!(
+                "Version metadata: {} of {} versions dated{}",
+                health.dated,
+                health.total,
+                if diagnostics.version_metadata_stale { " (may be stale)" } else { "" }
+            ),
+            None => "Version metadata: not fetched yet".to_string(),
+        },
+    ]
+    .join("\n");
+    Ok(diagnostics)
+}
+
+#[tauri::command]
+pub async fn set_path() -> Result<(), PhpVmError> {
+    if !config::Config::load().map_err(PhpVmError::from)?.manage_path {
+        return Err(PhpVmError::InvalidArgument(
+            "PATH management is disabled (manage_path is false in config.json)".to_string(),
+        ));
+    }
     let current_path = platform::get_current_path();
     let current_dir = current_path
         .parent()
-        .ok_or_else(|| "Invalid current path".to_string())?;
+        .ok_or_else(|| PhpVmError::Other("Invalid current path".to_string()))?;
 
     let current_dir_buf = current_dir.to_path_buf();
-    platform::add_to_path(&current_dir_buf).map_err(|e| e.to_string())
+    platform::add_to_path(&current_dir_buf).map_err(PhpVmError::from)
 }
 
 #[tauri::command]
 pub async fn get_version_status(
     state: State<'_, AppState>,
     version: String,
-) -> Result<VersionStatus, String> {
+) -> Result<VersionStatus, PhpVmError> {
     let manager = state.manager.lock().await;
-    let installed = manager.list_installed().map_err(|e| e.to_string())?;
-    let active = manager.get_active().map_err(|e| e.to_string())?;
+    let installed = manager.list_installed().map_err(PhpVmError::from)?;
+    let active = manager.get_active().map_err(PhpVmError::from)?;
 
     // Check if TS or NTS variant is installed
     let ts_installed = installed.contains(&format!("{}-ts", version));
@@ -247,9 +1063,26 @@ pub async fn get_version_status(
     // Check if any variant is active (active version is base version without suffix)
     let is_active = active.as_ref().map(|a| a == &version).unwrap_or(false);
 
+    let pinned = {
+        let php_state = phpvm_core::PhpState::load().map_err(PhpVmError::from)?;
+        ["ts", "nts"]
+            .iter()
+            .any(|variant| php_state.is_pinned(&format!("{}-{}", version, variant)))
+    };
+
+    let compiler = if is_installed {
+        let variant = if ts_installed { "ts" } else { "nts" };
+        manager
+            .get_version_metadata(&format!("{}-{}", version, variant))
+            .map_err(PhpVmError::from)?
+            .and_then(|metadata| metadata.compiler)
+    } else {
+        None
+    };
+
     // Get install path if installed (prefer TS if both exist)
     let install_path = if is_installed {
-        let config = config::Config::load().map_err(|e| e.to_string())?;
+        let config = config::Config::load().map_err(PhpVmError::from)?;
         let variant = if ts_installed { "ts" } else { "nts" };
         Some(
             config
@@ -262,16 +1095,27 @@ pub async fn get_version_status(
         None
     };
 
-    // Get release date, EOL date, and online status from provider
+    // Get release date, EOL date, and download URL from provider
     // This fetches from network, but only once per version (not multiple times)
-    let (release_date, eol_date, is_online) = match manager.get_version_info(&version).await {
-        Ok(Some(info)) => (
-            info.release_date,
-            info.eol_date,
-            info.download_url.is_some(),
-        ),
-        Ok(None) => (None, None, false),
-        Err(_) => (None, None, false),
+    let (release_date, eol_date, active_support_until, download_url) =
+        match manager.get_version_info(&version).await {
+            Ok(Some(info)) => (
+                info.release_date,
+                info.eol_date,
+                info.active_support_until,
+                info.download_url,
+            ),
+            Ok(None) => (None, None, None, None),
+            Err(_) => (None, None, None, None),
+        };
+
+    // A synthesized URL doesn't mean the archive still exists - check it actually responds,
+    // without holding the manager while the HEAD request runs
+    let provider = manager.provider().map_err(PhpVmError::from)?;
+    drop(manager);
+    let is_online = match download_url {
+        Some(url) => provider.is_url_available(&url).await,
+        None => false,
     };
 
     // Get thread-safe status - check which variants are installed
@@ -296,63 +1140,44 @@ pub async fn get_version_status(
         install_path,
         release_date,
         eol_date,
+        active_support_until,
         thread_safe,
+        compiler,
+        pinned,
     })
 }
 
+#[tauri::command]
+pub async fn check_versions_online(
+    state: State<'_, AppState>,
+    versions: Vec<String>,
+) -> Result<std::collections::HashMap<String, bool>, PhpVmError> {
+    let provider = state.manager.lock().await.provider().map_err(PhpVmError::from)?;
+    Ok(phpvm_core::manager::check_versions_online_with(&provider, &versions).await)
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct CachedFile {
+    /// Cache file name, derived from the download URL (not a checksum of the contents)
     pub hash: String,
     pub size: u64,
     pub modified: String,
     pub version: Option<String>, // e.g., "8.5.1-ts" or "8.5.1-nts"
+    /// SHA256 of the contents, only if already computed by `get_cached_file_sha256`
+    pub sha256: Option<String>,
 }
 
-// Helper function to hash a URL (same as in download.rs)
-fn hash_url(url: &str) -> String {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-    let mut hasher = DefaultHasher::new();
-    url.hash(&mut hasher);
-    format!("{:x}", hasher.finish())
-}
-
-// Helper function to determine VS/VC version based on PHP version
-// PHP 8.4+ uses vs17 (Visual Studio 2017/2019)
-// PHP 8.0-8.3 uses vs16 (Visual Studio 2016)
-// PHP 7.4 uses vc15 (Visual C++ 2017) or vs16
-// PHP 7.2-7.3 uses VC15 (Visual C++ 2017) - archived, capital VC
-// PHP 7.0-7.1 uses VC14 (Visual C++ 2015) - archived, capital VC
-// PHP 5.6 uses VC11 (Visual C++ 2012) - archived, capital VC
-fn get_vs_version(major: u8, minor: u8) -> &'static str {
-    if major > 8 || (major == 8 && minor >= 4) {
-        "vs17"
-    } else if major == 8 {
-        "vs16"
-    } else if major == 7 {
-        if minor >= 4 {
-            "vc15"
-        } else if minor >= 2 {
-            "VC15"
-        } else {
-            "VC14"
-        }
-    } else {
-        "VC11" // PHP 5.6 and earlier
-    }
-}
-
-// Helper function to get base URL for PHP version
-fn get_base_url(major: u8, minor: u8) -> &'static str {
-    if major < 7 || (major == 7 && minor < 4) {
-        "https://windows.php.net/downloads/releases/archives/"
-    } else {
-        "https://windows.php.net/downloads/releases/"
+// Map a candidate download URL to its cache file name, migrating files cached
+// under the legacy key so they stay labelled
+fn cache_key_for(cache_dir: &std::path::Path, url: &str) -> String {
+    if let Err(e) = Downloader::migrate_legacy_entry(cache_dir, url) {
+        eprintln!("Failed to migrate legacy cache entry for {}: {}", url, e);
     }
+    Downloader::cache_key(url)
 }
 
 #[tauri::command]
-pub async fn list_cached_files(state: State<'_, AppState>) -> Result<Vec<CachedFile>, String> {
+pub async fn list_cached_files(state: State<'_, AppState>) -> Result<Vec<CachedFile>, PhpVmError> {
     let cache_dir = config::get_base_directory().join("cache");
     
     if !cache_dir.exists() {
@@ -361,7 +1186,7 @@ pub async fn list_cached_files(state: State<'_, AppState>) -> Result<Vec<CachedF
     
     // Get all available versions to match against
     let manager = state.manager.lock().await;
-    let available_versions = manager.list_available().await.map_err(|e| e.to_string())?;
+    let available_versions = manager.list_available().await.map_err(PhpVmError::from)?;
     drop(manager);
     
     // Build a hash map of hash -> version once (O(m * p) instead of O(n * m * p))
@@ -378,68 +1203,11 @@ pub async fn list_cached_files(state: State<'_, AppState>) -> Result<Vec<CachedF
                 parts[1].parse::<u8>(),
                 parts[2].parse::<u8>(),
             ) {
-                let vs_version = get_vs_version(major, minor);
-                let base_url = get_base_url(major, minor);
-                
-                // Try TS URL
-                let ts_url = format!(
-                    "{}php-{}-Win32-{}-x64.zip",
-                    base_url, version_str, vs_version
-                );
-                hash_to_version.insert(hash_url(&ts_url), format!("{}-ts", version_str));
-                
-                // Try NTS URL
-                let nts_url = format!(
-                    "{}php-{}-nts-Win32-{}-x64.zip",
-                    base_url, version_str, vs_version
-                );
-                hash_to_version.insert(hash_url(&nts_url), format!("{}-nts", version_str));
-                
-                // Try fallback versions
-                // For vs17, try vs16 as fallback
-                if vs_version == "vs17" {
-                    let fallback_base = "https://windows.php.net/downloads/releases/";
-                    let ts_url_vs16 = format!(
-                        "{}php-{}-Win32-vs16-x64.zip",
-                        fallback_base, version_str
-                    );
-                    hash_to_version.insert(hash_url(&ts_url_vs16), format!("{}-ts", version_str));
-                    
-                    let nts_url_vs16 = format!(
-                        "{}php-{}-nts-Win32-vs16-x64.zip",
-                        fallback_base, version_str
-                    );
-                    hash_to_version.insert(hash_url(&nts_url_vs16), format!("{}-nts", version_str));
-                }
-                // For vs16, try vc15 as fallback (for PHP 7.4)
-                if vs_version == "vs16" {
-                    let fallback_base = "https://windows.php.net/downloads/releases/";
-                    let ts_url_vc15 = format!(
-                        "{}php-{}-Win32-vc15-x64.zip",
-                        fallback_base, version_str
-                    );
-                    hash_to_version.insert(hash_url(&ts_url_vc15), format!("{}-ts", version_str));
-                    
-                    let nts_url_vc15 = format!(
-                        "{}php-{}-nts-Win32-vc15-x64.zip",
-                        fallback_base, version_str
-                    );
-                    hash_to_version.insert(hash_url(&nts_url_vc15), format!("{}-nts", version_str));
-                }
-                // For VC15 (PHP 7.2-7.3), try VC14 as fallback
-                if vs_version == "VC15" {
-                    let fallback_base = "https://windows.php.net/downloads/releases/archives/";
-                    let ts_url_vc14 = format!(
-                        "{}php-{}-Win32-VC14-x64.zip",
-                        fallback_base, version_str
-                    );
-                    hash_to_version.insert(hash_url(&ts_url_vc14), format!("{}-ts", version_str));
-                    
-                    let nts_url_vc14 = format!(
-                        "{}php-{}-nts-Win32-VC14-x64.zip",
-                        fallback_base, version_str
-                    );
-                    hash_to_version.insert(hash_url(&nts_url_vc14), format!("{}-nts", version_str));
+                // Primary URL plus fallback compiler tags, for both TS and NTS
+                for (thread_safe, variant) in [(true, "ts"), (false, "nts")] {
+                    for url in Provider::candidate_download_urls(version_str, major, minor, thread_safe) {
+                        hash_to_version.insert(cache_key_for(&cache_dir, &url), format!("{}-{}", version_str, variant));
+                    }
                 }
             }
         }
@@ -480,6 +1248,7 @@ pub async fn list_cached_files(state: State<'_, AppState>) -> Result<Vec<CachedF
                                 size,
                                 modified,
                                 version: matched_version,
+                                sha256: phpvm_core::download::known_sha256(&path),
                             });
                         }
                     }
@@ -487,7 +1256,7 @@ pub async fn list_cached_files(state: State<'_, AppState>) -> Result<Vec<CachedF
             }
         }
         Err(e) => {
-            return Err(format!("Failed to read cache directory: {}", e));
+            return Err(PhpVmError::Io(format!("Failed to read cache directory: {}", e)));
         }
     }
     
@@ -498,22 +1267,37 @@ pub async fn list_cached_files(state: State<'_, AppState>) -> Result<Vec<CachedF
 }
 
 #[tauri::command]
-pub async fn remove_cached_file(hash: String) -> Result<(), String> {
+pub async fn remove_cached_file(hash: String) -> Result<(), PhpVmError> {
     let cache_dir = config::get_base_directory().join("cache");
     let file_path = cache_dir.join(&hash);
     
     if !file_path.exists() {
-        return Err(format!("Cached file not found: {}", hash));
+        return Err(PhpVmError::Other(format!("Cached file not found: {}", hash)));
     }
     
     std::fs::remove_file(&file_path)
-        .map_err(|e| format!("Failed to remove cached file: {}", e))?;
+        .map_err(|e| PhpVmError::Io(format!("Failed to remove cached file: {}", e)))?;
     
     Ok(())
 }
 
+/// SHA256 of a cached file's contents, to compare with the checksum php.net publishes
+#[tauri::command]
+pub async fn get_cached_file_sha256(hash: String) -> Result<String, PhpVmError> {
+    if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(PhpVmError::InvalidArgument(format!("Invalid cache file name: {}", hash)));
+    }
+    let file_path = config::get_base_directory().join("cache").join(&hash);
+    if !file_path.is_file() {
+        return Err(PhpVmError::Other(format!("Cached file not found: {}", hash)));
+    }
+    phpvm_core::download::file_sha256(file_path)
+        .await
+        .map_err(PhpVmError::from)
+}
+
 #[tauri::command]
-pub async fn clear_all_cache() -> Result<(), String> {
+pub async fn clear_all_cache() -> Result<(), PhpVmError> {
     let cache_dir = config::get_base_directory().join("cache");
     
     if !cache_dir.exists() {
@@ -534,134 +1318,160 @@ pub async fn clear_all_cache() -> Result<(), String> {
             }
         }
         Err(e) => {
-            return Err(format!("Failed to read cache directory: {}", e));
+            return Err(PhpVmError::Io(format!("Failed to read cache directory: {}", e)));
         }
     }
     
     Ok(())
 }
 
+/// Remove cached archives that no available version would install from, and those of
+/// EOL versions when `remove_eol` is set; `clear_all_cache` removes everything
+#[tauri::command]
+pub async fn clean_orphaned_cache(
+    state: State<'_, AppState>,
+    remove_eol: bool,
+) -> Result<phpvm_core::download::CacheCleanReport, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.clean_orphaned_cache(remove_eol).await.map_err(PhpVmError::from)
+}
+
 // ==================== Update Commands ====================
 
 #[tauri::command]
-pub async fn get_app_version() -> Result<String, String> {
+pub async fn get_app_version() -> Result<String, PhpVmError> {
     Ok(update::get_current_version())
 }
 
 #[tauri::command]
-pub async fn check_for_updates() -> Result<update::UpdateInfo, String> {
-    update::check_for_updates().await
+pub async fn check_for_updates() -> Result<update::UpdateInfo, PhpVmError> {
+    update::check_for_updates().await.map_err(PhpVmError::UpdateFailed)
 }
 
 #[tauri::command]
 pub async fn download_update(
     app: tauri::AppHandle,
     download_url: String,
-) -> Result<String, String> {
-    // Create a channel for progress updates
-    let (tx, mut rx) = mpsc::unbounded_channel::<(u64, u64, f64)>();
-    let app_for_events = app.clone();
-    
-    // Spawn a task to listen for progress updates and emit events
-    tokio::spawn(async move {
-        let mut last_emitted = std::time::Instant::now();
-        while let Some((downloaded, total, speed_mbps)) = rx.recv().await {
-            let now = std::time::Instant::now();
-            
-            // Emit progress event every 100ms or when complete
-            if now.duration_since(last_emitted).as_millis() >= 100 || downloaded == total {
-                let percent = if total > 0 { (downloaded * 100) / total } else { 0 };
-                
-                let payload = serde_json::json!({
-                    "downloaded": downloaded,
-                    "total": total,
-                    "speed_mbps": speed_mbps,
-                    "percent": percent
-                });
-                
-                if let Err(e) = app_for_events.emit("update-download-progress", &payload) {
-                    eprintln!("[Update Download] Failed to emit event: {}", e);
-                }
-                
-                last_emitted = now;
-            }
-        }
-    });
-    
-    // Create progress callback that sends to channel
-    let last_sent = std::sync::Arc::new(std::sync::Mutex::new((0u64, std::time::Instant::now())));
-    let tx_clone = tx.clone();
-    let progress_callback: Box<dyn FnMut(u64, u64, f64) + Send + Sync> = Box::new(move |downloaded: u64, total: u64, speed_mbps: f64| {
-        let mut last = last_sent.lock().unwrap();
-        let now = std::time::Instant::now();
-        
-        // Send progress update every 100ms or when complete
-        if now.duration_since(last.1).as_millis() >= 100 || downloaded == total || (total > 0 && last.0 == 0) {
-            if let Err(e) = tx_clone.send((downloaded, total, speed_mbps)) {
-                eprintln!("[Update Download] Failed to send to channel: {}", e);
-            }
-            *last = (downloaded, now);
-        }
-    });
-    
-    let update_file = update::download_update(&download_url, Some(progress_callback)).await?;
-    
-    // Send final progress update (100% complete) before closing channel
-    let file_size = std::fs::metadata(&update_file)
-        .map(|m| m.len())
-        .unwrap_or(0);
-    
-    // Send final update
-    if let Err(e) = tx.send((file_size, file_size, 0.0)) {
-        eprintln!("[Update Download] Failed to send final progress: {}", e);
-    }
-    
-    // Give a small delay to ensure the final event is processed
-    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-    
-    // Drop the sender to close the channel when done
-    drop(tx);
-    
+) -> Result<String, PhpVmError> {
+    let progress = emit_progress(&app, "update-download-progress");
+    let update_file = update::download_update(&download_url, Some(progress.callback())).await;
+    let file_size = update_file
+        .as_ref()
+        .ok()
+        .and_then(|file| std::fs::metadata(file).ok())
+        .map(|metadata| metadata.len());
+    progress.finish(file_size).await;
+    let update_file = update_file.map_err(PhpVmError::UpdateFailed)?;
+
     eprintln!("[Update Download] Download complete: {}", update_file.display());
     Ok(update_file.to_string_lossy().to_string())
 }
 
 #[tauri::command]
-pub async fn apply_update(update_file_path: String) -> Result<(), String> {
+pub async fn apply_update(update_file_path: String) -> Result<(), PhpVmError> {
     let update_file = std::path::PathBuf::from(update_file_path);
     if !update_file.exists() {
-        return Err("Update file not found".to_string());
+        return Err(PhpVmError::UpdateFailed("Update file not found".to_string()));
     }
     
-    update::apply_update(update_file)
+    // Waiting on an installer blocks, so keep it off the async runtime
+    tokio::task::spawn_blocking(move || update::apply_update(update_file))
+        .await
+        .map_err(|e| PhpVmError::UpdateFailed(format!("Update task failed: {}", e)))?
+        .map_err(PhpVmError::UpdateFailed)
+}
+
+/// Quit so a pending update can replace the executable. Called by the frontend once
+/// `apply_update` has returned and it has shown that the app is restarting; the update
+/// script waits for this process to exit before copying.
+#[tauri::command]
+pub fn exit_for_update(app: tauri::AppHandle) {
+    eprintln!("[Update] Exiting to let the update finish");
+    app.exit(0);
 }
 
 #[tauri::command]
-pub async fn open_url(url: String) -> Result<(), String> {
+pub async fn open_url(url: String) -> Result<(), PhpVmError> {
+    open_with_default_app(&url).map_err(|e| PhpVmError::Other(format!("Failed to open URL: {}", e)))
+}
+
+#[tauri::command]
+pub async fn get_active_ini_path(state: State<'_, AppState>) -> Result<Option<String>, PhpVmError> {
+    let manager = state.manager.lock().await;
+    let ini_path = manager.active_ini_path().map_err(PhpVmError::from)?;
+    Ok(ini_path.map(|p| p.to_string_lossy().to_string()))
+}
+
+#[tauri::command]
+pub async fn open_active_ini(state: State<'_, AppState>) -> Result<String, PhpVmError> {
+    let manager = state.manager.lock().await;
+    let ini_path = manager
+        .active_ini_path()
+        .map_err(PhpVmError::from)?
+        .ok_or_else(|| PhpVmError::NotInstalled("No active PHP version".to_string()))?;
+    if !ini_path.exists() {
+        return Err(PhpVmError::Io(format!(
+            "{} does not exist yet. Copy php.ini-development or php.ini-production from the same folder to create it.",
+            ini_path.display()
+        )));
+    }
+    open_with_default_app(&ini_path.to_string_lossy())
+        .map_err(|e| PhpVmError::Other(format!("Failed to open {}: {}", ini_path.display(), e)))?;
+    Ok(ini_path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn diff_modules(
+    state: State<'_, AppState>,
+    from: String,
+    to: String,
+) -> Result<phpvm_core::manager::ModuleDiff, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.diff_modules(&from, &to).map_err(PhpVmError::from)
+}
+
+#[tauri::command]
+pub async fn install_xdebug(version: String, state: State<'_, AppState>) -> Result<String, PhpVmError> {
+    let manager = state.manager.lock().await;
+    let dll_path = manager.install_xdebug(&version).await.map_err(PhpVmError::from)?;
+    Ok(dll_path.to_string_lossy().to_string())
+}
+
+/// Delete the bundled extensions of an installed version that aren't in `keep`; only a
+/// reinstall brings them back
+#[tauri::command]
+pub async fn strip_extensions(
+    version: String,
+    keep: Vec<String>,
+    state: State<'_, AppState>,
+) -> Result<phpvm_core::extension::StripReport, PhpVmError> {
+    let manager = state.manager.lock().await;
+    manager.strip_extensions(&version, &keep).map_err(PhpVmError::from)
+}
+
+// Open a URL or file with the platform's default handler
+fn open_with_default_app(target: &str) -> std::io::Result<()> {
     use std::process::Command;
     
     #[cfg(target_os = "windows")]
     {
         Command::new("cmd")
-            .args(["/C", "start", "", &url])
-            .spawn()
-            .map_err(|e| format!("Failed to open URL: {}", e))?;
+            .args(["/C", "start", "", target])
+            .spawn()?;
     }
     
     #[cfg(target_os = "macos")]
     {
         Command::new("open")
-            .arg(&url)
-            .spawn()
-            .map_err(|e| format!("Failed to open URL: {}", e))?;
+            .arg(target)
+            .spawn()?;
     }
     
     #[cfg(target_os = "linux")]
     {
         Command::new("xdg-open")
-            .arg(&url)
-            .spawn()
-            .map_err(|e| format!("Failed to open URL: {}", e))?;
+            .arg(target)
+            .spawn()?;
     }
     
     Ok(())
@@ -2,6 +2,7 @@
 
 mod app_state;
 mod commands;
+mod progress;
 mod update;
 
 use app_state::AppState;
@@ -14,6 +15,8 @@ fn main() {
             .parent()
             .unwrap(),
     );
+    // tracing output follows the level set_log_level picks
+    phpvm_core::logging::init_tracing();
 
     let app_state = match AppState::new() {
         Ok(state) => state,
@@ -27,16 +30,29 @@ fn main() {
         .manage(app_state)
         .invoke_handler(tauri::generate_handler![
             install_version,
+            plan_install,
             remove_version,
             switch_version,
+            switch_version_unchecked,
+            switch_variant,
+            write_activation_script,
             list_installed,
+            verify_integrity,
             list_available,
+            list_available_grouped,
+            check_patch_updates,
             get_active,
+            factory_reset,
             get_install_path,
             get_log_path,
+            read_log_tail,
+            clear_log,
+            get_log_files,
+            purge_old_logs,
             check_path_status,
             set_path,
             get_version_status,
+            check_versions_online,
             get_current_dir,
             list_cached_files,
             remove_cached_file,
@@ -45,11 +61,77 @@ fn main() {
             check_for_updates,
             download_update,
             apply_update,
-            open_url
+            open_url,
+            get_active_ini_path,
+            open_active_ini,
+            install_xdebug,
+            find_broken_installs,
+            prune_broken_installs,
+            diff_modules,
+            get_eol_status,
+            check_elevation,
+            list_branch_patches,
+            get_changelog,
+            get_diagnostics,
+            get_cached_file_sha256,
+            get_build_info,
+            get_usage_stats,
+            pin_version,
+            unpin_version,
+            prefetch_version,
+            list_cache_ready_versions,
+            get_default_variant,
+            set_default_variant,
+            register_with_vscode,
+            unregister_from_vscode,
+            get_ide_interpreter_path,
+            get_free_space,
+            rebuild_state,
+            get_status_report,
+            list_profiles,
+            create_profile,
+            switch_profile,
+            lint_ini,
+            upgrade_version,
+            pick_folder,
+            adopt_php,
+            exit_for_update,
+            get_project_config,
+            apply_project_config,
+            get_version_info,
+            list_available_filtered,
+            get_available_filter,
+            set_available_filter,
+            check_php_resolution,
+            shell_activate,
+            pick_file,
+            pick_save_file,
+            backup_all,
+            restore_all,
+            set_log_level,
+            get_log_level,
+            get_last_version_delta,
+            get_download_url,
+            clean_orphaned_cache,
+            strip_extensions,
+            get_effective_php,
+            list_installable,
+            get_active_detailed,
+            check_version_downloadable,
+            get_timezone,
+            set_timezone,
+            list_timezones
         ])
-        .setup(|_app| {
+        .setup(|app| {
             // App initialization code can go here
             // Update check is triggered from frontend after app loads
+            // Patch update check runs in the background so it never delays startup
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                // Give the frontend time to register its event listener
+                tokio::time::sleep(std::time::Duration::from_secs(3)).await;
+                commands::notify_patch_updates(handle).await;
+            });
             Ok(())
         })
         .run(tauri::generate_context!())
@@ -0,0 +1,71 @@
+use phpvm_core::download::ProgressCallback;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Minimum time between two progress events, so the frontend isn't flooded
+const EMIT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Forwards download progress to the frontend as `event_name` events with the payload
+/// `{ downloaded, total, speed_mbps, percent }`. Events are throttled to one per
+/// `EMIT_INTERVAL`; the first update and the completed one always go out.
+pub struct ProgressEmitter {
+    tx: mpsc::UnboundedSender<(u64, u64, f64)>,
+    listener: JoinHandle<()>,
+}
+
+/// Start forwarding progress for one operation. Pass `callback()` to the download and
+/// call `finish` once it's done.
+pub fn emit_progress(app: &tauri::AppHandle, event_name: &'static str) -> ProgressEmitter {
+    let (tx, mut rx) = mpsc::unbounded_channel::<(u64, u64, f64)>();
+    let app = app.clone();
+    let listener = tokio::spawn(async move {
+        while let Some((downloaded, total, speed_mbps)) = rx.recv().await {
+            let percent = (downloaded * 100).checked_div(total).unwrap_or(0);
+            let payload = serde_json::json!({
+                "downloaded": downloaded,
+                "total": total,
+                "speed_mbps": speed_mbps,
+                "percent": percent
+            });
+            if let Err(e) = app.emit(event_name, &payload) {
+                eprintln!("[Progress] Failed to emit {}: {}", event_name, e);
+            }
+        }
+    });
+    ProgressEmitter { tx, listener }
+}
+
+impl ProgressEmitter {
+    /// Callback for `Downloader`-style progress reporting (downloaded, total, MB/s)
+    pub fn callback(&self) -> ProgressCallback {
+        let tx = self.tx.clone();
+        let mut last_sent: Option<Instant> = None;
+        Box::new(move |downloaded: u64, total: u64, speed_mbps: f64| {
+            let now = Instant::now();
+            let due = match last_sent {
+                None => true,
+                Some(last) => now.duration_since(last) >= EMIT_INTERVAL,
+            };
+            if due || (total > 0 && downloaded == total) {
+                // The listener only stops once every sender is gone, so this can't fail
+                let _ = tx.send((downloaded, total, speed_mbps));
+                last_sent = Some(now);
+            }
+        })
+    }
+
+    /// Emit a final 100% event for `total_bytes` (when known) and wait until every
+    /// queued event has been sent to the frontend. Call it after the download has
+    /// returned, since a callback that's still alive keeps the event task open.
+    pub async fn finish(self, total_bytes: Option<u64>) {
+        if let Some(total) = total_bytes {
+            let _ = self.tx.send((total, total, 0.0));
+        }
+        drop(self.tx);
+        if let Err(e) = self.listener.await {
+            eprintln!("[Progress] Event task failed: {}", e);
+        }
+    }
+}
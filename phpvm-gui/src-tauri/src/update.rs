@@ -1,11 +1,34 @@
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 const GITHUB_REPO: &str = "vunf1/php-version-manager";
 const GITHUB_API_BASE: &str = "https://api.github.com/repos";
+const GITHUB_WEB_BASE: &str = "https://github.com";
+const DEFAULT_USER_AGENT: &str = "PHP-Version-Manager";
+/// Attempts per endpoint for network errors and 5xx responses
+const UPDATE_CHECK_ATTEMPTS: u32 = 3;
+/// Reuse the last successful check for this long so repeated checks don't hit the rate limit
+const UPDATE_CHECK_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
 
-#[derive(Debug, Serialize, Deserialize)]
+static LAST_UPDATE_INFO: Mutex<Option<(Instant, UpdateInfo)>> = Mutex::new(None);
+
+/// A launcher that dies within this window (pkexec without a polkit agent, a broken
+/// installer) is reported as an error instead of a silent no-op
+const LAUNCH_CHECK_WINDOW: Duration = Duration::from_secs(3);
+/// How long to wait for a quiet MSI install to report its exit code
+#[cfg(windows)]
+const MSI_WAIT_TIMEOUT: Duration = Duration::from_secs(600);
+/// How long the update script waits for the app to exit before trying the copy anyway
+#[cfg(any(windows, test))]
+const EXIT_WAIT_SECONDS: u32 = 30;
+/// Copy attempts, a second apart, while the old executable is still locked
+#[cfg(any(windows, test))]
+const COPY_ATTEMPTS: u32 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateInfo {
     pub current_version: String,
     pub latest_version: String,
@@ -176,35 +199,270 @@ fn detect_platform_asset(assets: &[GitHubAsset]) -> Option<String> {
         })
 }
 
-/// Check for updates by querying GitHub releases API
-pub async fn check_for_updates() -> Result<UpdateInfo, String> {
-    let current_version = get_current_version();
+/// Why fetching release info from one endpoint failed
+#[derive(Debug)]
+enum ReleaseFetchError {
+    /// HTTP 403/429 with the rate limit exhausted; carries the reset time (Unix seconds) if known
+    RateLimited(Option<u64>),
+    /// Network error or 5xx, worth retrying
+    Transient(String),
+    /// Anything else (404, bad JSON, ...)
+    Failed(String),
+}
+
+impl std::fmt::Display for ReleaseFetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReleaseFetchError::RateLimited(reset) => write!(f, "{}", rate_limit_message(*reset, now_secs())),
+            ReleaseFetchError::Transient(e) | ReleaseFetchError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn rate_limit_message(reset: Option<u64>, now: u64) -> String {
+    match reset {
+        Some(reset) => {
+            let minutes = reset.saturating_sub(now).div_ceil(60);
+            let secs_of_day = reset % 86_400;
+            format!(
+                "GitHub API rate limit exceeded. It resets at {:02}:{:02} UTC (in about {} minute{}).",
+                secs_of_day / 3600,
+                (secs_of_day % 3600) / 60,
+                minutes,
+                if minutes == 1 { "" } else { "s" }
+            )
+        }
+        None => "GitHub API rate limit exceeded. Please try again later.".to_string(),
+    }
+}
+
+/// Classify a non-success response, reading the rate limit headers GitHub sends
+fn classify_error_response(response: &reqwest::Response) -> ReleaseFetchError {
+    let status = response.status();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.trim().to_string())
+    };
+    
+    let remaining = header("x-ratelimit-remaining");
+    let retry_after = header("retry-after").and_then(|v| v.parse::<u64>().ok());
+    let is_rate_limited = (status == reqwest::StatusCode::FORBIDDEN
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS)
+        && (remaining.as_deref() == Some("0") || retry_after.is_some());
+    
+    if is_rate_limited {
+        let reset = header("x-ratelimit-reset")
+            .and_then(|v| v.parse::<u64>().ok())
+            .or_else(|| retry_after.map(|secs| now_secs() + secs));
+        ReleaseFetchError::RateLimited(reset)
+    } else if status.is_server_error() {
+        ReleaseFetchError::Transient(format!("GitHub API returned error: {}", status))
+    } else {
+        ReleaseFetchError::Failed(format!("GitHub API returned error: {}", status))
+    }
+}
+
+/// User-Agent and optional GitHub token for update requests, from config.
+/// The token is only ever sent to GitHub hosts and is never logged.
+struct HttpSettings {
+    user_agent: String,
+    github_token: Option<String>,
+}
+
+impl HttpSettings {
+    fn load() -> Self {
+        let config = phpvm_core::config::Config::load().ok();
+        Self::from_config(config.as_ref())
+    }
     
-    // Fetch latest release from GitHub
-    let client = reqwest::Client::builder()
-        .user_agent("PHP-Version-Manager")
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    fn from_config(config: Option<&phpvm_core::config::Config>) -> Self {
+        let non_empty = |value: Option<&String>| {
+            value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+        };
+        HttpSettings {
+            user_agent: non_empty(config.and_then(|c| c.user_agent.as_ref()))
+                .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+            github_token: non_empty(config.and_then(|c| c.github_token.as_ref())),
+        }
+    }
     
-    let url = format!("{}/{}/releases/latest", GITHUB_API_BASE, GITHUB_REPO);
+    fn client(&self, timeout: Option<Duration>) -> Result<reqwest::Client, String> {
+        let mut builder = reqwest::Client::builder().user_agent(self.user_agent.as_str());
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(timeout);
+        }
+        builder
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))
+    }
     
+    /// GET `url`, authenticated when a token is configured and `url` is on GitHub
+    fn get(&self, client: &reqwest::Client, url: &str) -> reqwest::RequestBuilder {
+        let request = client.get(url);
+        match &self.github_token {
+            Some(token) if is_github_url(url) => request.bearer_auth(token),
+            _ => request,
+        }
+    }
+}
+
+/// Whether `url` points at github.com or api.github.com (never mirrors or other hosts)
+fn is_github_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|host| host == "github.com" || host == "api.github.com"))
+        .unwrap_or(false)
+}
+
+/// Fetch the latest release JSON from one API endpoint, retrying transient failures with backoff
+async fn fetch_release_with_retries(
+    client: &reqwest::Client,
+    settings: &HttpSettings,
+    url: &str,
+) -> Result<GitHubRelease, ReleaseFetchError> {
+    let mut attempt = 1;
+    loop {
+        let result = match settings.get(client, url).send().await {
+            Ok(response) if response.status().is_success() => response
+                .json::<GitHubRelease>()
+                .await
+                .map_err(|e| ReleaseFetchError::Failed(format!("Failed to parse release info: {}", e))),
+            Ok(response) => Err(classify_error_response(&response)),
+            Err(e) => Err(ReleaseFetchError::Transient(format!("Failed to fetch release info: {}", e))),
+        };
+        
+        match result {
+            Err(ReleaseFetchError::Transient(e)) if attempt < UPDATE_CHECK_ATTEMPTS => {
+                let delay = Duration::from_secs(1 << (attempt - 1));
+                eprintln!("[Update] {} (attempt {}/{}), retrying in {}s", e, attempt, UPDATE_CHECK_ATTEMPTS, delay.as_secs());
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            other => return other,
+        }
+    }
+}
+
+/// Fallback that needs no API quota: follow the web `releases/latest` redirect to find
+/// the tag, then read asset links from the release's expanded assets fragment
+async fn fetch_release_from_html(client: &reqwest::Client) -> Result<GitHubRelease, String> {
+    let latest_url = format!("{}/{}/releases/latest", GITHUB_WEB_BASE, GITHUB_REPO);
     let response = client
-        .get(&url)
+        .get(&latest_url)
         .send()
         .await
-        .map_err(|e| format!("Failed to fetch release info: {}", e))?;
-    
+        .map_err(|e| format!("Failed to fetch releases page: {}", e))?;
     if !response.status().is_success() {
-        return Err(format!(
-            "GitHub API returned error: {}",
-            response.status()
-        ));
+        return Err(format!("GitHub releases page returned error: {}", response.status()));
     }
     
-    let release: GitHubRelease = response
-        .json()
+    let html_url = response.url().to_string();
+    let tag_name = html_url
+        .rsplit_once("/releases/tag/")
+        .map(|(_, tag)| tag.trim_end_matches('/').to_string())
+        .filter(|tag| !tag.is_empty())
+        .ok_or_else(|| format!("Could not find release tag in {}", html_url))?;
+    
+    let assets_url = format!("{}/{}/releases/expanded_assets/{}", GITHUB_WEB_BASE, GITHUB_REPO, tag_name);
+    let assets_html = client
+        .get(&assets_url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch release assets: {}", e))?
+        .text()
         .await
-        .map_err(|e| format!("Failed to parse release info: {}", e))?;
+        .map_err(|e| format!("Failed to read release assets: {}", e))?;
+    
+    Ok(GitHubRelease {
+        assets: parse_release_assets(&assets_html, &tag_name),
+        tag_name,
+        html_url,
+    })
+}
+
+/// Extract download links for `tag` from a GitHub release assets page
+fn parse_release_assets(html: &str, tag: &str) -> Vec<GitHubAsset> {
+    let prefix = format!("/{}/releases/download/{}/", GITHUB_REPO, tag);
+    let mut assets: Vec<GitHubAsset> = Vec::new();
+    
+    for part in html.split("href=\"").skip(1) {
+        let Some(href) = part.split('"').next() else { continue };
+        let Some(name) = href.strip_prefix(&prefix) else { continue };
+        if name.is_empty() || name.contains('/') || assets.iter().any(|a| a.name == name) {
+            continue;
+        }
+        assets.push(GitHubAsset {
+            name: name.replace("%20", " "),
+            browser_download_url: format!("{}{}", GITHUB_WEB_BASE, href),
+        });
+    }
+    assets
+}
+
+/// Check for updates by querying GitHub releases API.
+/// Tries api.github.com, then any configured mirrors, then the public releases page.
+pub async fn check_for_updates() -> Result<UpdateInfo, String> {
+    if let Some((checked_at, info)) = LAST_UPDATE_INFO.lock().unwrap().as_ref() {
+        if checked_at.elapsed() < UPDATE_CHECK_CACHE_TTL {
+            return Ok(info.clone());
+        }
+    }
+    
+    let current_version = get_current_version();
+    
+    let config = phpvm_core::config::Config::load().ok();
+    let settings = HttpSettings::from_config(config.as_ref());
+    let client = settings.client(Some(Duration::from_secs(20)))?;
+    if settings.github_token.is_some() {
+        eprintln!("[Update] Using the configured GitHub token");
+    }
+    
+    let mirrors = config.map(|c| c.update_mirrors).unwrap_or_default();
+    let endpoints: Vec<String> = std::iter::once(GITHUB_API_BASE.to_string())
+        .chain(mirrors)
+        .map(|base| format!("{}/{}/releases/latest", base.trim_end_matches('/'), GITHUB_REPO))
+        .collect();
+    
+    let mut errors: Vec<ReleaseFetchError> = Vec::new();
+    let mut release = None;
+    for url in &endpoints {
+        match fetch_release_with_retries(&client, &settings, url).await {
+            Ok(r) => {
+                release = Some(r);
+                break;
+            }
+            Err(e) => {
+                eprintln!("[Update] Release check via {} failed: {}", url, e);
+                errors.push(e);
+            }
+        }
+    }
+    
+    let release = match release {
+        Some(r) => r,
+        None => match fetch_release_from_html(&client).await {
+            Ok(r) => r,
+            Err(html_error) => {
+                eprintln!("[Update] Releases page fallback failed: {}", html_error);
+                // The rate limit is the most actionable cause, so report it first
+                let primary = errors
+                    .iter()
+                    .find(|e| matches!(e, ReleaseFetchError::RateLimited(_)))
+                    .or(errors.first());
+                return Err(primary.map(|e| e.to_string()).unwrap_or(html_error));
+            }
+        },
+    };
     
     // Remove 'v' prefix if present and normalize version string
     let latest_version = release.tag_name.trim_start_matches('v').to_string();
@@ -223,13 +481,15 @@ pub async fn check_for_updates() -> Result<UpdateInfo, String> {
         eprintln!("[Update] Found update asset: {}", download_url.as_ref().unwrap());
     }
     
-    Ok(UpdateInfo {
+    let info = UpdateInfo {
         current_version,
         latest_version,
         update_available,
         download_url,
         release_url: Some(release.html_url),
-    })
+    };
+    *LAST_UPDATE_INFO.lock().unwrap() = Some((Instant::now(), info.clone()));
+    Ok(info)
 }
 
 /// Compare two version strings (e.g., "0.1.0" vs "0.2.0")
@@ -295,13 +555,11 @@ fn get_file_extension_from_url(url: &str) -> &str {
 
 /// Download the update file to a temporary location
 pub async fn download_update(download_url: &str, mut progress_callback: Option<Box<dyn FnMut(u64, u64, f64) + Send + Sync>>) -> Result<PathBuf, String> {
-    let client = reqwest::Client::builder()
-        .user_agent("PHP-Version-Manager")
-        .build()
-        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let settings = HttpSettings::load();
+    let client = settings.client(None)?;
     
-    let response = client
-        .get(download_url)
+    let response = settings
+        .get(&client, download_url)
         .send()
         .await
         .map_err(|e| format!("Failed to download update: {}", e))?;
@@ -401,12 +659,30 @@ pub fn apply_update(update_file: PathBuf) -> Result<(), String> {
     // Handle MSI installer
     if file_ext.eq_ignore_ascii_case("msi") {
         eprintln!("[Update] Detected MSI installer, launching installation...");
-        // Launch MSI installer
-        Command::new("msiexec")
-            .args(&["/i", update_file.to_str().unwrap(), "/quiet", "/norestart"])
+        // Quiet installs don't show UI, so wait for the exit code and keep msiexec's own log
+        let msi_log = launcher_log_path("msiexec")?;
+        let mut child = Command::new("msiexec")
+            .arg("/i")
+            .arg(&update_file)
+            .args(["/quiet", "/norestart", "/log"])
+            .arg(&msi_log)
             .spawn()
-            .map_err(|e| format!("Failed to launch MSI installer: {}", e))?;
-        return Ok(());
+            .map_err(|e| spawn_error("msiexec", e))?;
+        
+        return match wait_with_timeout(&mut child, MSI_WAIT_TIMEOUT)
+            .map_err(|e| format!("Failed to wait for MSI installer: {}", e))?
+        {
+            Some(status) => {
+                let code = status.code().unwrap_or(-1);
+                eprintln!("[Update] msiexec exited with code {}", code);
+                msi_exit_result(code).map_err(|e| format!("{} See the installer log: {}", e, msi_log.display()))
+            }
+            None => Err(format!(
+                "MSI installer is still running after {} seconds. See the installer log: {}",
+                MSI_WAIT_TIMEOUT.as_secs(),
+                msi_log.display()
+            )),
+        };
     }
     
     // Check if this is an installer or standalone executable
@@ -422,9 +698,10 @@ pub fn apply_update(update_file: PathBuf) -> Result<(), String> {
     if is_installer {
         // This is an installer - launch it
         eprintln!("[Update] Detected installer executable, launching installation...");
-        Command::new(&update_file)
+        let mut child = Command::new(&update_file)
             .spawn()
-            .map_err(|e| format!("Failed to launch installer: {}", e))?;
+            .map_err(|e| spawn_error("installer", e))?;
+        ensure_launched(&mut child, "Installer", None)?;
         eprintln!("[Update] Installer launched successfully");
         return Ok(());
     }
@@ -440,45 +717,18 @@ pub fn apply_update(update_file: PathBuf) -> Result<(), String> {
     let batch_script = temp_dir.join("apply_update.bat");
     eprintln!("[Update] Batch script will be created at: {}", batch_script.display());
     
-    // Create batch script to replace the executable
-    // Escape backslashes in paths for batch script
-    let update_path = update_file.to_string_lossy().replace('\\', "\\\\");
-    let exe_path = current_exe.to_string_lossy().replace('\\', "\\\\");
-    
-    // Use proper quoting for paths that may contain spaces
-    let script_content = format!(
-        r#"@echo off
-setlocal enabledelayedexpansion
-echo Applying update...
-timeout /t 2 /nobreak >nul
-copy /Y "{}" "{}"
-if %ERRORLEVEL% EQU 0 (
-    echo Update applied successfully.
-    del "{}"
-    del "%~f0"
-    timeout /t 1 /nobreak >nul
-    start "" "{}"
-) else (
-    echo Update failed with error code: %ERRORLEVEL%
-    echo Please update manually.
-    pause
-)
-"#,
-        update_path,
-        exe_path,
-        update_path,
-        exe_path
-    );
+    let script_content = update_script_content(&update_file, &current_exe, std::process::id());
     
     std::fs::write(&batch_script, script_content)
         .map_err(|e| format!("Failed to create update script: {}", e))?;
     
     eprintln!("[Update] Batch script created successfully");
-    eprintln!("[Update] Application will close and update will be applied");
+    eprintln!("[Update] Update will be applied once the application exits");
     
-    // Execute the batch script (it will run after this process exits)
+    // The script waits for this process to exit (the frontend calls exit_for_update)
+    // The empty title stops `start` from treating a quoted script path as the window title
     Command::new("cmd")
-        .args(&["/C", "start", "/MIN", &batch_script.to_string_lossy()])
+        .args(["/C", "start", "", "/MIN", &batch_script.to_string_lossy()])
         .spawn()
         .map_err(|e| format!("Failed to start update script: {}", e))?;
     
@@ -487,6 +737,60 @@ if %ERRORLEVEL% EQU 0 (
     Ok(())
 }
 
+/// Batch script that replaces the running exe with the downloaded one and restarts it.
+/// It waits for process `pid` (the app) to exit rather than sleeping a fixed time, then
+/// retries the copy while Windows still holds the old exe locked.
+/// Paths are quoted for cmd.exe and the script switches to UTF-8 so user profile
+/// paths with spaces or non-ASCII characters survive (the file is written as UTF-8).
+#[cfg(any(windows, test))]
+fn update_script_content(update_file: &std::path::Path, current_exe: &std::path::Path, pid: u32) -> String {
+    use phpvm_core::platform::quote_batch_path;
+
+    let update_path = quote_batch_path(update_file);
+    let exe_path = quote_batch_path(current_exe);
+    
+    // No parenthesized blocks: %var% is expanded once per block, so counters use goto loops.
+    // The script deletes itself last: cmd.exe can't read further lines once the file is gone
+    let lines = [
+        "@echo off".to_string(),
+        "chcp 65001 >nul".to_string(),
+        "echo Waiting for PHP Version Manager to close...".to_string(),
+        "set /a waited=0".to_string(),
+        ":wait_for_exit".to_string(),
+        format!("tasklist /FI \"PID eq {pid}\" /NH 2>nul | find \" {pid} \" >nul"),
+        "if errorlevel 1 goto copy_update".to_string(),
+        format!("if %waited% GEQ {} goto copy_update", EXIT_WAIT_SECONDS),
+        "set /a waited+=1".to_string(),
+        "timeout /t 1 /nobreak >nul".to_string(),
+        "goto wait_for_exit".to_string(),
+        ":copy_update".to_string(),
+        "echo Applying update...".to_string(),
+        "set /a attempts=0".to_string(),
+        ":retry_copy".to_string(),
+        format!("copy /Y {} {} >nul", update_path, exe_path),
+        "if not errorlevel 1 goto copied".to_string(),
+        "set /a attempts+=1".to_string(),
+        format!("if %attempts% GEQ {} goto copy_failed", COPY_ATTEMPTS),
+        "echo The executable is still in use, retrying...".to_string(),
+        "timeout /t 1 /nobreak >nul".to_string(),
+        "goto retry_copy".to_string(),
+        ":copy_failed".to_string(),
+        "echo Update failed: the executable stayed locked.".to_string(),
+        "echo Please update manually.".to_string(),
+        "pause".to_string(),
+        "exit /b 1".to_string(),
+        ":copied".to_string(),
+        "echo Update applied successfully.".to_string(),
+        format!("del {}", update_path),
+        format!("start \"\" {}", exe_path),
+        "(goto) 2>nul & del \"%~f0\"".to_string(),
+    ];
+    
+    let mut content = lines.join("\r\n");
+    content.push_str("\r\n");
+    content
+}
+
 /// Apply the update on Linux
 #[cfg(target_os = "linux")]
 pub fn apply_update(update_file: PathBuf) -> Result<(), String> {
@@ -511,6 +815,19 @@ pub fn apply_update(update_file: PathBuf) -> Result<(), String> {
         perms.set_mode(0o755);
         std::fs::set_permissions(&update_file, perms)
             .map_err(|e| format!("Failed to set executable permissions: {}", e))?;
+        
+        // Some filesystems accept chmod without applying it, so check the result
+        let mode = std::fs::metadata(&update_file)
+            .map_err(|e| format!("Failed to get file metadata: {}", e))?
+            .permissions()
+            .mode();
+        if mode & 0o111 == 0 {
+            return Err(format!(
+                "Could not make the AppImage executable (mode {:o}). Run manually: chmod +x {}",
+                mode & 0o777,
+                update_file.display()
+            ));
+        }
         eprintln!("[Update] AppImage is ready. Please run it manually to complete the update.");
         eprintln!("[Update] Location: {}", update_file.display());
         return Ok(());
@@ -519,26 +836,118 @@ pub fn apply_update(update_file: PathBuf) -> Result<(), String> {
     // Handle .deb package
     if file_ext.eq_ignore_ascii_case("deb") {
         eprintln!("[Update] Detected DEB package, launching installation...");
-        Command::new("pkexec")
-            .args(&["dpkg", "-i", update_file.to_str().unwrap()])
-            .spawn()
-            .map_err(|_e| format!("Failed to launch package installer. Please install manually with: sudo dpkg -i {}", update_file.display()))?;
-        return Ok(());
+        let manual = format!("Please install manually with: sudo dpkg -i {}", update_file.display());
+        return launch_package_install("dpkg", &update_file).map_err(|e| format!("{}. {}", e, manual));
     }
     
     // Handle .rpm package
     if file_ext.eq_ignore_ascii_case("rpm") {
         eprintln!("[Update] Detected RPM package, launching installation...");
-        Command::new("pkexec")
-            .args(&["rpm", "-i", update_file.to_str().unwrap()])
-            .spawn()
-            .map_err(|_e| format!("Failed to launch package installer. Please install manually with: sudo rpm -i {}", update_file.display()))?;
-        return Ok(());
+        let manual = format!("Please install manually with: sudo rpm -i {}", update_file.display());
+        return launch_package_install("rpm", &update_file).map_err(|e| format!("{}. {}", e, manual));
     }
     
     Err(format!("Unsupported file type: {}", file_ext))
 }
 
+/// Run `pkexec <tool> -i <package>`, capturing its output to a log so an
+/// early failure (cancelled authentication, missing polkit agent) comes back as an error
+#[cfg(target_os = "linux")]
+fn launch_package_install(tool: &str, package: &Path) -> Result<(), String> {
+    let log_path = launcher_log_path(tool)?;
+    let log_file = std::fs::File::create(&log_path)
+        .map_err(|e| format!("Failed to create launcher log: {}", e))?;
+    let log_file_err = log_file
+        .try_clone()
+        .map_err(|e| format!("Failed to create launcher log: {}", e))?;
+    
+    let mut child = Command::new("pkexec")
+        .arg(tool)
+        .arg("-i")
+        .arg(package)
+        .stdout(std::process::Stdio::from(log_file))
+        .stderr(std::process::Stdio::from(log_file_err))
+        .spawn()
+        .map_err(|e| spawn_error("pkexec", e))?;
+    
+    ensure_launched(&mut child, "Package installer", Some(&log_path))
+}
+
+/// Path for a launcher's captured output, next to the downloaded update
+fn launcher_log_path(name: &str) -> Result<PathBuf, String> {
+    let temp_dir = std::env::temp_dir().join("phpvm-update");
+    std::fs::create_dir_all(&temp_dir)
+        .map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    Ok(temp_dir.join(format!("{}.log", name)))
+}
+
+/// Turn a spawn failure into something the user can act on
+fn spawn_error(program: &str, e: std::io::Error) -> String {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => format!("Failed to launch {}: program not found", program),
+        std::io::ErrorKind::PermissionDenied => {
+            format!("Failed to launch {}: permission denied", program)
+        }
+        _ => format!("Failed to launch {}: {}", program, e),
+    }
+}
+
+/// Poll a child until it exits or `timeout` elapses. `None` means it is still running.
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> std::io::Result<Option<ExitStatus>> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if start.elapsed() >= timeout {
+            return Ok(None);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Give a detached launcher a moment to fail. A non-zero exit inside the check window
+/// is an error (with the tail of its captured output); still running counts as launched
+/// and is left running.
+fn ensure_launched(child: &mut Child, what: &str, log_path: Option<&Path>) -> Result<(), String> {
+    let status = wait_with_timeout(child, LAUNCH_CHECK_WINDOW)
+        .map_err(|e| format!("Failed to check {} status: {}", what.to_lowercase(), e))?;
+    
+    match status {
+        Some(status) if !status.success() => {
+            let output = log_path
+                .and_then(|p| std::fs::read_to_string(p).ok())
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty());
+            let code = status
+                .code()
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| "none (terminated by signal)".to_string());
+            match output {
+                Some(output) => Err(format!("{} exited with code {}: {}", what, code, output)),
+                None => Err(format!("{} exited with code {}", what, code)),
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Map an msiexec exit code to success or a readable error
+#[cfg(any(windows, test))]
+fn msi_exit_result(code: i32) -> Result<(), String> {
+    match code {
+        0 => Ok(()),
+        // Success, but a restart is required to finish replacing files
+        1641 | 3010 => Ok(()),
+        1602 => Err("MSI installation was cancelled.".to_string()),
+        1618 => Err("Another installation is already in progress. Try again once it finishes.".to_string()),
+        1603 => Err("MSI installation failed with a fatal error (1603).".to_string()),
+        1619 => Err("MSI package could not be opened (1619). The download may be corrupt.".to_string()),
+        1625 => Err("MSI installation is blocked by system policy (1625).".to_string()),
+        _ => Err(format!("MSI installation failed with exit code {}.", code)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -552,6 +961,113 @@ mod tests {
         assert_eq!(compare_versions("1.0.0", "0.9.9"), 1);
     }
     
+    #[test]
+    fn test_update_script_quotes_paths() {
+        let update_file = PathBuf::from("C:/Users/José Doe/AppData/Local/Temp/phpvm-update/phpvm-update.exe");
+        let current_exe = PathBuf::from("C:/Users/José Doe/Apps/PHP Version Manager.exe");
+        let script = update_script_content(&update_file, &current_exe, 4242);
+        
+        assert!(script.contains("chcp 65001 >nul"));
+        assert!(script.contains(
+            "copy /Y \"C:/Users/José Doe/AppData/Local/Temp/phpvm-update/phpvm-update.exe\" \"C:/Users/José Doe/Apps/PHP Version Manager.exe\""
+        ));
+        assert!(script.contains("start \"\" \"C:/Users/José Doe/Apps/PHP Version Manager.exe\""));
+        // Waits for the app's own process instead of a fixed sleep, then retries a locked copy
+        assert!(script.contains("tasklist /FI \"PID eq 4242\" /NH 2>nul | find \" 4242 \" >nul"));
+        assert!(!script.contains("timeout /t 2"));
+        assert!(script.contains(&format!("if %attempts% GEQ {} goto copy_failed", COPY_ATTEMPTS)));
+        // Delayed expansion would mangle paths containing '!'
+        assert!(!script.contains("enabledelayedexpansion"));
+        assert!(script.ends_with("(goto) 2>nul & del \"%~f0\"\r\n"));
+    }
+    
+    #[test]
+    fn test_rate_limit_message() {
+        // 1700000000 is 22:13:20 UTC
+        assert_eq!(
+            rate_limit_message(Some(1_700_000_000), 1_700_000_000 - 600),
+            "GitHub API rate limit exceeded. It resets at 22:13 UTC (in about 10 minutes)."
+        );
+        assert!(rate_limit_message(None, 0).contains("try again later"));
+    }
+    
+    #[test]
+    fn test_parse_release_assets() {
+        let html = r#"
+            <a href="/vunf1/php-version-manager/releases/download/v0.2.0/PHP%20Version%20Manager_0.2.0_x64-setup.exe" rel="nofollow">
+            <a href="/vunf1/php-version-manager/releases/download/v0.2.0/phpvm_0.2.0_amd64.deb">
+            <a href="/vunf1/php-version-manager/releases/download/v0.2.0/phpvm_0.2.0_amd64.deb">
+            <a href="/vunf1/php-version-manager/archive/refs/tags/v0.2.0.zip">
+        "#;
+        let assets = parse_release_assets(html, "v0.2.0");
+        assert_eq!(assets.len(), 2);
+        assert_eq!(assets[0].name, "PHP Version Manager_0.2.0_x64-setup.exe");
+        assert_eq!(
+            assets[1].browser_download_url,
+            "https://github.com/vunf1/php-version-manager/releases/download/v0.2.0/phpvm_0.2.0_amd64.deb"
+        );
+    }
+    
+    #[test]
+    fn test_msi_exit_result() {
+        assert!(msi_exit_result(0).is_ok());
+        assert!(msi_exit_result(3010).is_ok());
+        assert!(msi_exit_result(1602).unwrap_err().contains("cancelled"));
+        assert!(msi_exit_result(1234).unwrap_err().contains("1234"));
+    }
+    
+    #[cfg(unix)]
+    #[test]
+    fn test_ensure_launched_reports_early_failure() {
+        let mut child = Command::new("sh")
+            .args(["-c", "exit 126"])
+            .spawn()
+            .unwrap();
+        let err = ensure_launched(&mut child, "Package installer", None).unwrap_err();
+        assert_eq!(err, "Package installer exited with code 126");
+        
+        let mut child = Command::new("sh").args(["-c", "sleep 5"]).spawn().unwrap();
+        let launched = ensure_launched(&mut child, "Package installer", None);
+        // Don't leave it running after the test
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(launched.is_ok());
+    }
+    
+    #[test]
+    fn test_http_settings_token_only_sent_to_github() {
+        let mut config = phpvm_core::config::Config::default();
+        let anonymous = HttpSettings::from_config(Some(&config));
+        assert_eq!(anonymous.user_agent, DEFAULT_USER_AGENT);
+        assert!(anonymous.github_token.is_none());
+        
+        config.github_token = Some("  ".to_string());
+        assert!(HttpSettings::from_config(Some(&config)).github_token.is_none());
+        
+        config.github_token = Some("ghp_secret".to_string());
+        config.user_agent = Some("corp-proxy-approved/1.0".to_string());
+        let settings = HttpSettings::from_config(Some(&config));
+        assert_eq!(settings.user_agent, "corp-proxy-approved/1.0");
+        
+        let client = settings.client(None).unwrap();
+        let authorization = |url: &str| {
+            settings
+                .get(&client, url)
+                .build()
+                .unwrap()
+                .headers()
+                .get(reqwest::header::AUTHORIZATION)
+                .map(|v| v.to_str().unwrap().to_string())
+        };
+        assert_eq!(
+            authorization("https://api.github.com/repos/vunf1/php-version-manager/releases/latest"),
+            Some("Bearer ghp_secret".to_string())
+        );
+        assert!(authorization("https://github.com/vunf1/php-version-manager/releases/download/v1/a.deb").is_some());
+        assert_eq!(authorization("https://mirror.example.com/repos/vunf1/php-version-manager/releases/latest"), None);
+        assert_eq!(authorization("https://api.github.com.evil.example/releases"), None);
+    }
+    
     #[test]
     fn test_matches_app_name() {
         assert!(matches_app_name("PHP Version Manager_0.1.0_x64-setup.exe"));
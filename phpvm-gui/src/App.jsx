@@ -3,6 +3,7 @@
  * Refactored into smaller, organized components
  */
 import { useState, useEffect } from "react";
+import { listen } from "@tauri-apps/api/event";
 import { useTauriReady } from "./hooks/useTauriReady";
 import { usePhpVersions } from "./hooks/usePhpVersions";
 import { useVersionOperations } from "./hooks/useVersionOperations";
@@ -56,6 +57,7 @@ function App() {
     loadData,
     refreshInstalledData,
     setError,
+    installUnsupportedReason,
   } = usePhpVersions();
 
   // Version operations hook
@@ -154,6 +156,59 @@ function App() {
     };
   }, [showInfo]);
 
+  // Notify about newer patch releases of installed versions (emitted by the backend on startup)
+  useEffect(() => {
+    let unlisten;
+
+    const setupListener = async () => {
+      try {
+        unlisten = await listen("patch-updates-available", (event) => {
+          const updates = event.payload || [];
+          updates.forEach((update) => {
+            showInfo(
+              `PHP ${update.latest} is available (you have ${update.installed} ${update.variant.toUpperCase()})`,
+              0
+            );
+          });
+        });
+      } catch (err) {
+        console.error("Failed to setup patch update listener:", err);
+      }
+    };
+
+    setupListener();
+
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [showInfo]);
+
+  // Reinstalling the active version repoints the shim at the new build
+  useEffect(() => {
+    let unlisten;
+
+    const setupListener = async () => {
+      try {
+        unlisten = await listen("version-switched", (event) => {
+          showInfo(`Now using the reinstalled PHP ${event.payload?.version}`);
+          refreshInstalledData();
+        });
+      } catch (err) {
+        console.error("Failed to setup version-switched listener:", err);
+      }
+    };
+
+    setupListener();
+
+    return () => {
+      if (unlisten) {
+        unlisten();
+      }
+    };
+  }, [showInfo, refreshInstalledData]);
+
   // Handle PATH setting
   const handleSetPath = async () => {
     try {
@@ -216,6 +271,7 @@ function App() {
         {activeTab === "available" && (
           <AvailableVersionsTab
             availableVersions={availableVersions}
+            installUnsupportedReason={installUnsupportedReason}
             installedVersions={installedVersions}
             versionStatuses={versionStatuses}
             loading={loading}
@@ -11,6 +11,7 @@ export const UpdateModal = ({ updateInfo, onClose, onUpdateComplete, showError,
   const [downloading, setDownloading] = useState(false);
   const [downloaded, setDownloaded] = useState(false);
   const [applying, setApplying] = useState(false);
+  const [restarting, setRestarting] = useState(false);
   const [progress, setProgress] = useState({ downloaded: 0, total: 0, speed_mbps: 0, percent: 0 });
   const [updateFilePath, setUpdateFilePath] = useState(null);
 
@@ -78,32 +79,26 @@ export const UpdateModal = ({ updateInfo, onClose, onUpdateComplete, showError,
     try {
       console.log("[UpdateModal] Applying update from:", updateFilePath);
       await phpvmApi.applyUpdate(updateFilePath);
-      console.log("[UpdateModal] Update applied successfully, closing app...");
-      showSuccess("Update will be applied when you restart the application. The application will now close.");
+      console.log("[UpdateModal] Update applied successfully, restarting...");
+      setRestarting(true);
+      showSuccess("Restarting to finish the update…");
       
-      // Close the app after a short delay to allow user to see the message
+      // Give the message a moment to render, then quit; the update script waits for the exit
       setTimeout(async () => {
-        console.log("[UpdateModal] Closing application window...");
         if (onUpdateComplete) {
           onUpdateComplete();
         }
-        // Close the window (Tauri API)
         try {
-          const appWindow = getCurrentWindow();
-          await appWindow.close();
-          console.log("[UpdateModal] Window closed successfully");
+          await phpvmApi.exitForUpdate();
         } catch (err) {
-          console.error("[UpdateModal] Failed to close window:", err);
-          // Fallback: try to exit the process
-          if (window.__TAURI__) {
-            try {
-              await window.__TAURI__.process.exit();
-            } catch (e) {
-              console.error("[UpdateModal] Failed to exit process:", e);
-            }
+          console.error("[UpdateModal] Failed to exit, closing the window instead:", err);
+          try {
+            await getCurrentWindow().close();
+          } catch (e) {
+            console.error("[UpdateModal] Failed to close window:", e);
           }
         }
-      }, 2000);
+      }, 800);
     } catch (err) {
       const errorMsg = err.toString();
       console.error("[UpdateModal] Failed to apply update:", err);
@@ -190,7 +185,7 @@ export const UpdateModal = ({ updateInfo, onClose, onUpdateComplete, showError,
           {applying && (
             <div style={{ marginTop: "1rem", padding: "1rem", backgroundColor: "#fff3e0", borderRadius: "4px" }}>
               <p style={{ margin: 0, color: "#e65100" }}>
-                Applying update... The application will close shortly.
+                {restarting ? "Restarting…" : "Applying update... The application will close shortly."}
               </p>
             </div>
           )}
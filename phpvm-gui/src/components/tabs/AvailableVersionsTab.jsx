@@ -5,8 +5,12 @@ import { useMemo, useState, useEffect } from "react";
 import { formatDate, getEolStatusClass } from "../../utils/dateUtils";
 import { phpvmApi } from "../../services/phpvmApi";
 
+// Versions shown before "Show more"; the backend returns the full list
+const PAGE_SIZE = 24;
+
 export const AvailableVersionsTab = ({
   availableVersions,
+  installUnsupportedReason,
   installedVersions,
   versionStatuses,
   loading,
@@ -19,6 +23,30 @@ export const AvailableVersionsTab = ({
 }) => {
   const [fetchedVersions, setFetchedVersions] = useState([]);
   const [fetchingVersion, setFetchingVersion] = useState(false);
+  const [cacheReady, setCacheReady] = useState({});
+  const [visibleCount, setVisibleCount] = useState(PAGE_SIZE);
+  const [versionDelta, setVersionDelta] = useState(null);
+
+  // Which versions can be installed straight from the download cache
+  useEffect(() => {
+    let cancelled = false;
+    phpvmApi.listCacheReadyVersions()
+      .then((ready) => {
+        if (cancelled) return;
+        const byVersion = {};
+        ready.forEach((entry) => { byVersion[entry.version] = entry; });
+        setCacheReady(byVersion);
+      })
+      .catch((err) => console.error("Failed to list cached versions:", err));
+    return () => { cancelled = true; };
+  }, [availableVersions, installedVersions]);
+
+  // What the last version list refresh brought
+  useEffect(() => {
+    phpvmApi.getLastVersionDelta()
+      .then(setVersionDelta)
+      .catch((err) => console.error("Failed to read version list changes:", err));
+  }, [availableVersions]);
 
   // Check if input looks like a version number (e.g., 8.2.0, 8.2, 6, etc.)
   const isValidVersionFormat = (text) => {
@@ -86,6 +114,11 @@ export const AvailableVersionsTab = ({
     );
   }, [allVersions, installVersion, availableVersions]);
 
+  // Start from the first page whenever the list or the search changes
+  useEffect(() => {
+    setVisibleCount(PAGE_SIZE);
+  }, [availableVersions, installVersion]);
+
   // Clear fetched versions when input is cleared
   useEffect(() => {
     if (!installVersion || installVersion.trim() === '') {
@@ -174,12 +207,18 @@ export const AvailableVersionsTab = ({
           Install
         </button>
       </div>
+      {!loading && versionDelta && versionDelta.added.length > 0 && (
+        <p className="setting-hint" title={versionDelta.added.join(", ")}>
+          {versionDelta.added.length} new patch release{versionDelta.added.length === 1 ? "" : "s"} since your last check
+          {` (found ${formatDate(versionDelta.checked_at * 1000)})`}
+        </p>
+      )}
       {loading && (
         <div className="loading">Loading available versions...</div>
       )}
       {!loading && availableVersions.length === 0 && (
         <div className="empty-state">
-          <p>No versions available.</p>
+          <p>{installUnsupportedReason || "No versions available."}</p>
         </div>
       )}
       {!loading && availableVersions.length > 0 && filteredVersions.length === 0 && (
@@ -193,12 +232,14 @@ export const AvailableVersionsTab = ({
       )}
       {!loading && filteredVersions.length > 0 && (
         <div className="version-grid">
-          {filteredVersions.map((version) => {
+          {filteredVersions.slice(0, visibleCount).map((version) => {
             const status = versionStatuses[version] || {};
             const tsInstalled = installedVersions.some(v => v.startsWith(`${version}-ts`) || v === `${version}-ts`);
             const ntsInstalled = installedVersions.some(v => v.startsWith(`${version}-nts`) || v === `${version}-nts`);
             const bothInstalled = tsInstalled && ntsInstalled;
             const isInstalled = tsInstalled || ntsInstalled;
+            const cached = cacheReady[version];
+            const cachedVariants = cached ? [cached.ts && "TS", cached.nts && "NTS"].filter(Boolean).join(" + ") : "";
 
             return (
               <div key={version} className={`version-card ${isInstalled ? "installed" : ""}`}>
@@ -213,6 +254,11 @@ export const AvailableVersionsTab = ({
                         {!status.online && (
                           <span className="badge offline-badge">Offline</span>
                         )}
+                        {cached && (
+                          <span className="badge cached-badge" title={`Archive cached (${cachedVariants}): installs without downloading`}>
+                            Cached
+                          </span>
+                        )}
                       </div>
                       <div className="version-actions">
                         {bothInstalled ? (
@@ -267,6 +313,13 @@ export const AvailableVersionsTab = ({
           })}
         </div>
       )}
+      {!loading && filteredVersions.length > visibleCount && (
+        <div className="empty-state">
+          <button className="btn btn-secondary" onClick={() => setVisibleCount(visibleCount + PAGE_SIZE)}>
+            Show more ({filteredVersions.length - visibleCount} hidden)
+          </button>
+        </div>
+      )}
     </div>
   );
 };
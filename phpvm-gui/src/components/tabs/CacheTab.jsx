@@ -10,9 +10,11 @@ export const CacheTab = ({ showSuccess, showError }) => {
   const [loading, setLoading] = useState(true);
   const [removingHash, setRemovingHash] = useState(null);
   const [clearing, setClearing] = useState(false);
+  const [cleaningOrphans, setCleaningOrphans] = useState(false);
   const [showRemoveConfirm, setShowRemoveConfirm] = useState(false);
   const [pendingRemoveHash, setPendingRemoveHash] = useState(null);
   const [showClearAllConfirm, setShowClearAllConfirm] = useState(false);
+  const [hashingHash, setHashingHash] = useState(null);
 
   const loadCachedFiles = async () => {
     try {
@@ -69,6 +71,35 @@ export const CacheTab = ({ showSuccess, showError }) => {
     }
   };
 
+  const handleComputeSha256 = async (hash) => {
+    try {
+      setHashingHash(hash);
+      const sha256 = await phpvmApi.getCachedFileSha256(hash);
+      setCachedFiles(files => files.map(file => file.hash === hash ? { ...file, sha256 } : file));
+    } catch (err) {
+      console.error("[CacheTab] Failed to compute SHA256:", err);
+      if (showError) {
+        showError(`Failed to compute SHA256: ${err.message || err}`);
+      }
+    } finally {
+      setHashingHash(null);
+    }
+  };
+
+  const handleCopySha256 = async (sha256) => {
+    try {
+      await navigator.clipboard.writeText(sha256);
+      if (showSuccess) {
+        showSuccess("SHA256 copied to clipboard");
+      }
+    } catch (err) {
+      console.error("[CacheTab] Failed to copy SHA256:", err);
+      if (showError) {
+        showError(`Failed to copy SHA256: ${err}`);
+      }
+    }
+  };
+
   const handleClearAllClick = () => {
     setShowClearAllConfirm(true);
   };
@@ -93,6 +124,25 @@ export const CacheTab = ({ showSuccess, showError }) => {
     }
   };
 
+  // Only archives no available version installs from; the rest of the cache stays
+  const handleCleanOrphaned = async () => {
+    try {
+      setCleaningOrphans(true);
+      const report = await phpvmApi.cleanOrphanedCache(false);
+      if (showSuccess) {
+        showSuccess(`Removed ${report.removed.length} orphaned file(s), freed ${formatSize(report.freed_bytes)}`);
+      }
+      await loadCachedFiles();
+    } catch (err) {
+      console.error("[CacheTab] Failed to clean orphaned cache files:", err);
+      if (showError) {
+        showError(`Failed to clean orphaned cache files: ${err}`);
+      }
+    } finally {
+      setCleaningOrphans(false);
+    }
+  };
+
   const formatSize = (bytes) => {
     if (!bytes || bytes === 0) return '0 B';
     const k = 1024;
@@ -116,14 +166,27 @@ export const CacheTab = ({ showSuccess, showError }) => {
       <div style={{ display: 'flex', justifyContent: 'space-between', alignItems: 'center', marginBottom: '1rem' }}>
         <h2>Cache Management</h2>
         {cachedFiles.length > 0 && (
-          <button
-            className="btn btn-danger"
-            onClick={handleClearAllClick}
-            disabled={clearing}
-            style={{ padding: '0.5rem 1rem', fontSize: '0.875rem' }}
-          >
-            {clearing ? 'Clearing...' : `Clear All (${cachedFiles.length})`}
-          </button>
+          <div style={{ display: 'flex', gap: '0.5rem' }}>
+            {cachedFiles.some((file) => !file.version) && (
+              <button
+                className="btn btn-secondary"
+                onClick={handleCleanOrphaned}
+                disabled={cleaningOrphans || clearing}
+                title="Remove cached archives that don't belong to any available version"
+                style={{ padding: '0.5rem 1rem', fontSize: '0.875rem' }}
+              >
+                {cleaningOrphans ? 'Cleaning...' : `Remove Orphaned (${cachedFiles.filter((file) => !file.version).length})`}
+              </button>
+            )}
+            <button
+              className="btn btn-danger"
+              onClick={handleClearAllClick}
+              disabled={clearing}
+              style={{ padding: '0.5rem 1rem', fontSize: '0.875rem' }}
+            >
+              {clearing ? 'Clearing...' : `Clear All (${cachedFiles.length})`}
+            </button>
+          </div>
         )}
       </div>
 
@@ -160,6 +223,31 @@ export const CacheTab = ({ showSuccess, showError }) => {
                   <span>Size: {formatSize(file.size)}</span>
                   <span>Modified: {file.formattedDate}</span>
                 </div>
+                <div style={{ display: 'flex', alignItems: 'center', gap: '0.5rem', marginTop: '0.5rem', fontSize: '0.75rem' }}>
+                  {file.sha256 ? (
+                    <>
+                      <span style={{ fontFamily: 'monospace', color: '#757575', wordBreak: 'break-all' }}>
+                        SHA256: {file.sha256}
+                      </span>
+                      <button
+                        className="btn btn-secondary"
+                        onClick={() => handleCopySha256(file.sha256)}
+                        style={{ padding: '0.25rem 0.5rem', fontSize: '0.75rem' }}
+                      >
+                        Copy
+                      </button>
+                    </>
+                  ) : (
+                    <button
+                      className="btn btn-secondary"
+                      onClick={() => handleComputeSha256(file.hash)}
+                      disabled={hashingHash === file.hash}
+                      style={{ padding: '0.25rem 0.5rem', fontSize: '0.75rem' }}
+                    >
+                      {hashingHash === file.hash ? 'Computing SHA256...' : 'Show SHA256'}
+                    </button>
+                  )}
+                </div>
               </div>
               <button
                 className="btn btn-danger"
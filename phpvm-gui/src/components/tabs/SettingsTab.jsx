@@ -29,6 +29,13 @@ export const SettingsTab = ({
   const [checkingUpdates, setCheckingUpdates] = useState(false);
   const [updateInfo, setUpdateInfo] = useState(externalUpdateInfo || null);
   const [showUpdateModal, setShowUpdateModal] = useState(false);
+  const [defaultVariant, setDefaultVariant] = useState("");
+  const [availableFilter, setAvailableFilter] = useState(null);
+  const [phpResolution, setPhpResolution] = useState(null);
+  const [effectivePhp, setEffectivePhp] = useState(null);
+  const [backupVersions, setBackupVersions] = useState(false);
+  const [profiles, setProfiles] = useState({ profiles: ["default"], active: "default" });
+  const [newProfileName, setNewProfileName] = useState("");
   
   // Sync external updateInfo with internal state
   useEffect(() => {
@@ -59,6 +66,170 @@ export const SettingsTab = ({
     loadVersion();
   }, []);
 
+  useEffect(() => {
+    phpvmApi.getDefaultVariant()
+      .then((variant) => setDefaultVariant(variant || ""))
+      .catch((err) => console.error("Failed to load default variant:", err));
+  }, []);
+
+  useEffect(() => {
+    phpvmApi.checkPhpResolution()
+      .then(setPhpResolution)
+      .catch((err) => console.error("Failed to check which php is on PATH:", err));
+    phpvmApi.getEffectivePhp()
+      .then(setEffectivePhp)
+      .catch((err) => console.error("Failed to run the php on PATH:", err));
+  }, [pathStatus, activeVersion]);
+
+  useEffect(() => {
+    phpvmApi.getAvailableFilter()
+      .then(setAvailableFilter)
+      .catch((err) => console.error("Failed to load version list filter:", err));
+  }, []);
+
+  const handleAvailableFilterChange = async (changes) => {
+    const filter = { ...availableFilter, ...changes };
+    try {
+      await phpvmApi.setAvailableFilter(filter);
+      setAvailableFilter(filter);
+      onRefresh();
+    } catch (err) {
+      showError(`Failed to save version list filter: ${err.message || err}`);
+    }
+  };
+
+  const loadProfiles = () =>
+    phpvmApi.listProfiles()
+      .then(setProfiles)
+      .catch((err) => console.error("Failed to load profiles:", err));
+
+  useEffect(() => {
+    loadProfiles();
+  }, []);
+
+  const handleSwitchProfile = async (name) => {
+    try {
+      await phpvmApi.switchProfile(name);
+      await loadProfiles();
+      showSuccess(`Switched to profile ${name}`);
+      onRefresh();
+    } catch (err) {
+      showError(`Failed to switch profile: ${err.message || err}`);
+    }
+  };
+
+  const handleCreateProfile = async () => {
+    const name = newProfileName.trim();
+    if (!name) return;
+    try {
+      await phpvmApi.createProfile(name);
+      setNewProfileName("");
+      await loadProfiles();
+      showSuccess(`Created profile ${name}`);
+    } catch (err) {
+      showError(`Failed to create profile: ${err.message || err}`);
+    }
+  };
+
+  const handleDefaultVariantChange = async (variant) => {
+    try {
+      await phpvmApi.setDefaultVariant(variant || null);
+      setDefaultVariant(variant);
+      showSuccess(variant ? `Default variant set to ${variant.toUpperCase()}` : "Default variant cleared");
+    } catch (err) {
+      showError(`Failed to set default variant: ${err.message || err}`);
+    }
+  };
+
+  const handleRegisterVscode = async () => {
+    try {
+      const settingsPath = await phpvmApi.registerWithVscode();
+      showSuccess(`VS Code now uses the active PHP (${settingsPath})`);
+    } catch (err) {
+      showError(`Failed to update VS Code settings: ${err.message || err}`);
+    }
+  };
+
+  const handleUnregisterVscode = async () => {
+    try {
+      const removed = await phpvmApi.unregisterFromVscode();
+      showInfo(removed ? "Removed the PHP path from VS Code settings" : "VS Code settings had no PHP path from phpvm");
+    } catch (err) {
+      showError(`Failed to update VS Code settings: ${err.message || err}`);
+    }
+  };
+
+  const handleCopyInterpreterPath = async () => {
+    try {
+      handleCopyToClipboard(await phpvmApi.getIdeInterpreterPath(), "interpreter");
+    } catch (err) {
+      showError(`No interpreter to copy: ${err.message || err}`);
+    }
+  };
+
+  const handleRebuildState = async () => {
+    try {
+      const state = await phpvmApi.rebuildState();
+      showSuccess(`Found ${state.installed_versions.length} installed version(s) on disk`);
+      onRefresh();
+    } catch (err) {
+      showError(`Failed to rebuild state: ${err.message || err}`);
+    }
+  };
+
+  const handleAdoptPhp = async () => {
+    try {
+      const path = await phpvmApi.pickFolder("Select an existing PHP directory");
+      if (!path) return;
+      const version = await phpvmApi.adoptPhp(path);
+      showSuccess(`Added PHP ${version} from ${path}`);
+      onRefresh();
+    } catch (err) {
+      showError(`Failed to add existing PHP: ${err.message || err}`);
+    }
+  };
+
+  const handleApplyProject = async () => {
+    try {
+      const dir = await phpvmApi.pickFolder("Select a project with a .phpvmrc");
+      if (!dir) return;
+      const project = await phpvmApi.getProjectConfig(dir);
+      if (!project) {
+        showInfo(`No .phpvmrc found in ${dir}`);
+        return;
+      }
+      showInfo(`Setting up PHP ${project.version} for ${dir}…`);
+      const report = await phpvmApi.applyProjectConfig(dir);
+      showSuccess(`Project ready: PHP ${report.version} (${report.steps.join(", ")})`);
+      onRefresh();
+    } catch (err) {
+      showError(`Failed to apply project config: ${err.message || err}`);
+    }
+  };
+
+  const handleBackup = async () => {
+    try {
+      const dest = await phpvmApi.pickSaveFile("Save phpvm backup", `phpvm-backup-${new Date().toISOString().slice(0, 10)}.zip`);
+      if (!dest) return;
+      const report = await phpvmApi.backupAll(dest, { includeVersions: backupVersions });
+      showSuccess(`Backed up ${report.files} files to ${report.path}`);
+    } catch (err) {
+      showError(`Backup failed: ${err.message || err}`);
+    }
+  };
+
+  const handleRestore = async () => {
+    try {
+      const src = await phpvmApi.pickFile("Select a phpvm backup", "zip");
+      if (!src) return;
+      const report = await phpvmApi.restoreAll(src);
+      showSuccess(`Restored ${report.files} files from ${src}`);
+      onRefresh();
+    } catch (err) {
+      showError(`Restore failed: ${err.message || err}`);
+    }
+  };
+
   const handleCheckUpdates = async () => {
     setCheckingUpdates(true);
     handleUpdateInfoChange(null);
@@ -118,10 +289,14 @@ export const SettingsTab = ({
           <label>PATH Environment Variable</label>
           <div className="setting-value">
             <div className="path-status-row">
-              <span className={pathStatus.is_set ? "status-ok" : "status-error"}>
-                {pathStatus.is_set ? "✓ Configured" : "✗ Not Configured"}
-              </span>
-              {!pathStatus.is_set && (
+              {pathStatus.status === "unmanaged" ? (
+                <span className="status-ok">Managed by you</span>
+              ) : (
+                <span className={pathStatus.is_set ? "status-ok" : "status-error"}>
+                  {pathStatus.is_set ? "✓ Configured" : "✗ Not Configured"}
+                </span>
+              )}
+              {!pathStatus.is_set && pathStatus.status !== "unmanaged" && (
                 <button
                   className="btn btn-primary"
                   onClick={onSetPath}
@@ -143,11 +318,54 @@ export const SettingsTab = ({
                 {pathStatus.current_path}
                 {copiedPath === "path" ? <CheckIcon /> : <CopyIcon />}
               </span>
-              {pathStatus.is_set
+              {pathStatus.status === "unmanaged"
+                ? " (phpvm doesn't modify PATH - add this directory in your own setup)"
+                : pathStatus.is_set
                 ? " (Added to PATH - restart your terminal/console for changes to take effect)"
                 : " (Not in PATH - click 'Set PATH' to add)"}
             </p>
           )}
+          {phpResolution && phpResolution.status !== "active" && activeVersion && (
+            <p className="setting-hint status-error">
+              {phpResolution.status === "shadowed"
+                ? `"php" currently runs ${effectivePhp?.version ? `PHP ${effectivePhp.version} from ` : ""}${phpResolution.resolved}, which comes before phpvm on PATH.`
+                : `"php" isn't reachable from this session's PATH yet.`}
+            </p>
+          )}
+          {effectivePhp?.version && effectivePhp.managed && (
+            <p className="setting-hint">
+              Your shell currently runs PHP {effectivePhp.version} from {effectivePhp.path}
+            </p>
+          )}
+        </div>
+      </div>
+      <div className="settings-section">
+        <h3>IDE Integration</h3>
+        <div className="setting-item">
+          <label>VS Code</label>
+          <div className="setting-value">
+            <button className="btn btn-primary" onClick={handleRegisterVscode} disabled={loading || !activeVersion}>
+              Use Active PHP in VS Code
+            </button>
+            <button className="btn btn-secondary" onClick={handleUnregisterVscode} disabled={loading} style={{ marginLeft: "0.5rem" }}>
+              Undo
+            </button>
+          </div>
+          <p className="setting-hint">
+            Sets php.validate.executablePath in your VS Code user settings (a backup is kept next to settings.json).
+          </p>
+        </div>
+        <div className="setting-item">
+          <label>PhpStorm</label>
+          <div className="setting-value">
+            <button className="btn btn-secondary" onClick={handleCopyInterpreterPath} disabled={loading || !activeVersion}>
+              Copy Interpreter Path
+              {copiedPath === "interpreter" && <CheckIcon />}
+            </button>
+          </div>
+          <p className="setting-hint">
+            Paste it under Settings &gt; PHP &gt; CLI Interpreter.
+          </p>
         </div>
       </div>
       <div className="settings-section">
@@ -167,6 +385,141 @@ export const SettingsTab = ({
             PHP versions will be installed in this directory.
           </p>
         </div>
+        <div className="setting-item">
+          <label>Installed Versions State</label>
+          <div className="setting-value">
+            <button className="btn btn-secondary" onClick={handleRebuildState} disabled={loading}>
+              Rebuild from Disk
+            </button>
+          </div>
+          <p className="setting-hint">
+            Re-detects installs if versions are missing from the list (state.json is backed up first).
+          </p>
+        </div>
+        <div className="setting-item">
+          <label>Backup</label>
+          <div className="setting-value">
+            <button className="btn btn-secondary" onClick={handleBackup} disabled={loading}>
+              Back Up…
+            </button>
+            <button className="btn btn-secondary" onClick={handleRestore} disabled={loading}>
+              Restore…
+            </button>
+            <label>
+              <input
+                type="checkbox"
+                checked={backupVersions}
+                onChange={(e) => setBackupVersions(e.target.checked)}
+              />
+              Include installed versions
+            </label>
+          </div>
+          <p className="setting-hint">
+            Saves config, state and logs to a zip. Restoring overwrites them and keeps installs the backup doesn't contain.
+          </p>
+        </div>
+        <div className="setting-item">
+          <label>Existing PHP</label>
+          <div className="setting-value">
+            <button className="btn btn-secondary" onClick={handleAdoptPhp} disabled={loading}>
+              Adopt Existing PHP…
+            </button>
+          </div>
+          <p className="setting-hint">
+            Copies a PHP directory you already have (e.g. XAMPP's php folder) into phpvm. The original is left untouched.
+          </p>
+        </div>
+        <div className="setting-item">
+          <label>Project Setup</label>
+          <div className="setting-value">
+            <button className="btn btn-secondary" onClick={handleApplyProject} disabled={loading}>
+              Apply .phpvmrc…
+            </button>
+          </div>
+          <p className="setting-hint">
+            Installs and switches to the version a project's .phpvmrc names and enables its extensions.
+          </p>
+        </div>
+        <div className="setting-item">
+          <label>Default Variant</label>
+          <div className="setting-value">
+            <select
+              value={defaultVariant}
+              onChange={(e) => handleDefaultVariantChange(e.target.value)}
+              disabled={loading}
+            >
+              <option value="">Ask each time</option>
+              <option value="ts">Thread Safe (TS)</option>
+              <option value="nts">Non-Thread Safe (NTS)</option>
+            </select>
+          </div>
+          <p className="setting-hint">
+            Pre-selected when installing, and preferred when switching to a branch like 8.2.
+          </p>
+        </div>
+        {availableFilter && (
+          <div className="setting-item">
+            <label>Version List</label>
+            <div className="setting-value">
+              <label>
+                <input
+                  type="checkbox"
+                  checked={availableFilter.includeEol}
+                  onChange={(e) => handleAvailableFilterChange({ includeEol: e.target.checked })}
+                  disabled={loading}
+                />
+                Show end-of-life branches
+              </label>
+              <label>
+                <input
+                  type="checkbox"
+                  checked={availableFilter.onlyInstalledBranches}
+                  onChange={(e) => handleAvailableFilterChange({ onlyInstalledBranches: e.target.checked })}
+                  disabled={loading}
+                />
+                Only branches I have installed
+              </label>
+              <select
+                value={availableFilter.ascending ? "asc" : "desc"}
+                onChange={(e) => handleAvailableFilterChange({ ascending: e.target.value === "asc" })}
+                disabled={loading}
+              >
+                <option value="desc">Newest first</option>
+                <option value="asc">Oldest first</option>
+              </select>
+            </div>
+            <p className="setting-hint">
+              Which available versions are listed, and in what order. Saved in config.json.
+            </p>
+          </div>
+        )}
+        <div className="setting-item">
+          <label>Profile</label>
+          <div className="setting-value">
+            <select
+              value={profiles.active}
+              onChange={(e) => handleSwitchProfile(e.target.value)}
+              disabled={loading}
+            >
+              {profiles.profiles.map((name) => (
+                <option key={name} value={name}>{name}</option>
+              ))}
+            </select>
+            <input
+              type="text"
+              value={newProfileName}
+              placeholder="new-profile"
+              onChange={(e) => setNewProfileName(e.target.value)}
+              disabled={loading}
+            />
+            <button className="btn btn-secondary" onClick={handleCreateProfile} disabled={loading || !newProfileName.trim()}>
+              Create
+            </button>
+          </div>
+          <p className="setting-hint">
+            Each profile has its own installed versions and active PHP, e.g. one per client.
+          </p>
+        </div>
       </div>
       <div className="settings-section">
         <h3>Logging</h3>
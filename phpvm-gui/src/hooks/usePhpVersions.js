@@ -7,6 +7,7 @@ import { phpvmApi } from "../services/phpvmApi";
 export const usePhpVersions = () => {
   const [installedVersions, setInstalledVersions] = useState([]);
   const [availableVersions, setAvailableVersions] = useState([]);
+  const [installUnsupportedReason, setInstallUnsupportedReason] = useState(null);
   const [activeVersion, setActiveVersion] = useState(null);
   const [installPath, setInstallPath] = useState("");
   const [logPath, setLogPath] = useState("");
@@ -94,16 +95,19 @@ export const usePhpVersions = () => {
         return null;
       });
       
-      // list_available can be slow (network request), so do it last
-      const available = await phpvmApi.listAvailable().catch(err => {
-        console.error("[loadData] Error in listAvailable:", err);
-        return [];
+      // list_installable can be slow (network request), so do it last. Only versions
+      // this OS can install; elsewhere it comes back empty with the reason.
+      const installable = await phpvmApi.listInstallable().catch(err => {
+        console.error("[loadData] Error in listInstallable:", err);
+        return { versions: [], reason: null };
       });
+      const available = installable.versions;
 
       console.log("[loadData] Data loaded successfully:", { installed, available, active });
       
       setInstalledVersions(installed || []);
       setAvailableVersions(available || []);
+      setInstallUnsupportedReason(installable.reason || null);
       setActiveVersion(active || null);
       setInstallPath(path || "");
       setLogPath(logPath || "");
@@ -148,6 +152,12 @@ export const usePhpVersions = () => {
         allVersions.add(baseVersion);
       });
 
+      // Warm the availability cache with one batched, parallel check so the
+      // per-version status calls below don't probe each URL sequentially
+      await phpvmApi.checkVersionsOnline(Array.from(allVersions)).catch(err => {
+        console.error("[loadVersionStatuses] Error in checkVersionsOnline:", err);
+      });
+
       // Load all statuses in parallel
       const statusPromises = Array.from(allVersions).map(async (version) => {
         try {
@@ -236,6 +246,7 @@ export const usePhpVersions = () => {
   return {
     installedVersions,
     availableVersions,
+    installUnsupportedReason,
     activeVersion,
     installPath,
     logPath,
@@ -3,7 +3,7 @@
  */
 import { useState, useRef, useEffect } from "react";
 import { listen } from "@tauri-apps/api/event";
-import { phpvmApi } from "../services/phpvmApi";
+import { phpvmApi, ErrorCodes } from "../services/phpvmApi";
 
 export const useVersionOperations = ({ 
   installedVersions,
@@ -48,7 +48,7 @@ export const useVersionOperations = ({
     isCached: false, // Track if file is from cache
   });
 
-  const handleInstallClick = (version) => {
+  const handleInstallClick = async (version) => {
     if (!version) return;
     
     const tsInstalled = installedVersions.some(v => v.startsWith(`${version}-ts`) || v === `${version}-ts`);
@@ -61,10 +61,12 @@ export const useVersionOperations = ({
       // Only NTS installed, install TS
       handleInstall(version, true);
     } else if (!tsInstalled && !ntsInstalled) {
-      // Neither installed, show modal
+      // Neither installed, show modal with the user's default variant pre-selected
+      const defaultVariant = await phpvmApi.getDefaultVariant().catch(() => null);
+      const preselectTs = defaultVariant !== "nts";
       setPendingInstallVersion(version);
-      setSelectedThreadSafe(true);
-      selectedThreadSafeRef.current = true;
+      setSelectedThreadSafe(preselectTs);
+      selectedThreadSafeRef.current = preselectTs;
       setShowThreadSafeModal(true);
     } else {
       // Both installed
@@ -115,17 +117,9 @@ export const useVersionOperations = ({
           
           console.log("[useVersionOperations] Parsed progress:", { downloaded, total, speed_mbps, percent });
           
-          // Detect if file is cached: speed is 0, percent is 100, and total > 0
-          const isCached = speed_mbps === 0 && percent === 100 && total > 0 && downloaded === total;
-          
-          // Update progress message immediately when we detect cached file or start downloading
-          if (total > 0) {
-            if (isCached) {
-              setInstallProgress("Using cached PHP archive...");
-            } else if (downloaded < total) {
-              // Only update to "Downloading" if we're actually downloading (not complete)
-              setInstallProgress("Downloading PHP archive...");
-            }
+          // Update progress message once the download starts
+          if (total > 0 && downloaded < total) {
+            setInstallProgress("Downloading PHP archive...");
           }
           
           // Update state regardless of values (to track progress even if it starts at 0)
@@ -135,9 +129,10 @@ export const useVersionOperations = ({
             total: Number(total) || 0,
             speed: Number(speed_mbps) || 0,
             percent: Number(percent) || 0,
-            isCached: isCached,
+            // Cached archives arrive as a separate "using-cache" event, so this is a live download
+            isCached: false,
           });
-          console.log("[useVersionOperations] Updated downloadProgress state:", { downloaded, total, speed_mbps, percent, isCached });
+          console.log("[useVersionOperations] Updated downloadProgress state:", { downloaded, total, speed_mbps, percent });
         });
         console.log("[useVersionOperations] Download progress listener set up successfully");
       } catch (err) {
@@ -145,13 +140,47 @@ export const useVersionOperations = ({
       }
     };
     
+    // Archive already in cache: no download phase, go straight to extraction
+    let unlistenCacheFn = null;
+    const setupCacheListener = async () => {
+      try {
+        unlistenCacheFn = await listen("using-cache", () => {
+          setInstallProgress("Using cached PHP archive, extracting...");
+          setDownloadProgress({ downloaded: 0, total: 0, speed: 0, percent: 100, isCached: true });
+        });
+      } catch (err) {
+        console.error("[useVersionOperations] Failed to set up using-cache listener:", err);
+      }
+    };
+    
+    // Extraction done: the installed files are being hashed
+    let unlistenChecksumFn = null;
+    const setupChecksumListener = async () => {
+      try {
+        unlistenChecksumFn = await listen("checksum-progress", (event) => {
+          const { done, total } = event.payload || {};
+          setInstallProgress(`Verifying installation… (${done}/${total} files)`);
+        });
+      } catch (err) {
+        console.error("[useVersionOperations] Failed to set up checksum-progress listener:", err);
+      }
+    };
+    
     setupListener();
+    setupCacheListener();
+    setupChecksumListener();
     
     return () => {
       if (unlistenFn) {
         console.log("[useVersionOperations] Cleaning up download progress listener");
         unlistenFn();
       }
+      if (unlistenCacheFn) {
+        unlistenCacheFn();
+      }
+      if (unlistenChecksumFn) {
+        unlistenChecksumFn();
+      }
     };
   }, [isInstalling]);
 
@@ -165,6 +194,12 @@ export const useVersionOperations = ({
       setDownloadProgress(prev => prev.total === 0 ? { downloaded: 0, total: 0, speed: 0, percent: 0, isCached: false } : prev);
       
       const wasFirstInstall = installedVersions.length === 0;
+
+      // A PHP install needs ~100 MB (archive plus extracted files); warn early on a full drive
+      const freeSpace = await phpvmApi.getFreeSpace().catch(() => null);
+      if (freeSpace !== null && freeSpace < 200 * 1024 * 1024 && showWarning) {
+        showWarning(`Only ${Math.round(freeSpace / (1024 * 1024))} MB free on the install drive; the install may fail.`);
+      }
       
       let threadSafeParam;
       if (threadSafe === true) {
@@ -196,7 +231,11 @@ export const useVersionOperations = ({
       
       if (wasFirstInstall) {
         setInstallProgress("Activating version...");
-        await phpvmApi.switchVersion(version);
+        // Name the build just installed, not whichever the default variant prefers
+        const { path_warning: pathWarning } = await phpvmApi.switchVersion(`${version}-${threadSafeParam}`);
+        if (pathWarning && showWarning) {
+          showWarning(`PHP ${version} is active, but ${pathWarning}`, 6000);
+        }
         // Quick refresh without showing loading state
         await refreshInstalledData();
       }
@@ -208,7 +247,12 @@ export const useVersionOperations = ({
       await new Promise(resolve => setTimeout(resolve, 500));
     } catch (err) {
       const errorMsg = err.toString();
-      if (showError) {
+      if (err.code === ErrorCodes.ALREADY_INSTALLED && showWarning) {
+        showWarning(errorMsg);
+        await refreshInstalledData();
+      } else if (err.code === ErrorCodes.INSTALL_IN_PROGRESS && showWarning) {
+        showWarning(errorMsg);
+      } else if (showError) {
         showError(`Failed to install PHP ${version}: ${errorMsg}`);
       }
       setInstallProgress("Installation failed");
@@ -257,7 +301,7 @@ export const useVersionOperations = ({
       setSwitchingVersion(version);
       setSwitchProgress("Switching PHP version...");
       
-      await phpvmApi.switchVersion(version);
+      const { path_warning: pathWarning } = await phpvmApi.switchVersion(version);
       
       setSwitchProgress("Version switched successfully!");
       // Quick refresh without showing loading state
@@ -266,7 +310,9 @@ export const useVersionOperations = ({
         showSuccess(`Switched to PHP ${version}`);
       }
       // Show warning about restarting terminal (2x longer than success notification: 6000ms)
-      if (showWarning) {
+      if (pathWarning && showWarning) {
+        showWarning(`PHP ${version} is active, but ${pathWarning}`, 6000);
+      } else if (showWarning) {
         showWarning('Please restart your terminal/console for PATH changes to take effect. The "php" command will not work in your current terminal until you restart it.', 6000);
       }
       await new Promise(resolve => setTimeout(resolve, 500));
@@ -358,7 +404,9 @@ export const useVersionOperations = ({
       await new Promise(resolve => setTimeout(resolve, 500));
     } catch (err) {
       const errorMsg = err.toString();
-      if (showError) {
+      if (err.code === ErrorCodes.ACTIVE_VERSION_IN_USE && showWarning) {
+        showWarning(`PHP ${displayVersion} is the active version. Switch to another version before removing it.`);
+      } else if (showError) {
         showError(`Failed to remove PHP ${displayVersion}: ${errorMsg}`);
       }
       setDeleteProgress("Removal failed");
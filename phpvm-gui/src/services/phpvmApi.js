@@ -2,7 +2,65 @@
  * API service layer for Tauri invocations
  * Centralizes all backend communication
  */
-import { invoke } from "@tauri-apps/api/core";
+import { invoke as tauriInvoke } from "@tauri-apps/api/core";
+
+/**
+ * Stable error codes returned by backend commands
+ */
+export const ErrorCodes = {
+  NOT_INSTALLED: "NOT_INSTALLED",
+  ALREADY_INSTALLED: "ALREADY_INSTALLED",
+  INSTALL_IN_PROGRESS: "INSTALL_IN_PROGRESS",
+  ACTIVE_VERSION_IN_USE: "ACTIVE_VERSION_IN_USE",
+  INVALID_VERSION: "INVALID_VERSION",
+  INVALID_ARGUMENT: "INVALID_ARGUMENT",
+  NETWORK: "NETWORK",
+  CHECKSUM_MISMATCH: "CHECKSUM_MISMATCH",
+  EXTRACTION_FAILED: "EXTRACTION_FAILED",
+  IO: "IO",
+  UPDATE_FAILED: "UPDATE_FAILED",
+  OTHER: "OTHER",
+};
+
+/**
+ * Error thrown for failed commands. `code` is one of ErrorCodes;
+ * toString() returns the human message so existing `${err}` displays keep working.
+ */
+export class PhpVmError extends Error {
+  constructor(code, message) {
+    super(message);
+    this.name = "PhpVmError";
+    this.code = code;
+  }
+
+  toString() {
+    return this.message;
+  }
+}
+
+/**
+ * Invoke a command, turning `{ code, message }` rejections into PhpVmError
+ */
+const invoke = async (command, args) => {
+  try {
+    return await tauriInvoke(command, args);
+  } catch (err) {
+    if (err && typeof err === "object" && "code" in err && "message" in err) {
+      throw new PhpVmError(err.code, err.message);
+    }
+    throw err;
+  }
+};
+
+/**
+ * Version list filter in the backend's snake_case shape
+ */
+const toFilterPayload = (options = {}) => ({
+  include_eol: options.includeEol ?? true,
+  only_installed_branches: options.onlyInstalledBranches ?? false,
+  limit: options.limit ?? null,
+  ascending: options.ascending ?? false,
+});
 
 export const phpvmApi = {
   /**
@@ -20,6 +78,14 @@ export const phpvmApi = {
     }
   },
 
+  /**
+   * Available versions this machine can install: { os, arch, versions, reason }.
+   * Off Windows, versions is empty and reason says why.
+   */
+  listInstallable: async () => {
+    return await invoke("list_installable");
+  },
+
   /**
    * Get list of available PHP versions
    */
@@ -35,6 +101,55 @@ export const phpvmApi = {
     }
   },
 
+  /**
+   * Get available versions grouped by major.minor branch
+   */
+  listAvailableGrouped: async () => {
+    return await invoke("list_available_grouped");
+  },
+
+  /**
+   * List available versions with { includeEol, onlyInstalledBranches, limit, ascending } applied
+   */
+  listAvailableFiltered: async (options) => {
+    return await invoke("list_available_filtered", { options: toFilterPayload(options) });
+  },
+
+  /**
+   * Get the saved version list filter
+   */
+  getAvailableFilter: async () => {
+    const filter = await invoke("get_available_filter");
+    return {
+      includeEol: filter.include_eol,
+      onlyInstalledBranches: filter.only_installed_branches,
+      limit: filter.limit,
+      ascending: filter.ascending,
+    };
+  },
+
+  /**
+   * Save the version list filter listAvailable applies
+   */
+  setAvailableFilter: async (options) => {
+    return await invoke("set_available_filter", { options: toFilterPayload(options) });
+  },
+
+  /**
+   * What the last version list refresh changed, or null
+   * { source, checked_at, previous_fetched_at, added: [...], eol_changes: [{ version, from, to }] }
+   */
+  getLastVersionDelta: async () => {
+    return await invoke("get_last_version_delta");
+  },
+
+  /**
+   * Get installed versions that have a newer patch in the same branch
+   */
+  checkPatchUpdates: async () => {
+    return await invoke("check_patch_updates");
+  },
+
   /**
    * Get currently active PHP version
    */
@@ -50,6 +165,13 @@ export const phpvmApi = {
     }
   },
 
+  /**
+   * The active install split up: { version: "8.3.0-ts", base_version, variant, install_path }, or null
+   */
+  getActiveDetailed: async () => {
+    return await invoke("get_active_detailed");
+  },
+
   /**
    * Get installation path
    */
@@ -80,6 +202,49 @@ export const phpvmApi = {
     }
   },
 
+  /**
+   * Get the last N lines of the log file
+   */
+  readLogTail: async (maxLines) => {
+    return await invoke("read_log_tail", { maxLines });
+  },
+
+  /**
+   * Clear the log file
+   */
+  clearLog: async () => {
+    return await invoke("clear_log");
+  },
+
+  /**
+   * List the active log and rotated backups
+   */
+  getLogFiles: async () => {
+    return await invoke("get_log_files");
+  },
+
+  /**
+   * Delete rotated logs beyond the `keep` most recent
+   * Returns the number of files removed
+   */
+  purgeOldLogs: async (keep) => {
+    return await invoke("purge_old_logs", { keep });
+  },
+
+  /**
+   * Only log messages at this level or more severe: "ERROR", "WARN", "INFO" or "DEBUG"
+   */
+  setLogLevel: async (level) => {
+    return await invoke("set_log_level", { level });
+  },
+
+  /**
+   * Current log level threshold
+   */
+  getLogLevel: async () => {
+    return await invoke("get_log_level");
+  },
+
   /**
    * Check PATH status
    */
@@ -95,6 +260,20 @@ export const phpvmApi = {
     }
   },
 
+  /**
+   * Check what `php` on PATH runs: { status: "active" | "shadowed" | "not_found", resolved, shim_dir }
+   */
+  checkPhpResolution: async () => {
+    return await invoke("check_php_resolution");
+  },
+
+  /**
+   * Run the php on PATH: { path, version, managed, active }, what the shell really gets
+   */
+  getEffectivePhp: async () => {
+    return await invoke("get_effective_php");
+  },
+
   /**
    * Set PATH
    */
@@ -110,7 +289,17 @@ export const phpvmApi = {
   },
 
   /**
-   * Install a PHP version
+   * Check which versions still have a reachable download (batched HEAD requests)
+   */
+  checkVersionsOnline: async (versions) => {
+    return await invoke("check_versions_online", { versions });
+  },
+
+  /**
+   * Install a PHP version: { version, thread_safe: "ts" | "nts", force_reinstall?, compiler_tag?, keep_extensions? }.
+   * force_reinstall replaces an existing install of that variant; compiler_tag ("vs16",
+   * "VC15", ...) downloads the build made with that compiler instead of the usual one;
+   * keep_extensions deletes every other bundled extension (only a reinstall restores them).
    */
   installVersion: async (params) => {
     console.log("[phpvmApi] Calling install_version with params:", params);
@@ -125,19 +314,407 @@ export const phpvmApi = {
   },
 
   /**
-   * Remove a PHP version
+   * Dry-run an install: resolved URL, variant, compiler tag and target directory
+   */
+  planInstall: async (params) => {
+    return await invoke("plan_install", { params });
+  },
+
+  /**
+   * URLs an install would download from, without any network access: { primary, fallbacks }
+   */
+  getDownloadUrl: async (version, threadSafe) => {
+    return await invoke("get_download_url", { version, threadSafe });
+  },
+
+  /**
+   * Probe those URLs now: { url, status_code, reachable, content_length, probes }
+   */
+  checkVersionDownloadable: async (version, threadSafe) => {
+    return await invoke("check_version_downloadable", { version, threadSafe });
+  },
+
+  /**
+   * Remove a PHP version. The active version is refused (ACTIVE_VERSION_IN_USE) unless
+   * `force` is set, which switches to another installed version (or deactivates PHP) first.
    */
-  removeVersion: async (version) => {
-    return await invoke("remove_version", { version });
+  removeVersion: async (version, force = false) => {
+    return await invoke("remove_version", { version, force });
   },
 
   /**
-   * Switch to a PHP version
+   * Switch to a PHP version. Accepts "8.3.0-ts", "8.3.0", or a branch like "8.2"
+   * (newest installed patch in the default or active TS/NTS kind; a branch rejects
+   * with NOT_INSTALLED if that patch isn't installed in that kind).
+   * If the new php can't run, the previous version is restored and this rejects.
+   * Resolves to { path_warning }: why PATH couldn't be updated, or null
    */
   switchVersion: async (version) => {
     return await invoke("switch_version", { version });
   },
 
+  /**
+   * Switch without running the new version afterwards. switchVersion switches back
+   * automatically when the new php can't start; this doesn't.
+   */
+  switchVersionUnchecked: async (version) => {
+    return await invoke("switch_version_unchecked", { version });
+  },
+
+  /**
+   * Verify an installed version's files against the checksum recorded at install time
+   */
+  verifyIntegrity: async (version) => {
+    return await invoke("verify_integrity", { version });
+  },
+
+  /**
+   * Switch the given version to its TS or NTS build
+   */
+  switchVariant: async (version, threadSafe) => {
+    return await invoke("switch_variant", { version, threadSafe });
+  },
+
+  /**
+   * Write a script that activates a version for one shell session ("cmd", "powershell" or "bash")
+   * Returns the path of the generated script
+   */
+  writeActivationScript: async (version, shell) => {
+    return await invoke("write_activation_script", { version, shell });
+  },
+
+  /**
+   * Resolve a version for one shell: { version, path_dir, script } (script only when shell is given)
+   */
+  shellActivate: async (version, shell = null) => {
+    return await invoke("shell_activate", { version, shell });
+  },
+
+  /**
+   * Remove all versions, the PATH entry, state, config and logs (and the cache unless keepDownloads)
+   * Returns { removed, kept, errors }
+   */
+  factoryReset: async (confirm, keepDownloads) => {
+    return await invoke("factory_reset", { confirm, keepDownloads });
+  },
+
+  /**
+   * Version directories missing their PHP executable (reported only, nothing is deleted)
+   * Returns [{ version, path, in_state }]
+   */
+  findBrokenInstalls: async () => {
+    return await invoke("find_broken_installs");
+  },
+
+  /**
+   * Delete the directories reported by findBrokenInstalls
+   * Returns the removed entries
+   */
+  pruneBrokenInstalls: async () => {
+    return await invoke("prune_broken_installs");
+  },
+
+  /**
+   * Path of the php.ini the active version loads (null if no version is active)
+   */
+  getActiveIniPath: async () => {
+    return await invoke("get_active_ini_path");
+  },
+
+  /**
+   * Open the active version's php.ini in the default editor
+   * Returns the opened path
+   */
+  openActiveIni: async () => {
+    return await invoke("open_active_ini");
+  },
+
+  /**
+   * Download Xdebug for an installed version and enable it in its php.ini
+   * Returns the installed DLL path
+   */
+  installXdebug: async (version) => {
+    return await invoke("install_xdebug", { version });
+  },
+
+  /**
+   * Delete the bundled extensions of an installed version that aren't in keep (names like
+   * "curl") and comment them out in php.ini. Irreversible short of reinstalling.
+   * Returns { removed, freed_bytes }
+   */
+  stripExtensions: async (version, keep) => {
+    return await invoke("strip_extensions", { version, keep });
+  },
+
+  /**
+   * Compare the modules (php -m) loaded by two installed versions
+   * Returns { from, to, added, removed }
+   */
+  diffModules: async (from, to) => {
+    return await invoke("diff_modules", { from, to });
+  },
+
+  /**
+   * Support status of a version's branch as of today (null if unknown)
+   * Returns { status: "supported" | "security_only", days_left } or { status: "ended", days_ago }
+   */
+  getEolStatus: async (version) => {
+    return await invoke("get_eol_status", { version });
+  },
+
+  /**
+   * Whether the app runs elevated (admin on Windows, root on Unix)
+   */
+  checkElevation: async () => {
+    return await invoke("check_elevation");
+  },
+
+  /**
+   * Every published patch of a branch (e.g. 8.1.0 through 8.1.34), newest first
+   */
+  listBranchPatches: async (major, minor) => {
+    return await invoke("list_branch_patches", { major, minor });
+  },
+
+  /**
+   * php.net release notes for a version as plain text, or a "No changelog available" message
+   */
+  getChangelog: async (version) => {
+    return await invoke("get_changelog", { version });
+  },
+
+  /**
+   * App version, OS, directories, installed/active versions and PATH state for bug
+   * reports; `summary` is the same as a copy-pasteable text block. No network calls.
+   * `version_metadata_stale` is set when the last versionlog.com scrape found too few dates.
+   */
+  getDiagnostics: async () => {
+    return await invoke("get_diagnostics");
+  },
+
+  /**
+   * How an installed version was built: { php_version, compiler, arch, thread_safe }
+   */
+  getBuildInfo: async (version) => {
+    return await invoke("get_build_info", { version });
+  },
+
+  /**
+   * Local usage counters: installs, switches, removals, bytes_downloaded,
+   * switches_by_version and most_used_version. Never leaves the machine.
+   */
+  getUsageStats: async () => {
+    return await invoke("get_usage_stats");
+  },
+
+  /**
+   * Pin an installed version so it's never pruned or offered patch updates
+   */
+  pinVersion: async (version) => {
+    return await invoke("pin_version", { version });
+  },
+
+  /**
+   * Unpin a version ("8.1.34" unpins both variants)
+   */
+  unpinVersion: async (version) => {
+    return await invoke("unpin_version", { version });
+  },
+
+  /**
+   * Download a version into the cache without installing it; resolves to the cached path.
+   * Emits `download-progress` like installVersion.
+   */
+  prefetchVersion: async (params) => {
+    return await invoke("prefetch_version", { params });
+  },
+
+  /**
+   * Available versions whose archive is already cached: [{ version, ts, nts }]
+   */
+  listCacheReadyVersions: async () => {
+    return await invoke("list_cache_ready_versions");
+  },
+
+  /**
+   * Default variant for installs and branch switches: "ts", "nts" or null (not chosen yet)
+   */
+  getDefaultVariant: async () => {
+    return await invoke("get_default_variant");
+  },
+
+  /**
+   * Set the default variant ("ts" or "nts"); null clears it
+   */
+  setDefaultVariant: async (variant) => {
+    return await invoke("set_default_variant", { variant });
+  },
+
+  /**
+   * Set VS Code's php.validate.executablePath to the active PHP; resolves to the settings.json path
+   */
+  registerWithVscode: async () => {
+    return await invoke("register_with_vscode");
+  },
+
+  /**
+   * Remove the VS Code setting again; resolves to false if it wasn't set
+   */
+  unregisterFromVscode: async () => {
+    return await invoke("unregister_from_vscode");
+  },
+
+  /**
+   * PHP executable to add as a PhpStorm CLI interpreter
+   */
+  getIdeInterpreterPath: async () => {
+    return await invoke("get_ide_interpreter_path");
+  },
+
+  /**
+   * Free bytes on the drive holding `path` (the install directory by default)
+   */
+  getFreeSpace: async (path = null) => {
+    return await invoke("get_free_space", { path });
+  },
+
+  /**
+   * Rebuild state.json from the installed version directories (the old file is backed up)
+   */
+  rebuildState: async () => {
+    return await invoke("rebuild_state");
+  },
+
+  /**
+   * { schema_version, installed: [{ version, variant, active, path }], active,
+   *   available, available_error, path_configured, install_dir }
+   */
+  getStatusReport: async () => {
+    return await invoke("get_status_report");
+  },
+
+  /**
+   * Profiles keep separate installs and active versions: { profiles, active }
+   */
+  listProfiles: async () => {
+    return await invoke("list_profiles");
+  },
+
+  createProfile: async (name) => {
+    return await invoke("create_profile", { name });
+  },
+
+  /**
+   * Make a profile active; installed/active versions change with it, so refresh after
+   */
+  switchProfile: async (name) => {
+    return await invoke("switch_profile", { name });
+  },
+
+  /**
+   * Problems PHP reports when starting with a version's php.ini:
+   * [{ severity: "error" | "warning", kind: "syntax" | "extension_load" | "other",
+   *    message, line, extension }]. Empty when the file is clean.
+   */
+  lintIni: async (version) => {
+    return await invoke("lint_ini", { version });
+  },
+
+  /**
+   * date.timezone in a version's php.ini, e.g. "Europe/Lisbon"; null when unset.
+   */
+  getTimezone: async (version) => {
+    return await invoke("get_timezone", { version });
+  },
+
+  /**
+   * Set date.timezone in a version's php.ini (created from php.ini-development if
+   * missing). Rejects names that aren't IANA timezones; resolves to the name as the
+   * tz database spells it.
+   */
+  setTimezone: async (version, timezone) => {
+    return await invoke("set_timezone", { version, timezone });
+  },
+
+  /**
+   * IANA timezone names setTimezone accepts, sorted.
+   */
+  listTimezones: async () => {
+    return await invoke("list_timezones");
+  },
+
+  /**
+   * Move a version to the newest patch of its branch (same variant), carrying over
+   * php.ini, extra extensions and its pin, then switch to it. Resolves to
+   * { from, to, steps }. Emits `download-progress` like installVersion.
+   */
+  upgradeVersion: async (version, removeOld = false) => {
+    return await invoke("upgrade_version", { version, removeOld });
+  },
+
+  /**
+   * Open the native folder picker; resolves to null if cancelled
+   */
+  pickFolder: async (title = null) => {
+    return await invoke("pick_folder", { title });
+  },
+
+  /**
+   * Open the native file picker filtered to an extension; resolves to null if cancelled
+   */
+  pickFile: async (title, extension = null) => {
+    return await invoke("pick_file", { title, extension });
+  },
+
+  /**
+   * Open the native save dialog suggesting fileName; resolves to null if cancelled
+   */
+  pickSaveFile: async (title, fileName) => {
+    return await invoke("pick_save_file", { title, fileName });
+  },
+
+  /**
+   * Copy an existing PHP directory into management; resolves to the registered version
+   */
+  adoptPhp: async (path, threadSafe = null) => {
+    return await invoke("adopt_php", { path, threadSafe });
+  },
+
+  /**
+   * Read a directory's .phpvmrc; resolves to null if it has none
+   */
+  getProjectConfig: async (dir) => {
+    return await invoke("get_project_config", { dir });
+  },
+
+  /**
+   * Install, switch to and enable the extensions a directory's .phpvmrc asks for
+   */
+  applyProjectConfig: async (dir) => {
+    return await invoke("apply_project_config", { dir });
+  },
+
+  /**
+   * Zip the phpvm directory to dest; installs and cache only when asked
+   */
+  backupAll: async (dest, { includeVersions = false, includeCache = false } = {}) => {
+    return await invoke("backup_all", { dest, includeVersions, includeCache });
+  },
+
+  /**
+   * Restore a backup zip over the phpvm directory; resolves to { files, rewritten_paths }
+   */
+  restoreAll: async (src) => {
+    return await invoke("restore_all", { src });
+  },
+
+  /**
+   * Raw release data for a version: release/EOL dates, download URL, checksum
+   */
+  getVersionInfo: async (version) => {
+    return await invoke("get_version_info", { version });
+  },
+
   /**
    * List all cached files
    */
@@ -152,6 +729,13 @@ export const phpvmApi = {
     return await invoke("remove_cached_file", { hash });
   },
 
+  /**
+   * SHA256 of a cached file's contents (computed in the background, then remembered)
+   */
+  getCachedFileSha256: async (hash) => {
+    return await invoke("get_cached_file_sha256", { hash });
+  },
+
   /**
    * Clear all cached files
    */
@@ -159,6 +743,14 @@ export const phpvmApi = {
     return await invoke("clear_all_cache");
   },
 
+  /**
+   * Remove cached archives no available version installs from (and EOL ones when `removeEol`)
+   * Returns { removed: [file names], freed_bytes }
+   */
+  cleanOrphanedCache: async (removeEol = false) => {
+    return await invoke("clean_orphaned_cache", { removeEol });
+  },
+
   /**
    * Get current application version
    */
@@ -187,6 +779,13 @@ export const phpvmApi = {
     return await invoke("apply_update", { updateFilePath });
   },
 
+  /**
+   * Quit the app so an applied update can replace it and restart
+   */
+  exitForUpdate: async () => {
+    return await invoke("exit_for_update");
+  },
+
   /**
    * Open URL in default browser
    */